@@ -3,7 +3,7 @@ use thiserror::Error;
 #[derive(Error, Debug)]
 pub enum UniSqliteError {
     #[error("Database error: {0}")]
-    Database(#[from] rusqlite::Error),
+    Database(rusqlite::Error),
 
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
@@ -20,6 +20,18 @@ pub enum UniSqliteError {
     #[error("Database not found: {0}")]
     DatabaseNotFound(String),
 
+    #[error("No database connected. Call connect first")]
+    NotConnected,
+
+    #[error("Database is locked by another process: {0}. Retry once the lock is released")]
+    DatabaseLocked(String),
+
+    #[error("Query interrupted: {0}")]
+    Interrupted(String),
+
+    #[error("Query timed out: {0}")]
+    Timeout(String),
+
     #[error("Query failed: {0}")]
     QueryFailed(String),
 
@@ -33,8 +45,83 @@ pub enum UniSqliteError {
     Other(String),
 }
 
+/// JSON-RPC server error code for failures the client may simply retry.
+const RETRYABLE_ERROR: rmcp::model::ErrorCode = rmcp::model::ErrorCode(-32000);
+
+/// JSON-RPC server error code for tools called before `connect`.
+const NOT_CONNECTED_ERROR: rmcp::model::ErrorCode = rmcp::model::ErrorCode(-32001);
+
+impl From<rusqlite::Error> for UniSqliteError {
+    fn from(err: rusqlite::Error) -> Self {
+        match &err {
+            rusqlite::Error::SqliteFailure(e, _)
+                if e.code == rusqlite::ErrorCode::OperationInterrupted =>
+            {
+                UniSqliteError::Interrupted(err.to_string())
+            }
+            _ => UniSqliteError::Database(err),
+        }
+    }
+}
+
 impl From<UniSqliteError> for rmcp::ErrorData {
     fn from(err: UniSqliteError) -> Self {
-        rmcp::ErrorData::internal_error(err.to_string(), None)
+        match err {
+            UniSqliteError::Interrupted(_)
+            | UniSqliteError::Timeout(_)
+            | UniSqliteError::DatabaseLocked(_) => rmcp::ErrorData::new(
+                RETRYABLE_ERROR,
+                err.to_string(),
+                Some(serde_json::json!({ "retryable": true })),
+            ),
+            UniSqliteError::NotConnected => rmcp::ErrorData::new(
+                NOT_CONNECTED_ERROR,
+                err.to_string(),
+                Some(serde_json::json!({ "not_connected": true })),
+            ),
+            UniSqliteError::Database(ref db_err) if is_user_error(db_err) => {
+                rmcp::ErrorData::invalid_params(err.to_string(), None)
+            }
+            UniSqliteError::DatabaseNotFound(_) => {
+                rmcp::ErrorData::resource_not_found(err.to_string(), None)
+            }
+            UniSqliteError::InvalidPath(_)
+            | UniSqliteError::QueryFailed(_)
+            | UniSqliteError::ImportFailed(_)
+            | UniSqliteError::Json(_) => rmcp::ErrorData::invalid_params(err.to_string(), None),
+            UniSqliteError::Csv(ref csv_err) if !csv_err.is_io_error() => {
+                rmcp::ErrorData::invalid_params(err.to_string(), None)
+            }
+            _ => rmcp::ErrorData::internal_error(err.to_string(), None),
+        }
+    }
+}
+
+/// Whether a SQLite error was caused by the request (bad SQL, constraint violation, wrong
+/// parameters) rather than by the database file or the environment.
+fn is_user_error(err: &rusqlite::Error) -> bool {
+    use rusqlite::ErrorCode;
+
+    match err {
+        // SQLITE_ERROR covers syntax errors and missing tables or columns.
+        rusqlite::Error::SqliteFailure(e, _) => matches!(
+            e.code,
+            ErrorCode::Unknown
+                | ErrorCode::ConstraintViolation
+                | ErrorCode::TypeMismatch
+                | ErrorCode::ParameterOutOfRange
+                | ErrorCode::TooBig
+        ),
+        rusqlite::Error::SqlInputError { .. }
+        | rusqlite::Error::InvalidParameterName(_)
+        | rusqlite::Error::InvalidParameterCount(_, _)
+        | rusqlite::Error::InvalidColumnName(_)
+        | rusqlite::Error::InvalidColumnIndex(_)
+        | rusqlite::Error::InvalidColumnType(_, _, _)
+        | rusqlite::Error::MultipleStatement
+        | rusqlite::Error::ExecuteReturnedResults
+        | rusqlite::Error::QueryReturnedNoRows
+        | rusqlite::Error::ToSqlConversionFailure(_) => true,
+        _ => false,
     }
 }
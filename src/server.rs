@@ -1,6 +1,8 @@
 #![allow(clippy::redundant_closure)]
 
+use base64::{Engine, prelude::BASE64_STANDARD};
 use chrono::{DateTime, Utc};
+use flate2::{Compression, write::GzEncoder};
 use rmcp::{
     ServerHandler, ServiceExt,
     model::{
@@ -10,14 +12,17 @@ use rmcp::{
     service::RequestContext,
     transport::stdio,
 };
-use rusqlite::{Connection, OpenFlags, Transaction};
+use rusqlite::{Connection, OpenFlags, OptionalExtension, Transaction, functions::FunctionFlags};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::borrow::Cow;
+use std::collections::{HashMap, VecDeque};
 use std::fs;
+use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::Mutex;
 
 use crate::error::UniSqliteError;
@@ -28,16 +33,122 @@ pub struct SqliteHandler {
     pub current_db: Arc<Mutex<Option<Connection>>>,
     // Path to current database
     pub current_path: Arc<Mutex<Option<PathBuf>>>,
+    // SQL trace logging state, re-applied whenever a new connection is opened
+    pub sql_trace: Arc<Mutex<SqlTraceState>>,
+    // Prepared statement cache configuration and hit statistics for the current connection
+    // (a blocking mutex, since it is updated while a non-Send &Connection is held)
+    pub statement_cache: Arc<std::sync::Mutex<StatementCacheState>>,
+    // SQL registered via prepare_statement, keyed by statement id (cleared on reconnect)
+    pub prepared_statements: Arc<Mutex<HashMap<String, String>>>,
+    // Retry policy for writes that still hit SQLITE_BUSY/SQLITE_LOCKED after the busy timeout
+    pub busy_retry: Arc<std::sync::Mutex<BusyRetryState>>,
+    // Last tool activity and the connect options needed to reopen after an idle close
+    pub idle: Arc<std::sync::Mutex<IdleState>>,
+    // SQL functions registered on the current connection by this server, for list_functions
+    pub custom_functions: Arc<std::sync::Mutex<Vec<CustomFunctionInfo>>>,
+    // Runtime limits set via set_limits, re-applied whenever a new connection is opened
+    pub limits: Arc<std::sync::Mutex<SetLimitsRequest>>,
+    // Recent read results for `query` calls with `cache` set (cleared on reconnect)
+    pub query_cache: Arc<std::sync::Mutex<QueryCache>>,
+    /// Identity of the database file captured at connect time
+    pub file_identity: Arc<std::sync::Mutex<Option<FileIdentity>>>,
+    /// Extensions loaded on the current connection via `load_extension`
+    pub loaded_extensions: Arc<std::sync::Mutex<Vec<LoadedExtension>>>,
+    /// Row limit for queries that don't set one (0 = unlimited), from `default_fetch_limit`
+    pub fetch_limit: Arc<std::sync::Mutex<usize>>,
 }
 
 // Connection and Basic Query Types
-#[derive(Debug, Deserialize, JsonSchema)]
+const CONNECT_BUSY_TIMEOUT: Duration = Duration::from_secs(1);
+// rusqlite's default for new connections
+const DEFAULT_BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+// Rows `query` returns when neither the request nor the connection sets a limit
+const DEFAULT_FETCH_LIMIT: usize = 1000;
+
+#[derive(Debug, Default, Clone, Deserialize, JsonSchema)]
 pub struct ConnectRequest {
     #[schemars(description = "Path to the SQLite database file")]
     pub path: String,
     #[schemars(description = "Create the database if it doesn't exist")]
     #[serde(default)]
     pub create_if_missing: bool,
+    #[schemars(description = "Register the uuid() SQL function on the connection")]
+    #[serde(default)]
+    pub enable_uuid_function: bool,
+    #[schemars(
+        description = "Register the cosine_similarity(a, b) SQL function over packed float32 BLOBs or JSON arrays"
+    )]
+    #[serde(default)]
+    pub enable_vector_functions: bool,
+    #[schemars(
+        description = "Register the UNICODE_NOCASE collation, a case-insensitive comparison for non-ASCII text (built-in NOCASE only folds ASCII)"
+    )]
+    #[serde(default)]
+    pub enable_unicode_collation: bool,
+    #[schemars(description = "Performance pragmas to apply once connected")]
+    #[serde(default)]
+    pub performance: Option<PerformanceSettings>,
+    #[schemars(description = "Reuse prepared statements across query and batch_insert calls")]
+    #[serde(default)]
+    pub use_cache: bool,
+    #[schemars(description = "Number of prepared statements to keep cached (default 16)")]
+    pub statement_cache_capacity: Option<usize>,
+    #[schemars(
+        description = "Retry policy for writes that fail with SQLITE_BUSY or SQLITE_LOCKED"
+    )]
+    #[serde(default)]
+    pub busy_retry: Option<BusyRetryPolicy>,
+    #[schemars(
+        description = "Treat path as a SQLite URI filename such as file:data.db?mode=ro&cache=shared; the file path is still validated"
+    )]
+    #[serde(default)]
+    pub uri: bool,
+    #[schemars(
+        description = "Rows a query returns when it sets no limit (default 1000, 0 = no limit); results cut off by it are flagged truncated"
+    )]
+    pub default_fetch_limit: Option<usize>,
+}
+
+#[derive(Debug, Default, Clone, Copy, Deserialize, JsonSchema)]
+pub struct BusyRetryPolicy {
+    #[schemars(description = "Maximum retries after the first attempt (default 3, 0 disables)")]
+    pub max_retries: Option<u32>,
+    #[schemars(
+        description = "Delay before the first retry in milliseconds, doubled on each further retry up to 2000 (default 50)"
+    )]
+    pub backoff_ms: Option<u64>,
+}
+
+/// Resolved busy retry policy for the current connection.
+#[derive(Debug, Clone, Copy)]
+pub struct BusyRetryState {
+    pub max_retries: u32,
+    pub backoff: Duration,
+}
+
+// Upper bound for a single busy retry delay, however many times it has doubled
+const MAX_BUSY_BACKOFF: Duration = Duration::from_secs(2);
+
+impl Default for BusyRetryState {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            backoff: Duration::from_millis(50),
+        }
+    }
+}
+
+impl From<BusyRetryPolicy> for BusyRetryState {
+    fn from(policy: BusyRetryPolicy) -> Self {
+        let default = Self::default();
+        Self {
+            max_retries: policy.max_retries.unwrap_or(default.max_retries),
+            backoff: policy
+                .backoff_ms
+                .map(Duration::from_millis)
+                .unwrap_or(default.backoff),
+        }
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -45,23 +156,254 @@ pub struct ConnectResult {
     pub success: bool,
     pub path: String,
     pub database_size: Option<u64>,
+    pub supports_window_functions: bool,
+    pub performance: Option<PerformanceSettingsResult>,
 }
 
-#[derive(Debug, Deserialize, JsonSchema)]
+#[derive(Debug, Serialize)]
+pub struct SwitchDatabaseResult {
+    pub previous_path: Option<String>,
+    pub connection: ConnectResult,
+}
+
+#[derive(Debug, Default, Deserialize, JsonSchema)]
 pub struct QueryRequest {
     #[schemars(description = "SQL query to execute")]
     pub sql: String,
     #[schemars(description = "Parameters for a prepared statement")]
     #[serde(default)]
     pub parameters: Vec<serde_json::Value>,
+    #[schemars(
+        description = "Convert BOOLEAN columns to true/false and parse JSON columns, based on declared column types. Only applies to plain column references; computed expressions are returned as-is"
+    )]
+    #[serde(default)]
+    pub typed: bool,
+    #[schemars(description = "Interrupt the query if it runs longer than this many milliseconds")]
+    pub timeout_ms: Option<u64>,
+    #[schemars(
+        description = "Return only the number of rows a SELECT would produce, in rows_affected, without the data"
+    )]
+    #[serde(default)]
+    pub count_only: bool,
+    #[schemars(
+        description = "For a single-table SELECT without ORDER BY, order rows by rowid so repeated runs return the same order"
+    )]
+    #[serde(default)]
+    pub stable_order: bool,
+    #[schemars(
+        description = "Run a write inside a savepoint and roll it back, reporting the rows it would affect"
+    )]
+    #[serde(default)]
+    pub dry_run: bool,
+    #[schemars(
+        description = "Serve a repeated identical read from the short-lived result cache (default: false)"
+    )]
+    #[serde(default)]
+    pub cache: bool,
+    #[schemars(
+        description = "SQLite type to bind each parameter as, by position: integer, real, text, blob (from hex), or auto (default JSON mapping). Overrides the JSON type, e.g. text for a ZIP code sent as a number"
+    )]
+    #[serde(default)]
+    pub param_types: Vec<String>,
+    #[schemars(
+        description = "How to return BLOB cells: content (hex, default) or size ({\"blob_bytes\": n}), for browsing tables with large attachments"
+    )]
+    #[serde(default)]
+    pub blob_as: BlobAs,
+    #[schemars(
+        description = "For writes, also report total_changes: every row changed including by triggers and cascades, unlike rows_affected"
+    )]
+    #[serde(default)]
+    pub report_total_changes: bool,
+    #[schemars(
+        description = "Maximum rows to return (default: the connection's default_fetch_limit, normally 1000; 0 = no limit). Results cut off are flagged truncated"
+    )]
+    pub limit: Option<usize>,
+    #[schemars(description = "Return every row, ignoring limit and the default fetch limit")]
+    #[serde(default)]
+    pub unlimited: bool,
 }
 
-#[derive(Debug, Serialize)]
+/// Column names, rows, and whether rows past a limit were left behind.
+type FetchedRows = (Vec<String>, Vec<Vec<Value>>, bool);
+
+/// How BLOB cells appear in query results.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BlobAs {
+    #[default]
+    Content,
+    Size,
+}
+
+/// JSON conversion applied to a result column in typed query mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TypeHint {
+    None,
+    Boolean,
+    Json,
+}
+
+impl TypeHint {
+    fn from_declared_type(declared: Option<&str>) -> Self {
+        match declared.map(|d| d.trim().to_ascii_uppercase()) {
+            Some(d) if d.starts_with("BOOL") => Self::Boolean,
+            Some(d) if d == "JSON" || d == "JSONB" => Self::Json,
+            _ => Self::None,
+        }
+    }
+
+    fn apply(self, value: Value) -> Value {
+        match (self, value) {
+            (Self::Boolean, Value::Number(n)) if n.as_i64() == Some(0) => Value::Bool(false),
+            (Self::Boolean, Value::Number(n)) if n.as_i64() == Some(1) => Value::Bool(true),
+            // Unparseable text stays a string rather than failing the query
+            (Self::Json, Value::String(s)) => serde_json::from_str(&s).unwrap_or(Value::String(s)),
+            (_, value) => value,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct QueryResult {
     pub message: String,
     pub rows_affected: Option<usize>,
     pub data: Option<Vec<Vec<serde_json::Value>>>,
     pub columns: Option<Vec<String>>,
+    pub elapsed_ms: Option<u64>,
+    /// Times the statement was retried after SQLITE_BUSY/SQLITE_LOCKED.
+    pub busy_retries: u32,
+    /// How `stable_order` was applied, or why it was skipped
+    pub ordering_note: Option<String>,
+    /// Whether the statement was rolled back (`dry_run`)
+    pub dry_run: bool,
+    /// Whether the result was served from the query cache
+    pub cached: bool,
+    /// Rows changed by the statement including trigger and foreign key cascade
+    /// changes (`report_total_changes`); `rows_affected` counts only the statement's own rows
+    pub total_changes: Option<u64>,
+    /// Whether more rows matched than the row limit let through
+    pub truncated: bool,
+    /// Row limit applied to a read, if any
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QueryScalarResult {
+    pub column: String,
+    pub value: serde_json::Value,
+}
+
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+pub struct QueryRowRequest {
+    #[schemars(description = "SQL query to execute")]
+    pub sql: String,
+    #[schemars(description = "Parameters for a prepared statement")]
+    #[serde(default)]
+    pub parameters: Vec<serde_json::Value>,
+    #[schemars(description = "Convert BOOLEAN and JSON columns based on declared column types")]
+    #[serde(default)]
+    pub typed: bool,
+    #[schemars(
+        description = "Return a null row instead of an error when the query matches nothing"
+    )]
+    #[serde(default)]
+    pub allow_none: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QueryRowResult {
+    pub row: Option<serde_json::Map<String, serde_json::Value>>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct PrepareStatementRequest {
+    #[schemars(description = "SQL statement to prepare, using ? placeholders for parameters")]
+    pub sql: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PrepareStatementResult {
+    pub statement_id: String,
+    pub parameter_count: usize,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ExecutePreparedRequest {
+    #[schemars(description = "Statement id returned by prepare_statement")]
+    pub statement_id: String,
+    #[schemars(description = "Parameters for the prepared statement")]
+    #[serde(default)]
+    pub parameters: Vec<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct FinalizeStatementRequest {
+    #[schemars(description = "Statement id returned by prepare_statement")]
+    pub statement_id: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FinalizeStatementResult {
+    pub success: bool,
+    pub message: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CrossQueryDatabase {
+    #[schemars(
+        description = "Schema alias used to reference the database in the query (alias.table)"
+    )]
+    pub alias: String,
+    #[schemars(description = "Path to the database file to attach read-only")]
+    pub path: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CrossQueryRequest {
+    #[schemars(description = "Databases to attach read-only for the duration of the query")]
+    pub databases: Vec<CrossQueryDatabase>,
+    #[schemars(description = "SELECT query that may reference alias.table")]
+    pub sql: String,
+    #[schemars(description = "Parameters for a prepared statement")]
+    #[serde(default)]
+    pub parameters: Vec<serde_json::Value>,
+    #[schemars(description = "Convert BOOLEAN and JSON columns based on declared column types")]
+    #[serde(default)]
+    pub typed: bool,
+}
+
+/// Interrupts a running query once its deadline passes. `finish` stops the timer and
+/// reports whether it fired, so an interrupted query can be reported as a timeout.
+struct QueryWatchdog {
+    done: std::sync::mpsc::Sender<()>,
+    fired: Arc<std::sync::atomic::AtomicBool>,
+    thread: std::thread::JoinHandle<()>,
+}
+
+impl QueryWatchdog {
+    fn start(interrupt: rusqlite::InterruptHandle, timeout: Duration) -> Self {
+        let (done, rx) = std::sync::mpsc::channel::<()>();
+        let fired = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let thread_fired = fired.clone();
+        let thread = std::thread::spawn(move || {
+            if let Err(std::sync::mpsc::RecvTimeoutError::Timeout) = rx.recv_timeout(timeout) {
+                thread_fired.store(true, std::sync::atomic::Ordering::SeqCst);
+                interrupt.interrupt();
+            }
+        });
+        Self {
+            done,
+            fired,
+            thread,
+        }
+    }
+
+    fn finish(self) -> bool {
+        let _ = self.done.send(());
+        let _ = self.thread.join();
+        self.fired.load(std::sync::atomic::Ordering::SeqCst)
+    }
 }
 
 // Transaction Types
@@ -84,10 +426,52 @@ pub struct TransactionResult {
     pub message: String,
     pub results: Vec<QueryResult>,
     pub total_rows_affected: usize,
+    pub busy_retries: u32,
+}
+
+// Batch Types
+/// One typed step of a `batch`, tagged by `op`.
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum BatchOperation {
+    Insert(BatchInsertRequest),
+    Update(BatchUpdateRequest),
+    Delete(DeleteRowsRequest),
+    CreateTable(CreateTableRequest),
+    Query(QueryRequest),
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum BatchOperationResult {
+    Insert(BatchInsertResult),
+    Update(BatchUpdateResult),
+    Delete(DeleteRowsResult),
+    CreateTable(CreateTableResult),
+    Query(QueryResult),
 }
 
-// Schema Management Types
 #[derive(Debug, Deserialize, JsonSchema)]
+pub struct BatchRequest {
+    #[schemars(
+        description = "Operations to run in order in one transaction, each tagged with op: insert, update, delete, create_table, or query"
+    )]
+    pub operations: Vec<BatchOperation>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BatchResult {
+    pub success: bool,
+    pub message: String,
+    /// Results of the operations that ran, in order
+    pub results: Vec<BatchOperationResult>,
+    /// Index of the operation that failed, when the batch was rolled back
+    pub failed_operation: Option<usize>,
+    pub busy_retries: u32,
+}
+
+// Schema Management Types
+#[derive(Debug, Default, Deserialize, JsonSchema)]
 pub struct CreateTableRequest {
     #[schemars(description = "Name of the table to create")]
     pub table_name: String,
@@ -98,6 +482,14 @@ pub struct CreateTableRequest {
     #[schemars(description = "Create table only if it doesn't exist")]
     #[serde(default)]
     pub if_not_exists: bool,
+    #[schemars(
+        description = "Create a STRICT table; every column needs one of INT, INTEGER, REAL, TEXT, BLOB, or ANY"
+    )]
+    #[serde(default)]
+    pub strict: bool,
+    #[schemars(description = "Create a WITHOUT ROWID table (requires a PRIMARY KEY)")]
+    #[serde(default)]
+    pub without_rowid: bool,
 }
 
 #[derive(Debug, Serialize)]
@@ -143,1442 +535,18775 @@ pub struct DescribeTableResult {
     pub indexes: Vec<String>,
 }
 
-// Backup Types
+#[derive(Debug, Serialize)]
+pub struct ForeignKeyInfo {
+    pub id: i64,
+    pub from_column: String,
+    pub to_table: String,
+    pub to_column: Option<String>,
+}
+
 #[derive(Debug, Deserialize, JsonSchema)]
-pub struct BackupRequest {
-    #[schemars(description = "Destination path for the backup file")]
-    pub destination_path: String,
+pub struct GenerateSchemaReportRequest {
+    #[schemars(description = "Optional .md file path to also write the Markdown report to")]
+    pub output_path: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
-pub struct BackupResult {
-    pub success: bool,
-    pub message: String,
-    pub backup_path: String,
-    pub backup_size: Option<u64>,
-    pub timestamp: DateTime<Utc>,
+pub struct GenerateSchemaReportResult {
+    pub markdown: String,
+    pub table_count: usize,
+    pub output_path: Option<String>,
+}
+
+/// Output syntax for `export_er_diagram`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagramFormat {
+    #[default]
+    Mermaid,
+    Dot,
 }
 
-// Batch Operations Types
 #[derive(Debug, Deserialize, JsonSchema)]
-pub struct BatchInsertRequest {
-    #[schemars(description = "Name of the table to insert into")]
+pub struct ExportErDiagramRequest {
+    #[schemars(description = "Diagram syntax: mermaid (erDiagram) or dot (Graphviz)")]
+    #[serde(default)]
+    pub format: DiagramFormat,
+    #[schemars(
+        description = "Optional file path to also write the diagram to (.mmd for mermaid, .dot or .gv for dot)"
+    )]
+    pub output_path: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExportErDiagramResult {
+    pub diagram: String,
+    pub table_count: usize,
+    pub relationship_count: usize,
+    pub output_path: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct FindOrphansRequest {
+    #[schemars(description = "Child table whose foreign keys should be checked")]
     pub table_name: String,
-    #[schemars(description = "Column names for the insert")]
+    #[schemars(description = "Maximum orphan rows to return per relationship (default 100)")]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OrphanRelationship {
+    pub parent_table: String,
+    pub from_columns: Vec<String>,
+    pub to_columns: Vec<String>,
+    pub orphan_count: usize,
     pub columns: Vec<String>,
-    #[schemars(description = "Rows of data to insert")]
     pub rows: Vec<Vec<serde_json::Value>>,
-    #[schemars(description = "Use INSERT OR REPLACE instead of INSERT")]
-    #[serde(default)]
-    pub replace_on_conflict: bool,
 }
 
 #[derive(Debug, Serialize)]
-pub struct BatchInsertResult {
-    pub success: bool,
-    pub message: String,
-    pub rows_inserted: usize,
+pub struct FindOrphansResult {
+    pub table_name: String,
+    pub relationships: Vec<OrphanRelationship>,
+    pub total_orphans: usize,
 }
 
-// Export Types
 #[derive(Debug, Deserialize, JsonSchema)]
-pub struct ExportCsvRequest {
-    #[schemars(description = "SQL query to export")]
-    pub query: String,
-    #[schemars(description = "Output file path")]
-    pub output_path: String,
-    #[schemars(description = "Include column headers")]
-    #[serde(default = "default_true")]
-    pub include_headers: bool,
+pub struct ForeignKeyCheckRequest {
+    #[schemars(description = "Only check this table (all tables if omitted)")]
+    pub table_name: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
-pub struct ExportCsvResult {
-    pub success: bool,
-    pub message: String,
-    pub output_path: String,
-    pub rows_exported: usize,
+pub struct ForeignKeyViolation {
+    pub table: String,
+    /// None for WITHOUT ROWID tables.
+    pub rowid: Option<i64>,
+    pub parent: String,
+    pub fk_index: i64,
 }
 
-// Health Check Types
 #[derive(Debug, Serialize)]
-pub struct HealthCheckResult {
-    pub connected: bool,
-    pub database_path: Option<String>,
-    pub database_size: Option<u64>,
-    pub table_count: Option<usize>,
-    pub last_modified: Option<DateTime<Utc>>,
-    pub sqlite_version: String,
+pub struct ForeignKeyCheckResult {
+    pub violations: Vec<ForeignKeyViolation>,
+    pub total_count: usize,
 }
 
-impl SqliteHandler {
-    pub fn new() -> Self {
-        Self {
-            current_db: Arc::new(Mutex::new(None)),
-            current_path: Arc::new(Mutex::new(None)),
-        }
-    }
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct GetTableDdlRequest {
+    #[schemars(description = "Name of the table")]
+    pub table_name: String,
+}
 
-    pub async fn connect_tool(&self, req: ConnectRequest) -> Result<ConnectResult, UniSqliteError> {
-        let requested_path = PathBuf::from(&req.path);
-        let path = self.validate_db_path(&requested_path)?;
+#[derive(Debug, Serialize)]
+pub struct SchemaObjectSql {
+    pub name: String,
+    pub sql: String,
+}
 
-        let flags = if req.create_if_missing {
-            OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE
-        } else {
-            OpenFlags::SQLITE_OPEN_READ_WRITE
-        };
+#[derive(Debug, Serialize)]
+pub struct TableDdlResult {
+    pub table_name: String,
+    pub table_sql: String,
+    pub indexes: Vec<SchemaObjectSql>,
+    pub triggers: Vec<SchemaObjectSql>,
+    /// All statements in creation order, each terminated by `;`.
+    pub script: String,
+}
 
-        let conn = Connection::open_with_flags(&path, flags)?;
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CloneTableStructureRequest {
+    #[schemars(description = "Table whose structure should be copied")]
+    pub source_table: String,
+    #[schemars(description = "Name of the new, empty table")]
+    pub new_table: String,
+    #[schemars(description = "Also recreate the source table's explicit indexes on the new table")]
+    #[serde(default)]
+    pub copy_indexes: bool,
+}
 
-        // Get database size
-        let database_size = fs::metadata(&path).ok().map(|m| m.len());
+#[derive(Debug, Serialize)]
+pub struct CloneTableStructureResult {
+    pub success: bool,
+    pub message: String,
+    pub table_name: String,
+    pub indexes: Vec<String>,
+}
 
-        *self.current_db.lock().await = Some(conn);
-        *self.current_path.lock().await = Some(path.clone());
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SwapTablesRequest {
+    #[schemars(description = "First table")]
+    pub table_a: String,
+    #[schemars(description = "Second table")]
+    pub table_b: String,
+}
 
-        Ok(ConnectResult {
-            success: true,
-            path: path.display().to_string(),
-            database_size,
-        })
-    }
+#[derive(Debug, Serialize)]
+pub struct SwapTablesResult {
+    pub success: bool,
+    pub message: String,
+}
 
-    /// Validate and sanitize a database file path (prevents directory traversal)
-    fn validate_db_path(&self, requested_path: &Path) -> Result<PathBuf, UniSqliteError> {
-        let canonical_path = if requested_path.exists() {
-            requested_path.canonicalize()
-        } else {
-            let parent = requested_path
-                .parent()
-                .ok_or_else(|| UniSqliteError::InvalidPath("No parent directory".into()))?;
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ObjectExistsRequest {
+    #[schemars(description = "Name of the schema object")]
+    pub name: String,
+    #[schemars(
+        description = "Object type to match: table, view, index, or trigger (any if omitted)"
+    )]
+    #[serde(rename = "type")]
+    pub object_type: Option<String>,
+}
 
-            let canonical_parent = parent.canonicalize().map_err(|_| {
-                UniSqliteError::InvalidPath("Parent directory does not exist".into())
-            })?;
+#[derive(Debug, Serialize)]
+pub struct ObjectExistsResult {
+    pub exists: bool,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub object_type: Option<String>,
+    pub sql: Option<String>,
+}
 
-            let file_name = requested_path
-                .file_name()
-                .ok_or_else(|| UniSqliteError::InvalidPath("No filename".into()))?;
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct GetCreateSqlRequest {
+    #[schemars(description = "Name of the table, view, index, or trigger")]
+    pub name: String,
+}
 
-            Ok(canonical_parent.join(file_name))
-        }
-        .map_err(|e| UniSqliteError::InvalidPath(e.to_string()))?;
+#[derive(Debug, Serialize)]
+pub struct GetCreateSqlResult {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub object_type: String,
+    pub sql: String,
+}
 
-        // In test mode, allow temp directories
-        #[cfg(test)]
-        {
-            if canonical_path.to_string_lossy().contains("tmp") {
-                match canonical_path.extension().and_then(|e| e.to_str()) {
-                    Some("db") | Some("sqlite") | Some("sqlite3") => return Ok(canonical_path),
-                    _ => {
-                        return Err(UniSqliteError::InvalidPath(
-                            "Invalid database file extension".into(),
-                        ));
-                    }
-                }
-            }
-        }
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct GenerateInsertTemplateRequest {
+    #[schemars(description = "Name of the table to generate an INSERT for")]
+    pub table_name: String,
+}
 
-        let current_dir = std::env::current_dir()
-            .and_then(|p| p.canonicalize())
-            .map_err(|_| {
-                UniSqliteError::InvalidPath("Cannot determine current directory".into())
-            })?;
+#[derive(Debug, Serialize)]
+pub struct GenerateInsertTemplateResult {
+    pub table_name: String,
+    pub sql: String,
+    pub columns: Vec<ColumnInfo>,
+    pub placeholder_count: usize,
+}
 
-        if !canonical_path.starts_with(&current_dir) {
-            return Err(UniSqliteError::InvalidPath(
-                "Path outside allowed directory".into(),
-            ));
-        }
+// Backup Types
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct BackupRequest {
+    #[schemars(description = "Destination path for the backup file")]
+    pub destination_path: String,
+}
 
-        match canonical_path.extension().and_then(|e| e.to_str()) {
-            Some("db") | Some("sqlite") | Some("sqlite3") => Ok(canonical_path),
-            _ => Err(UniSqliteError::InvalidPath(
-                "Invalid database file extension".into(),
-            )),
-        }
-    }
+#[derive(Debug, Serialize)]
+pub struct BackupResult {
+    pub success: bool,
+    pub message: String,
+    pub backup_path: String,
+    pub backup_size: Option<u64>,
+    pub timestamp: DateTime<Utc>,
+}
 
-    /// Convert a JSON value to a rusqlite parameter.
-    fn json_to_sql_param(
-        value: &serde_json::Value,
-    ) -> Result<Box<dyn rusqlite::ToSql>, UniSqliteError> {
-        match value {
-            serde_json::Value::Null => Ok(Box::new(rusqlite::types::Null)),
-            serde_json::Value::Bool(b) => Ok(Box::new(*b)),
-            serde_json::Value::Number(n) => {
-                if let Some(i) = n.as_i64() {
-                    Ok(Box::new(i))
-                } else if let Some(f) = n.as_f64() {
-                    Ok(Box::new(f))
-                } else {
-                    Err(UniSqliteError::QueryFailed(
-                        "Invalid number parameter".into(),
-                    ))
-                }
-            }
-            serde_json::Value::String(s) => Ok(Box::new(s.clone())),
-            _ => Err(UniSqliteError::QueryFailed(
-                "Unsupported parameter type".into(),
-            )),
+// Batch Operations Types
+/// SQLite's conflict resolution algorithms, as used in `INSERT OR <strategy>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictStrategy {
+    Abort,
+    Ignore,
+    Replace,
+    Rollback,
+    Fail,
+}
+
+impl ConflictStrategy {
+    fn insert_verb(self) -> &'static str {
+        match self {
+            Self::Abort => "INSERT OR ABORT",
+            Self::Ignore => "INSERT OR IGNORE",
+            Self::Replace => "INSERT OR REPLACE",
+            Self::Rollback => "INSERT OR ROLLBACK",
+            Self::Fail => "INSERT OR FAIL",
         }
     }
+}
 
-    /// Validate SQL query - now allows more admin operations
-    fn validate_sql_query(sql: &str) -> Result<(), UniSqliteError> {
-        let sql_trim = sql.trim_start();
-        let sql_upper = sql_trim.to_ascii_uppercase();
-        let allowed = [
-            "SELECT", "INSERT", "UPDATE", "DELETE", "CREATE", "ALTER", "DROP", "PRAGMA", "EXPLAIN",
-            "ANALYZE",
-        ];
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+pub struct BatchInsertRequest {
+    #[schemars(description = "Name of the table to insert into")]
+    pub table_name: String,
+    #[schemars(description = "Column names for the insert")]
+    pub columns: Vec<String>,
+    #[schemars(description = "Rows of data to insert, positionally aligned to columns")]
+    #[serde(default)]
+    pub rows: Vec<Vec<serde_json::Value>>,
+    #[schemars(description = "Use INSERT OR REPLACE instead of INSERT")]
+    #[serde(default)]
+    pub replace_on_conflict: bool,
+    #[schemars(
+        description = "Conflict resolution: abort, ignore, replace, rollback, or fail (INSERT OR <strategy>). ignore makes bulk loads idempotent"
+    )]
+    pub on_conflict: Option<ConflictStrategy>,
+    #[schemars(
+        description = "Also report total_changes: every row changed including by triggers and REPLACE deletions"
+    )]
+    #[serde(default)]
+    pub report_total_changes: bool,
+    #[schemars(
+        description = "Records keyed by column name; omitted columns take their DEFAULT (or NULL)"
+    )]
+    #[serde(default)]
+    pub records: Vec<serde_json::Map<String, serde_json::Value>>,
+    #[schemars(
+        description = "Roll back instead of committing, reporting the rows that would be inserted"
+    )]
+    #[serde(default)]
+    pub dry_run: bool,
+}
 
-        // Ensure exactly one statement (no extra semicolons).
-        if sql_trim.matches(';').count() > 1 {
-            return Err(UniSqliteError::QueryFailed(
-                "Multiple statements are not allowed".into(),
-            ));
-        }
+#[derive(Debug, Serialize)]
+pub struct BatchInsertResult {
+    pub success: bool,
+    pub message: String,
+    pub rows_inserted: usize,
+    /// Rows skipped by `on_conflict: ignore`
+    pub rows_ignored: usize,
+    /// All rows changed, including by triggers (`report_total_changes`)
+    pub total_changes: Option<u64>,
+    pub busy_retries: u32,
+    pub dry_run: bool,
+}
 
-        for cmd in &allowed {
-            if sql_upper.starts_with(cmd) {
-                return Ok(());
-            }
-        }
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct BatchUpdateRequest {
+    #[schemars(description = "Name of the table to update")]
+    pub table_name: String,
+    #[schemars(description = "Columns to assign (the SET clause)")]
+    pub set_columns: Vec<String>,
+    #[schemars(description = "Key columns identifying each row (the WHERE clause)")]
+    pub where_columns: Vec<String>,
+    #[schemars(
+        description = "Rows of values: set_columns values followed by where_columns values"
+    )]
+    pub rows: Vec<Vec<serde_json::Value>>,
+}
 
-        Err(UniSqliteError::QueryFailed(format!(
-            "Only {} statements are allowed",
-            allowed.join(", ")
-        )))
-    }
+#[derive(Debug, Serialize)]
+pub struct BatchUpdateResult {
+    pub success: bool,
+    pub message: String,
+    pub rows_matched: usize,
+    pub rows_changed: usize,
+}
 
-    pub async fn query_tool(&self, req: QueryRequest) -> Result<QueryResult, UniSqliteError> {
-        Self::validate_sql_query(&req.sql)?;
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+pub struct DeleteRowsRequest {
+    #[schemars(description = "Name of the table to delete from")]
+    pub table_name: String,
+    #[schemars(description = "WHERE clause without the WHERE keyword (e.g., 'id = ?')")]
+    #[serde(default)]
+    pub where_clause: String,
+    #[schemars(description = "Parameters for the WHERE clause placeholders")]
+    #[serde(default)]
+    pub parameters: Vec<serde_json::Value>,
+    #[schemars(description = "Allow deleting every row when where_clause is empty")]
+    #[serde(default)]
+    pub allow_full_table: bool,
+    #[schemars(description = "Roll back the delete, reporting the rows that would be deleted")]
+    #[serde(default)]
+    pub dry_run: bool,
+    #[schemars(
+        description = "Also report total_changes: every row changed including by triggers and ON DELETE cascades"
+    )]
+    #[serde(default)]
+    pub report_total_changes: bool,
+}
 
-        let guard = self.current_db.lock().await;
-        let conn = guard
-            .as_ref()
-            .ok_or_else(|| UniSqliteError::Other("No database connected".into()))?;
+#[derive(Debug, Serialize)]
+pub struct DeleteRowsResult {
+    pub success: bool,
+    pub message: String,
+    pub rows_deleted: usize,
+    pub dry_run: bool,
+    /// All rows changed, including by triggers and cascades (`report_total_changes`)
+    pub total_changes: Option<u64>,
+}
 
-        // Convert JSON parameters to rusqlite parameters.
-        let params: Vec<Box<dyn rusqlite::ToSql>> = req
-            .parameters
-            .iter()
-            .map(Self::json_to_sql_param)
-            .collect::<Result<_, _>>()?;
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ResetSequenceRequest {
+    #[schemars(description = "AUTOINCREMENT table whose counter to read or set")]
+    pub table_name: String,
+    #[schemars(
+        description = "New sqlite_sequence value; the next insert gets value + 1 unless larger rowids exist. Omit to only read"
+    )]
+    pub value: Option<i64>,
+}
 
-        let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| &**p).collect();
+#[derive(Debug, Serialize)]
+pub struct ResetSequenceResult {
+    pub table_name: String,
+    pub previous_value: Option<i64>,
+    pub value: Option<i64>,
+    pub max_rowid: Option<i64>,
+    /// Rowid the next insert without an explicit id will get
+    pub next_rowid: i64,
+    pub warning: Option<String>,
+}
 
-        // Determine query type.
-        let sql_upper = req.sql.trim_start().to_ascii_uppercase();
-        if sql_upper.starts_with("SELECT")
-            || sql_upper.starts_with("PRAGMA")
-            || sql_upper.starts_with("EXPLAIN")
-        {
-            // SELECT-like queries – return rows.
-            let mut stmt = conn.prepare(&req.sql)?;
-            let column_count = stmt.column_count();
-            let column_names: Vec<String> =
-                stmt.column_names().iter().map(|s| s.to_string()).collect();
-
-            let rows = stmt.query_map(&param_refs[..], |row| {
-                let mut values = Vec::new();
-                for i in 0..column_count {
-                    let v = row.get_ref(i)?;
-                    let json = match v {
-                        rusqlite::types::ValueRef::Null => Value::Null,
-                        rusqlite::types::ValueRef::Integer(i) => Value::Number(i.into()),
-                        rusqlite::types::ValueRef::Real(f) => Value::Number(
-                            serde_json::Number::from_f64(f)
-                                .unwrap_or_else(|| serde_json::Number::from(0)),
-                        ),
-                        rusqlite::types::ValueRef::Text(t) => {
-                            Value::String(String::from_utf8_lossy(t).into_owned())
-                        }
-                        rusqlite::types::ValueRef::Blob(b) => Value::String(hex::encode(b)),
-                    };
-                    values.push(json);
-                }
-                Ok(values)
-            })?;
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct TruncateTableRequest {
+    #[schemars(description = "Name of the table to empty")]
+    pub table_name: String,
+    #[schemars(description = "Also reset the AUTOINCREMENT counter in sqlite_sequence")]
+    #[serde(default)]
+    pub reset_autoincrement: bool,
+    #[schemars(description = "Must be true to confirm deleting every row")]
+    #[serde(default)]
+    pub confirm: bool,
+}
 
-            let mut data = Vec::new();
-            for row in rows {
-                data.push(row?);
-            }
+#[derive(Debug, Serialize)]
+pub struct TruncateTableResult {
+    pub success: bool,
+    pub message: String,
+    pub rows_deleted: usize,
+    pub sequence_reset: bool,
+}
 
-            Ok(QueryResult {
-                message: format!("Query executed successfully, returned {} rows", data.len()),
-                rows_affected: Some(data.len()),
-                data: Some(data),
-                columns: Some(column_names),
-            })
-        } else {
-            // Non‑SELECT – execute and report affected rows.
-            let rows_affected = conn.execute(&req.sql, &param_refs[..])?;
-            Ok(QueryResult {
-                message: "Query executed successfully".into(),
-                rows_affected: Some(rows_affected),
-                data: None,
-                columns: None,
-            })
-        }
-    }
+// Export Types
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+pub struct ExportCsvRequest {
+    #[schemars(description = "SQL query to export (or use table_name instead)")]
+    #[serde(default)]
+    pub query: String,
+    #[schemars(description = "Table to export instead of a query")]
+    pub table_name: Option<String>,
+    #[schemars(description = "Columns to export from table_name, in order (default: all)")]
+    pub columns: Option<Vec<String>>,
+    #[schemars(description = "WHERE clause (without WHERE) filtering table_name rows")]
+    pub where_clause: Option<String>,
+    #[schemars(description = "ORDER BY clause (without ORDER BY) for table_name rows")]
+    pub order_by: Option<String>,
+    #[schemars(description = "Output file path (.csv)")]
+    pub output_path: String,
+    #[schemars(description = "Include column headers")]
+    #[serde(default = "default_true")]
+    pub include_headers: bool,
+    #[schemars(
+        description = "How to write BLOB values: hex (default), base64, skip, or external_files"
+    )]
+    #[serde(default)]
+    pub blob_handling: BlobHandling,
+    #[schemars(description = "Gzip the output and append .gz to the path (default: false)")]
+    #[serde(default)]
+    pub compress: bool,
+}
 
-    pub async fn transaction_tool(
-        &self,
-        req: TransactionRequest,
-    ) -> Result<TransactionResult, UniSqliteError> {
-        let guard = self.current_db.lock().await;
-        let conn = guard
-            .as_ref()
-            .ok_or_else(|| UniSqliteError::Other("No database connected".into()))?;
+/// How BLOB cells are written to CSV.
+///
+/// CSV has no binary type, so every strategy is lossy on a naive re-import: `hex` and
+/// `base64` come back as TEXT unless decoded (e.g. with `unhex()`), `skip` drops the data,
+/// and `external_files` cells hold a path relative to the CSV that must be read back
+/// separately.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BlobHandling {
+    #[default]
+    Hex,
+    Base64,
+    Skip,
+    ExternalFiles,
+}
 
-        let tx = conn.unchecked_transaction()?;
-        let mut results = Vec::new();
-        let mut total_rows_affected = 0;
-        let mut success = true;
+#[derive(Debug, Serialize)]
+pub struct ExportCsvResult {
+    pub success: bool,
+    pub message: String,
+    pub output_path: String,
+    pub rows_exported: usize,
+    pub blob_directory: Option<String>,
+    /// Bytes of CSV produced before any compression
+    pub uncompressed_bytes: u64,
+    /// Size of the gzip file on disk, when `compress` was set
+    pub compressed_bytes: Option<u64>,
+}
 
-        for query_req in req.queries {
-            match self.execute_query_in_transaction(&tx, query_req) {
-                Ok(result) => {
-                    if let Some(rows) = result.rows_affected {
-                        total_rows_affected += rows;
-                    }
-                    results.push(result);
-                }
-                Err(e) => {
-                    success = false;
-                    results.push(QueryResult {
-                        message: format!("Error: {e}"),
-                        rows_affected: None,
-                        data: None,
-                        columns: None,
-                    });
-                    if req.rollback_on_error {
-                        break;
-                    }
-                }
-            }
-        }
+/// File writer for exports, gzip-compressed when requested.
+enum ExportSink {
+    Plain(std::fs::File),
+    Gzip(GzEncoder<std::fs::File>),
+}
 
-        if success || !req.rollback_on_error {
-            tx.commit()?;
-            Ok(TransactionResult {
-                success,
-                message: if success {
-                    "Transaction completed successfully".into()
-                } else {
-                    "Transaction completed with errors".into()
-                },
-                results,
-                total_rows_affected,
-            })
+impl ExportSink {
+    fn create(path: &Path, compress: bool) -> std::io::Result<Self> {
+        let file = std::fs::File::create(path)?;
+        Ok(if compress {
+            Self::Gzip(GzEncoder::new(file, Compression::default()))
         } else {
-            // Transaction will be rolled back when dropped
-            Ok(TransactionResult {
-                success: false,
-                message: "Transaction rolled back due to errors".into(),
-                results,
-                total_rows_affected: 0,
-            })
-        }
+            Self::Plain(file)
+        })
     }
 
-    fn execute_query_in_transaction(
-        &self,
-        tx: &Transaction<'_>,
-        req: QueryRequest,
-    ) -> Result<QueryResult, UniSqliteError> {
-        Self::validate_sql_query(&req.sql)?;
+    /// Flush everything, writing the gzip trailer if compressing.
+    fn finish(self) -> std::io::Result<()> {
+        match self {
+            Self::Plain(mut file) => file.flush(),
+            Self::Gzip(encoder) => encoder.finish()?.flush(),
+        }
+    }
+}
 
-        let params: Vec<Box<dyn rusqlite::ToSql>> = req
-            .parameters
-            .iter()
-            .map(Self::json_to_sql_param)
-            .collect::<Result<_, _>>()?;
+impl Write for ExportSink {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Self::Plain(file) => file.write(buf),
+            Self::Gzip(encoder) => encoder.write(buf),
+        }
+    }
 
-        let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| &**p).collect();
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Self::Plain(file) => file.flush(),
+            Self::Gzip(encoder) => encoder.flush(),
+        }
+    }
+}
 
-        let sql_upper = req.sql.trim_start().to_ascii_uppercase();
-        if sql_upper.starts_with("SELECT")
-            || sql_upper.starts_with("PRAGMA")
-            || sql_upper.starts_with("EXPLAIN")
-        {
-            let mut stmt = tx.prepare(&req.sql)?;
-            let column_count = stmt.column_count();
-            let column_names: Vec<String> =
-                stmt.column_names().iter().map(|s| s.to_string()).collect();
-
-            let rows = stmt.query_map(&param_refs[..], |row| {
-                let mut values = Vec::new();
-                for i in 0..column_count {
-                    let v = row.get_ref(i)?;
-                    let json = match v {
-                        rusqlite::types::ValueRef::Null => Value::Null,
-                        rusqlite::types::ValueRef::Integer(i) => Value::Number(i.into()),
-                        rusqlite::types::ValueRef::Real(f) => Value::Number(
-                            serde_json::Number::from_f64(f)
-                                .unwrap_or_else(|| serde_json::Number::from(0)),
-                        ),
-                        rusqlite::types::ValueRef::Text(t) => {
-                            Value::String(String::from_utf8_lossy(t).into_owned())
-                        }
-                        rusqlite::types::ValueRef::Blob(b) => Value::String(hex::encode(b)),
-                    };
-                    values.push(json);
-                }
-                Ok(values)
-            })?;
+/// Writer adapter that counts bytes passing through it.
+struct CountingWriter<W> {
+    inner: W,
+    bytes: u64,
+}
 
-            let mut data = Vec::new();
-            for row in rows {
-                data.push(row?);
-            }
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.bytes += written as u64;
+        Ok(written)
+    }
 
-            Ok(QueryResult {
-                message: format!("Query executed successfully, returned {} rows", data.len()),
-                rows_affected: Some(data.len()),
-                data: Some(data),
-                columns: Some(column_names),
-            })
-        } else {
-            let rows_affected = tx.execute(&req.sql, &param_refs[..])?;
-            Ok(QueryResult {
-                message: "Query executed successfully".into(),
-                rows_affected: Some(rows_affected),
-                data: None,
-                columns: None,
-            })
-        }
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
     }
+}
 
-    pub async fn create_table_tool(
-        &self,
-        req: CreateTableRequest,
-    ) -> Result<CreateTableResult, UniSqliteError> {
-        let guard = self.current_db.lock().await;
-        let conn = guard
-            .as_ref()
-            .ok_or_else(|| UniSqliteError::Other("No database connected".into()))?;
+#[cfg(feature = "xlsx")]
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+pub struct ExportXlsxRequest {
+    #[schemars(description = "SQL query to export (or use table_name instead)")]
+    #[serde(default)]
+    pub query: String,
+    #[schemars(description = "Table to export instead of a query")]
+    pub table_name: Option<String>,
+    #[schemars(description = "Columns to export from table_name, in order (default: all)")]
+    pub columns: Option<Vec<String>>,
+    #[schemars(description = "WHERE clause (without WHERE) filtering table_name rows")]
+    pub where_clause: Option<String>,
+    #[schemars(description = "ORDER BY clause (without ORDER BY) for table_name rows")]
+    pub order_by: Option<String>,
+    #[schemars(description = "Output file path (must end in .xlsx)")]
+    pub output_path: String,
+    #[schemars(description = "Worksheet name (default: Sheet1)")]
+    pub sheet_name: Option<String>,
+    #[schemars(description = "How to write BLOB values: hex (default), base64, or skip")]
+    #[serde(default)]
+    pub blob_handling: BlobHandling,
+}
 
-        let if_not_exists = if req.if_not_exists {
-            "IF NOT EXISTS "
-        } else {
-            ""
-        };
-        let sql = format!(
-            "CREATE TABLE {}{}({});",
-            if_not_exists, req.table_name, req.columns
-        );
+#[cfg(feature = "xlsx")]
+#[derive(Debug, Serialize)]
+pub struct ExportXlsxResult {
+    pub success: bool,
+    pub message: String,
+    pub output_path: String,
+    pub rows_exported: usize,
+}
 
-        conn.execute(&sql, [])?;
+// UUID Types
+#[derive(Debug, Serialize)]
+pub struct NewUuidResult {
+    pub uuid: String,
+}
 
-        Ok(CreateTableResult {
-            success: true,
-            message: format!("Table '{}' created successfully", req.table_name),
-            table_name: req.table_name,
-        })
-    }
+// Vacuum Types
+#[derive(Debug, Serialize)]
+pub struct VacuumResult {
+    pub success: bool,
+    pub message: String,
+    /// `page_count * page_size` before and after the VACUUM
+    pub bytes_before: i64,
+    pub bytes_after: i64,
+    pub bytes_reclaimed: i64,
+    pub duration_ms: u64,
+}
 
-    pub async fn list_tables_tool(&self) -> Result<ListTablesResult, UniSqliteError> {
-        let guard = self.current_db.lock().await;
-        let conn = guard
-            .as_ref()
-            .ok_or_else(|| UniSqliteError::Other("No database connected".into()))?;
+/// Fragmentation ratio above which `vacuum_check` recommends a VACUUM
+const DEFAULT_VACUUM_THRESHOLD: f64 = 0.25;
 
-        let mut stmt = conn.prepare(
-            "SELECT name, sql FROM sqlite_master WHERE type='table' AND name NOT LIKE 'sqlite_%' ORDER BY name"
-        )?;
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+pub struct VacuumCheckRequest {
+    #[schemars(
+        description = "Fragmentation ratio (freelist_count / page_count) above which a VACUUM is recommended (default: 0.25)"
+    )]
+    pub threshold: Option<f64>,
+}
 
-        let rows = stmt.query_map([], |row| {
-            let name: String = row.get(0)?;
-            let sql: Option<String> = row.get(1)?;
-            Ok((name, sql))
-        })?;
+#[derive(Debug, Serialize)]
+pub struct VacuumCheckResult {
+    pub page_count: i64,
+    pub freelist_count: i64,
+    pub page_size: i64,
+    /// `freelist_count / page_count`
+    pub fragmentation_ratio: f64,
+    /// Bytes a VACUUM would likely reclaim (`freelist_count * page_size`)
+    pub reclaimable_bytes: i64,
+    pub auto_vacuum_mode: String,
+    pub threshold: f64,
+    pub vacuum_recommended: bool,
+    pub recommendation: String,
+}
 
-        let mut tables = Vec::new();
-        for row in rows {
-            let (name, sql) = row?;
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct VacuumIntoRequest {
+    #[schemars(description = "Destination path for the compacted database copy")]
+    pub destination_path: String,
+}
 
-            // Get row count for each table
-            let count_sql = format!("SELECT COUNT(*) FROM [{name}]");
-            let row_count: Option<i64> = conn.query_row(&count_sql, [], |row| row.get(0)).ok();
+#[derive(Debug, Serialize)]
+pub struct VacuumIntoResult {
+    pub success: bool,
+    pub message: String,
+    pub destination_path: String,
+    pub destination_size: Option<u64>,
+}
 
-            tables.push(TableInfo {
-                name,
-                sql,
-                row_count,
-            });
-        }
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SetAutoVacuumRequest {
+    #[schemars(description = "Auto-vacuum mode: NONE, FULL, or INCREMENTAL")]
+    pub mode: String,
+    #[schemars(
+        description = "Run VACUUM afterwards so the new mode takes effect (required when switching from NONE)"
+    )]
+    #[serde(default = "default_true")]
+    pub vacuum: bool,
+}
 
-        let total_count = tables.len();
+#[derive(Debug, Serialize)]
+pub struct SetAutoVacuumResult {
+    pub success: bool,
+    pub message: String,
+    pub previous_mode: String,
+    pub mode: String,
+    pub vacuumed: bool,
+}
 
-        Ok(ListTablesResult {
-            tables,
-            total_count,
-        })
-    }
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct IncrementalVacuumRequest {
+    #[schemars(description = "Number of freelist pages to reclaim (omit to reclaim all)")]
+    pub pages: Option<u32>,
+}
 
-    pub async fn describe_table_tool(
-        &self,
-        req: DescribeTableRequest,
-    ) -> Result<DescribeTableResult, UniSqliteError> {
-        let guard = self.current_db.lock().await;
-        let conn = guard
-            .as_ref()
-            .ok_or_else(|| UniSqliteError::Other("No database connected".into()))?;
+#[derive(Debug, Serialize)]
+pub struct IncrementalVacuumResult {
+    pub success: bool,
+    pub message: String,
+    pub auto_vacuum_mode: String,
+    pub freelist_pages_before: i64,
+    pub freelist_pages_after: i64,
+}
 
-        // Get column information
-        let mut stmt = conn.prepare(&format!("PRAGMA table_info([{}])", req.table_name))?;
-        let rows = stmt.query_map([], |row| {
-            Ok(ColumnInfo {
-                name: row.get(1)?,
-                data_type: row.get(2)?,
-                not_null: row.get::<_, i32>(3)? != 0,
-                default_value: row.get(4)?,
-                primary_key: row.get::<_, i32>(5)? != 0,
-            })
-        })?;
+// Performance Tuning Types
+#[derive(Debug, Default, Clone, Deserialize, JsonSchema)]
+pub struct PerformanceSettings {
+    #[schemars(description = "Page cache size (positive = pages, negative = KiB)")]
+    pub cache_size: Option<i64>,
+    #[schemars(description = "Maximum bytes of the database to memory-map (0 disables mmap)")]
+    pub mmap_size: Option<i64>,
+    #[schemars(description = "Temporary storage location: DEFAULT, FILE, or MEMORY")]
+    pub temp_store: Option<String>,
+    #[schemars(description = "Synchronous mode: OFF, NORMAL, FULL, or EXTRA")]
+    pub synchronous: Option<String>,
+}
 
-        let mut columns = Vec::new();
-        for row in rows {
-            columns.push(row?);
+#[derive(Debug, Serialize)]
+pub struct PerformanceSettingsResult {
+    pub cache_size: i64,
+    pub mmap_size: i64,
+    pub temp_store: String,
+    pub synchronous: String,
+}
+
+// SQL Trace Types
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct SqlTraceState {
+    pub enabled: bool,
+    pub verbose: bool,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct EnableSqlTraceRequest {
+    #[schemars(description = "Log statements with bound parameter values expanded inline")]
+    #[serde(default)]
+    pub verbose: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SqlTraceResult {
+    pub success: bool,
+    pub message: String,
+    pub state: SqlTraceState,
+}
+
+// Statement Cache Types
+const DEFAULT_STATEMENT_CACHE_CAPACITY: usize = 16;
+
+#[derive(Debug, Default)]
+pub struct StatementCacheState {
+    pub enabled: bool,
+    pub capacity: usize,
+    // Mirrors rusqlite's LRU so hits can be counted (most recently used at the back)
+    recent: VecDeque<String>,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl StatementCacheState {
+    fn new(enabled: bool, capacity: usize) -> Self {
+        Self {
+            enabled,
+            capacity,
+            ..Default::default()
         }
+    }
 
-        // Get index information
-        let mut index_stmt = conn.prepare(&format!("PRAGMA index_list([{}])", req.table_name))?;
-        let index_rows = index_stmt.query_map([], |row| {
-            let name: String = row.get(1)?;
-            Ok(name)
-        })?;
+    /// Record a `prepare_cached` lookup for `sql`.
+    fn record(&mut self, sql: &str) {
+        if !self.enabled || self.capacity == 0 {
+            return;
+        }
 
-        let mut indexes = Vec::new();
-        for row in index_rows {
-            indexes.push(row?);
+        let key = sql.trim();
+        if let Some(pos) = self.recent.iter().position(|s| s == key) {
+            self.hits += 1;
+            let entry = self.recent.remove(pos).unwrap_or_default();
+            self.recent.push_back(entry);
+        } else {
+            self.misses += 1;
+            self.recent.push_back(key.to_string());
+            if self.recent.len() > self.capacity {
+                self.recent.pop_front();
+            }
         }
+    }
 
-        Ok(DescribeTableResult {
-            table_name: req.table_name,
-            columns,
-            indexes,
-        })
+    fn stats(&self) -> StatementCacheStats {
+        StatementCacheStats {
+            enabled: self.enabled,
+            capacity: self.capacity,
+            cached_statements: self.recent.len(),
+            hits: self.hits,
+            misses: self.misses,
+        }
     }
+}
 
-    pub async fn backup_tool(&self, req: BackupRequest) -> Result<BackupResult, UniSqliteError> {
-        let guard = self.current_db.lock().await;
-        let conn = guard
-            .as_ref()
-            .ok_or_else(|| UniSqliteError::Other("No database connected".into()))?;
+#[derive(Debug, Serialize)]
+pub struct StatementCacheStats {
+    pub enabled: bool,
+    pub capacity: usize,
+    pub cached_statements: usize,
+    pub hits: u64,
+    pub misses: u64,
+}
 
-        let backup_path = PathBuf::from(&req.destination_path);
-        let validated_path = self.validate_db_path(&backup_path)?;
+// Import Types
+/// A UTF-8 CSV byte stream, the encoding it was decoded from, and any lossy-decode warning
+type CsvSource = (
+    Box<dyn std::io::Read>,
+    &'static encoding_rs::Encoding,
+    Option<String>,
+);
 
-        // Use SQLite's backup API
-        let mut backup_conn = Connection::open(&validated_path)?;
-        let backup = rusqlite::backup::Backup::new(conn, &mut backup_conn)?;
-        backup.run_to_completion(5, std::time::Duration::from_millis(250), None)?;
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ImportCsvRequest {
+    #[schemars(description = "Name of the existing table to import into")]
+    pub table_name: String,
+    #[schemars(description = "Path of the CSV file to read")]
+    pub input_path: String,
+    #[schemars(
+        description = "First row holds column names; otherwise fields map to table columns in order"
+    )]
+    #[serde(default = "default_true")]
+    pub has_headers: bool,
+    #[schemars(
+        description = "Write rows that fail to import (plus an error column) here and keep going instead of aborting"
+    )]
+    pub reject_path: Option<String>,
+    #[schemars(
+        description = "Source encoding label, e.g. 'latin1', 'windows-1252', 'utf-16le' (default: detect a BOM, else UTF-8)"
+    )]
+    pub encoding: Option<String>,
+}
 
-        let backup_size = fs::metadata(&validated_path).ok().map(|m| m.len());
+#[derive(Debug, Serialize)]
+pub struct ImportCsvResult {
+    pub success: bool,
+    pub message: String,
+    pub rows_imported: usize,
+    pub rows_rejected: usize,
+    /// Encoding the file was decoded from
+    pub encoding: String,
+    /// Set when undecodable bytes were replaced with U+FFFD
+    pub warning: Option<String>,
+}
 
-        Ok(BackupResult {
-            success: true,
-            message: "Backup completed successfully".into(),
-            backup_path: validated_path.display().to_string(),
-            backup_size,
-            timestamp: Utc::now(),
-        })
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct PreviewCsvRequest {
+    #[schemars(description = "Path of the CSV file to read")]
+    pub input_path: String,
+    #[schemars(description = "How many data rows to read (default: 20)")]
+    pub rows: Option<usize>,
+    #[schemars(description = "First row holds column names (default: true)")]
+    #[serde(default = "default_true")]
+    pub has_headers: bool,
+    #[schemars(description = "Source encoding label, as for import_csv (default: BOM or UTF-8)")]
+    pub encoding: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PreviewCsvResult {
+    pub columns: Vec<CsvColumnPreview>,
+    pub sample: Vec<Vec<String>>,
+    pub rows_read: usize,
+    pub encoding: String,
+    pub warning: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CsvColumnPreview {
+    pub name: String,
+    pub inferred_type: CsvFieldType,
+    /// Sampled rows where this field was empty or missing
+    pub empty_count: usize,
+}
+
+/// What a CSV column's values look like, ordered from narrowest to widest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CsvFieldType {
+    Empty,
+    Int,
+    Float,
+    Text,
+}
+
+impl CsvFieldType {
+    /// Classify a field the way the importer would store it in an untyped column
+    fn infer(field: &str) -> Self {
+        use rusqlite::types::Value as SqlValue;
+
+        match ColumnAffinity::Untyped.coerce_csv_field(field) {
+            SqlValue::Null => Self::Empty,
+            SqlValue::Integer(_) => Self::Int,
+            SqlValue::Real(_) => Self::Float,
+            _ => Self::Text,
+        }
     }
 
-    pub async fn batch_insert_tool(
-        &self,
-        req: BatchInsertRequest,
-    ) -> Result<BatchInsertResult, UniSqliteError> {
-        let guard = self.current_db.lock().await;
-        let conn = guard
-            .as_ref()
-            .ok_or_else(|| UniSqliteError::Other("No database connected".into()))?;
+    /// Column type for a generated table; all-empty columns are left untyped
+    fn declared_type(self) -> Option<&'static str> {
+        match self {
+            Self::Empty => None,
+            Self::Int => Some("INTEGER"),
+            Self::Float => Some("REAL"),
+            Self::Text => Some("TEXT"),
+        }
+    }
+}
 
-        let tx = conn.unchecked_transaction()?;
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CreateTableFromCsvRequest {
+    #[schemars(description = "Name of the table to create")]
+    pub table_name: String,
+    #[schemars(description = "Path of the CSV file to read")]
+    pub input_path: String,
+    #[schemars(description = "How many data rows to scan when inferring types (default: 1000)")]
+    pub sample_rows: Option<usize>,
+    #[schemars(description = "Load the file into the new table (default: true)")]
+    #[serde(default = "default_true")]
+    pub import: bool,
+    #[schemars(description = "First row holds column names (default: true)")]
+    #[serde(default = "default_true")]
+    pub has_headers: bool,
+    #[schemars(description = "Source encoding label, as for import_csv (default: BOM or UTF-8)")]
+    pub encoding: Option<String>,
+}
 
-        let placeholders = vec!["?"; req.columns.len()].join(", ");
-        let insert_type = if req.replace_on_conflict {
-            "INSERT OR REPLACE"
-        } else {
-            "INSERT"
-        };
-        let sql = format!(
-            "{} INTO [{}] ({}) VALUES ({})",
-            insert_type,
-            req.table_name,
-            req.columns
-                .iter()
-                .map(|c| format!("[{c}]"))
-                .collect::<Vec<_>>()
-                .join(", "),
-            placeholders
-        );
+#[derive(Debug, Serialize)]
+pub struct CreateTableFromCsvResult {
+    pub success: bool,
+    pub message: String,
+    pub ddl: String,
+    pub rows_imported: usize,
+    pub warning: Option<String>,
+}
 
-        let mut rows_inserted = 0;
+/// The first rows of a CSV with per-column types widened across them
+struct CsvSample {
+    headers: Vec<String>,
+    types: Vec<CsvFieldType>,
+    empty_counts: Vec<usize>,
+    rows: Vec<Vec<String>>,
+    encoding: &'static encoding_rs::Encoding,
+    warning: Option<String>,
+}
 
-        {
-            let mut stmt = tx.prepare(&sql)?;
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct DetectEncodingRequest {
+    #[schemars(description = "Path of the text file (e.g. a CSV) to inspect")]
+    pub path: String,
+    #[schemars(description = "How many leading bytes to examine (default: 65536)")]
+    pub sample_bytes: Option<usize>,
+}
 
-            for row in req.rows {
-                if row.len() != req.columns.len() {
-                    return Err(UniSqliteError::QueryFailed(
-                        "Row data length doesn't match column count".into(),
-                    ));
-                }
+#[derive(Debug, Serialize)]
+pub struct DetectEncodingResult {
+    /// Encoding name, usable as import_csv's `encoding`
+    pub encoding: String,
+    pub has_bom: bool,
+    /// How the guess was made: bom, valid_utf8, or heuristic
+    pub basis: String,
+}
 
-                let params: Vec<Box<dyn rusqlite::ToSql>> = row
-                    .iter()
-                    .map(Self::json_to_sql_param)
-                    .collect::<Result<_, _>>()?;
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ImportJsonRequest {
+    #[schemars(description = "Name of the existing table to import into")]
+    pub table_name: String,
+    #[schemars(description = "Path of a JSON file containing an array of objects")]
+    pub input_path: String,
+    #[schemars(
+        description = "How to treat columns missing from an object: default (use column DEFAULT), null, or error"
+    )]
+    #[serde(default)]
+    pub missing_keys: MissingKeyHandling,
+    #[schemars(description = "Ignore object keys that don't match a table column")]
+    #[serde(default)]
+    pub ignore_unknown_keys: bool,
+}
 
-                let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| &**p).collect();
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MissingKeyHandling {
+    #[default]
+    Default,
+    Null,
+    Error,
+}
 
-                stmt.execute(&param_refs[..])?;
-                rows_inserted += 1;
-            }
+#[derive(Debug, Serialize)]
+pub struct ImportJsonResult {
+    pub success: bool,
+    pub message: String,
+    pub rows_imported: usize,
+}
+
+/// SQLite column affinity, derived from a declared type using SQLite's own rules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ColumnAffinity {
+    Integer,
+    Text,
+    Blob,
+    Real,
+    Numeric,
+    /// No declared type at all
+    Untyped,
+}
+
+impl ColumnAffinity {
+    fn from_declared_type(declared: &str) -> Self {
+        let upper = declared.to_ascii_uppercase();
+        if upper.trim().is_empty() {
+            Self::Untyped
+        } else if upper.contains("INT") {
+            Self::Integer
+        } else if upper.contains("CHAR") || upper.contains("CLOB") || upper.contains("TEXT") {
+            Self::Text
+        } else if upper.contains("BLOB") {
+            Self::Blob
+        } else if upper.contains("REAL") || upper.contains("FLOA") || upper.contains("DOUB") {
+            Self::Real
+        } else {
+            Self::Numeric
         }
+    }
 
-        tx.commit()?;
+    /// Coerce a CSV field to the SQL value this affinity expects. Empty fields become NULL.
+    fn coerce_csv_field(self, field: &str) -> rusqlite::types::Value {
+        use rusqlite::types::Value as SqlValue;
 
-        Ok(BatchInsertResult {
-            success: true,
-            message: format!("Successfully inserted {rows_inserted} rows"),
-            rows_inserted,
-        })
+        if field.is_empty() {
+            return SqlValue::Null;
+        }
+
+        let as_integer = || {
+            field
+                .parse::<i64>()
+                .ok()
+                .map(SqlValue::Integer)
+                .or_else(|| {
+                    field
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|f| f.fract() == 0.0 && f.abs() < i64::MAX as f64)
+                        .map(|f| SqlValue::Integer(f as i64))
+                })
+        };
+        let as_real = || field.parse::<f64>().ok().map(SqlValue::Real);
+        let as_text = || SqlValue::Text(field.to_string());
+
+        match self {
+            Self::Text | Self::Blob => as_text(),
+            Self::Integer => as_integer().unwrap_or_else(as_text),
+            Self::Real => as_real().unwrap_or_else(as_text),
+            Self::Numeric | Self::Untyped => as_integer().or_else(as_real).unwrap_or_else(as_text),
+        }
     }
+}
 
-    pub async fn export_csv_tool(
-        &self,
-        req: ExportCsvRequest,
-    ) -> Result<ExportCsvResult, UniSqliteError> {
-        let guard = self.current_db.lock().await;
-        let conn = guard
-            .as_ref()
-            .ok_or_else(|| UniSqliteError::Other("No database connected".into()))?;
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct OptimizeRequest {
+    #[schemars(
+        description = "Set PRAGMA analysis_limit first (approximate rows examined per index; 0 = no limit)"
+    )]
+    pub analysis_limit: Option<u32>,
+    #[schemars(
+        description = "Consider every table, not only those queried on this connection (SQLite 3.46+)"
+    )]
+    #[serde(default)]
+    pub check_all_tables: bool,
+}
 
-        Self::validate_sql_query(&req.query)?;
+#[derive(Debug, Serialize)]
+pub struct OptimizeResult {
+    pub success: bool,
+    pub message: String,
+    pub operations: Vec<String>,
+    pub analysis_limit: i64,
+}
 
-        let output_path = PathBuf::from(&req.output_path);
+#[derive(Debug, Serialize)]
+pub struct CheckpointResult {
+    pub success: bool,
+    pub message: String,
+    pub journal_mode: String,
+    pub log_frames: i64,
+    pub checkpointed_frames: i64,
+    pub wal_size: Option<u64>,
+}
 
-        // Create CSV writer
-        let file = std::fs::File::create(&output_path)?;
-        let mut wtr = csv::Writer::from_writer(file);
+// Size Breakdown Types
+#[derive(Debug, Serialize)]
+pub struct ObjectSizeInfo {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub object_type: String,
+    pub pages: i64,
+    pub bytes: i64,
+    pub percent: f64,
+}
 
-        let mut stmt = conn.prepare(&req.query)?;
-        let column_names: Vec<String> = stmt.column_names().iter().map(|s| s.to_string()).collect();
+#[derive(Debug, Serialize)]
+pub struct DatabaseSizeBreakdownResult {
+    pub objects: Vec<ObjectSizeInfo>,
+    pub total_pages: i64,
+    pub total_bytes: i64,
+}
 
-        // Write headers if requested
-        if req.include_headers {
-            wtr.write_record(&column_names)?;
-        }
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct TableSizeBytesRequest {
+    #[schemars(description = "Table to measure")]
+    pub table_name: String,
+}
 
-        let rows = stmt.query_map([], |row| {
-            let mut record = Vec::new();
-            for i in 0..column_names.len() {
-                let value = match row.get_ref(i)? {
-                    rusqlite::types::ValueRef::Null => String::new(),
-                    rusqlite::types::ValueRef::Integer(i) => i.to_string(),
-                    rusqlite::types::ValueRef::Real(f) => f.to_string(),
-                    rusqlite::types::ValueRef::Text(t) => String::from_utf8_lossy(t).into_owned(),
-                    rusqlite::types::ValueRef::Blob(b) => hex::encode(b),
-                };
-                record.push(value);
-            }
-            Ok(record)
-        })?;
+#[derive(Debug, Serialize)]
+pub struct TableSizeBytesResult {
+    pub table_name: String,
+    pub table_pages: i64,
+    pub table_bytes: i64,
+    pub index_pages: i64,
+    pub index_bytes: i64,
+    pub total_pages: i64,
+    pub total_bytes: i64,
+    /// The table and each of its indexes; `percent` is the share of this table's total.
+    pub objects: Vec<ObjectSizeInfo>,
+}
 
-        let mut rows_exported = 0;
-        for row in rows {
-            let record = row?;
-            wtr.write_record(&record)?;
-            rows_exported += 1;
-        }
+// Attached Database Types
+#[derive(Debug, Serialize)]
+pub struct AttachedDatabase {
+    pub name: String,
+    pub file: Option<String>,
+}
 
-        wtr.flush()?;
+#[derive(Debug, Serialize)]
+pub struct ListAttachedDatabasesResult {
+    pub databases: Vec<AttachedDatabase>,
+    pub total_count: usize,
+}
 
-        Ok(ExportCsvResult {
-            success: true,
-            message: format!("Successfully exported {rows_exported} rows to CSV"),
-            output_path: output_path.display().to_string(),
-            rows_exported,
-        })
+// Idle Connection Types
+/// Environment variable holding the idle auto-close timeout in minutes (unset or 0 disables).
+const IDLE_TIMEOUT_ENV: &str = "UNI_SQLITE_IDLE_TIMEOUT_MINUTES";
+
+#[derive(Debug)]
+pub struct IdleState {
+    pub last_activity: Instant,
+    /// Options of the last successful connect, replayed when reopening.
+    pub last_connect: Option<ConnectRequest>,
+    /// Set when the idle monitor closed the connection; the next tool call reopens it.
+    pub closed_for_idle: bool,
+    /// Set when a query assigned a PRAGMA on the live connection; a reopen would lose it.
+    pub session_pragmas: bool,
+}
+
+impl Default for IdleState {
+    fn default() -> Self {
+        Self {
+            last_activity: Instant::now(),
+            last_connect: None,
+            closed_for_idle: false,
+            session_pragmas: false,
+        }
     }
+}
 
-    pub async fn health_check_tool(&self) -> Result<HealthCheckResult, UniSqliteError> {
-        let db_guard = self.current_db.lock().await;
-        let path_guard = self.current_path.lock().await;
+// Incremental BLOB I/O Types
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct BlobReadRequest {
+    #[schemars(description = "Table containing the BLOB")]
+    pub table_name: String,
+    #[schemars(description = "BLOB column name")]
+    pub column: String,
+    #[schemars(description = "Rowid of the row holding the BLOB")]
+    pub rowid: i64,
+    #[schemars(description = "Byte offset to start reading from (default 0)")]
+    #[serde(default)]
+    pub offset: usize,
+    #[schemars(description = "Number of bytes to read (default: to the end of the BLOB)")]
+    pub length: Option<usize>,
+}
 
-        let connected = db_guard.is_some();
-        let database_path = path_guard.as_ref().map(|p| p.display().to_string());
+#[derive(Debug, Serialize)]
+pub struct BlobReadResult {
+    pub data_base64: String,
+    pub offset: usize,
+    pub bytes_read: usize,
+    pub blob_size: usize,
+}
 
-        let (database_size, table_count, last_modified) = if let (Some(conn), Some(path)) =
-            (db_guard.as_ref(), path_guard.as_ref())
-        {
-            let size = fs::metadata(path).ok().map(|m| m.len());
-            let modified = fs::metadata(path)
-                .ok()
-                .and_then(|m| m.modified().ok())
-                .map(|t| DateTime::from(t));
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct BlobWriteRequest {
+    #[schemars(description = "Table containing the BLOB")]
+    pub table_name: String,
+    #[schemars(description = "BLOB column name")]
+    pub column: String,
+    #[schemars(description = "Rowid of the row holding the BLOB")]
+    pub rowid: i64,
+    #[schemars(description = "Byte offset to start writing at (default 0)")]
+    #[serde(default)]
+    pub offset: usize,
+    #[schemars(
+        description = "Base64-encoded bytes to write; the BLOB cannot grow, so preallocate it with zeroblob(n)"
+    )]
+    pub data_base64: String,
+}
 
-            let count: Result<i32, _> = conn.query_row(
-                "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name NOT LIKE 'sqlite_%'",
-                [],
-                |row| row.get(0)
-            );
+#[derive(Debug, Serialize)]
+pub struct BlobWriteResult {
+    pub success: bool,
+    pub offset: usize,
+    pub bytes_written: usize,
+    pub blob_size: usize,
+}
 
-            (size, count.ok().map(|c| c as usize), modified)
-        } else {
-            (None, None, None)
-        };
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ExportTableToDbRequest {
+    #[schemars(description = "Table to export")]
+    pub table_name: String,
+    #[schemars(description = "Path of the new SQLite database file to create")]
+    pub dest_path: String,
+}
 
-        // Get SQLite version
-        let sqlite_version = if let Some(conn) = db_guard.as_ref() {
-            conn.query_row("SELECT sqlite_version()", [], |row| {
-                let version: String = row.get(0)?;
-                Ok(version)
-            })
-            .unwrap_or_else(|_| "Unknown".to_string())
-        } else {
-            "Not connected".to_string()
-        };
+#[derive(Debug, Serialize)]
+pub struct ExportTableToDbResult {
+    pub success: bool,
+    pub message: String,
+    pub dest_path: String,
+    pub rows_copied: usize,
+    pub indexes_created: usize,
+    pub dest_size: Option<u64>,
+}
 
-        Ok(HealthCheckResult {
-            connected,
-            database_path,
-            database_size,
-            table_count,
-            last_modified,
-            sqlite_version,
-        })
-    }
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CompareTablesRequest {
+    #[schemars(description = "First table (A)")]
+    pub table_a: String,
+    #[schemars(description = "Second table (B), with the same columns as A")]
+    pub table_b: String,
+    #[schemars(
+        description = "Optional database file holding table B, attached read-only for the comparison"
+    )]
+    pub database_b: Option<String>,
+    #[schemars(description = "Columns that identify a row in both tables")]
+    pub key_columns: Vec<String>,
+    #[schemars(description = "Maximum sample rows returned per category (default 20)")]
+    pub sample_limit: Option<usize>,
+}
 
-    fn get_tools() -> Vec<Tool> {
-        vec![
-            Tool {
-                name: Cow::Borrowed("connect"),
-                description: Some(Cow::Borrowed("Connect to a SQLite database")),
-                input_schema: serde_json::to_value(schemars::schema_for!(ConnectRequest).schema)
-                    .unwrap()
+#[derive(Debug, Serialize)]
+pub struct RowSample {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<serde_json::Value>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CompareTablesResult {
+    pub identical: bool,
+    pub only_in_a_count: i64,
+    pub only_in_b_count: i64,
+    pub differing_count: i64,
+    pub only_in_a: RowSample,
+    pub only_in_b: RowSample,
+    /// Key columns followed by `<column>_a` / `<column>_b` pairs for each compared column.
+    pub differing: RowSample,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RandomSampleRequest {
+    #[schemars(description = "Table to sample (must have a rowid)")]
+    pub table_name: String,
+    #[schemars(description = "Number of rows to return")]
+    pub count: usize,
+    #[schemars(
+        description = "Seed; the same seed returns the same rows while the table is unchanged"
+    )]
+    #[serde(default)]
+    pub seed: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RandomSampleResult {
+    pub seed: i64,
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<serde_json::Value>>,
+    pub row_count: usize,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct KeysetPageRequest {
+    #[schemars(description = "Table to page through")]
+    pub table_name: String,
+    #[schemars(description = "Columns that uniquely order the rows, most significant first")]
+    pub key_columns: Vec<String>,
+    #[schemars(
+        description = "Key values of the last row of the previous page (next_cursor); omit for the first page"
+    )]
+    pub last_seen: Option<Vec<serde_json::Value>>,
+    #[schemars(description = "Maximum rows per page (default 100)")]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct KeysetPageResult {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<serde_json::Value>>,
+    /// Pass as `last_seen` to fetch the next page; None once the table is exhausted.
+    pub next_cursor: Option<Vec<serde_json::Value>>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CountEstimateRequest {
+    #[schemars(description = "Table to count")]
+    pub table_name: String,
+    #[schemars(
+        description = "Count exactly when the estimate is at most this many rows (default 10000, 0 never counts)"
+    )]
+    pub exact_threshold: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CountEstimateResult {
+    pub table_name: String,
+    pub row_count: i64,
+    pub approximate: bool,
+    /// "sqlite_stat1" (from ANALYZE), "rowid_range", or "exact".
+    pub source: String,
+}
+
+// Connection Info Types
+#[derive(Debug, Serialize)]
+pub struct ConnectionInfoResult {
+    /// Canonical path of the main database
+    pub database_path: String,
+    /// Whether SQLite opened the main database read-only
+    pub readonly: bool,
+    /// Whether the database file itself is writable on disk
+    pub file_writable: bool,
+    /// Flags the connection was opened with, e.g. `READ_WRITE`, `CREATE`, `URI`
+    pub open_flags: Vec<String>,
+    pub journal_mode: String,
+    pub foreign_keys_on: bool,
+    pub busy_timeout_ms: i64,
+}
+
+// Function Discovery Types
+#[derive(Debug, Clone, Serialize)]
+pub struct CustomFunctionInfo {
+    pub name: String,
+    /// Number of arguments, or -1 for variadic
+    pub num_args: i32,
+    pub deterministic: bool,
+    pub description: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SqlCapabilities {
+    pub json1: bool,
+    pub fts5: bool,
+    pub rtree: bool,
+    pub math_functions: bool,
+    pub window_functions: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ListFunctionsResult {
+    pub functions: Vec<CustomFunctionInfo>,
+    pub capabilities: SqlCapabilities,
+}
+
+// Runtime Limit Types
+#[derive(Debug, Default, Clone, Copy, Deserialize, JsonSchema)]
+pub struct SetLimitsRequest {
+    #[schemars(description = "Maximum bytes in a string, BLOB, or row (SQLITE_LIMIT_LENGTH)")]
+    pub length: Option<i32>,
+    #[schemars(description = "Maximum bytes in an SQL statement (SQLITE_LIMIT_SQL_LENGTH)")]
+    pub sql_length: Option<i32>,
+    #[schemars(description = "Maximum columns in a table, index, or result (SQLITE_LIMIT_COLUMN)")]
+    pub column: Option<i32>,
+    #[schemars(description = "Maximum expression tree depth (SQLITE_LIMIT_EXPR_DEPTH)")]
+    pub expr_depth: Option<i32>,
+    #[schemars(description = "Maximum terms in a compound SELECT (SQLITE_LIMIT_COMPOUND_SELECT)")]
+    pub compound_select: Option<i32>,
+    #[schemars(description = "Maximum host parameter number (SQLITE_LIMIT_VARIABLE_NUMBER)")]
+    pub variable_number: Option<i32>,
+    #[schemars(description = "Maximum arguments to a function (SQLITE_LIMIT_FUNCTION_ARG)")]
+    pub function_arg: Option<i32>,
+    #[schemars(description = "Maximum attached databases (SQLITE_LIMIT_ATTACHED)")]
+    pub attached: Option<i32>,
+    #[schemars(
+        description = "Maximum LIKE/GLOB pattern length (SQLITE_LIMIT_LIKE_PATTERN_LENGTH)"
+    )]
+    pub like_pattern_length: Option<i32>,
+    #[schemars(description = "Maximum trigger recursion depth (SQLITE_LIMIT_TRIGGER_DEPTH)")]
+    pub trigger_depth: Option<i32>,
+}
+
+impl SetLimitsRequest {
+    /// Each limit with its name and requested value.
+    fn entries(&self) -> [(&'static str, rusqlite::limits::Limit, Option<i32>); 10] {
+        use rusqlite::limits::Limit;
+        [
+            ("length", Limit::SQLITE_LIMIT_LENGTH, self.length),
+            (
+                "sql_length",
+                Limit::SQLITE_LIMIT_SQL_LENGTH,
+                self.sql_length,
+            ),
+            ("column", Limit::SQLITE_LIMIT_COLUMN, self.column),
+            (
+                "expr_depth",
+                Limit::SQLITE_LIMIT_EXPR_DEPTH,
+                self.expr_depth,
+            ),
+            (
+                "compound_select",
+                Limit::SQLITE_LIMIT_COMPOUND_SELECT,
+                self.compound_select,
+            ),
+            (
+                "variable_number",
+                Limit::SQLITE_LIMIT_VARIABLE_NUMBER,
+                self.variable_number,
+            ),
+            (
+                "function_arg",
+                Limit::SQLITE_LIMIT_FUNCTION_ARG,
+                self.function_arg,
+            ),
+            ("attached", Limit::SQLITE_LIMIT_ATTACHED, self.attached),
+            (
+                "like_pattern_length",
+                Limit::SQLITE_LIMIT_LIKE_PATTERN_LENGTH,
+                self.like_pattern_length,
+            ),
+            (
+                "trigger_depth",
+                Limit::SQLITE_LIMIT_TRIGGER_DEPTH,
+                self.trigger_depth,
+            ),
+        ]
+    }
+
+    /// Overlay the limits set in `other` onto these.
+    fn merge(&mut self, other: &SetLimitsRequest) {
+        let fields = [
+            (&mut self.length, other.length),
+            (&mut self.sql_length, other.sql_length),
+            (&mut self.column, other.column),
+            (&mut self.expr_depth, other.expr_depth),
+            (&mut self.compound_select, other.compound_select),
+            (&mut self.variable_number, other.variable_number),
+            (&mut self.function_arg, other.function_arg),
+            (&mut self.attached, other.attached),
+            (&mut self.like_pattern_length, other.like_pattern_length),
+            (&mut self.trigger_depth, other.trigger_depth),
+        ];
+        for (field, value) in fields {
+            if value.is_some() {
+                *field = value;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct LimitChange {
+    pub name: String,
+    pub previous: i32,
+    pub current: i32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SetLimitsResult {
+    /// Every limit, with its value before and after the call
+    pub limits: Vec<LimitChange>,
+}
+
+// Query Cache Types
+const QUERY_CACHE_CAPACITY: usize = 64;
+const QUERY_CACHE_TTL: Duration = Duration::from_secs(30);
+
+/// Snapshot of the database state a result was read under. Writes through this connection
+/// bump `total_changes`, DDL bumps `schema_version`, and commits by other connections bump
+/// `data_version`, so any difference means a cached result may be stale.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DataStamp {
+    total_changes: u64,
+    data_version: i64,
+    schema_version: i64,
+}
+
+#[derive(Debug)]
+struct CachedQuery {
+    key: String,
+    stamp: DataStamp,
+    stored_at: Instant,
+    result: QueryResult,
+}
+
+/// Small LRU of read results, least recently used first.
+#[derive(Debug, Default)]
+pub struct QueryCache {
+    entries: VecDeque<CachedQuery>,
+}
+
+impl QueryCache {
+    /// Return a fresh result for `key`, dropping it instead if it has expired or the
+    /// database has changed since it was stored.
+    fn get(&mut self, key: &str, stamp: DataStamp) -> Option<QueryResult> {
+        let index = self.entries.iter().position(|e| e.key == key)?;
+        let entry = self.entries.remove(index)?;
+        if entry.stamp != stamp || entry.stored_at.elapsed() > QUERY_CACHE_TTL {
+            return None;
+        }
+        let result = entry.result.clone();
+        self.entries.push_back(entry);
+        Some(result)
+    }
+
+    fn insert(&mut self, key: String, stamp: DataStamp, result: QueryResult) {
+        self.entries.retain(|e| e.key != key);
+        if self.entries.len() >= QUERY_CACHE_CAPACITY {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(CachedQuery {
+            key,
+            stamp,
+            stored_at: Instant::now(),
+            result,
+        });
+    }
+
+    /// Drop every entry, returning how many there were.
+    fn clear(&mut self) -> usize {
+        let cleared = self.entries.len();
+        self.entries.clear();
+        cleared
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ClearQueryCacheResult {
+    pub success: bool,
+    pub message: String,
+    pub entries_cleared: usize,
+}
+
+// Query Description Types
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct DescribeQueryRequest {
+    #[schemars(description = "SQL statement to describe; it is prepared but not executed")]
+    pub sql: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResultColumnInfo {
+    pub name: String,
+    /// Declared type of the source column; None for expressions
+    pub declared_type: Option<String>,
+    /// Schema, table, and column the value comes from; None for expressions
+    pub origin_database: Option<String>,
+    pub origin_table: Option<String>,
+    pub origin_column: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DescribeQueryResult {
+    pub columns: Vec<ResultColumnInfo>,
+    pub parameter_count: usize,
+    /// Whether the statement leaves the database unchanged
+    pub readonly: bool,
+}
+
+/// Identifies the file behind the open connection. Our own writes change the
+/// mtime, so only the device/inode pair (or creation time off unix) is compared:
+/// it changes when the file is deleted or replaced by a rename, which leaves the
+/// connection reading the old, unlinked file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileIdentity {
+    #[cfg(unix)]
+    dev: u64,
+    #[cfg(unix)]
+    ino: u64,
+    #[cfg(not(unix))]
+    created: Option<std::time::SystemTime>,
+    /// Modification time when the identity was captured (informational only)
+    modified: Option<std::time::SystemTime>,
+}
+
+impl FileIdentity {
+    fn of(path: &Path) -> Option<Self> {
+        let meta = fs::metadata(path).ok()?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            Some(Self {
+                dev: meta.dev(),
+                ino: meta.ino(),
+                modified: meta.modified().ok(),
+            })
+        }
+        #[cfg(not(unix))]
+        {
+            Some(Self {
+                created: meta.created().ok(),
+                modified: meta.modified().ok(),
+            })
+        }
+    }
+
+    fn same_file(&self, other: &Self) -> bool {
+        #[cfg(unix)]
+        {
+            self.dev == other.dev && self.ino == other.ino
+        }
+        #[cfg(not(unix))]
+        {
+            self.created == other.created
+        }
+    }
+}
+
+// Extension Loading Types
+/// Environment variable naming the only directory `load_extension` may load from.
+#[cfg(feature = "extensions")]
+const EXTENSION_DIR_ENV: &str = "UNI_SQLITE_EXTENSION_DIR";
+
+#[cfg(feature = "extensions")]
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct LoadExtensionRequest {
+    #[schemars(
+        description = "Extension file name inside the allowlisted directory, e.g. vec0 (the platform suffix is optional)"
+    )]
+    pub name: String,
+    #[schemars(description = "Entry point symbol (default: derived by SQLite from the file name)")]
+    pub entry_point: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LoadedExtension {
+    pub name: String,
+    pub path: String,
+    pub entry_point: Option<String>,
+}
+
+#[cfg(feature = "extensions")]
+#[derive(Debug, Serialize)]
+pub struct LoadExtensionResult {
+    pub success: bool,
+    pub extension: LoadedExtension,
+    pub loaded_extensions: Vec<LoadedExtension>,
+}
+
+// Vector Search Types
+/// Vector extension a vector index is built on.
+#[cfg(feature = "extensions")]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum VectorBackend {
+    /// sqlite-vec (`vec0` virtual tables)
+    #[default]
+    SqliteVec,
+    /// sqlite-vss (`vss0` virtual tables)
+    Vss,
+}
+
+#[cfg(feature = "extensions")]
+impl VectorBackend {
+    fn module(self) -> &'static str {
+        match self {
+            VectorBackend::SqliteVec => "vec0",
+            VectorBackend::Vss => "vss0",
+        }
+    }
+}
+
+#[cfg(feature = "extensions")]
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CreateVectorIndexRequest {
+    #[schemars(description = "Name of the virtual table to create")]
+    pub table: String,
+    #[schemars(description = "Vector column name (default: embedding)")]
+    pub column: Option<String>,
+    #[schemars(description = "Number of dimensions of each vector")]
+    pub dimensions: usize,
+    #[schemars(description = "Vector extension to use: sqlite_vec (default) or vss")]
+    #[serde(default)]
+    pub backend: VectorBackend,
+    #[schemars(description = "Don't fail if the table already exists")]
+    #[serde(default)]
+    pub if_not_exists: bool,
+}
+
+#[cfg(feature = "extensions")]
+#[derive(Debug, Serialize)]
+pub struct CreateVectorIndexResult {
+    pub success: bool,
+    pub table: String,
+    pub sql: String,
+}
+
+#[cfg(feature = "extensions")]
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct VectorSearchRequest {
+    #[schemars(description = "Vector index table created by create_vector_index")]
+    pub table: String,
+    #[schemars(description = "Vector column name (default: embedding)")]
+    pub column: Option<String>,
+    #[schemars(description = "Query vector as a JSON array of numbers")]
+    pub query_vector: Vec<f64>,
+    #[schemars(description = "Number of nearest rows to return")]
+    pub k: usize,
+    #[schemars(description = "Vector extension the index uses: sqlite_vec (default) or vss")]
+    #[serde(default)]
+    pub backend: VectorBackend,
+    #[schemars(
+        description = "Table whose rows share rowids with the index; its columns are returned alongside each match"
+    )]
+    pub join_table: Option<String>,
+}
+
+#[cfg(feature = "extensions")]
+#[derive(Debug, Serialize)]
+pub struct VectorSearchResult {
+    /// `rowid`, `distance`, then the join table's columns when given
+    pub columns: Vec<String>,
+    /// Matches ordered nearest first
+    pub rows: Vec<Vec<Value>>,
+    pub row_count: usize,
+}
+
+// Embedding Types
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct PackEmbeddingRequest {
+    #[schemars(description = "Embedding as a JSON array of numbers")]
+    pub values: Vec<f32>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct UnpackEmbeddingRequest {
+    #[schemars(description = "Packed little-endian float32 BLOB, base64-encoded")]
+    pub base64: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EmbeddingResult {
+    /// Little-endian float32 BLOB, base64-encoded
+    pub base64: String,
+    pub values: Vec<f32>,
+    pub dimensions: usize,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SimilaritySearchRequest {
+    #[schemars(description = "Table holding the embeddings")]
+    pub table: String,
+    #[schemars(description = "Column holding packed float32 BLOBs or JSON arrays")]
+    pub column: String,
+    #[schemars(description = "Query vector as a JSON array of numbers")]
+    pub query_vector: Vec<f32>,
+    #[schemars(description = "Number of most similar rows to return")]
+    pub k: usize,
+    #[schemars(description = "Extra columns to return with each match (default: none)")]
+    #[serde(default)]
+    pub return_columns: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SimilaritySearchResult {
+    /// `rowid`, `similarity`, then `return_columns`
+    pub columns: Vec<String>,
+    /// Matches ordered most similar first
+    pub rows: Vec<Vec<Value>>,
+    pub row_count: usize,
+}
+
+// Change Tracking Types
+/// Table the change tracking triggers write to.
+const CHANGELOG_TABLE: &str = "_changelog";
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ChangeTrackingRequest {
+    #[schemars(description = "Table whose inserts, updates, and deletes are tracked")]
+    pub table: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChangeTrackingResult {
+    pub success: bool,
+    pub message: String,
+    pub table: String,
+    /// Triggers created (enable) or dropped (disable)
+    pub triggers: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct GetChangesSinceRequest {
+    #[schemars(description = "Only return changes to this table (all tracked tables if omitted)")]
+    pub table: Option<String>,
+    #[schemars(description = "RFC 3339 timestamp; changes strictly after it are returned")]
+    pub timestamp: String,
+    #[schemars(description = "Maximum number of changes to return (default: 1000)")]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChangeLogEntry {
+    pub id: i64,
+    pub table: String,
+    /// INSERT, UPDATE, or DELETE
+    pub op: String,
+    pub rowid: i64,
+    pub changed_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GetChangesSinceResult {
+    pub changes: Vec<ChangeLogEntry>,
+    pub count: usize,
+    /// Timestamp of the newest change returned, to pass as the next `timestamp`
+    pub latest: Option<String>,
+}
+
+// Soft Delete Types
+/// Column `enable_soft_delete` adds; NULL means the row is live.
+const SOFT_DELETE_COLUMN: &str = "deleted_at";
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct EnableSoftDeleteRequest {
+    #[schemars(description = "Table to enable soft deletes on")]
+    pub table_name: String,
+    #[schemars(description = "Name of the view over live rows (default: <table>_active)")]
+    pub view_name: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EnableSoftDeleteResult {
+    pub success: bool,
+    pub message: String,
+    /// False when the table already had a `deleted_at` column
+    pub column_added: bool,
+    pub view_name: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SoftDeleteRequest {
+    #[schemars(description = "Table with soft deletes enabled")]
+    pub table_name: String,
+    #[schemars(description = "WHERE clause without the WHERE keyword (e.g., 'id = ?')")]
+    #[serde(default)]
+    pub where_clause: String,
+    #[schemars(description = "Parameters for the WHERE clause placeholders")]
+    #[serde(default)]
+    pub parameters: Vec<serde_json::Value>,
+    #[schemars(description = "Allow affecting every row when where_clause is empty")]
+    #[serde(default)]
+    pub allow_full_table: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SoftDeleteResult {
+    pub success: bool,
+    pub message: String,
+    /// Rows whose `deleted_at` changed
+    pub rows_affected: usize,
+}
+
+// Tail Types
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct TailRequest {
+    #[schemars(description = "Table to follow")]
+    pub table_name: String,
+    #[schemars(
+        description = "Return rows with a rowid greater than this cursor (default: 0, i.e. from the start)"
+    )]
+    #[serde(default)]
+    pub after_rowid: i64,
+    #[schemars(description = "Maximum rows to return (default: 100)")]
+    pub limit: Option<usize>,
+    #[schemars(description = "Map values by declared column type, as in query")]
+    #[serde(default)]
+    pub typed: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TailResult {
+    /// `rowid` followed by the table's columns
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+    pub row_count: usize,
+    /// Largest rowid returned (or the input cursor when nothing is new); pass it back as `after_rowid`
+    pub next_cursor: i64,
+}
+
+// Metadata Types
+/// Key/value table backing the metadata tools, created on first write.
+const META_TABLE: &str = "_uni_meta";
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SetMetaRequest {
+    #[schemars(description = "Metadata key")]
+    pub key: String,
+    #[schemars(description = "Any JSON value; stored as JSON text")]
+    pub value: Value,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct GetMetaRequest {
+    #[schemars(description = "Metadata key")]
+    pub key: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MetaEntry {
+    pub key: String,
+    pub value: Value,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GetMetaResult {
+    pub found: bool,
+    pub entry: Option<MetaEntry>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ListMetaResult {
+    pub entries: Vec<MetaEntry>,
+    pub total_count: usize,
+}
+
+// Temp Table Types
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct QueryToTempTableRequest {
+    #[schemars(description = "Name of the temporary table to create")]
+    pub table_name: String,
+    #[schemars(description = "SELECT (or WITH ... SELECT / VALUES) whose rows fill the table")]
+    pub sql: String,
+    #[schemars(description = "Parameters for the query placeholders")]
+    #[serde(default)]
+    pub parameters: Vec<serde_json::Value>,
+    #[schemars(description = "Drop an existing temporary table of the same name first")]
+    #[serde(default)]
+    pub replace: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QueryToTempTableResult {
+    pub success: bool,
+    /// Name to use in later queries (also reachable as `temp.<name>`)
+    pub table_name: String,
+    pub columns: Vec<String>,
+    pub row_count: i64,
+}
+
+// Detailed Table Listing Types
+/// First SQLite version with `PRAGMA table_list` (3.37.0).
+const TABLE_LIST_MIN_VERSION: i32 = 3_037_000;
+
+#[derive(Debug, Serialize)]
+pub struct DetailedTableInfo {
+    /// Schema the object lives in (`main`, `temp`, or an attached name)
+    pub schema: String,
+    pub name: String,
+    /// `table`, `view`, `virtual`, or `shadow`
+    pub table_type: String,
+    pub column_count: i64,
+    pub without_rowid: bool,
+    pub strict: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ListTablesDetailedResult {
+    pub tables: Vec<DetailedTableInfo>,
+    pub total_count: usize,
+    /// `table_list` on SQLite 3.37+, otherwise `sqlite_master`
+    pub source: String,
+}
+
+// Migration Types
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct GenerateMigrationRequest {
+    #[schemars(
+        description = "Target schema as a script of CREATE statements (give this or target_database)"
+    )]
+    pub target_sql: Option<String>,
+    #[schemars(description = "Reference database whose schema is the target (opened read-only)")]
+    pub target_database: Option<String>,
+    #[schemars(description = "Run the migration in one transaction instead of only returning it")]
+    #[serde(default)]
+    pub apply: bool,
+    #[schemars(description = "Let apply run migrations that drop tables or columns")]
+    #[serde(default)]
+    pub allow_destructive: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GenerateMigrationResult {
+    /// True when the schemas already match
+    pub up_to_date: bool,
+    pub statements: Vec<String>,
+    /// The statements as a single script
+    pub sql: String,
+    /// Steps that lose data: dropped tables and columns
+    pub destructive_changes: Vec<String>,
+    pub applied: bool,
+}
+
+/// Migration statements, and the tables or columns they drop.
+type MigrationSteps = (Vec<String>, Vec<String>);
+
+/// One `sqlite_master` entry compared by `generate_migration`.
+#[derive(Debug)]
+struct SchemaEntry {
+    object_type: String,
+    name: String,
+    table: String,
+    sql: String,
+}
+
+// SQL Dump Types
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct DumpToSqlRequest {
+    #[schemars(
+        description = "Output .sql file, or the directory to write into when export_sql_per_table is set"
+    )]
+    pub output_path: String,
+    #[schemars(
+        description = "Write one NNNN_<table>.sql file per table (schema, rows, indexes, triggers) in foreign-key order, plus a views file, into output_path"
+    )]
+    #[serde(default)]
+    pub export_sql_per_table: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DumpToSqlResult {
+    pub success: bool,
+    pub message: String,
+    /// Files written, in restore order
+    pub files: Vec<String>,
+    pub tables: usize,
+    pub rows: usize,
+}
+
+// SQL Restore Types
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RestoreFromDirectoryRequest {
+    #[schemars(
+        description = "Directory of .sql files (e.g. from dump_to_sql with export_sql_per_table), applied in file name order"
+    )]
+    pub directory: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RestoreFromDirectoryResult {
+    pub success: bool,
+    pub message: String,
+    pub files_applied: Vec<String>,
+    pub statements_executed: usize,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RestoreFromSqlRequest {
+    #[schemars(description = "Path of the .sql script to run (e.g. from dump_to_sql)")]
+    pub input_path: String,
+    #[schemars(description = "Log progress every this many statements (default: 10000)")]
+    pub progress_interval: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RestoreFromSqlResult {
+    pub success: bool,
+    pub message: String,
+    pub statements_executed: usize,
+    pub bytes_read: u64,
+    pub elapsed_ms: u64,
+}
+
+/// Reads a SQL script one complete statement at a time, using SQLite's own
+/// `sqlite3_complete` so quotes, comments, and trigger bodies are respected.
+struct SqlStatements<R> {
+    reader: R,
+    buffer: String,
+    bytes_read: u64,
+}
+
+impl<R: std::io::BufRead> SqlStatements<R> {
+    fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buffer: String::new(),
+            bytes_read: 0,
+        }
+    }
+
+    /// Bytes consumed from the reader so far.
+    fn bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+
+    fn is_complete(sql: &str) -> Result<bool, UniSqliteError> {
+        let c_sql = std::ffi::CString::new(sql)
+            .map_err(|_| UniSqliteError::QueryFailed("SQL contains a NUL byte".into()))?;
+        // SAFETY: sqlite3_complete only reads the NUL-terminated string
+        Ok(unsafe { rusqlite::ffi::sqlite3_complete(c_sql.as_ptr()) } != 0)
+    }
+}
+
+impl<R: std::io::BufRead> Iterator for SqlStatements<R> {
+    type Item = Result<String, UniSqliteError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            match self.reader.read_line(&mut line) {
+                Err(e) => return Some(Err(e.into())),
+                // A final statement may lack its semicolon
+                Ok(0) => {
+                    let rest = std::mem::take(&mut self.buffer);
+                    return (!SqliteHandler::strip_leading_comments(&rest).is_empty())
+                        .then_some(Ok(rest));
+                }
+                Ok(n) => {
+                    self.bytes_read += n as u64;
+                    self.buffer.push_str(&line);
+                    if line.contains(';') {
+                        match Self::is_complete(&self.buffer) {
+                            Ok(true) => return Some(Ok(std::mem::take(&mut self.buffer))),
+                            Ok(false) => {}
+                            Err(e) => return Some(Err(e)),
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+pub struct QueryExplainAnalyzeRequest {
+    #[schemars(description = "Read-only query to plan and time")]
+    pub sql: String,
+    #[schemars(description = "Parameters for a prepared statement")]
+    #[serde(default)]
+    pub parameters: Vec<serde_json::Value>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QueryExplainAnalyzeResult {
+    pub plan: Vec<QueryPlanStep>,
+    /// Wall time to run the query and step through every row
+    pub elapsed_ms: u64,
+    pub rows: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct QueryPlanStep {
+    pub id: i64,
+    pub parent: i64,
+    /// Nesting level in the plan tree, 0 for top-level steps
+    pub depth: usize,
+    pub detail: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct TableChecksumRequest {
+    #[schemars(description = "Table to checksum")]
+    pub table_name: String,
+    #[schemars(description = "Columns to cover, in this order (default: all columns)")]
+    pub columns: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TableChecksumResult {
+    pub table_name: String,
+    pub columns: Vec<String>,
+    pub row_count: u64,
+    /// Hex SHA-256 digest, independent of row order
+    pub digest: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct GenerateFakeDataRequest {
+    #[schemars(description = "Table to fill")]
+    pub table_name: String,
+    #[schemars(description = "Number of rows to insert (at most 100000)")]
+    pub count: usize,
+    #[schemars(
+        description = "RNG seed; the same seed and schema produce the same rows (default: 1)"
+    )]
+    pub seed: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GenerateFakeDataResult {
+    pub success: bool,
+    pub message: String,
+    pub rows_inserted: usize,
+    pub seed: u64,
+    /// Generator chosen for each filled column, e.g. "email" or "foreign_key(users.id)"
+    pub generators: Vec<FakeColumnGenerator>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FakeColumnGenerator {
+    pub column: String,
+    pub generator: String,
+}
+
+/// How generate_fake_data fills a column, picked from its name and declared type.
+enum FakeGenerator {
+    Email,
+    PersonName,
+    Date,
+    DateTime,
+    Boolean,
+    Integer,
+    Real,
+    Text,
+    Blob,
+    /// Existing values of the referenced parent column
+    ForeignKey(String, Vec<rusqlite::types::Value>),
+}
+
+impl FakeGenerator {
+    const FIRST_NAMES: [&'static str; 8] = [
+        "Ada", "Grace", "Alan", "Edsger", "Barbara", "Ken", "Frances", "Dennis",
+    ];
+    const LAST_NAMES: [&'static str; 8] = [
+        "Lovelace", "Hopper", "Turing", "Dijkstra", "Liskov", "Thompson", "Allen", "Ritchie",
+    ];
+    const WORDS: [&'static str; 12] = [
+        "alpha", "bravo", "delta", "echo", "harbor", "lumen", "matrix", "nova", "orbit", "quartz",
+        "sierra", "vector",
+    ];
+
+    fn for_column(name: &str, declared: &str) -> Self {
+        let name = name.to_ascii_lowercase();
+        let declared = declared.to_ascii_uppercase();
+        if name.contains("email") {
+            Self::Email
+        } else if name.contains("name") && !declared.contains("INT") {
+            Self::PersonName
+        } else if declared.contains("DATETIME") || declared.contains("TIMESTAMP") {
+            Self::DateTime
+        } else if declared.contains("DATE") {
+            Self::Date
+        } else if declared.starts_with("BOOL") {
+            Self::Boolean
+        } else {
+            match ColumnAffinity::from_declared_type(&declared) {
+                ColumnAffinity::Integer => Self::Integer,
+                ColumnAffinity::Real | ColumnAffinity::Numeric => Self::Real,
+                ColumnAffinity::Blob => Self::Blob,
+                ColumnAffinity::Text | ColumnAffinity::Untyped => Self::Text,
+            }
+        }
+    }
+
+    fn label(&self) -> String {
+        match self {
+            Self::Email => "email".into(),
+            Self::PersonName => "person_name".into(),
+            Self::Date => "date".into(),
+            Self::DateTime => "datetime".into(),
+            Self::Boolean => "boolean".into(),
+            Self::Integer => "integer".into(),
+            Self::Real => "real".into(),
+            Self::Text => "text".into(),
+            Self::Blob => "blob".into(),
+            Self::ForeignKey(parent, _) => format!("foreign_key({parent})"),
+        }
+    }
+
+    fn generate(&self, rng: &mut SplitMix64, row: usize) -> rusqlite::types::Value {
+        use rusqlite::types::Value as SqlValue;
+
+        // Dates fall within 2020-2025
+        let timestamp = |rng: &mut SplitMix64| {
+            DateTime::from_timestamp(1_577_836_800 + rng.below(6 * 365 * 86_400) as i64, 0)
+                .unwrap_or_default()
+        };
+        match self {
+            Self::Email => SqlValue::Text(format!(
+                "{}.{row}@example.com",
+                rng.pick(&Self::FIRST_NAMES).to_ascii_lowercase()
+            )),
+            Self::PersonName => SqlValue::Text(format!(
+                "{} {}",
+                rng.pick(&Self::FIRST_NAMES),
+                rng.pick(&Self::LAST_NAMES)
+            )),
+            Self::Date => SqlValue::Text(timestamp(rng).format("%Y-%m-%d").to_string()),
+            Self::DateTime => {
+                SqlValue::Text(timestamp(rng).format("%Y-%m-%d %H:%M:%S").to_string())
+            }
+            Self::Boolean => SqlValue::Integer(rng.below(2) as i64),
+            Self::Integer => SqlValue::Integer(rng.below(100_000) as i64),
+            Self::Real => SqlValue::Real(rng.below(10_000_000) as f64 / 100.0),
+            Self::Text => SqlValue::Text(format!(
+                "{} {}",
+                rng.pick(&Self::WORDS),
+                rng.pick(&Self::WORDS)
+            )),
+            Self::Blob => SqlValue::Blob(rng.next_u64().to_be_bytes().to_vec()),
+            Self::ForeignKey(_, values) => values[rng.below(values.len() as u64) as usize].clone(),
+        }
+    }
+}
+
+/// Small seeded PRNG whose output is fixed by the seed alone, so fake data is reproducible
+/// across builds and platforms.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next_u64() % bound.max(1)
+    }
+
+    fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+        items[self.below(items.len() as u64) as usize]
+    }
+}
+
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+pub struct LockStatusRequest {
+    #[schemars(
+        description = "Take and release locks from the probe connection (BEGIN IMMEDIATE, and BEGIN EXCLUSIVE outside WAL) and run a passive WAL checkpoint to tell reserved/shared/unlocked apart and measure reader pressure. These briefly contend with other writers and the checkpoint copies frames back into the database (default false)"
+    )]
+    #[serde(default)]
+    pub probe_locks: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LockStatusResult {
+    pub journal_mode: String,
+    /// Best-effort lock held by other connections: unlocked, shared, reserved,
+    /// pending_or_exclusive, or unknown (in-memory databases, failed probes, or
+    /// `probe_locks` off while readers are not blocked)
+    pub lock_state: String,
+    /// This server's own transaction on `main`: none, read, or write
+    pub own_transaction: String,
+    /// Whether a new reader would be refused (PENDING/EXCLUSIVE, or exclusive locking mode)
+    pub readers_blocked: Option<bool>,
+    /// Whether another connection holds the write lock
+    pub writer_active: Option<bool>,
+    /// WAL frames and how many a passive checkpoint could copy back; a shortfall means
+    /// a reader or writer is pinning older frames
+    pub wal_frames: Option<i64>,
+    pub wal_frames_checkpointed: Option<i64>,
+    pub wal_readers_blocking_checkpoint: Option<bool>,
+    /// Caveats about how each field was determined
+    pub notes: Vec<String>,
+}
+
+// Health Check Types
+#[derive(Debug, Serialize)]
+pub struct HealthCheckResult {
+    pub connected: bool,
+    pub database_path: Option<String>,
+    pub database_size: Option<u64>,
+    pub table_count: Option<usize>,
+    pub last_modified: Option<DateTime<Utc>>,
+    pub sqlite_version: String,
+    pub supports_window_functions: Option<bool>,
+    pub statement_cache: Option<StatementCacheStats>,
+    pub attached_databases: Option<Vec<AttachedDatabase>>,
+    /// Whether the database file was deleted or replaced since connect
+    pub file_changed_since_connect: Option<bool>,
+    pub warning: Option<String>,
+    pub loaded_extensions: Option<Vec<LoadedExtension>>,
+}
+
+impl SqliteHandler {
+    pub fn new() -> Self {
+        Self {
+            current_db: Arc::new(Mutex::new(None)),
+            current_path: Arc::new(Mutex::new(None)),
+            sql_trace: Arc::new(Mutex::new(SqlTraceState::default())),
+            statement_cache: Arc::new(std::sync::Mutex::new(StatementCacheState::default())),
+            prepared_statements: Arc::new(Mutex::new(HashMap::new())),
+            busy_retry: Arc::new(std::sync::Mutex::new(BusyRetryState::default())),
+            idle: Arc::new(std::sync::Mutex::new(IdleState::default())),
+            custom_functions: Arc::new(std::sync::Mutex::new(Vec::new())),
+            limits: Arc::new(std::sync::Mutex::new(SetLimitsRequest::default())),
+            query_cache: Arc::new(std::sync::Mutex::new(QueryCache::default())),
+            file_identity: Arc::new(std::sync::Mutex::new(None)),
+            loaded_extensions: Arc::new(std::sync::Mutex::new(Vec::new())),
+            fetch_limit: Arc::new(std::sync::Mutex::new(DEFAULT_FETCH_LIMIT)),
+        }
+    }
+
+    pub async fn connect_tool(&self, req: ConnectRequest) -> Result<ConnectResult, UniSqliteError> {
+        let (path, target) = if req.uri {
+            self.resolve_db_uri(&req.path)?
+        } else {
+            let path = self.validate_db_path(&PathBuf::from(&req.path))?;
+            (path.clone(), path)
+        };
+
+        let mut conn = Connection::open_with_flags(&target, Self::open_flags(&req))?;
+
+        // Read the schema under a short busy timeout so a lock held by another
+        // process surfaces now, as a clear error, rather than on the first query
+        conn.busy_timeout(CONNECT_BUSY_TIMEOUT)?;
+        if let Err(e) = conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |row| {
+            row.get::<_, i64>(0)
+        }) {
+            return Err(if Self::is_busy_error(&e) {
+                UniSqliteError::DatabaseLocked(path.display().to_string())
+            } else {
+                e.into()
+            });
+        }
+        conn.busy_timeout(DEFAULT_BUSY_TIMEOUT)?;
+
+        Self::apply_sql_trace(&mut conn, *self.sql_trace.lock().await);
+        Self::apply_limits(&conn, &self.limits.lock().unwrap());
+
+        // With caching disabled a zero capacity makes prepare_cached behave like prepare
+        let cache_capacity = if req.use_cache {
+            req.statement_cache_capacity
+                .unwrap_or(DEFAULT_STATEMENT_CACHE_CAPACITY)
+        } else {
+            0
+        };
+        conn.set_prepared_statement_cache_capacity(cache_capacity);
+
+        // Collected locally; the shared lists describe the old connection until the swap
+        let mut functions = Vec::new();
+        if req.enable_uuid_function {
+            Self::register_uuid_function(&conn, &mut functions)?;
+        }
+        if req.enable_vector_functions {
+            Self::register_vector_functions(&conn, &mut functions)?;
+        }
+        if req.enable_unicode_collation {
+            Self::register_unicode_collation(&conn)?;
+        }
+
+        let performance = match &req.performance {
+            Some(settings) => {
+                Self::apply_performance_settings(&conn, settings)?;
+                Some(Self::read_performance_settings(&conn)?)
+            }
+            None => None,
+        };
+
+        // Get database size
+        let database_size = fs::metadata(&path).ok().map(|m| m.len());
+        let supports_window_functions = Self::probe_window_functions(&conn);
+
+        *self.current_db.lock().await = Some(conn);
+        *self.custom_functions.lock().unwrap() = functions;
+        self.loaded_extensions.lock().unwrap().clear();
+        *self.current_path.lock().await = Some(path.clone());
+        *self.file_identity.lock().unwrap() = FileIdentity::of(&path);
+        *self.statement_cache.lock().unwrap() =
+            StatementCacheState::new(req.use_cache, cache_capacity);
+        self.prepared_statements.lock().await.clear();
+        self.query_cache.lock().unwrap().clear();
+        *self.busy_retry.lock().unwrap() = req.busy_retry.map(Into::into).unwrap_or_default();
+        *self.fetch_limit.lock().unwrap() = req.default_fetch_limit.unwrap_or(DEFAULT_FETCH_LIMIT);
+        {
+            let mut idle = self.idle.lock().unwrap();
+            idle.last_activity = Instant::now();
+            idle.closed_for_idle = false;
+            idle.session_pragmas = false;
+            idle.last_connect = Some(req);
+        }
+
+        Ok(ConnectResult {
+            success: true,
+            path: path.display().to_string(),
+            database_size,
+            supports_window_functions,
+            performance,
+        })
+    }
+
+    /// Lock the current connection for a tool call, reopening it first if the idle monitor
+    /// closed it, and record the activity. Prepared statement ids survive the reopen.
+    async fn lock_db(
+        &self,
+    ) -> Result<tokio::sync::MutexGuard<'_, Option<Connection>>, UniSqliteError> {
+        let reopen = {
+            let mut idle = self.idle.lock().unwrap();
+            idle.last_activity = Instant::now();
+            if idle.closed_for_idle {
+                idle.last_connect.clone()
+            } else {
+                None
+            }
+        };
+
+        if let Some(connect) = reopen {
+            tracing::info!("Reopening database closed for inactivity: {}", connect.path);
+            let prepared = self.prepared_statements.lock().await.clone();
+            self.connect_tool(connect).await?;
+            let guard = self.current_db.lock().await;
+            if let Some(conn) = guard.as_ref() {
+                self.size_statement_cache(conn, prepared.len());
+            }
+            *self.prepared_statements.lock().await = prepared;
+            return Ok(guard);
+        }
+
+        Ok(self.current_db.lock().await)
+    }
+
+    /// Run `f` against the current connection on a blocking thread, so busy retries and
+    /// long writes don't stall the async runtime. The connection is moved out for the
+    /// duration while `current_db` stays locked, and is lost if `f` panics.
+    async fn with_blocking_connection<T, F>(&self, f: F) -> Result<T, UniSqliteError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&SqliteHandler, &Connection) -> Result<T, UniSqliteError> + Send + 'static,
+    {
+        let mut guard = self.lock_db().await?;
+        let conn = guard.take().ok_or(UniSqliteError::NotConnected)?;
+        let handler = self.clone();
+        let (conn, result) = tokio::task::spawn_blocking(move || {
+            let result = f(&handler, &conn);
+            (conn, result)
+        })
+        .await
+        .map_err(|e| UniSqliteError::Other(format!("Database task failed: {e}")))?;
+        *guard = Some(conn);
+        result
+    }
+
+    /// Describe state that lives only on the open connection and that reopening it with the
+    /// last connect options would silently drop, or `None` if it is safe to close.
+    fn session_state(&self, conn: &Connection, idle: &IdleState) -> Option<&'static str> {
+        if !conn.is_autocommit() {
+            return Some("a transaction is open");
+        }
+        if idle.session_pragmas {
+            return Some("PRAGMAs were set by query");
+        }
+        if !self.loaded_extensions.lock().unwrap().is_empty() {
+            return Some("extensions are loaded");
+        }
+        match Self::attached_databases(conn) {
+            Ok(databases)
+                if databases
+                    .iter()
+                    .all(|d| d.name == "main" || d.name == "temp") => {}
+            Ok(_) => return Some("databases are attached"),
+            Err(_) => return Some("attached databases could not be listed"),
+        }
+        match conn.query_row("SELECT COUNT(*) FROM sqlite_temp_master", [], |row| {
+            row.get::<_, i64>(0)
+        }) {
+            Ok(0) => None,
+            Ok(_) => Some("temporary tables exist"),
+            Err(_) => Some("temporary objects could not be listed"),
+        }
+    }
+
+    /// Close the connection once no tool has used it for `timeout`. It is reopened
+    /// transparently with the same connect options on the next tool call. Connections
+    /// holding session state (see `session_state`) are left open.
+    pub fn spawn_idle_monitor(&self, timeout: Duration) -> tokio::task::JoinHandle<()> {
+        let handler = self.clone();
+        let interval = (timeout / 4).clamp(Duration::from_millis(10), Duration::from_secs(60));
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+
+                let mut db = handler.current_db.lock().await;
+                let mut idle = handler.idle.lock().unwrap();
+                let Some(conn) = db.as_ref() else {
+                    continue;
+                };
+                if idle.last_activity.elapsed() < timeout {
+                    continue;
+                }
+                if let Some(reason) = handler.session_state(conn, &idle) {
+                    tracing::debug!("Keeping idle connection open: {reason}");
+                    continue;
+                }
+                // Dropping the connection closes its file handles and checkpoints the WAL
+                *db = None;
+                idle.closed_for_idle = true;
+                tracing::info!("Closed database connection after {:?} idle", timeout);
+            }
+        })
+    }
+
+    /// Open a new database and replace the current connection with it. The previous
+    /// connection stays active if the new one can't be opened.
+    pub async fn switch_database_tool(
+        &self,
+        req: ConnectRequest,
+    ) -> Result<SwitchDatabaseResult, UniSqliteError> {
+        let previous_path = self
+            .current_path
+            .lock()
+            .await
+            .as_ref()
+            .map(|p| p.display().to_string());
+
+        let connection = self.connect_tool(req).await?;
+
+        Ok(SwitchDatabaseResult {
+            previous_path,
+            connection,
+        })
+    }
+
+    /// Register a non-deterministic `uuid()` scalar function returning a v4 UUID string.
+    fn register_uuid_function(
+        conn: &Connection,
+        functions: &mut Vec<CustomFunctionInfo>,
+    ) -> Result<(), UniSqliteError> {
+        conn.create_scalar_function("uuid", 0, FunctionFlags::SQLITE_UTF8, |_ctx| {
+            Ok(uuid::Uuid::new_v4().to_string())
+        })?;
+        Self::push_function(functions, "uuid", 0, false, "Random v4 UUID string");
+        Ok(())
+    }
+
+    /// `COLLATE UNICODE_NOCASE`: compares text after Unicode lowercasing, so "Äpfel" and
+    /// "äpfel" are equal and sort together.
+    fn register_unicode_collation(conn: &Connection) -> Result<(), UniSqliteError> {
+        conn.create_collation("UNICODE_NOCASE", |a, b| {
+            a.chars()
+                .flat_map(char::to_lowercase)
+                .cmp(b.chars().flat_map(char::to_lowercase))
+        })?;
+        Ok(())
+    }
+
+    fn register_vector_functions(
+        conn: &Connection,
+        functions: &mut Vec<CustomFunctionInfo>,
+    ) -> Result<(), UniSqliteError> {
+        conn.create_scalar_function(
+            "cosine_similarity",
+            2,
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+            |ctx| {
+                let (Some(a), Some(b)) = (
+                    Self::decode_embedding(ctx.get_raw(0))?,
+                    Self::decode_embedding(ctx.get_raw(1))?,
+                ) else {
+                    return Ok(None);
+                };
+                Self::cosine_similarity(&a, &b)
+                    .map_err(|e| rusqlite::Error::UserFunctionError(e.into()))
+            },
+        )?;
+        Self::push_function(
+            functions,
+            "cosine_similarity",
+            2,
+            true,
+            "Cosine similarity of two packed float32 BLOBs or JSON arrays (NULL if either is NULL)",
+        );
+        Ok(())
+    }
+
+    /// Read an embedding stored as a little-endian float32 BLOB or as JSON array text.
+    fn decode_embedding(
+        value: rusqlite::types::ValueRef<'_>,
+    ) -> Result<Option<Vec<f32>>, rusqlite::Error> {
+        use rusqlite::types::ValueRef;
+        let invalid = |msg: String| rusqlite::Error::UserFunctionError(msg.into());
+        match value {
+            ValueRef::Null => Ok(None),
+            ValueRef::Blob(bytes) => Self::unpack_f32(bytes).map(Some).map_err(invalid),
+            ValueRef::Text(text) => serde_json::from_slice(text)
+                .map(Some)
+                .map_err(|e| invalid(format!("Embedding is not a JSON number array: {e}"))),
+            _ => Err(invalid(
+                "Embedding must be a float32 BLOB or a JSON array".into(),
+            )),
+        }
+    }
+
+    fn unpack_f32(bytes: &[u8]) -> Result<Vec<f32>, String> {
+        if !bytes.len().is_multiple_of(4) {
+            return Err(format!(
+                "Embedding BLOB length {} is not a multiple of 4",
+                bytes.len()
+            ));
+        }
+        Ok(bytes
+            .chunks_exact(4)
+            .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+            .collect())
+    }
+
+    fn pack_f32(values: &[f32]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_le_bytes()).collect()
+    }
+
+    /// Cosine similarity, or `None` when either vector has zero magnitude.
+    fn cosine_similarity(a: &[f32], b: &[f32]) -> Result<Option<f64>, String> {
+        if a.len() != b.len() {
+            return Err(format!(
+                "Embedding dimensions differ: {} vs {}",
+                a.len(),
+                b.len()
+            ));
+        }
+        let (mut dot, mut norm_a, mut norm_b) = (0.0f64, 0.0f64, 0.0f64);
+        for (x, y) in a.iter().zip(b) {
+            let (x, y) = (f64::from(*x), f64::from(*y));
+            dot += x * y;
+            norm_a += x * x;
+            norm_b += y * y;
+        }
+        if norm_a == 0.0 || norm_b == 0.0 {
+            return Ok(None);
+        }
+        Ok(Some(dot / (norm_a.sqrt() * norm_b.sqrt())))
+    }
+
+    /// Remember a function registered on the current connection, for list_functions.
+    fn record_function(&self, name: &str, num_args: i32, deterministic: bool, description: &str) {
+        let mut functions = self.custom_functions.lock().unwrap();
+        Self::push_function(&mut functions, name, num_args, deterministic, description);
+    }
+
+    /// Add a function to `functions` unless one with the same name and arity is listed.
+    fn push_function(
+        functions: &mut Vec<CustomFunctionInfo>,
+        name: &str,
+        num_args: i32,
+        deterministic: bool,
+        description: &str,
+    ) {
+        if !functions
+            .iter()
+            .any(|f| f.name == name && f.num_args == num_args)
+        {
+            functions.push(CustomFunctionInfo {
+                name: name.to_string(),
+                num_args,
+                deterministic,
+                description: description.to_string(),
+            });
+        }
+    }
+
+    /// Whether the linked SQLite was compiled with `option` (e.g. `ENABLE_FTS5`).
+    fn compile_option_used(conn: &Connection, option: &str) -> bool {
+        conn.query_row("SELECT sqlite_compileoption_used(?1)", [option], |row| {
+            row.get::<_, bool>(0)
+        })
+        .unwrap_or(false)
+    }
+
+    /// Check whether the linked SQLite supports window functions (3.25+).
+    fn probe_window_functions(conn: &Connection) -> bool {
+        conn.query_row("SELECT row_number() OVER ()", [], |row| {
+            row.get::<_, i64>(0)
+        })
+        .is_ok()
+    }
+
+    /// Read the current `PRAGMA auto_vacuum` setting as its symbolic name.
+    fn auto_vacuum_mode(conn: &Connection) -> Result<String, UniSqliteError> {
+        let mode: i64 = conn.pragma_query_value(None, "auto_vacuum", |row| row.get(0))?;
+        Ok(match mode {
+            0 => "NONE",
+            1 => "FULL",
+            2 => "INCREMENTAL",
+            _ => "UNKNOWN",
+        }
+        .to_string())
+    }
+
+    /// Validate and apply runtime performance pragmas to a connection.
+    fn apply_performance_settings(
+        conn: &Connection,
+        settings: &PerformanceSettings,
+    ) -> Result<(), UniSqliteError> {
+        if let Some(mmap_size) = settings.mmap_size
+            && mmap_size < 0
+        {
+            return Err(UniSqliteError::QueryFailed(
+                "mmap_size must not be negative".into(),
+            ));
+        }
+
+        let temp_store = settings
+            .temp_store
+            .as_ref()
+            .map(|v| v.trim().to_ascii_uppercase());
+        if let Some(temp_store) = &temp_store
+            && !["DEFAULT", "FILE", "MEMORY"].contains(&temp_store.as_str())
+        {
+            return Err(UniSqliteError::QueryFailed(format!(
+                "Invalid temp_store '{temp_store}': expected DEFAULT, FILE, or MEMORY"
+            )));
+        }
+
+        let synchronous = settings
+            .synchronous
+            .as_ref()
+            .map(|v| v.trim().to_ascii_uppercase());
+        if let Some(synchronous) = &synchronous
+            && !["OFF", "NORMAL", "FULL", "EXTRA"].contains(&synchronous.as_str())
+        {
+            return Err(UniSqliteError::QueryFailed(format!(
+                "Invalid synchronous '{synchronous}': expected OFF, NORMAL, FULL, or EXTRA"
+            )));
+        }
+
+        if let Some(cache_size) = settings.cache_size {
+            conn.pragma_update(None, "cache_size", cache_size)?;
+        }
+        if let Some(mmap_size) = settings.mmap_size {
+            // PRAGMA mmap_size echoes the new value as a row
+            conn.pragma_update_and_check(None, "mmap_size", mmap_size, |row| row.get::<_, i64>(0))?;
+        }
+        if let Some(temp_store) = temp_store {
+            conn.pragma_update(None, "temp_store", temp_store)?;
+        }
+        if let Some(synchronous) = synchronous {
+            conn.pragma_update(None, "synchronous", synchronous)?;
+        }
+
+        Ok(())
+    }
+
+    /// Read back the effective performance pragmas for a connection.
+    fn read_performance_settings(
+        conn: &Connection,
+    ) -> Result<PerformanceSettingsResult, UniSqliteError> {
+        let cache_size: i64 = conn.pragma_query_value(None, "cache_size", |row| row.get(0))?;
+        let mmap_size: i64 = conn.pragma_query_value(None, "mmap_size", |row| row.get(0))?;
+        let temp_store: i64 = conn.pragma_query_value(None, "temp_store", |row| row.get(0))?;
+        let synchronous: i64 = conn.pragma_query_value(None, "synchronous", |row| row.get(0))?;
+
+        Ok(PerformanceSettingsResult {
+            cache_size,
+            mmap_size,
+            temp_store: match temp_store {
+                0 => "DEFAULT",
+                1 => "FILE",
+                2 => "MEMORY",
+                _ => "UNKNOWN",
+            }
+            .to_string(),
+            synchronous: match synchronous {
+                0 => "OFF",
+                1 => "NORMAL",
+                2 => "FULL",
+                3 => "EXTRA",
+                _ => "UNKNOWN",
+            }
+            .to_string(),
+        })
+    }
+
+    /// Log a completed statement (as written, without parameter values) and its timing.
+    fn log_sql_profile(sql: &str, elapsed: std::time::Duration) {
+        tracing::info!(
+            target: "uni_sqlite::sql",
+            elapsed_ms = elapsed.as_millis() as u64,
+            "{sql}"
+        );
+    }
+
+    /// Log a statement with its bound parameter values expanded inline.
+    fn log_sql_trace(sql: &str) {
+        tracing::info!(target: "uni_sqlite::sql", "{sql}");
+    }
+
+    /// Install or remove the SQL logging callbacks on a connection.
+    fn apply_sql_trace(conn: &mut Connection, state: SqlTraceState) {
+        let verbose = state.enabled && state.verbose;
+        conn.profile(
+            state
+                .enabled
+                .then_some(Self::log_sql_profile as fn(&str, _)),
+        );
+        conn.trace(verbose.then_some(Self::log_sql_trace as fn(&str)));
+    }
+
+    /// Whether an error means another connection holds a conflicting lock.
+    fn is_busy_error(err: &rusqlite::Error) -> bool {
+        matches!(
+            err,
+            rusqlite::Error::SqliteFailure(e, _)
+                if e.code == rusqlite::ErrorCode::DatabaseBusy
+                    || e.code == rusqlite::ErrorCode::DatabaseLocked
+        )
+    }
+
+    /// Run a write, retrying with exponential backoff while it fails with SQLITE_BUSY or
+    /// SQLITE_LOCKED, up to the connection's retry limit. Retries are added to `retries`.
+    /// The backoff sleeps the thread, so only call this inside `with_blocking_connection`.
+    fn with_busy_retry<T>(
+        &self,
+        retries: &mut u32,
+        mut op: impl FnMut() -> rusqlite::Result<T>,
+    ) -> rusqlite::Result<T> {
+        let policy = *self.busy_retry.lock().unwrap();
+        let mut delay = policy.backoff;
+        let mut attempt = 0;
+        loop {
+            match op() {
+                Err(e) if attempt < policy.max_retries && Self::is_busy_error(&e) => {
+                    tracing::debug!("Write hit a busy database, retrying in {:?}", delay);
+                    std::thread::sleep(delay);
+                    delay = (delay * 2).min(MAX_BUSY_BACKOFF);
+                    attempt += 1;
+                    *retries += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Commit a transaction, retrying on SQLITE_BUSY. The transaction rolls back when
+    /// dropped if every attempt fails; after a successful COMMIT dropping it is a no-op.
+    fn commit_with_busy_retry(
+        &self,
+        tx: Transaction<'_>,
+        retries: &mut u32,
+    ) -> Result<(), UniSqliteError> {
+        self.with_busy_retry(retries, || tx.execute_batch("COMMIT"))?;
+        Ok(())
+    }
+
+    /// List the schemas on a connection (`main`, `temp`, and any attached databases).
+    fn attached_databases(conn: &Connection) -> Result<Vec<AttachedDatabase>, UniSqliteError> {
+        let mut stmt = conn.prepare("PRAGMA database_list")?;
+        let rows = stmt.query_map([], |row| {
+            let file: String = row.get(2)?;
+            Ok(AttachedDatabase {
+                name: row.get(1)?,
+                // Temporary and in-memory schemas report an empty file name
+                file: (!file.is_empty()).then_some(file),
+            })
+        })?;
+
+        let mut databases = Vec::new();
+        for row in rows {
+            databases.push(row?);
+        }
+        Ok(databases)
+    }
+
+    /// Convert a SQLite value to JSON (BLOBs are hex-encoded).
+    fn sql_value_to_json(value: rusqlite::types::ValueRef<'_>) -> Value {
+        match value {
+            rusqlite::types::ValueRef::Null => Value::Null,
+            rusqlite::types::ValueRef::Integer(i) => Value::Number(i.into()),
+            rusqlite::types::ValueRef::Real(f) => Value::Number(
+                serde_json::Number::from_f64(f).unwrap_or_else(|| serde_json::Number::from(0)),
+            ),
+            rusqlite::types::ValueRef::Text(t) => {
+                Value::String(String::from_utf8_lossy(t).into_owned())
+            }
+            rusqlite::types::ValueRef::Blob(b) => Value::String(hex::encode(b)),
+        }
+    }
+
+    /// Run a row-returning statement and collect its column names and rows as JSON.
+    ///
+    /// In `typed` mode, values are converted using each result column's declared type.
+    /// SQLite only knows that type when the column is a direct reference to a table
+    /// column, so expressions, aggregates, and casts are never converted.
+    fn collect_rows(
+        stmt: &mut rusqlite::Statement<'_>,
+        params: &[&dyn rusqlite::ToSql],
+        typed: bool,
+    ) -> Result<(Vec<String>, Vec<Vec<Value>>), UniSqliteError> {
+        let (column_names, data, _) =
+            Self::collect_rows_with(stmt, params, typed, BlobAs::Content, None)?;
+        Ok((column_names, data))
+    }
+
+    /// `collect_rows`, optionally replacing BLOB cells with `{"blob_bytes": n}` and stopping
+    /// after `limit` rows. The flag reports whether rows were left behind.
+    fn collect_rows_with(
+        stmt: &mut rusqlite::Statement<'_>,
+        params: &[&dyn rusqlite::ToSql],
+        typed: bool,
+        blob_as: BlobAs,
+        limit: Option<usize>,
+    ) -> Result<FetchedRows, UniSqliteError> {
+        let column_names: Vec<String> = stmt.column_names().iter().map(|s| s.to_string()).collect();
+        let hints: Vec<TypeHint> = if typed {
+            stmt.columns()
+                .iter()
+                .map(|c| TypeHint::from_declared_type(c.decl_type()))
+                .collect()
+        } else {
+            vec![TypeHint::None; column_names.len()]
+        };
+
+        let rows = stmt.query_map(params, |row| {
+            let mut values = Vec::with_capacity(hints.len());
+            for (i, hint) in hints.iter().enumerate() {
+                let value = match (row.get_ref(i)?, blob_as) {
+                    (rusqlite::types::ValueRef::Blob(b), BlobAs::Size) => {
+                        serde_json::json!({ "blob_bytes": b.len() })
+                    }
+                    (value, _) => hint.apply(Self::sql_value_to_json(value)),
+                };
+                values.push(value);
+            }
+            Ok(values)
+        })?;
+
+        // One row past the limit shows whether anything was cut off
+        let mut data = Vec::new();
+        for row in rows.take(limit.map_or(usize::MAX, |limit| limit.saturating_add(1))) {
+            data.push(row?);
+        }
+        let truncated = limit.is_some_and(|limit| data.len() > limit);
+        if let Some(limit) = limit {
+            data.truncate(limit);
+        }
+
+        Ok((column_names, data, truncated))
+    }
+
+    /// Run a read-only query (SELECT, PRAGMA, EXPLAIN) and collect all rows.
+    fn run_read_query(
+        conn: &Connection,
+        sql: &str,
+        parameters: &[Value],
+        param_types: &[String],
+        typed: bool,
+    ) -> Result<(Vec<String>, Vec<Vec<Value>>), UniSqliteError> {
+        let (columns, data, _) =
+            Self::run_read_query_limited(conn, sql, parameters, param_types, typed, None)?;
+        Ok((columns, data))
+    }
+
+    /// Run a read query that must return at most one row. Stops stepping at the second
+    /// row, so a query matching millions of rows fails without loading them.
+    fn run_single_row_query(
+        conn: &Connection,
+        sql: &str,
+        parameters: &[Value],
+        param_types: &[String],
+        typed: bool,
+    ) -> Result<(Vec<String>, Option<Vec<Value>>), UniSqliteError> {
+        let (columns, mut data, truncated) =
+            Self::run_read_query_limited(conn, sql, parameters, param_types, typed, Some(1))?;
+        if truncated {
+            return Err(UniSqliteError::QueryFailed(
+                "Expected exactly one row, got more than one".into(),
+            ));
+        }
+        Ok((columns, data.pop()))
+    }
+
+    /// `run_read_query` returning at most `limit` rows and whether more matched.
+    fn run_read_query_limited(
+        conn: &Connection,
+        sql: &str,
+        parameters: &[Value],
+        param_types: &[String],
+        typed: bool,
+        limit: Option<usize>,
+    ) -> Result<FetchedRows, UniSqliteError> {
+        Self::validate_sql_query(sql)?;
+
+        if !Self::is_read_query(sql) {
+            return Err(UniSqliteError::QueryFailed(
+                "Only row-returning queries (SELECT, WITH ... SELECT, VALUES, PRAGMA, EXPLAIN) are allowed"
+                    .into(),
+            ));
+        }
+
+        let params = Self::bind_params(parameters, param_types)?;
+
+        let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| &**p).collect();
+
+        let mut stmt = conn.prepare(sql)?;
+        Self::collect_rows_with(&mut stmt, &param_refs, typed, BlobAs::Content, limit)
+    }
+
+    /// Build a `file:` URI that opens the given database read-only.
+    fn read_only_uri(path: &Path) -> String {
+        let mut uri = String::from("file:");
+        for c in path.to_string_lossy().chars() {
+            match c {
+                '%' => uri.push_str("%25"),
+                '?' => uri.push_str("%3f"),
+                '#' => uri.push_str("%23"),
+                c => uri.push(c),
+            }
+        }
+        uri.push_str("?mode=ro");
+        uri
+    }
+
+    /// Column information for a table, via `PRAGMA table_info`.
+    fn table_columns(
+        conn: &Connection,
+        table_name: &str,
+    ) -> Result<Vec<ColumnInfo>, UniSqliteError> {
+        let mut stmt = conn.prepare(&format!(
+            "PRAGMA table_info({})",
+            Self::quote_ident(table_name)?
+        ))?;
+        let rows = stmt.query_map([], |row| {
+            Ok(ColumnInfo {
+                name: row.get(1)?,
+                data_type: row.get(2)?,
+                not_null: row.get::<_, i32>(3)? != 0,
+                default_value: row.get(4)?,
+                primary_key: row.get::<_, i32>(5)? != 0,
+            })
+        })?;
+
+        let mut columns = Vec::new();
+        for row in rows {
+            columns.push(row?);
+        }
+        Ok(columns)
+    }
+
+    /// Index names for a table, via `PRAGMA index_list`.
+    fn table_indexes(conn: &Connection, table_name: &str) -> Result<Vec<String>, UniSqliteError> {
+        let mut stmt = conn.prepare(&format!(
+            "PRAGMA index_list({})",
+            Self::quote_ident(table_name)?
+        ))?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(1))?;
+
+        let mut indexes = Vec::new();
+        for row in rows {
+            indexes.push(row?);
+        }
+        Ok(indexes)
+    }
+
+    /// Foreign key references for a table, via `PRAGMA foreign_key_list`.
+    fn table_foreign_keys(
+        conn: &Connection,
+        table_name: &str,
+    ) -> Result<Vec<ForeignKeyInfo>, UniSqliteError> {
+        let mut stmt = conn.prepare(&format!(
+            "PRAGMA foreign_key_list({})",
+            Self::quote_ident(table_name)?
+        ))?;
+        let rows = stmt.query_map([], |row| {
+            Ok(ForeignKeyInfo {
+                id: row.get(0)?,
+                from_column: row.get(3)?,
+                to_table: row.get(2)?,
+                to_column: row.get(4)?,
+            })
+        })?;
+
+        let mut foreign_keys = Vec::new();
+        for row in rows {
+            foreign_keys.push(row?);
+        }
+        Ok(foreign_keys)
+    }
+
+    /// Quote an identifier for interpolation into SQL, doubling any embedded double quotes.
+    /// Empty names and names containing NUL are rejected.
+    fn quote_ident(name: &str) -> Result<String, UniSqliteError> {
+        if name.is_empty() || name.contains('\0') {
+            return Err(UniSqliteError::QueryFailed(format!(
+                "Invalid identifier: {:?}",
+                name
+            )));
+        }
+        Ok(format!("\"{}\"", name.replace('"', "\"\"")))
+    }
+
+    /// Quote each identifier and join them into a comma-separated column list.
+    fn quote_ident_list<'a>(
+        names: impl IntoIterator<Item = &'a str>,
+    ) -> Result<String, UniSqliteError> {
+        Ok(names
+            .into_iter()
+            .map(Self::quote_ident)
+            .collect::<Result<Vec<_>, _>>()?
+            .join(", "))
+    }
+
+    /// Check that a column-definition list cannot escape the surrounding `CREATE TABLE (...)`:
+    /// outside of quoted text there must be no statement terminators or comments, and
+    /// parentheses must stay balanced.
+    fn validate_column_definitions(columns: &str) -> Result<(), UniSqliteError> {
+        let invalid = |reason: &str| {
+            Err(UniSqliteError::QueryFailed(format!(
+                "Invalid column definitions: {reason}"
+            )))
+        };
+
+        if columns.trim().is_empty() {
+            return invalid("no columns given");
+        }
+
+        let mut depth = 0usize;
+        let mut quote: Option<char> = None;
+        let mut chars = columns.chars().peekable();
+        while let Some(c) = chars.next() {
+            if let Some(q) = quote {
+                if c == q {
+                    quote = None;
+                }
+                continue;
+            }
+            match c {
+                '\'' | '"' | '`' => quote = Some(c),
+                '[' => quote = Some(']'),
+                '(' => depth += 1,
+                ')' if depth == 0 => return invalid("unbalanced ')'"),
+                ')' => depth -= 1,
+                ';' => return invalid("statement terminators are not allowed"),
+                '-' if chars.peek() == Some(&'-') => return invalid("comments are not allowed"),
+                '/' if chars.peek() == Some(&'*') => return invalid("comments are not allowed"),
+                _ => {}
+            }
+        }
+
+        if quote.is_some() {
+            return invalid("unterminated quote");
+        }
+        if depth != 0 {
+            return invalid("unbalanced '('");
+        }
+        Ok(())
+    }
+
+    /// Split already-validated column definitions on top-level commas.
+    fn split_column_definitions(columns: &str) -> Vec<&str> {
+        let mut parts = Vec::new();
+        let mut depth = 0usize;
+        let mut quote: Option<char> = None;
+        let mut start = 0;
+        for (i, c) in columns.char_indices() {
+            if let Some(q) = quote {
+                if c == q {
+                    quote = None;
+                }
+                continue;
+            }
+            match c {
+                '\'' | '"' | '`' => quote = Some(c),
+                '[' => quote = Some(']'),
+                '(' => depth += 1,
+                ')' => depth = depth.saturating_sub(1),
+                ',' if depth == 0 => {
+                    parts.push(columns[start..i].trim());
+                    start = i + 1;
+                }
+                _ => {}
+            }
+        }
+        parts.push(columns[start..].trim());
+        parts
+    }
+
+    /// Split a column definition into its (possibly quoted) name token and the rest.
+    fn split_column_name(definition: &str) -> (&str, &str) {
+        match definition.chars().next() {
+            Some(open @ ('"' | '`' | '[')) => {
+                let close = if open == '[' { ']' } else { open };
+                let end = definition[1..]
+                    .find(close)
+                    .map(|i| i + 2)
+                    .unwrap_or(definition.len());
+                definition.split_at(end)
+            }
+            _ => definition.split_at(
+                definition
+                    .find(char::is_whitespace)
+                    .unwrap_or(definition.len()),
+            ),
+        }
+    }
+
+    /// Whether an entry of a CREATE TABLE body is a table constraint rather than a column.
+    fn is_table_constraint(definition: &str) -> bool {
+        let (first, _) = Self::split_column_name(definition);
+        ["PRIMARY", "UNIQUE", "CHECK", "FOREIGN", "CONSTRAINT"]
+            .contains(&first.to_ascii_uppercase().as_str())
+    }
+
+    /// Check every column of a STRICT table declares one of the types STRICT accepts, so
+    /// the caller gets the offending column instead of SQLite's generic error.
+    fn validate_strict_types(columns: &str) -> Result<(), UniSqliteError> {
+        const STRICT_TYPES: [&str; 6] = ["INT", "INTEGER", "REAL", "TEXT", "BLOB", "ANY"];
+        const CONSTRAINTS: [&str; 11] = [
+            "CONSTRAINT",
+            "PRIMARY",
+            "NOT",
+            "NULL",
+            "UNIQUE",
+            "CHECK",
+            "DEFAULT",
+            "COLLATE",
+            "REFERENCES",
+            "GENERATED",
+            "AS",
+        ];
+
+        for definition in Self::split_column_definitions(columns) {
+            if Self::is_table_constraint(definition) {
+                continue;
+            }
+            // The type is the first word after the name
+            let (name, rest) = Self::split_column_name(definition);
+
+            let data_type = rest
+                .trim_start()
+                .split(|c: char| c.is_whitespace() || c == '(')
+                .next()
+                .unwrap_or_default()
+                .to_ascii_uppercase();
+            if data_type.is_empty() || CONSTRAINTS.contains(&data_type.as_str()) {
+                return Err(UniSqliteError::QueryFailed(format!(
+                    "Column {name} needs a type in a STRICT table: one of {}",
+                    STRICT_TYPES.join(", ")
+                )));
+            }
+            if !STRICT_TYPES.contains(&data_type.as_str()) {
+                return Err(UniSqliteError::QueryFailed(format!(
+                    "Column {name} has type {data_type}, which STRICT tables do not allow; use one of {}",
+                    STRICT_TYPES.join(", ")
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Collect the `CREATE` statements for a table and its explicit indexes and triggers,
+    /// in the order they must be run to recreate it.
+    fn table_ddl(conn: &Connection, table_name: &str) -> Result<TableDdlResult, UniSqliteError> {
+        let table_sql: Option<String> = conn
+            .query_row(
+                "SELECT sql FROM sqlite_master WHERE type = 'table' AND name = ?",
+                [table_name],
+                |row| row.get(0),
+            )
+            .optional()?
+            .flatten();
+        let table_sql = table_sql.ok_or_else(|| {
+            UniSqliteError::QueryFailed(format!("Table '{table_name}' not found"))
+        })?;
+
+        // Automatic indexes (UNIQUE/PRIMARY KEY constraints) have NULL sql and come back
+        // with the table itself
+        let objects = |object_type: &str| -> Result<Vec<SchemaObjectSql>, UniSqliteError> {
+            let mut stmt = conn.prepare(
+                "SELECT name, sql FROM sqlite_master
+                 WHERE type = ?1 AND tbl_name = ?2 AND sql IS NOT NULL
+                 ORDER BY rowid",
+            )?;
+            let rows = stmt.query_map([object_type, table_name], |row| {
+                Ok(SchemaObjectSql {
+                    name: row.get(0)?,
+                    sql: row.get(1)?,
+                })
+            })?;
+            Ok(rows.collect::<Result<_, _>>()?)
+        };
+        let indexes = objects("index")?;
+        let triggers = objects("trigger")?;
+
+        let script = std::iter::once(table_sql.as_str())
+            .chain(indexes.iter().map(|o| o.sql.as_str()))
+            .chain(triggers.iter().map(|o| o.sql.as_str()))
+            .map(|sql| format!("{sql};\n"))
+            .collect();
+
+        Ok(TableDdlResult {
+            table_name: table_name.to_string(),
+            table_sql,
+            indexes,
+            triggers,
+            script,
+        })
+    }
+
+    /// Read one SQL token: a quoted identifier/string or a bare word. Returns the token
+    /// and the remaining input with leading whitespace trimmed.
+    fn next_sql_token(sql: &str) -> Option<(&str, &str)> {
+        let sql = sql.trim_start();
+        let close = match sql.chars().next()? {
+            '"' => Some('"'),
+            '`' => Some('`'),
+            '\'' => Some('\''),
+            '[' => Some(']'),
+            _ => None,
+        };
+        let end = match close {
+            Some(close) => sql[1..].find(close)? + 2,
+            None => sql
+                .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.' || c == '$'))
+                .unwrap_or(sql.len()),
+        };
+        if end == 0 {
+            return None;
+        }
+        Some((&sql[..end], sql[end..].trim_start()))
+    }
+
+    /// Rewrite a stored `CREATE [UNIQUE] INDEX [IF NOT EXISTS] name ON table ...` statement to
+    /// create `index_name` on `table_name`, keeping the column list and any WHERE clause.
+    fn retarget_index_sql(
+        sql: &str,
+        index_name: &str,
+        table_name: &str,
+    ) -> Result<String, UniSqliteError> {
+        let unparsable = || UniSqliteError::QueryFailed(format!("Cannot parse index SQL: {sql}"));
+
+        let (_create, mut rest) = Self::next_sql_token(sql).ok_or_else(unparsable)?;
+        let (mut token, mut after) = Self::next_sql_token(rest).ok_or_else(unparsable)?;
+        let unique = token.eq_ignore_ascii_case("UNIQUE");
+        if unique {
+            (token, after) = Self::next_sql_token(after).ok_or_else(unparsable)?;
+        }
+        if !token.eq_ignore_ascii_case("INDEX") {
+            return Err(unparsable());
+        }
+        rest = after;
+        if rest.to_ascii_uppercase().starts_with("IF NOT EXISTS") {
+            rest = &rest["IF NOT EXISTS".len()..];
+        }
+        let (_old_name, rest) = Self::next_sql_token(rest).ok_or_else(unparsable)?;
+        let (on, rest) = Self::next_sql_token(rest).ok_or_else(unparsable)?;
+        if !on.eq_ignore_ascii_case("ON") {
+            return Err(unparsable());
+        }
+        let (_old_table, rest) = Self::next_sql_token(rest).ok_or_else(unparsable)?;
+
+        Ok(format!(
+            "CREATE {}INDEX {} ON {}{}",
+            if unique { "UNIQUE " } else { "" },
+            Self::quote_ident(index_name)?,
+            Self::quote_ident(table_name)?,
+            rest
+        ))
+    }
+
+    /// Register `sample_hash(seed, rowid)`, a deterministic SplitMix64 mix used to order
+    /// rows reproducibly for `random_sample`.
+    fn register_sample_hash_function(&self, conn: &Connection) -> Result<(), UniSqliteError> {
+        conn.create_scalar_function(
+            "sample_hash",
+            2,
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+            |ctx| {
+                let seed = ctx.get::<i64>(0)? as u64;
+                let rowid = ctx.get::<i64>(1)? as u64;
+                let mut z = seed ^ rowid.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+                z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+                z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+                Ok((z ^ (z >> 31)) as i64)
+            },
+        )?;
+        self.record_function(
+            "sample_hash",
+            2,
+            true,
+            "Seeded hash of a rowid, used by random_sample",
+        );
+        Ok(())
+    }
+
+    /// Explain a missing `dbstat` virtual table instead of reporting "no such table".
+    fn dbstat_error(e: rusqlite::Error) -> UniSqliteError {
+        if e.to_string().contains("no such table: dbstat") {
+            UniSqliteError::QueryFailed(
+                "The dbstat virtual table is not available in this SQLite build \
+                 (requires SQLITE_ENABLE_DBSTAT_VTAB)"
+                    .into(),
+            )
+        } else {
+            e.into()
+        }
+    }
+
+    /// Build the SELECT for a table-oriented export from an optional projection, filter,
+    /// and ordering.
+    fn table_export_query(
+        table_name: &str,
+        columns: Option<&[String]>,
+        where_clause: Option<&str>,
+        order_by: Option<&str>,
+    ) -> Result<String, UniSqliteError> {
+        let projection = match columns {
+            Some(columns) if !columns.is_empty() => {
+                Self::quote_ident_list(columns.iter().map(String::as_str))?
+            }
+            _ => "*".to_string(),
+        };
+        let mut sql = format!(
+            "SELECT {projection} FROM {}",
+            Self::quote_ident(table_name)?
+        );
+
+        for (keyword, clause) in [("WHERE", where_clause), ("ORDER BY", order_by)] {
+            let Some(clause) = clause.map(str::trim).filter(|c| !c.is_empty()) else {
+                continue;
+            };
+            if clause.contains(';') {
+                return Err(UniSqliteError::QueryFailed(
+                    "Multiple statements are not allowed".into(),
+                ));
+            }
+            sql.push_str(&format!(" {keyword} {clause}"));
+        }
+        Ok(sql)
+    }
+
+    /// Flags used to open the main connection. URI filenames are enabled so
+    /// cross_query can attach files with `mode=ro`.
+    fn open_flags(req: &ConnectRequest) -> OpenFlags {
+        if req.create_if_missing {
+            OpenFlags::SQLITE_OPEN_READ_WRITE
+                | OpenFlags::SQLITE_OPEN_CREATE
+                | OpenFlags::SQLITE_OPEN_URI
+        } else {
+            OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_URI
+        }
+    }
+
+    /// Names of the set flags in `flags`, without the `SQLITE_OPEN_` prefix.
+    fn open_flag_names(flags: OpenFlags) -> Vec<String> {
+        [
+            (OpenFlags::SQLITE_OPEN_READ_ONLY, "READ_ONLY"),
+            (OpenFlags::SQLITE_OPEN_READ_WRITE, "READ_WRITE"),
+            (OpenFlags::SQLITE_OPEN_CREATE, "CREATE"),
+            (OpenFlags::SQLITE_OPEN_URI, "URI"),
+            (OpenFlags::SQLITE_OPEN_MEMORY, "MEMORY"),
+            (OpenFlags::SQLITE_OPEN_NO_MUTEX, "NO_MUTEX"),
+            (OpenFlags::SQLITE_OPEN_FULL_MUTEX, "FULL_MUTEX"),
+            (OpenFlags::SQLITE_OPEN_SHARED_CACHE, "SHARED_CACHE"),
+            (OpenFlags::SQLITE_OPEN_PRIVATE_CACHE, "PRIVATE_CACHE"),
+            (OpenFlags::SQLITE_OPEN_NOFOLLOW, "NOFOLLOW"),
+        ]
+        .into_iter()
+        .filter(|(flag, _)| flags.contains(*flag))
+        .map(|(_, name)| name.to_string())
+        .collect()
+    }
+
+    /// Apply the limits set in `limits` to a connection. SQLite clamps values above
+    /// its compile-time maximum.
+    fn apply_limits(conn: &Connection, limits: &SetLimitsRequest) {
+        for (_, limit, value) in limits.entries() {
+            if let Some(value) = value {
+                conn.set_limit(limit, value);
+            }
+        }
+    }
+
+    /// Upper-cased words outside quotes, comments, and parentheses, with their byte offsets.
+    fn top_level_words(sql: &str) -> Vec<(usize, String)> {
+        let bytes = sql.as_bytes();
+        let mut words = Vec::new();
+        let mut depth = 0usize;
+        let mut i = 0;
+        while i < bytes.len() {
+            match bytes[i] {
+                quote @ (b'\'' | b'"' | b'`' | b'[') => {
+                    let close = if quote == b'[' { ']' } else { quote as char };
+                    i = sql[i + 1..].find(close).map_or(bytes.len(), |p| i + p + 2);
+                }
+                b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                    i = sql[i..].find('\n').map_or(bytes.len(), |p| i + p + 1);
+                }
+                b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                    i = sql[i + 2..].find("*/").map_or(bytes.len(), |p| i + p + 4);
+                }
+                b'(' => {
+                    depth += 1;
+                    i += 1;
+                }
+                b')' => {
+                    depth = depth.saturating_sub(1);
+                    i += 1;
+                }
+                b if b.is_ascii_alphabetic() || b == b'_' => {
+                    let start = i;
+                    while i < bytes.len()
+                        && (bytes[i].is_ascii_alphanumeric()
+                            || bytes[i] == b'_'
+                            || bytes[i] == b'$')
+                    {
+                        i += 1;
+                    }
+                    if depth == 0 {
+                        words.push((start, sql[start..i].to_ascii_uppercase()));
+                    }
+                }
+                _ => i += 1,
+            }
+        }
+        words
+    }
+
+    /// Strip the quotes from an identifier token, undoubling escaped quote characters.
+    fn unquote_ident(token: &str) -> String {
+        match token.chars().next() {
+            Some(q @ ('"' | '`')) if token.len() >= 2 && token.ends_with(q) => {
+                token[1..token.len() - 1].replace(&format!("{q}{q}"), &q.to_string())
+            }
+            Some('[') if token.ends_with(']') => token[1..token.len() - 1].to_string(),
+            _ => token.to_string(),
+        }
+    }
+
+    /// Rewrite a single-table SELECT without ORDER BY to order by the table's rowid, for
+    /// `stable_order`. Queries that can't be ordered that way (aggregates, compounds,
+    /// joins, views, WITHOUT ROWID tables) are returned unchanged with a note saying why.
+    fn stable_order_sql<'a>(
+        conn: &Connection,
+        sql: &'a str,
+    ) -> Result<(Cow<'a, str>, Option<String>), UniSqliteError> {
+        const AGGREGATES: [&str; 10] = [
+            "COUNT",
+            "SUM",
+            "AVG",
+            "MIN",
+            "MAX",
+            "TOTAL",
+            "GROUP_CONCAT",
+            "STRING_AGG",
+            "JSON_GROUP_ARRAY",
+            "JSON_GROUP_OBJECT",
+        ];
+
+        let sql = sql.trim_end().trim_end_matches(';');
+        let words = Self::top_level_words(sql);
+        let has = |word: &str| words.iter().any(|(_, w)| w == word);
+        let skip = |reason: String| {
+            Ok((
+                Cow::Borrowed(sql),
+                Some(format!("stable_order not applied: {reason}")),
+            ))
+        };
+
+        if words.first().map(|(_, w)| w.as_str()) != Some("SELECT") {
+            return skip("only plain SELECT statements are reordered".into());
+        }
+        if words
+            .windows(2)
+            .any(|pair| pair[0].1 == "ORDER" && pair[1].1 == "BY")
+        {
+            return Ok((Cow::Borrowed(sql), None));
+        }
+        if ["UNION", "INTERSECT", "EXCEPT"].iter().any(|w| has(w)) {
+            return skip("compound SELECT".into());
+        }
+        if ["GROUP", "HAVING", "DISTINCT"].iter().any(|w| has(w))
+            || AGGREGATES.iter().any(|w| has(w))
+        {
+            return skip("aggregate and DISTINCT queries have no per-row rowid".into());
+        }
+
+        let Some(from) = words.iter().position(|(_, w)| w == "FROM") else {
+            return skip("no FROM clause".into());
+        };
+        let clause = words[from + 1..]
+            .iter()
+            .find(|(_, w)| matches!(w.as_str(), "WHERE" | "LIMIT" | "WINDOW"));
+        let source = &sql[words[from].0 + "FROM".len()..clause.map_or(sql.len(), |(o, _)| *o)];
+
+        // Expect `table [[AS] alias]` and nothing else
+        let single_table = Self::next_sql_token(source).and_then(|(table, rest)| {
+            let rest = match Self::next_sql_token(rest) {
+                Some((kw, after)) if kw.eq_ignore_ascii_case("AS") => after,
+                _ => rest,
+            };
+            match Self::next_sql_token(rest) {
+                None if rest.is_empty() => Some((table, None)),
+                Some((alias, "")) if !alias.starts_with('\'') => Some((table, Some(alias))),
+                _ => None,
+            }
+        });
+        let Some((table, alias)) = single_table.filter(|_| !source.contains([',', '('])) else {
+            return skip("only single-table queries are reordered (no joins or subqueries)".into());
+        };
+
+        let (schema, name) = match table.split_once('.') {
+            Some((schema, name)) if !table.starts_with(['"', '`', '[']) => {
+                (Some(schema.to_string()), name.to_string())
+            }
+            _ => (None, Self::unquote_ident(table)),
+        };
+        let kind: Option<(String, bool)> = conn
+            .query_row(
+                "SELECT type, wr FROM pragma_table_list
+                 WHERE name = ?1 COLLATE NOCASE AND (?2 IS NULL OR schema = ?2 COLLATE NOCASE)
+                 ORDER BY CASE schema WHEN 'temp' THEN 0 WHEN 'main' THEN 1 ELSE 2 END
+                 LIMIT 1",
+                rusqlite::params![name, schema],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        match kind {
+            None => return skip(format!("'{name}' is not a table")),
+            Some((kind, _)) if kind != "table" => {
+                return skip(format!("'{name}' is a {kind}, which has no stable rowid"));
+            }
+            Some((_, true)) => return skip(format!("'{name}' is a WITHOUT ROWID table")),
+            Some(_) => {}
+        }
+
+        let order_by = format!("ORDER BY {}.rowid", alias.unwrap_or(table));
+        let limit = words[from + 1..].iter().find(|(_, w)| w == "LIMIT");
+        // A newline keeps a trailing `--` comment from swallowing the appended clause
+        let ordered = match limit {
+            Some((offset, _)) => format!("{}{order_by} {}", &sql[..*offset], &sql[*offset..]),
+            None => format!("{sql}\n{order_by}"),
+        };
+        Ok((
+            Cow::Owned(ordered),
+            Some(format!("Ordered by rowid of '{name}'")),
+        ))
+    }
+
+    /// Size of the main database in bytes, as `page_count * page_size`.
+    fn database_bytes(conn: &Connection) -> Result<i64, UniSqliteError> {
+        let page_count: i64 = conn.pragma_query_value(None, "page_count", |row| row.get(0))?;
+        let page_size: i64 = conn.pragma_query_value(None, "page_size", |row| row.get(0))?;
+        Ok(page_count * page_size)
+    }
+
+    /// Run `op` inside a savepoint that is always rolled back, so a dry run leaves the
+    /// database untouched whether or not `op` succeeds.
+    fn with_rollback<T>(
+        conn: &Connection,
+        op: impl FnOnce() -> Result<T, UniSqliteError>,
+    ) -> Result<T, UniSqliteError> {
+        conn.execute_batch("SAVEPOINT dry_run")?;
+        let result = op();
+        conn.execute_batch("ROLLBACK TO dry_run; RELEASE dry_run")?;
+        result
+    }
+
+    /// Current change counters of a connection, used to validate cached query results.
+    fn data_stamp(conn: &Connection) -> Result<DataStamp, UniSqliteError> {
+        Ok(DataStamp {
+            total_changes: conn.total_changes(),
+            data_version: conn.pragma_query_value(None, "data_version", |row| row.get(0))?,
+            schema_version: conn.pragma_query_value(None, "schema_version", |row| row.get(0))?,
+        })
+    }
+
+    /// Convert JSON parameters to rusqlite parameters, binding each as the SQLite type
+    /// named at the same position in `param_types` when one is given.
+    fn bind_params(
+        parameters: &[Value],
+        param_types: &[String],
+    ) -> Result<Vec<Box<dyn rusqlite::ToSql>>, UniSqliteError> {
+        if param_types.len() > parameters.len() {
+            return Err(UniSqliteError::QueryFailed(format!(
+                "{} param_types given for {} parameters",
+                param_types.len(),
+                parameters.len()
+            )));
+        }
+        parameters
+            .iter()
+            .enumerate()
+            .map(|(i, value)| match param_types.get(i) {
+                Some(sql_type) => Self::coerce_sql_param(value, sql_type),
+                None => Self::json_to_sql_param(value),
+            })
+            .collect()
+    }
+
+    /// Convert a JSON value to a rusqlite parameter of the given SQLite type. JSON null
+    /// always binds as NULL.
+    fn coerce_sql_param(
+        value: &Value,
+        sql_type: &str,
+    ) -> Result<Box<dyn rusqlite::ToSql>, UniSqliteError> {
+        let mismatch = || UniSqliteError::QueryFailed(format!("Cannot bind {value} as {sql_type}"));
+        let sql_type = sql_type.trim().to_ascii_uppercase();
+
+        if sql_type.is_empty() || sql_type == "AUTO" {
+            return Self::json_to_sql_param(value);
+        }
+        if value.is_null() {
+            return Ok(Box::new(rusqlite::types::Null));
+        }
+
+        match sql_type.as_str() {
+            "INTEGER" => {
+                let i = match value {
+                    Value::Number(n) => n.as_i64().or_else(|| {
+                        n.as_f64()
+                            .filter(|f| f.fract() == 0.0 && f.abs() < i64::MAX as f64)
+                            .map(|f| f as i64)
+                    }),
+                    Value::String(s) => s.trim().parse().ok(),
+                    Value::Bool(b) => Some(i64::from(*b)),
+                    _ => None,
+                };
+                Ok(Box::new(i.ok_or_else(mismatch)?))
+            }
+            "REAL" => {
+                let f = match value {
+                    Value::Number(n) => n.as_f64(),
+                    Value::String(s) => s.trim().parse().ok(),
+                    Value::Bool(b) => Some(f64::from(u8::from(*b))),
+                    _ => None,
+                };
+                Ok(Box::new(f.ok_or_else(mismatch)?))
+            }
+            "TEXT" => match value {
+                Value::String(s) => Ok(Box::new(s.clone())),
+                Value::Number(n) => Ok(Box::new(n.to_string())),
+                Value::Bool(b) => Ok(Box::new(b.to_string())),
+                _ => Err(mismatch()),
+            },
+            "BLOB" => match value {
+                Value::String(s) => Ok(Box::new(hex::decode(s.trim()).map_err(|_| mismatch())?)),
+                _ => Err(mismatch()),
+            },
+            _ => Err(UniSqliteError::QueryFailed(format!(
+                "Unknown parameter type '{sql_type}': expected integer, real, text, blob, or auto"
+            ))),
+        }
+    }
+
+    /// Database, table, and column each result column of `sql` is read from.
+    ///
+    /// rusqlite has no wrapper for `sqlite3_column_table_name` and friends, so this prepares
+    /// a raw statement (never stepped) to read them. The bundled SQLite is built with
+    /// SQLITE_ENABLE_COLUMN_METADATA, which these functions require.
+    fn column_origins(
+        conn: &Connection,
+        sql: &str,
+    ) -> Result<Vec<[Option<String>; 3]>, UniSqliteError> {
+        use rusqlite::ffi;
+        use std::ffi::{CStr, CString, c_char};
+
+        let c_sql = CString::new(sql)
+            .map_err(|_| UniSqliteError::QueryFailed("SQL contains a NUL byte".into()))?;
+        let text = |ptr: *const c_char| {
+            // SAFETY: SQLite returns NULL or a NUL-terminated string owned by the statement,
+            // which outlives this call
+            (!ptr.is_null()).then(|| {
+                unsafe { CStr::from_ptr(ptr) }
+                    .to_string_lossy()
+                    .into_owned()
+            })
+        };
+
+        // SAFETY: the connection handle is valid while `conn` is borrowed, and the raw
+        // statement is finalized before returning
+        unsafe {
+            let mut stmt = std::ptr::null_mut();
+            let rc = ffi::sqlite3_prepare_v2(
+                conn.handle(),
+                c_sql.as_ptr(),
+                -1,
+                &mut stmt,
+                std::ptr::null_mut(),
+            );
+            if rc != ffi::SQLITE_OK {
+                return Err(UniSqliteError::QueryFailed(format!(
+                    "Failed to prepare statement (code {rc})"
+                )));
+            }
+
+            let origins = (0..ffi::sqlite3_column_count(stmt))
+                .map(|i| {
+                    [
+                        text(ffi::sqlite3_column_database_name(stmt, i)),
+                        text(ffi::sqlite3_column_table_name(stmt, i)),
+                        text(ffi::sqlite3_column_origin_name(stmt, i)),
+                    ]
+                })
+                .collect();
+            ffi::sqlite3_finalize(stmt);
+            Ok(origins)
+        }
+    }
+
+    /// Split a `file:` URI into its path and query string, validate the path with
+    /// `validate_db_path`, and rebuild the URI around the validated path. Returns the
+    /// validated path alongside the URI to open.
+    fn resolve_db_uri(&self, uri: &str) -> Result<(PathBuf, PathBuf), UniSqliteError> {
+        let rest = uri
+            .strip_prefix("file:")
+            .ok_or_else(|| UniSqliteError::InvalidPath("URI must start with file:".into()))?;
+        let rest = rest.split('#').next().unwrap_or_default();
+        let (raw_path, query) = match rest.split_once('?') {
+            Some((path, query)) => (path, Some(query)),
+            None => (rest, None),
+        };
+
+        // file://host/path: only an empty or localhost authority is meaningful
+        let raw_path = match raw_path.strip_prefix("//") {
+            Some(authority_and_path) => {
+                let slash = authority_and_path
+                    .find('/')
+                    .unwrap_or(authority_and_path.len());
+                let (authority, path) = authority_and_path.split_at(slash);
+                if !authority.is_empty() && authority != "localhost" {
+                    return Err(UniSqliteError::InvalidPath(format!(
+                        "Unsupported URI authority: {authority}"
+                    )));
+                }
+                path
+            }
+            None => raw_path,
+        };
+
+        let path = self.validate_db_path(&PathBuf::from(Self::percent_decode(raw_path)?))?;
+
+        let mut rebuilt = String::from("file:");
+        for c in path.to_string_lossy().chars() {
+            match c {
+                '%' => rebuilt.push_str("%25"),
+                '?' => rebuilt.push_str("%3f"),
+                '#' => rebuilt.push_str("%23"),
+                c => rebuilt.push(c),
+            }
+        }
+        if let Some(query) = query {
+            rebuilt.push('?');
+            rebuilt.push_str(query);
+        }
+
+        Ok((path, PathBuf::from(rebuilt)))
+    }
+
+    /// Decode `%XX` escapes in a URI path component.
+    fn percent_decode(input: &str) -> Result<String, UniSqliteError> {
+        let bytes = input.as_bytes();
+        let mut decoded = Vec::with_capacity(bytes.len());
+        let mut i = 0;
+        while i < bytes.len() {
+            if bytes[i] == b'%' {
+                let byte = input
+                    .get(i + 1..i + 3)
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                    .ok_or_else(|| {
+                        UniSqliteError::InvalidPath(format!(
+                            "Invalid percent escape in URI: {input}"
+                        ))
+                    })?;
+                decoded.push(byte);
+                i += 3;
+            } else {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+        String::from_utf8(decoded)
+            .map_err(|_| UniSqliteError::InvalidPath("URI path is not valid UTF-8".into()))
+    }
+
+    /// Resolve an extension `name` to a file directly inside `dir`. Names carrying a
+    /// path (separators, `..`, absolute paths) are rejected, and the platform suffix
+    /// (`.so`, `.dylib`, `.dll`) is tried when the bare name does not exist.
+    #[cfg(feature = "extensions")]
+    fn resolve_extension(dir: &Path, name: &str) -> Result<PathBuf, UniSqliteError> {
+        let mut components = Path::new(name).components();
+        if !matches!(
+            (components.next(), components.next()),
+            (Some(std::path::Component::Normal(_)), None)
+        ) {
+            return Err(UniSqliteError::InvalidPath(format!(
+                "Extension name must be a plain file name, got {name}"
+            )));
+        }
+
+        let dir = dir
+            .canonicalize()
+            .map_err(|e| UniSqliteError::InvalidPath(format!("Extension directory: {e}")))?;
+        let candidates = [
+            dir.join(name),
+            dir.join(format!("{name}.{}", std::env::consts::DLL_EXTENSION)),
+        ];
+        let path = candidates
+            .iter()
+            .find(|path| path.is_file())
+            .ok_or_else(|| {
+                UniSqliteError::InvalidPath(format!("Extension {name} not found in allowlist"))
+            })?
+            .canonicalize()?;
+
+        // A symlink must not lead outside the allowlisted directory
+        if path.parent() != Some(dir.as_path()) {
+            return Err(UniSqliteError::InvalidPath(format!(
+                "Extension {name} resolves outside the allowlisted directory"
+            )));
+        }
+        Ok(path)
+    }
+
+    /// Vector column name for vector index tools. The extensions parse the column
+    /// definition themselves, so only plain identifiers are accepted.
+    #[cfg(feature = "extensions")]
+    fn vector_column(column: Option<&str>) -> Result<String, UniSqliteError> {
+        let column = column.unwrap_or("embedding");
+        let mut chars = column.chars();
+        let valid = chars
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid {
+            return Err(UniSqliteError::QueryFailed(format!(
+                "Vector column must be a plain identifier, got {column:?}"
+            )));
+        }
+        Ok(column.to_string())
+    }
+
+    /// Encode a query vector as the JSON array text both vector extensions accept.
+    #[cfg(feature = "extensions")]
+    fn vector_json(vector: &[f64]) -> Result<String, UniSqliteError> {
+        if vector.is_empty() {
+            return Err(UniSqliteError::QueryFailed("query_vector is empty".into()));
+        }
+        Ok(serde_json::to_string(vector)?)
+    }
+
+    /// Nearest-neighbour query for `backend`, binding the query vector as `?1` and k as `?2`.
+    #[cfg(feature = "extensions")]
+    fn vector_search_sql(
+        backend: VectorBackend,
+        table: &str,
+        column: &str,
+        join_table: Option<&str>,
+    ) -> Result<String, UniSqliteError> {
+        let table = Self::quote_ident(table)?;
+        let matches = match backend {
+            VectorBackend::SqliteVec => {
+                format!("SELECT rowid, distance FROM {table} WHERE {column} MATCH ?1 AND k = ?2")
+            }
+            VectorBackend::Vss => format!(
+                "SELECT rowid, distance FROM {table} WHERE vss_search({column}, vss_search_params(?1, ?2))"
+            ),
+        };
+        Ok(match join_table {
+            Some(join_table) => format!(
+                "SELECT m.rowid AS rowid, m.distance AS distance, j.* FROM ({matches}) m \
+                 JOIN {} j ON j.rowid = m.rowid ORDER BY m.distance",
+                Self::quote_ident(join_table)?
+            ),
+            None => format!("{matches} ORDER BY distance"),
+        })
+    }
+
+    /// Point at `load_extension` when the vector module is missing.
+    #[cfg(feature = "extensions")]
+    fn vector_module_error(err: rusqlite::Error, backend: VectorBackend) -> UniSqliteError {
+        if err.to_string().contains("no such module") {
+            UniSqliteError::QueryFailed(format!(
+                "{} module not available; load the vector extension with load_extension first",
+                backend.module()
+            ))
+        } else {
+            err.into()
+        }
+    }
+
+    /// Fail with "Table not found" unless `table` is an existing table.
+    fn ensure_table_exists(conn: &Connection, table: &str) -> Result<(), UniSqliteError> {
+        let exists: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?)",
+            [table],
+            |row| row.get(0),
+        )?;
+        if exists {
+            Ok(())
+        } else {
+            Err(UniSqliteError::QueryFailed(format!(
+                "Table '{table}' not found"
+            )))
+        }
+    }
+
+    fn changelog_trigger_name(table: &str, op: &str) -> String {
+        format!("{CHANGELOG_TABLE}_{table}_{}", op.to_ascii_lowercase())
+    }
+
+    /// Set or clear `deleted_at` on rows matching the request, skipping rows already in
+    /// the target state so the count reflects real changes.
+    fn set_soft_deleted(
+        conn: &Connection,
+        req: &SoftDeleteRequest,
+        deleted: bool,
+    ) -> Result<usize, UniSqliteError> {
+        let where_clause = req.where_clause.trim();
+        if where_clause.contains(';') {
+            return Err(UniSqliteError::QueryFailed(
+                "Multiple statements are not allowed".into(),
+            ));
+        }
+        if where_clause.is_empty() && !req.allow_full_table {
+            return Err(UniSqliteError::QueryFailed(
+                "A where_clause is required; set allow_full_table to affect every row".into(),
+            ));
+        }
+        if !Self::table_columns(conn, &req.table_name)?
+            .iter()
+            .any(|c| c.name.eq_ignore_ascii_case(SOFT_DELETE_COLUMN))
+        {
+            return Err(UniSqliteError::QueryFailed(format!(
+                "Table '{}' has no {SOFT_DELETE_COLUMN} column; call enable_soft_delete first",
+                req.table_name
+            )));
+        }
+
+        let (value, state) = if deleted {
+            ("strftime('%Y-%m-%dT%H:%M:%fZ', 'now')", "IS NULL")
+        } else {
+            ("NULL", "IS NOT NULL")
+        };
+        let mut sql = format!(
+            "UPDATE {} SET {SOFT_DELETE_COLUMN} = {value} WHERE {SOFT_DELETE_COLUMN} {state}",
+            Self::quote_ident(&req.table_name)?
+        );
+        if !where_clause.is_empty() {
+            sql.push_str(&format!(" AND ({where_clause})"));
+        }
+
+        let params: Vec<Box<dyn rusqlite::ToSql>> = req
+            .parameters
+            .iter()
+            .map(Self::json_to_sql_param)
+            .collect::<Result<_, _>>()?;
+        let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| &**p).collect();
+
+        Ok(conn.execute(&sql, &param_refs[..])?)
+    }
+
+    /// Metadata entries ordered by key, optionally just `key`. Empty until the first
+    /// `set_meta` creates the table.
+    fn meta_entries(
+        conn: &Connection,
+        key: Option<&str>,
+    ) -> Result<Vec<MetaEntry>, UniSqliteError> {
+        let exists: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?)",
+            [META_TABLE],
+            |row| row.get(0),
+        )?;
+        if !exists {
+            return Ok(Vec::new());
+        }
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT key, value, updated_at FROM {META_TABLE}
+             WHERE ?1 IS NULL OR key = ?1 ORDER BY key"
+        ))?;
+        let rows = stmt.query_map([key], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })?;
+
+        let mut entries = Vec::new();
+        for row in rows {
+            let (key, value, updated_at) = row?;
+            entries.push(MetaEntry {
+                // Values written outside set_meta may not be JSON; return them as strings
+                value: serde_json::from_str(&value).unwrap_or(Value::String(value)),
+                key,
+                updated_at,
+            });
+        }
+        Ok(entries)
+    }
+
+    /// Schema objects with SQL (auto-indexes excluded), in creation order.
+    fn schema_entries(conn: &Connection) -> Result<Vec<SchemaEntry>, UniSqliteError> {
+        let mut stmt = conn.prepare(
+            "SELECT type, name, tbl_name, sql FROM sqlite_master
+             WHERE sql IS NOT NULL AND name NOT LIKE 'sqlite_%' ORDER BY rowid",
+        )?;
+        let entries = stmt
+            .query_map([], |row| {
+                Ok(SchemaEntry {
+                    object_type: row.get(0)?,
+                    name: row.get(1)?,
+                    table: row.get(2)?,
+                    sql: row.get(3)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(entries)
+    }
+
+    /// Canonical form of a statement for comparison: whitespace collapsed, keywords and
+    /// identifiers lower-cased outside quotes, no spaces around punctuation.
+    fn normalize_sql(sql: &str) -> String {
+        const PUNCTUATION: [char; 5] = ['(', ')', ',', ';', '='];
+        let mut out = String::with_capacity(sql.len());
+        let mut quote: Option<char> = None;
+        let mut space = false;
+        for c in sql.trim().trim_end_matches(';').chars() {
+            if let Some(q) = quote {
+                out.push(c);
+                if c == q {
+                    quote = None;
+                }
+                continue;
+            }
+            if c.is_whitespace() {
+                space = true;
+                continue;
+            }
+            if space && !PUNCTUATION.contains(&c) && !out.ends_with(PUNCTUATION) {
+                out.push(' ');
+            }
+            space = false;
+            match c {
+                '\'' | '"' | '`' => quote = Some(c),
+                '[' => quote = Some(']'),
+                _ => {}
+            }
+            out.push(c.to_ascii_lowercase());
+        }
+        out
+    }
+
+    /// Byte offset of the first `(` outside quotes.
+    fn first_open_paren(sql: &str) -> Option<usize> {
+        let mut quote: Option<char> = None;
+        for (i, c) in sql.char_indices() {
+            match quote {
+                Some(q) if c == q => quote = None,
+                Some(_) => {}
+                None => match c {
+                    '\'' | '"' | '`' => quote = Some(c),
+                    '[' => quote = Some(']'),
+                    '(' => return Some(i),
+                    _ => {}
+                },
+            }
+        }
+        None
+    }
+
+    /// Comparison key for a schema entry. Tables are compared from their column list on,
+    /// since renames and ALTERs rewrite how SQLite stores the table name.
+    fn schema_key(entry: &SchemaEntry) -> String {
+        match (
+            entry.object_type.as_str(),
+            Self::first_open_paren(&entry.sql),
+        ) {
+            ("table", Some(open)) => Self::normalize_sql(&entry.sql[open..]),
+            _ => Self::normalize_sql(&entry.sql),
+        }
+    }
+
+    /// Column definitions and table constraints of a CREATE TABLE, plus the table options
+    /// (STRICT, WITHOUT ROWID) after the closing parenthesis.
+    fn create_table_parts(sql: &str) -> Option<(Vec<&str>, &str)> {
+        let open = Self::first_open_paren(sql)?;
+        let mut depth = 0usize;
+        let mut quote: Option<char> = None;
+        for (i, c) in sql[open..].char_indices() {
+            match quote {
+                Some(q) if c == q => quote = None,
+                Some(_) => {}
+                None => match c {
+                    '\'' | '"' | '`' => quote = Some(c),
+                    '[' => quote = Some(']'),
+                    '(' => depth += 1,
+                    ')' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            let close = open + i;
+                            return Some((
+                                Self::split_column_definitions(&sql[open + 1..close]),
+                                &sql[close + 1..],
+                            ));
+                        }
+                    }
+                    _ => {}
+                },
+            }
+        }
+        None
+    }
+
+    fn entries_of<'a>(entries: &'a [SchemaEntry], object_type: &str) -> Vec<&'a SchemaEntry> {
+        entries
+            .iter()
+            .filter(|e| e.object_type == object_type)
+            .collect()
+    }
+
+    /// Split CREATE TABLE body entries into (unquoted name, definition) columns and
+    /// normalized table constraints.
+    fn split_table_body(definitions: Vec<&str>) -> (Vec<(String, &str)>, Vec<String>) {
+        let (constraints, columns): (Vec<&str>, Vec<&str>) = definitions
+            .into_iter()
+            .partition(|d| Self::is_table_constraint(d));
+        let columns = columns
+            .into_iter()
+            .map(|d| (Self::unquote_ident(Self::split_column_name(d).0), d))
+            .collect();
+        let constraints = constraints.into_iter().map(Self::normalize_sql).collect();
+        (columns, constraints)
+    }
+
+    /// Statements migrating `current`'s schema to `target`'s, plus descriptions of the
+    /// steps that lose data.
+    fn migration_statements(
+        current: &Connection,
+        target: &Connection,
+    ) -> Result<MigrationSteps, UniSqliteError> {
+        let current_entries = Self::schema_entries(current)?;
+        let target_entries = Self::schema_entries(target)?;
+        let find = |entries: &'_ [SchemaEntry], object_type: &str, name: &str| {
+            entries
+                .iter()
+                .find(|e| e.object_type == object_type && e.name.eq_ignore_ascii_case(name))
+                .map(Self::schema_key)
+        };
+
+        let mut destructive = Vec::new();
+        let mut dropped_tables = Vec::new();
+        for table in Self::entries_of(&current_entries, "table") {
+            if find(&target_entries, "table", &table.name).is_none() {
+                destructive.push(format!("drops table '{}'", table.name));
+                dropped_tables.push(table);
+            }
+        }
+
+        let mut table_steps = Vec::new();
+        let mut rebuilt = Vec::new();
+        for table in Self::entries_of(&target_entries, "table") {
+            let Some(existing) = current_entries
+                .iter()
+                .find(|e| e.object_type == "table" && e.name.eq_ignore_ascii_case(&table.name))
+            else {
+                table_steps.push(table.sql.clone());
+                continue;
+            };
+            if Self::schema_key(existing) == Self::schema_key(table) {
+                continue;
+            }
+            if let Some((steps, dropped_columns)) =
+                Self::alter_table_steps(current, existing, table)?
+            {
+                for column in dropped_columns {
+                    destructive.push(format!("drops column '{}.{column}'", table.name));
+                }
+                table_steps.extend(steps);
+            } else {
+                let (steps, lost_columns) =
+                    Self::rebuild_table_steps(current, target, existing, table)?;
+                for column in lost_columns {
+                    destructive.push(format!("drops column '{}.{column}'", table.name));
+                }
+                table_steps.extend(steps);
+                rebuilt.push(table.name.to_ascii_lowercase());
+            }
+        }
+        let is_rebuilt = |table: &str| rebuilt.contains(&table.to_ascii_lowercase());
+        let is_dropped = |table: &str| {
+            dropped_tables
+                .iter()
+                .any(|t| t.name.eq_ignore_ascii_case(table))
+        };
+
+        // Renaming a rebuilt table into place fails while views or triggers reference the
+        // dropped original, so a rebuild recreates all of them
+        let recreate_all = !rebuilt.is_empty();
+        let mut statements = Vec::new();
+        for kind in ["view", "trigger"] {
+            for entry in Self::entries_of(&current_entries, kind) {
+                let unchanged = !recreate_all
+                    && find(&target_entries, kind, &entry.name) == Some(Self::schema_key(entry));
+                if !unchanged && !is_dropped(&entry.table) {
+                    statements.push(format!(
+                        "DROP {} IF EXISTS {}",
+                        kind.to_ascii_uppercase(),
+                        Self::quote_ident(&entry.name)?
+                    ));
+                }
+            }
+        }
+        for index in Self::entries_of(&current_entries, "index") {
+            let unchanged =
+                find(&target_entries, "index", &index.name) == Some(Self::schema_key(index));
+            if !unchanged && !is_dropped(&index.table) && !is_rebuilt(&index.table) {
+                statements.push(format!(
+                    "DROP INDEX IF EXISTS {}",
+                    Self::quote_ident(&index.name)?
+                ));
+            }
+        }
+        for table in &dropped_tables {
+            statements.push(format!("DROP TABLE {}", Self::quote_ident(&table.name)?));
+        }
+        statements.extend(table_steps);
+
+        for kind in ["index", "trigger", "view"] {
+            for entry in Self::entries_of(&target_entries, kind) {
+                let rebuilt_dependency = match kind {
+                    "index" => is_rebuilt(&entry.table),
+                    _ => recreate_all,
+                };
+                let unchanged = !rebuilt_dependency
+                    && find(&current_entries, kind, &entry.name) == Some(Self::schema_key(entry));
+                if !unchanged {
+                    statements.push(entry.sql.clone());
+                }
+            }
+        }
+
+        Ok((statements, destructive))
+    }
+
+    /// ALTER TABLE ADD/DROP COLUMN steps turning `existing` into `target`, or `None` when
+    /// the change needs a rebuild: changed column definitions, constraints, or options,
+    /// reordered columns, or added/dropped columns SQLite cannot ALTER in place.
+    fn alter_table_steps(
+        conn: &Connection,
+        existing: &SchemaEntry,
+        target: &SchemaEntry,
+    ) -> Result<Option<MigrationSteps>, UniSqliteError> {
+        let (Some((current_defs, current_options)), Some((target_defs, target_options))) = (
+            Self::create_table_parts(&existing.sql),
+            Self::create_table_parts(&target.sql),
+        ) else {
+            return Ok(None);
+        };
+        if Self::normalize_sql(current_options) != Self::normalize_sql(target_options) {
+            return Ok(None);
+        }
+
+        let (current_columns, current_constraints) = Self::split_table_body(current_defs);
+        let (target_columns, target_constraints) = Self::split_table_body(target_defs);
+        if current_constraints != target_constraints {
+            return Ok(None);
+        }
+
+        let has = |columns: &[(String, &str)], name: &str| {
+            columns.iter().any(|(n, _)| n.eq_ignore_ascii_case(name))
+        };
+        let kept: Vec<&(String, &str)> = current_columns
+            .iter()
+            .filter(|(name, _)| has(&target_columns, name))
+            .collect();
+        // ADD COLUMN appends, so the target must list the kept columns first, in order
+        for (i, (name, definition)) in target_columns.iter().enumerate() {
+            let in_place = match kept.get(i) {
+                Some((kept_name, kept_definition)) => {
+                    kept_name.eq_ignore_ascii_case(name)
+                        && Self::normalize_sql(kept_definition) == Self::normalize_sql(definition)
+                }
+                None => !has(&current_columns, name),
+            };
+            if !in_place {
+                return Ok(None);
+            }
+        }
+
+        let table = Self::quote_ident(&existing.name)?;
+        let mut steps = Vec::new();
+        for (_, definition) in &target_columns[kept.len()..] {
+            let normalized = Self::normalize_sql(definition);
+            let needs_rebuild = [
+                "primary key",
+                "unique",
+                "current_",
+                "generated",
+                " as(",
+                "default(",
+            ]
+            .iter()
+            .any(|keyword| normalized.contains(keyword))
+                || (normalized.contains("not null") && !normalized.contains("default"));
+            if needs_rebuild {
+                return Ok(None);
+            }
+            steps.push(format!("ALTER TABLE {table} ADD COLUMN {definition}"));
+        }
+
+        let mut dropped = Vec::new();
+        for (name, definition) in &current_columns {
+            if has(&target_columns, name) {
+                continue;
+            }
+            let normalized = Self::normalize_sql(definition);
+            let lower = name.to_ascii_lowercase();
+            let indexed: bool = conn.query_row(
+                "SELECT EXISTS(SELECT 1 FROM pragma_index_list(?1) il, pragma_index_info(il.name) ii
+                 WHERE ii.name = ?2 COLLATE NOCASE)",
+                [&existing.name, name],
+                |row| row.get(0),
+            )?;
+            if indexed
+                || ["primary key", "unique", "references"]
+                    .iter()
+                    .any(|keyword| normalized.contains(keyword))
+                || current_constraints.iter().any(|c| c.contains(&lower))
+            {
+                return Ok(None);
+            }
+            steps.push(format!(
+                "ALTER TABLE {table} DROP COLUMN {}",
+                Self::quote_ident(name)?
+            ));
+            dropped.push(name.clone());
+        }
+
+        Ok(Some((steps, dropped)))
+    }
+
+    /// Rebuild `existing` as `target`: create the new table under a scratch name, copy the
+    /// shared columns, drop the original, and rename the copy into place. Also returns the
+    /// current columns the target lacks.
+    fn rebuild_table_steps(
+        current: &Connection,
+        target_conn: &Connection,
+        existing: &SchemaEntry,
+        target: &SchemaEntry,
+    ) -> Result<MigrationSteps, UniSqliteError> {
+        let open = Self::first_open_paren(&target.sql).ok_or_else(|| {
+            UniSqliteError::QueryFailed(format!("Cannot rebuild table '{}'", target.name))
+        })?;
+        let new_table = Self::quote_ident(&format!("_migration_new_{}", target.name))?;
+        let table = Self::quote_ident(&existing.name)?;
+
+        let current_columns: Vec<String> = Self::table_columns(current, &existing.name)?
+            .into_iter()
+            .map(|c| c.name)
+            .collect();
+        // Generated columns (hidden != 0) are computed, not copied
+        let mut stmt = target_conn.prepare("SELECT name, hidden FROM pragma_table_xinfo(?1)")?;
+        let target_columns = stmt
+            .query_map([&target.name], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let shared: Vec<&str> = target_columns
+            .iter()
+            .filter(|(name, hidden)| {
+                *hidden == 0 && current_columns.iter().any(|c| c.eq_ignore_ascii_case(name))
+            })
+            .map(|(name, _)| name.as_str())
+            .collect();
+        let lost: Vec<String> = current_columns
+            .iter()
+            .filter(|c| {
+                !target_columns
+                    .iter()
+                    .any(|(name, _)| name.eq_ignore_ascii_case(c))
+            })
+            .cloned()
+            .collect();
+
+        let mut steps = vec![format!("CREATE TABLE {new_table} {}", &target.sql[open..])];
+        if !shared.is_empty() {
+            let columns = Self::quote_ident_list(shared.iter().copied())?;
+            steps.push(format!(
+                "INSERT INTO {new_table} ({columns}) SELECT {columns} FROM {table}"
+            ));
+        }
+        steps.push(format!("DROP TABLE {table}"));
+        steps.push(format!(
+            "ALTER TABLE {new_table} RENAME TO {}",
+            Self::quote_ident(&target.name)?
+        ));
+
+        Ok((steps, lost))
+    }
+
+    /// Tables to dump, parents before the tables whose foreign keys reference them (ties
+    /// and cycles fall back to creation order). Shadow tables of virtual tables are left
+    /// out, since recreating the virtual table recreates them.
+    fn dump_table_order(conn: &Connection) -> Result<Vec<SchemaEntry>, UniSqliteError> {
+        let shadow: Vec<String> = conn
+            .prepare(
+                "SELECT name FROM pragma_table_list WHERE schema = 'main' AND type = 'shadow'",
+            )?
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<_, _>>()?;
+        let mut pending: Vec<SchemaEntry> = Self::schema_entries(conn)?
+            .into_iter()
+            .filter(|e| e.object_type == "table" && !shadow.contains(&e.name))
+            .collect();
+
+        let mut parents = HashMap::new();
+        for table in &pending {
+            let referenced: Vec<String> = conn
+                .prepare("SELECT DISTINCT \"table\" FROM pragma_foreign_key_list(?1)")?
+                .query_map([&table.name], |row| row.get(0))?
+                .collect::<Result<_, _>>()?;
+            parents.insert(table.name.clone(), referenced);
+        }
+
+        let mut ordered: Vec<SchemaEntry> = Vec::with_capacity(pending.len());
+        while !pending.is_empty() {
+            let ready = pending
+                .iter()
+                .position(|table| {
+                    parents[&table.name].iter().all(|parent| {
+                        parent.eq_ignore_ascii_case(&table.name)
+                            || !pending.iter().any(|p| p.name.eq_ignore_ascii_case(parent))
+                    })
+                })
+                .unwrap_or(0);
+            ordered.push(pending.remove(ready));
+        }
+        Ok(ordered)
+    }
+
+    /// Write one table's CREATE statement, rows as INSERTs, AUTOINCREMENT counter, and
+    /// indexes/triggers; returns the number of rows written.
+    fn dump_table(
+        conn: &Connection,
+        out: &mut impl Write,
+        table: &SchemaEntry,
+        objects: &[&SchemaEntry],
+    ) -> Result<usize, UniSqliteError> {
+        writeln!(out, "{};", table.sql)?;
+
+        let mut rows = 0;
+        // Virtual tables hold no rows of their own
+        if !table
+            .sql
+            .to_ascii_uppercase()
+            .starts_with("CREATE VIRTUAL TABLE")
+        {
+            let columns: Vec<String> = conn
+                .prepare("SELECT name FROM pragma_table_xinfo(?1) WHERE hidden = 0")?
+                .query_map([&table.name], |row| row.get(0))?
+                .collect::<Result<_, _>>()?;
+            let column_list = Self::quote_ident_list(columns.iter().map(String::as_str))?;
+            let quoted_table = Self::quote_ident(&table.name)?;
+
+            let mut stmt = conn.prepare(&format!("SELECT {column_list} FROM {quoted_table}"))?;
+            let mut result = stmt.query([])?;
+            while let Some(row) = result.next()? {
+                let values = (0..columns.len())
+                    .map(|i| Ok(Self::sql_literal(row.get_ref(i)?)))
+                    .collect::<Result<Vec<_>, rusqlite::Error>>()?;
+                writeln!(
+                    out,
+                    "INSERT INTO {quoted_table}({column_list}) VALUES({});",
+                    values.join(",")
+                )?;
+                rows += 1;
+            }
+
+            let sequence: Option<i64> = conn
+                .query_row(
+                    "SELECT seq FROM sqlite_sequence WHERE name = ?1",
+                    [&table.name],
+                    |row| row.get(0),
+                )
+                .optional()
+                .unwrap_or(None);
+            if let Some(seq) = sequence {
+                let name =
+                    Self::sql_literal(rusqlite::types::ValueRef::Text(table.name.as_bytes()));
+                writeln!(out, "DELETE FROM sqlite_sequence WHERE name = {name};")?;
+                writeln!(
+                    out,
+                    "INSERT INTO sqlite_sequence(name, seq) VALUES({name}, {seq});"
+                )?;
+            }
+        }
+
+        for object in objects {
+            writeln!(out, "{};", object.sql)?;
+        }
+        Ok(rows)
+    }
+
+    /// A value as a SQL literal that reads back as the same value and type.
+    fn sql_literal(value: rusqlite::types::ValueRef<'_>) -> String {
+        use rusqlite::types::ValueRef;
+        match value {
+            ValueRef::Null => "NULL".to_string(),
+            ValueRef::Integer(i) => i.to_string(),
+            // Debug keeps a fractional part so the value stays REAL; SQLite has no infinity literal
+            ValueRef::Real(f) if f.is_infinite() => {
+                if f > 0.0 { "1e999" } else { "-1e999" }.to_string()
+            }
+            ValueRef::Real(f) => format!("{f:?}"),
+            ValueRef::Text(t) => format!("'{}'", String::from_utf8_lossy(t).replace('\'', "''")),
+            ValueRef::Blob(b) => format!("X'{}'", hex::encode_upper(b)),
+        }
+    }
+
+    /// A table name made safe to use in a file name.
+    fn file_name_component(name: &str) -> String {
+        name.chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect()
+    }
+
+    /// Whether upper-cased `sql` begins with `keyword` as a whole word.
+    fn starts_with_keyword(sql: &str, keyword: &str) -> bool {
+        sql.strip_prefix(keyword)
+            .is_some_and(|rest| !rest.starts_with(|c: char| c.is_ascii_alphanumeric() || c == '_'))
+    }
+
+    /// BEGIN/COMMIT/END statements, skipped by restores that run in their own transaction.
+    fn is_transaction_control(statement: &str) -> bool {
+        let head = Self::strip_leading_comments(statement).to_ascii_uppercase();
+        ["BEGIN", "COMMIT", "END"]
+            .iter()
+            .any(|keyword| Self::starts_with_keyword(&head, keyword))
+    }
+
+    /// Read up to `limit` records and infer each column's type from them. Columns past the
+    /// header (or in headerless files) are named column1, column2, ...
+    fn scan_csv_sample(
+        path: &Path,
+        has_headers: bool,
+        encoding: Option<&str>,
+        limit: usize,
+    ) -> Result<CsvSample, UniSqliteError> {
+        let (source, encoding, warning) = Self::open_csv_source(path, encoding)?;
+        let mut rdr = csv::ReaderBuilder::new()
+            .has_headers(has_headers)
+            .flexible(true)
+            .from_reader(source);
+
+        let mut headers: Vec<String> = if has_headers {
+            rdr.headers()?
+                .iter()
+                .map(|h| h.trim().to_string())
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let rows = rdr
+            .records()
+            .take(limit)
+            .map(|record| Ok(record?.iter().map(str::to_string).collect()))
+            .collect::<Result<Vec<Vec<String>>, UniSqliteError>>()?;
+
+        let width = rows
+            .iter()
+            .map(Vec::len)
+            .max()
+            .unwrap_or(0)
+            .max(headers.len());
+        for i in headers.len()..width {
+            headers.push(format!("column{}", i + 1));
+        }
+
+        let mut types = vec![CsvFieldType::Empty; width];
+        let mut empty_counts = vec![0; width];
+        for row in &rows {
+            for (i, (ty, empty)) in types.iter_mut().zip(&mut empty_counts).enumerate() {
+                match row.get(i).map(|field| CsvFieldType::infer(field)) {
+                    None | Some(CsvFieldType::Empty) => *empty += 1,
+                    Some(field_type) => *ty = (*ty).max(field_type),
+                }
+            }
+        }
+
+        Ok(CsvSample {
+            headers,
+            types,
+            empty_counts,
+            rows,
+            encoding,
+            warning,
+        })
+    }
+
+    /// Open a CSV as UTF-8. UTF-8 input streams straight from disk; anything else
+    /// (named explicitly or announced by a BOM) is transcoded up front.
+    fn open_csv_source(path: &Path, label: Option<&str>) -> Result<CsvSource, UniSqliteError> {
+        use std::io::Read;
+
+        let mut file = fs::File::open(path)?;
+        let encoding = match label {
+            Some(label) => {
+                encoding_rs::Encoding::for_label(label.trim().as_bytes()).ok_or_else(|| {
+                    UniSqliteError::ImportFailed(format!("Unknown encoding '{label}'"))
+                })?
+            }
+            None => {
+                let mut head = Vec::with_capacity(3);
+                (&mut file).take(3).read_to_end(&mut head)?;
+                match encoding_rs::Encoding::for_bom(&head) {
+                    Some((encoding, _)) if encoding != encoding_rs::UTF_8 => {
+                        let mut bytes = head;
+                        file.read_to_end(&mut bytes)?;
+                        return Ok(Self::transcode_csv(encoding, &bytes));
+                    }
+                    bom => {
+                        // Put back whatever of the peeked bytes isn't a UTF-8 BOM
+                        let rest = head.split_off(bom.map_or(0, |(_, len)| len));
+                        let source = std::io::Cursor::new(rest).chain(file);
+                        return Ok((Box::new(source), encoding_rs::UTF_8, None));
+                    }
+                }
+            }
+        };
+
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+        Ok(Self::transcode_csv(encoding, &bytes))
+    }
+
+    fn transcode_csv(encoding: &'static encoding_rs::Encoding, bytes: &[u8]) -> CsvSource {
+        let (text, had_errors) = encoding.decode_with_bom_removal(bytes);
+        let warning = had_errors.then(|| {
+            format!(
+                "Some bytes were not valid {} and were replaced with U+FFFD",
+                encoding.name()
+            )
+        });
+        let source = std::io::Cursor::new(text.into_owned().into_bytes());
+        (Box::new(source), encoding, warning)
+    }
+
+    /// Tell UTF-16 (NUL in every other byte for ASCII-heavy text) from a single-byte
+    /// Windows codepage, which is what non-UTF-8 CSVs without a BOM usually are
+    fn guess_legacy_encoding(sample: &[u8]) -> &'static encoding_rs::Encoding {
+        let pairs = sample.len() / 2;
+        if pairs > 0 {
+            let even_nuls = sample.iter().step_by(2).filter(|&&b| b == 0).count();
+            let odd_nuls = sample
+                .iter()
+                .skip(1)
+                .step_by(2)
+                .filter(|&&b| b == 0)
+                .count();
+            if odd_nuls * 4 > pairs && odd_nuls > even_nuls * 4 {
+                return encoding_rs::UTF_16LE;
+            }
+            if even_nuls * 4 > pairs && even_nuls > odd_nuls * 4 {
+                return encoding_rs::UTF_16BE;
+            }
+        }
+        encoding_rs::WINDOWS_1252
+    }
+
+    /// Feed a value to a checksum unambiguously: a type tag, then a length for variable-size
+    /// values. Storage classes are distinct, so integer 1 and real 1.0 hash differently.
+    fn hash_value(hasher: &mut sha2::Sha256, value: rusqlite::types::ValueRef<'_>) {
+        use rusqlite::types::ValueRef;
+        use sha2::Digest;
+
+        match value {
+            ValueRef::Null => hasher.update([0]),
+            ValueRef::Integer(i) => {
+                hasher.update([1]);
+                hasher.update(i.to_be_bytes());
+            }
+            ValueRef::Real(f) => {
+                hasher.update([2]);
+                hasher.update(f.to_bits().to_be_bytes());
+            }
+            ValueRef::Text(bytes) | ValueRef::Blob(bytes) => {
+                hasher.update([if matches!(value, ValueRef::Text(_)) {
+                    3
+                } else {
+                    4
+                }]);
+                hasher.update((bytes.len() as u64).to_be_bytes());
+                hasher.update(bytes);
+            }
+        }
+    }
+
+    /// Try to take a lock with `begin` (e.g. "BEGIN IMMEDIATE") and release it at once.
+    /// Returns false when another connection's lock prevents it.
+    fn try_lock(probe: &Connection, begin: &str) -> Result<bool, UniSqliteError> {
+        match probe.execute_batch(begin) {
+            Ok(()) => {
+                probe.execute_batch("ROLLBACK")?;
+                Ok(true)
+            }
+            Err(e) if Self::is_busy_error(&e) => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Validate and sanitize a database file path (prevents directory traversal)
+    fn validate_db_path(&self, requested_path: &Path) -> Result<PathBuf, UniSqliteError> {
+        self.validate_file_path(requested_path, &["db", "sqlite", "sqlite3"])
+    }
+
+    /// Validate and sanitize a file path, requiring one of `extensions`
+    fn validate_file_path(
+        &self,
+        requested_path: &Path,
+        extensions: &[&str],
+    ) -> Result<PathBuf, UniSqliteError> {
+        Self::check_extension(self.validate_path(requested_path)?, extensions)
+    }
+
+    /// Canonicalize a file or directory path (which need not exist yet, though its parent
+    /// must) and require it to lie inside the current directory
+    fn validate_path(&self, requested_path: &Path) -> Result<PathBuf, UniSqliteError> {
+        let canonical_path = if requested_path.exists() {
+            requested_path.canonicalize()
+        } else {
+            let parent = requested_path
+                .parent()
+                .ok_or_else(|| UniSqliteError::InvalidPath("No parent directory".into()))?;
+
+            let canonical_parent = parent.canonicalize().map_err(|_| {
+                UniSqliteError::InvalidPath("Parent directory does not exist".into())
+            })?;
+
+            let file_name = requested_path
+                .file_name()
+                .ok_or_else(|| UniSqliteError::InvalidPath("No filename".into()))?;
+
+            Ok(canonical_parent.join(file_name))
+        }
+        .map_err(|e| UniSqliteError::InvalidPath(e.to_string()))?;
+
+        // In test mode, allow temp directories
+        #[cfg(test)]
+        {
+            if canonical_path.to_string_lossy().contains("tmp") {
+                return Ok(canonical_path);
+            }
+        }
+
+        let current_dir = std::env::current_dir()
+            .and_then(|p| p.canonicalize())
+            .map_err(|_| {
+                UniSqliteError::InvalidPath("Cannot determine current directory".into())
+            })?;
+
+        if !canonical_path.starts_with(&current_dir) {
+            return Err(UniSqliteError::InvalidPath(
+                "Path outside allowed directory".into(),
+            ));
+        }
+
+        Ok(canonical_path)
+    }
+
+    fn check_extension(path: PathBuf, extensions: &[&str]) -> Result<PathBuf, UniSqliteError> {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if extensions.contains(&ext) => Ok(path),
+            _ => Err(UniSqliteError::InvalidPath(format!(
+                "Invalid file extension (expected .{})",
+                extensions.join(", .")
+            ))),
+        }
+    }
+
+    /// Convert a JSON value to a rusqlite parameter.
+    fn json_to_sql_param(
+        value: &serde_json::Value,
+    ) -> Result<Box<dyn rusqlite::ToSql>, UniSqliteError> {
+        match value {
+            serde_json::Value::Null => Ok(Box::new(rusqlite::types::Null)),
+            serde_json::Value::Bool(b) => Ok(Box::new(*b)),
+            serde_json::Value::Number(n) => {
+                if let Some(i) = n.as_i64() {
+                    Ok(Box::new(i))
+                } else if let Some(f) = n.as_f64() {
+                    Ok(Box::new(f))
+                } else {
+                    Err(UniSqliteError::QueryFailed(
+                        "Invalid number parameter".into(),
+                    ))
+                }
+            }
+            serde_json::Value::String(s) => Ok(Box::new(s.clone())),
+            _ => Err(UniSqliteError::QueryFailed(
+                "Unsupported parameter type".into(),
+            )),
+        }
+    }
+
+    /// Skip leading whitespace and `--` / `/* */` comments so the first keyword can be inspected.
+    fn strip_leading_comments(sql: &str) -> &str {
+        let mut rest = sql.trim_start();
+        loop {
+            if let Some(after) = rest.strip_prefix("--") {
+                rest = match after.find('\n') {
+                    Some(end) => after[end + 1..].trim_start(),
+                    None => "",
+                };
+            } else if let Some(after) = rest.strip_prefix("/*") {
+                rest = match after.find("*/") {
+                    Some(end) => after[end + 2..].trim_start(),
+                    None => "",
+                };
+            } else {
+                return rest;
+            }
+        }
+    }
+
+    /// Whether a statement returns rows and should be run through `query` rather than `execute`.
+    fn is_read_query(sql: &str) -> bool {
+        let sql_upper = Self::strip_leading_comments(sql).to_ascii_uppercase();
+        Self::is_select_query(sql)
+            || sql_upper.starts_with("PRAGMA")
+            || sql_upper.starts_with("EXPLAIN")
+    }
+
+    /// Whether a statement is a SELECT (or VALUES, optionally behind a WITH clause), so it
+    /// can be used as a subquery.
+    fn is_select_query(sql: &str) -> bool {
+        let sql_upper = Self::strip_leading_comments(sql).to_ascii_uppercase();
+        let main = if sql_upper.starts_with("WITH") {
+            Self::cte_main_statement(&sql_upper)
+        } else {
+            &sql_upper
+        };
+        main.starts_with("SELECT") || main.starts_with("VALUES")
+    }
+
+    /// Skip the common table expressions of an upper-cased `WITH` statement and return the
+    /// main statement that follows them (e.g. `SELECT ...` or `INSERT ...`).
+    fn cte_main_statement(sql_upper: &str) -> &str {
+        let bytes = sql_upper.as_bytes();
+        let mut depth = 0usize;
+        let mut quote: Option<u8> = None;
+        let mut i = "WITH".len();
+
+        while i < bytes.len() {
+            let b = bytes[i];
+            if let Some(q) = quote {
+                if b == q {
+                    quote = None;
+                }
+            } else {
+                match b {
+                    b'\'' | b'"' | b'`' => quote = Some(b),
+                    b'[' => quote = Some(b']'),
+                    b'(' => depth += 1,
+                    b')' => depth = depth.saturating_sub(1),
+                    _ if depth == 0 && b.is_ascii_alphabetic() => {
+                        let start = i;
+                        while i < bytes.len()
+                            && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_')
+                        {
+                            i += 1;
+                        }
+                        let word = &sql_upper[start..i];
+                        if matches!(
+                            word,
+                            "SELECT" | "VALUES" | "INSERT" | "REPLACE" | "UPDATE" | "DELETE"
+                        ) {
+                            return &sql_upper[start..];
+                        }
+                        continue;
+                    }
+                    _ => {}
+                }
+            }
+            i += 1;
+        }
+
+        ""
+    }
+
+    /// Validate SQL query - now allows more admin operations
+    fn validate_sql_query(sql: &str) -> Result<(), UniSqliteError> {
+        let sql_trim = sql.trim_start();
+        let sql_upper = Self::strip_leading_comments(sql_trim).to_ascii_uppercase();
+        let allowed = [
+            "SELECT", "WITH", "VALUES", "INSERT", "UPDATE", "DELETE", "CREATE", "ALTER", "DROP",
+            "PRAGMA", "EXPLAIN", "ANALYZE",
+        ];
+
+        // Ensure exactly one statement (no extra semicolons).
+        if sql_trim.matches(';').count() > 1 {
+            return Err(UniSqliteError::QueryFailed(
+                "Multiple statements are not allowed".into(),
+            ));
+        }
+
+        for cmd in &allowed {
+            if sql_upper.starts_with(cmd) {
+                return Ok(());
+            }
+        }
+
+        Err(UniSqliteError::QueryFailed(format!(
+            "Only {} statements are allowed",
+            allowed.join(", ")
+        )))
+    }
+
+    pub async fn query_tool(&self, req: QueryRequest) -> Result<QueryResult, UniSqliteError> {
+        Self::validate_sql_query(&req.sql)?;
+        if req.count_only && !Self::is_select_query(&req.sql) {
+            return Err(UniSqliteError::QueryFailed(
+                "count_only requires a SELECT query".into(),
+            ));
+        }
+
+        self.with_blocking_connection(move |handler, conn| handler.run_query(conn, req))
+            .await
+    }
+
+    fn run_query(
+        &self,
+        conn: &Connection,
+        req: QueryRequest,
+    ) -> Result<QueryResult, UniSqliteError> {
+        let sql_upper = Self::strip_leading_comments(&req.sql).to_ascii_uppercase();
+        if sql_upper.starts_with("PRAGMA") && sql_upper.contains('=') {
+            // Keeps the idle monitor from closing a connection with this setting on it
+            self.idle.lock().unwrap().session_pragmas = true;
+        }
+
+        // Convert JSON parameters to rusqlite parameters.
+        let params = Self::bind_params(&req.parameters, &req.param_types)?;
+
+        let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| &**p).collect();
+
+        let limit = match (req.unlimited, req.limit) {
+            (true, _) => None,
+            (false, Some(limit)) => Some(limit),
+            (false, None) => Some(*self.fetch_limit.lock().unwrap()),
+        }
+        .filter(|&limit| limit > 0);
+
+        // Only plain reads are cached; the key covers everything that shapes the result
+        let cache_slot = if req.cache && !req.count_only && Self::is_read_query(&req.sql) {
+            let key = format!(
+                "{}\u{0}{}\u{0}{:?}\u{0}{}{}{:?}{:?}",
+                req.sql,
+                serde_json::to_string(&req.parameters)?,
+                req.param_types,
+                req.typed,
+                req.stable_order,
+                req.blob_as,
+                limit
+            );
+            let stamp = Self::data_stamp(conn)?;
+            if let Some(mut hit) = self.query_cache.lock().unwrap().get(&key, stamp) {
+                hit.cached = true;
+                return Ok(hit);
+            }
+            Some((key, stamp))
+        } else {
+            None
+        };
+
+        let watchdog = req
+            .timeout_ms
+            .map(|ms| QueryWatchdog::start(conn.get_interrupt_handle(), Duration::from_millis(ms)));
+
+        let run = || -> Result<QueryResult, UniSqliteError> {
+            // Determine query type.
+            if req.count_only {
+                let started = Instant::now();
+                // The newline keeps a trailing `--` comment from swallowing the parenthesis
+                let count_sql = format!(
+                    "SELECT COUNT(*) FROM ({}\n)",
+                    req.sql.trim_end().trim_end_matches(';')
+                );
+                let count: i64 = conn.query_row(&count_sql, &param_refs[..], |row| row.get(0))?;
+
+                Ok(QueryResult {
+                    message: format!("Query would return {count} rows"),
+                    rows_affected: Some(count as usize),
+                    data: None,
+                    columns: None,
+                    elapsed_ms: Some(started.elapsed().as_millis() as u64),
+                    busy_retries: 0,
+                    ordering_note: None,
+                    dry_run: false,
+                    cached: false,
+                    total_changes: None,
+                    truncated: false,
+                    limit: None,
+                })
+            } else if Self::is_read_query(&req.sql) {
+                // SELECT-like queries – return rows.
+                let started = Instant::now();
+                let (sql, ordering_note) = if req.stable_order {
+                    Self::stable_order_sql(conn, &req.sql)?
+                } else {
+                    (Cow::Borrowed(req.sql.as_str()), None)
+                };
+                self.statement_cache.lock().unwrap().record(&sql);
+                let mut stmt = conn.prepare_cached(&sql)?;
+                let (column_names, data, truncated) =
+                    Self::collect_rows_with(&mut stmt, &param_refs, req.typed, req.blob_as, limit)?;
+
+                Ok(QueryResult {
+                    message: if truncated {
+                        format!(
+                            "Query executed successfully, returned the first {} rows; more matched (raise limit, or set limit 0 or unlimited for all)",
+                            data.len()
+                        )
+                    } else {
+                        format!("Query executed successfully, returned {} rows", data.len())
+                    },
+                    rows_affected: Some(data.len()),
+                    data: Some(data),
+                    columns: Some(column_names),
+                    elapsed_ms: Some(started.elapsed().as_millis() as u64),
+                    busy_retries: 0,
+                    ordering_note,
+                    dry_run: false,
+                    cached: false,
+                    total_changes: None,
+                    truncated,
+                    limit,
+                })
+            } else {
+                // Non‑SELECT – execute and report affected rows.
+                self.invalidate_query_cache();
+                let started = Instant::now();
+                self.statement_cache.lock().unwrap().record(&req.sql);
+                let mut busy_retries = 0;
+                let mut execute = || -> Result<usize, UniSqliteError> {
+                    let mut stmt = conn.prepare_cached(&req.sql)?;
+                    Ok(self.with_busy_retry(&mut busy_retries, || stmt.execute(&param_refs[..]))?)
+                };
+                let changes_before = conn.total_changes();
+                let rows_affected = if req.dry_run {
+                    Self::with_rollback(conn, execute)?
+                } else {
+                    execute()?
+                };
+                let total_changes = req
+                    .report_total_changes
+                    .then(|| conn.total_changes() - changes_before);
+                Ok(QueryResult {
+                    message: if req.dry_run {
+                        format!("Dry run: query would affect {rows_affected} rows; rolled back")
+                    } else {
+                        "Query executed successfully".into()
+                    },
+                    rows_affected: Some(rows_affected),
+                    data: None,
+                    columns: None,
+                    elapsed_ms: Some(started.elapsed().as_millis() as u64),
+                    busy_retries,
+                    ordering_note: None,
+                    dry_run: req.dry_run,
+                    cached: false,
+                    total_changes,
+                    truncated: false,
+                    limit: None,
+                })
+            }
+        };
+        let result = run();
+
+        if let (Some((key, stamp)), Ok(result)) = (cache_slot, &result) {
+            self.query_cache
+                .lock()
+                .unwrap()
+                .insert(key, stamp, result.clone());
+        }
+
+        let timed_out = watchdog.is_some_and(QueryWatchdog::finish);
+        match result {
+            Err(UniSqliteError::Interrupted(_)) if timed_out => Err(UniSqliteError::Timeout(
+                format!("query exceeded {} ms", req.timeout_ms.unwrap_or_default()),
+            )),
+            result => result,
+        }
+    }
+
+    /// Run a read-only query for timing and return its EXPLAIN QUERY PLAN alongside
+    pub async fn query_explain_analyze_tool(
+        &self,
+        req: QueryExplainAnalyzeRequest,
+    ) -> Result<QueryExplainAnalyzeResult, UniSqliteError> {
+        Self::validate_sql_query(&req.sql)?;
+
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+
+        let params = Self::bind_params(&req.parameters, &[])?;
+        let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| &**p).collect();
+
+        let mut stmt = conn.prepare(&req.sql)?;
+        if !stmt.readonly() {
+            return Err(UniSqliteError::QueryFailed(
+                "query_explain_analyze only runs read-only statements; use query with dry_run for writes"
+                    .into(),
+            ));
+        }
+
+        let mut plan_stmt = conn.prepare(&format!("EXPLAIN QUERY PLAN {}", req.sql))?;
+        let mut plan = Vec::new();
+        let mut rows = plan_stmt.query(&param_refs[..])?;
+        while let Some(row) = rows.next()? {
+            let (id, parent): (i64, i64) = (row.get(0)?, row.get(1)?);
+            // Parents always precede their children, so a parent's depth is already known
+            let depth = plan
+                .iter()
+                .find(|step: &&QueryPlanStep| step.id == parent)
+                .map_or(0, |step| step.depth + 1);
+            plan.push(QueryPlanStep {
+                id,
+                parent,
+                depth,
+                detail: row.get(3)?,
+            });
+        }
+
+        // Rows are stepped but not decoded, so the timing reflects SQLite's work
+        let started = Instant::now();
+        let mut result_rows = stmt.query(&param_refs[..])?;
+        let mut row_count = 0;
+        while result_rows.next()?.is_some() {
+            row_count += 1;
+        }
+
+        Ok(QueryExplainAnalyzeResult {
+            plan,
+            elapsed_ms: started.elapsed().as_millis() as u64,
+            rows: row_count,
+        })
+    }
+
+    /// Drop every cached `query` result. Tools that write data or schema call this, so
+    /// cached reads never outlive a mutation the data stamp cannot see (e.g. blob I/O).
+    fn invalidate_query_cache(&self) {
+        self.query_cache.lock().unwrap().clear();
+    }
+
+    pub async fn clear_query_cache_tool(&self) -> Result<ClearQueryCacheResult, UniSqliteError> {
+        let entries_cleared = self.query_cache.lock().unwrap().clear();
+        Ok(ClearQueryCacheResult {
+            success: true,
+            message: format!("Cleared {entries_cleared} cached query results"),
+            entries_cleared,
+        })
+    }
+
+    /// Describe the result columns of a statement without running it.
+    pub async fn describe_query_tool(
+        &self,
+        req: DescribeQueryRequest,
+    ) -> Result<DescribeQueryResult, UniSqliteError> {
+        Self::validate_sql_query(&req.sql)?;
+
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+
+        // Preparing through rusqlite first surfaces SQL errors with SQLite's message
+        let stmt = conn.prepare(&req.sql)?;
+        let origins = Self::column_origins(conn, &req.sql)?;
+
+        let columns = stmt
+            .columns()
+            .into_iter()
+            .zip(origins)
+            .map(
+                |(column, [origin_database, origin_table, origin_column])| ResultColumnInfo {
+                    name: column.name().to_string(),
+                    declared_type: column.decl_type().map(str::to_string),
+                    origin_database,
+                    origin_table,
+                    origin_column,
+                },
+            )
+            .collect();
+
+        Ok(DescribeQueryResult {
+            columns,
+            parameter_count: stmt.parameter_count(),
+            readonly: stmt.readonly(),
+        })
+    }
+
+    pub async fn query_scalar_tool(
+        &self,
+        req: QueryRequest,
+    ) -> Result<QueryScalarResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+
+        let (mut columns, row) = Self::run_single_row_query(
+            conn,
+            &req.sql,
+            &req.parameters,
+            &req.param_types,
+            req.typed,
+        )?;
+
+        if columns.len() != 1 {
+            return Err(UniSqliteError::QueryFailed(format!(
+                "Expected exactly one column, got {}",
+                columns.len()
+            )));
+        }
+        let Some(mut row) = row else {
+            return Err(UniSqliteError::QueryFailed(
+                "Expected exactly one row, got 0".into(),
+            ));
+        };
+
+        Ok(QueryScalarResult {
+            column: columns.remove(0),
+            value: row.remove(0),
+        })
+    }
+
+    pub async fn query_row_tool(
+        &self,
+        req: QueryRowRequest,
+    ) -> Result<QueryRowResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+
+        let (columns, row) =
+            Self::run_single_row_query(conn, &req.sql, &req.parameters, &[], req.typed)?;
+
+        match row {
+            Some(row) => Ok(QueryRowResult {
+                row: Some(columns.into_iter().zip(row).collect()),
+            }),
+            None if req.allow_none => Ok(QueryRowResult { row: None }),
+            None => Err(UniSqliteError::QueryFailed(
+                "Expected exactly one row, got 0".into(),
+            )),
+        }
+    }
+
+    /// Prepare a statement once and register it under a new id. The statement itself lives
+    /// in the connection's prepared statement cache; only the SQL is kept on the handler.
+    pub async fn prepare_statement_tool(
+        &self,
+        req: PrepareStatementRequest,
+    ) -> Result<PrepareStatementResult, UniSqliteError> {
+        Self::validate_sql_query(&req.sql)?;
+
+        let guard = self.lock_db().await?;
+        let mut prepared = self.prepared_statements.lock().await;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+
+        // Make room before preparing so the statement stays cached even with use_cache off
+        self.size_statement_cache(conn, prepared.len() + 1);
+        let parameter_count = conn.prepare_cached(&req.sql)?.parameter_count();
+
+        let statement_id = uuid::Uuid::new_v4().to_string();
+        prepared.insert(statement_id.clone(), req.sql);
+
+        Ok(PrepareStatementResult {
+            statement_id,
+            parameter_count,
+        })
+    }
+
+    pub async fn execute_prepared_tool(
+        &self,
+        req: ExecutePreparedRequest,
+    ) -> Result<QueryResult, UniSqliteError> {
+        let sql = self
+            .prepared_statements
+            .lock()
+            .await
+            .get(&req.statement_id)
+            .cloned()
+            .ok_or_else(|| {
+                UniSqliteError::QueryFailed(format!("Unknown statement id: {}", req.statement_id))
+            })?;
+
+        self.query_tool(QueryRequest {
+            sql,
+            parameters: req.parameters,
+            ..Default::default()
+        })
+        .await
+    }
+
+    pub async fn finalize_statement_tool(
+        &self,
+        req: FinalizeStatementRequest,
+    ) -> Result<FinalizeStatementResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let mut prepared = self.prepared_statements.lock().await;
+        if prepared.remove(&req.statement_id).is_none() {
+            return Err(UniSqliteError::QueryFailed(format!(
+                "Unknown statement id: {}",
+                req.statement_id
+            )));
+        }
+        if let Some(conn) = guard.as_ref() {
+            self.size_statement_cache(conn, prepared.len());
+        }
+
+        Ok(FinalizeStatementResult {
+            success: true,
+            message: format!("Statement {} finalized", req.statement_id),
+        })
+    }
+
+    /// Size the connection's statement cache to the `use_cache` capacity plus one slot per
+    /// registered prepared statement, so registered statements are kept even when caching
+    /// is off (capacity 0).
+    fn size_statement_cache(&self, conn: &Connection, registered: usize) {
+        let base = {
+            let cache = self.statement_cache.lock().unwrap();
+            if cache.enabled { cache.capacity } else { 0 }
+        };
+        conn.set_prepared_statement_cache_capacity(base + registered);
+    }
+
+    /// Attach the given databases read-only, run a read query across them, and detach them
+    /// again whether or not the query succeeded.
+    pub async fn cross_query_tool(
+        &self,
+        req: CrossQueryRequest,
+    ) -> Result<QueryResult, UniSqliteError> {
+        let mut targets = Vec::with_capacity(req.databases.len());
+        for db in &req.databases {
+            let valid_alias = db
+                .alias
+                .chars()
+                .next()
+                .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                && db
+                    .alias
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if !valid_alias
+                || db.alias.eq_ignore_ascii_case("main")
+                || db.alias.eq_ignore_ascii_case("temp")
+            {
+                return Err(UniSqliteError::QueryFailed(format!(
+                    "Invalid database alias: {}",
+                    db.alias
+                )));
+            }
+
+            let path = self.validate_db_path(Path::new(&db.path))?;
+            if !path.exists() {
+                return Err(UniSqliteError::DatabaseNotFound(path.display().to_string()));
+            }
+            targets.push((db.alias.clone(), path));
+        }
+
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+
+        let mut attached = Vec::new();
+        let mut result = Ok((Vec::new(), Vec::new()));
+        for (alias, path) in &targets {
+            match conn.execute(
+                &format!("ATTACH DATABASE ? AS {}", Self::quote_ident(alias)?),
+                [Self::read_only_uri(path)],
+            ) {
+                Ok(_) => attached.push(alias),
+                Err(e) => {
+                    result = Err(e.into());
+                    break;
+                }
+            }
+        }
+
+        let started = Instant::now();
+        if result.is_ok() {
+            result = Self::run_read_query(conn, &req.sql, &req.parameters, &[], req.typed);
+        }
+        let elapsed_ms = started.elapsed().as_millis() as u64;
+
+        for alias in attached {
+            let detach = format!("DETACH DATABASE {}", Self::quote_ident(alias)?);
+            if let Err(e) = conn.execute(&detach, []) {
+                tracing::warn!("Failed to detach {}: {}", alias, e);
+            }
+        }
+
+        let (columns, data) = result?;
+        Ok(QueryResult {
+            message: format!("Query returned {} rows", data.len()),
+            rows_affected: Some(data.len()),
+            data: Some(data),
+            columns: Some(columns),
+            elapsed_ms: Some(elapsed_ms),
+            busy_retries: 0,
+            ordering_note: None,
+            dry_run: false,
+            cached: false,
+            total_changes: None,
+            truncated: false,
+            limit: None,
+        })
+    }
+
+    pub async fn transaction_tool(
+        &self,
+        req: TransactionRequest,
+    ) -> Result<TransactionResult, UniSqliteError> {
+        self.with_blocking_connection(move |handler, conn| handler.run_transaction(conn, req))
+            .await
+    }
+
+    fn run_transaction(
+        &self,
+        conn: &Connection,
+        req: TransactionRequest,
+    ) -> Result<TransactionResult, UniSqliteError> {
+        self.invalidate_query_cache();
+        let tx = conn.unchecked_transaction()?;
+        let mut results = Vec::new();
+        let mut total_rows_affected = 0;
+        let mut success = true;
+
+        for query_req in req.queries {
+            match self.execute_query_in_transaction(&tx, query_req) {
+                Ok(result) => {
+                    if let Some(rows) = result.rows_affected {
+                        total_rows_affected += rows;
+                    }
+                    results.push(result);
+                }
+                Err(e) => {
+                    success = false;
+                    results.push(QueryResult {
+                        message: format!("Error: {e}"),
+                        rows_affected: None,
+                        data: None,
+                        columns: None,
+                        elapsed_ms: None,
+                        busy_retries: 0,
+                        ordering_note: None,
+                        dry_run: false,
+                        cached: false,
+                        total_changes: None,
+                        truncated: false,
+                        limit: None,
+                    });
+                    if req.rollback_on_error {
+                        break;
+                    }
+                }
+            }
+        }
+
+        if success || !req.rollback_on_error {
+            let mut busy_retries = 0;
+            self.commit_with_busy_retry(tx, &mut busy_retries)?;
+            Ok(TransactionResult {
+                success,
+                message: if success {
+                    "Transaction completed successfully".into()
+                } else {
+                    "Transaction completed with errors".into()
+                },
+                results,
+                total_rows_affected,
+                busy_retries,
+            })
+        } else {
+            // Transaction will be rolled back when dropped
+            Ok(TransactionResult {
+                success: false,
+                message: "Transaction rolled back due to errors".into(),
+                results,
+                total_rows_affected: 0,
+                busy_retries: 0,
+            })
+        }
+    }
+
+    /// Run typed operations in one transaction, rolling everything back if any fails.
+    pub async fn batch_tool(&self, req: BatchRequest) -> Result<BatchResult, UniSqliteError> {
+        self.with_blocking_connection(move |handler, conn| handler.run_batch(conn, req))
+            .await
+    }
+
+    fn run_batch(
+        &self,
+        conn: &Connection,
+        req: BatchRequest,
+    ) -> Result<BatchResult, UniSqliteError> {
+        self.invalidate_query_cache();
+        let tx = conn.unchecked_transaction()?;
+        let mut busy_retries = 0;
+        let mut results = Vec::new();
+
+        for (index, operation) in req.operations.into_iter().enumerate() {
+            let result = match operation {
+                BatchOperation::Insert(op) => self
+                    .batch_insert_on(&tx, op, &mut busy_retries)
+                    .map(BatchOperationResult::Insert),
+                BatchOperation::Update(op) => {
+                    Self::batch_update_on(&tx, op).map(BatchOperationResult::Update)
+                }
+                BatchOperation::Delete(op) => {
+                    Self::delete_rows_on(&tx, op).map(BatchOperationResult::Delete)
+                }
+                BatchOperation::CreateTable(op) => {
+                    Self::create_table_on(&tx, op).map(BatchOperationResult::CreateTable)
+                }
+                BatchOperation::Query(op) => self
+                    .execute_query_in_transaction(&tx, op)
+                    .map(BatchOperationResult::Query),
+            };
+
+            match result {
+                Ok(result) => results.push(result),
+                Err(e) => {
+                    // Dropping the transaction rolls back every operation
+                    return Ok(BatchResult {
+                        success: false,
+                        message: format!("Batch rolled back: operation {index} failed: {e}"),
+                        results,
+                        failed_operation: Some(index),
+                        busy_retries,
+                    });
+                }
+            }
+        }
+
+        self.commit_with_busy_retry(tx, &mut busy_retries)?;
+
+        Ok(BatchResult {
+            success: true,
+            message: format!("Batch of {} operations committed", results.len()),
+            results,
+            failed_operation: None,
+            busy_retries,
+        })
+    }
+
+    fn execute_query_in_transaction(
+        &self,
+        tx: &Transaction<'_>,
+        req: QueryRequest,
+    ) -> Result<QueryResult, UniSqliteError> {
+        Self::validate_sql_query(&req.sql)?;
+
+        let params = Self::bind_params(&req.parameters, &req.param_types)?;
+
+        let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| &**p).collect();
+
+        if Self::is_read_query(&req.sql) {
+            let started = Instant::now();
+            let mut stmt = tx.prepare(&req.sql)?;
+            let (column_names, data) = Self::collect_rows(&mut stmt, &param_refs, req.typed)?;
+
+            Ok(QueryResult {
+                message: format!("Query executed successfully, returned {} rows", data.len()),
+                rows_affected: Some(data.len()),
+                data: Some(data),
+                columns: Some(column_names),
+                elapsed_ms: Some(started.elapsed().as_millis() as u64),
+                busy_retries: 0,
+                ordering_note: None,
+                dry_run: false,
+                cached: false,
+                total_changes: None,
+                truncated: false,
+                limit: None,
+            })
+        } else {
+            let started = Instant::now();
+            let rows_affected = tx.execute(&req.sql, &param_refs[..])?;
+            Ok(QueryResult {
+                message: "Query executed successfully".into(),
+                rows_affected: Some(rows_affected),
+                data: None,
+                columns: None,
+                elapsed_ms: Some(started.elapsed().as_millis() as u64),
+                busy_retries: 0,
+                ordering_note: None,
+                dry_run: false,
+                cached: false,
+                total_changes: None,
+                truncated: false,
+                limit: None,
+            })
+        }
+    }
+
+    pub async fn create_table_tool(
+        &self,
+        req: CreateTableRequest,
+    ) -> Result<CreateTableResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+        self.invalidate_query_cache();
+        Self::create_table_on(conn, req)
+    }
+
+    /// Create a table on `conn`; shared by create_table and batch.
+    fn create_table_on(
+        conn: &Connection,
+        req: CreateTableRequest,
+    ) -> Result<CreateTableResult, UniSqliteError> {
+        Self::validate_column_definitions(&req.columns)?;
+        if req.strict {
+            Self::validate_strict_types(&req.columns)?;
+        }
+
+        let if_not_exists = if req.if_not_exists {
+            "IF NOT EXISTS "
+        } else {
+            ""
+        };
+        let options: Vec<&str> = [(req.strict, "STRICT"), (req.without_rowid, "WITHOUT ROWID")]
+            .into_iter()
+            .filter_map(|(enabled, option)| enabled.then_some(option))
+            .collect();
+        let sql = format!(
+            "CREATE TABLE {}{}({}){};",
+            if_not_exists,
+            Self::quote_ident(&req.table_name)?,
+            req.columns,
+            if options.is_empty() {
+                String::new()
+            } else {
+                format!(" {}", options.join(", "))
+            }
+        );
+
+        conn.execute(&sql, [])?;
+
+        Ok(CreateTableResult {
+            success: true,
+            message: format!("Table '{}' created successfully", req.table_name),
+            table_name: req.table_name,
+        })
+    }
+
+    pub async fn list_tables_detailed_tool(
+        &self,
+    ) -> Result<ListTablesDetailedResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+
+        let (tables, source) = if rusqlite::version_number() >= TABLE_LIST_MIN_VERSION {
+            (Self::tables_from_table_list(conn)?, "table_list")
+        } else {
+            (Self::tables_from_sqlite_master(conn)?, "sqlite_master")
+        };
+
+        Ok(ListTablesDetailedResult {
+            total_count: tables.len(),
+            tables,
+            source: source.to_string(),
+        })
+    }
+
+    /// Tables and views across every schema via `PRAGMA table_list`, skipping SQLite's own.
+    fn tables_from_table_list(conn: &Connection) -> Result<Vec<DetailedTableInfo>, UniSqliteError> {
+        let mut stmt = conn.prepare(
+            "SELECT schema, name, type, ncol, wr, strict FROM pragma_table_list
+             WHERE name NOT LIKE 'sqlite_%' ORDER BY schema = 'main' DESC, schema, name",
+        )?;
+        let tables = stmt
+            .query_map([], |row| {
+                Ok(DetailedTableInfo {
+                    schema: row.get(0)?,
+                    name: row.get(1)?,
+                    table_type: row.get(2)?,
+                    column_count: row.get(3)?,
+                    without_rowid: row.get(4)?,
+                    strict: row.get(5)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(tables)
+    }
+
+    /// Fallback for SQLite before 3.37: read `main`'s tables and views from `sqlite_master`
+    /// and infer the flags from the table options after the closing parenthesis.
+    fn tables_from_sqlite_master(
+        conn: &Connection,
+    ) -> Result<Vec<DetailedTableInfo>, UniSqliteError> {
+        let mut stmt = conn.prepare(
+            "SELECT name, type, sql FROM sqlite_master
+             WHERE type IN ('table', 'view') AND name NOT LIKE 'sqlite_%' ORDER BY name",
+        )?;
+        let objects = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut tables = Vec::new();
+        for (name, object_type, sql) in objects {
+            let sql = sql.unwrap_or_default().to_ascii_uppercase();
+            let options = sql.rsplit_once(')').map(|(_, tail)| tail).unwrap_or("");
+            let options: Vec<&str> = options
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|word| !word.is_empty())
+                .collect();
+            let virtual_table = sql.starts_with("CREATE VIRTUAL TABLE");
+
+            tables.push(DetailedTableInfo {
+                schema: "main".to_string(),
+                column_count: Self::table_columns(conn, &name)?.len() as i64,
+                table_type: if virtual_table {
+                    "virtual".to_string()
+                } else {
+                    object_type
+                },
+                without_rowid: options.windows(2).any(|w| w == ["WITHOUT", "ROWID"]),
+                strict: options.contains(&"STRICT"),
+                name,
+            });
+        }
+        Ok(tables)
+    }
+
+    pub async fn list_tables_tool(&self) -> Result<ListTablesResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+
+        let mut stmt = conn.prepare(
+            "SELECT name, sql FROM sqlite_master WHERE type='table' AND name NOT LIKE 'sqlite_%' ORDER BY name"
+        )?;
+
+        let rows = stmt.query_map([], |row| {
+            let name: String = row.get(0)?;
+            let sql: Option<String> = row.get(1)?;
+            Ok((name, sql))
+        })?;
+
+        let mut tables = Vec::new();
+        for row in rows {
+            let (name, sql) = row?;
+
+            // Get row count for each table
+            let count_sql = format!("SELECT COUNT(*) FROM {}", Self::quote_ident(&name)?);
+            let row_count: Option<i64> = conn.query_row(&count_sql, [], |row| row.get(0)).ok();
+
+            tables.push(TableInfo {
+                name,
+                sql,
+                row_count,
+            });
+        }
+
+        let total_count = tables.len();
+
+        Ok(ListTablesResult {
+            tables,
+            total_count,
+        })
+    }
+
+    pub async fn describe_table_tool(
+        &self,
+        req: DescribeTableRequest,
+    ) -> Result<DescribeTableResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+
+        let columns = Self::table_columns(conn, &req.table_name)?;
+        let indexes = Self::table_indexes(conn, &req.table_name)?;
+
+        Ok(DescribeTableResult {
+            table_name: req.table_name,
+            columns,
+            indexes,
+        })
+    }
+
+    pub async fn generate_schema_report_tool(
+        &self,
+        req: GenerateSchemaReportRequest,
+    ) -> Result<GenerateSchemaReportResult, UniSqliteError> {
+        let db_name = self
+            .current_path
+            .lock()
+            .await
+            .as_ref()
+            .and_then(|p| p.file_name().map(|n| n.to_string_lossy().to_string()))
+            .unwrap_or_else(|| "database".to_string());
+
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+
+        let mut stmt = conn.prepare(
+            "SELECT name FROM sqlite_master WHERE type='table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+        )?;
+        let table_names = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let cell = |s: &str| s.replace('|', "\\|");
+        let mut markdown = format!("# Schema Report: {}\n", db_name);
+
+        for table_name in &table_names {
+            let columns = Self::table_columns(conn, table_name)?;
+            let indexes = Self::table_indexes(conn, table_name)?;
+            let foreign_keys = Self::table_foreign_keys(conn, table_name)?;
+
+            markdown.push_str(&format!("\n## {}\n\n", table_name));
+            markdown.push_str("| Column | Type | Not Null | Default | Primary Key |\n");
+            markdown.push_str("|---|---|---|---|---|\n");
+            for column in &columns {
+                markdown.push_str(&format!(
+                    "| {} | {} | {} | {} | {} |\n",
+                    cell(&column.name),
+                    cell(&column.data_type),
+                    if column.not_null { "yes" } else { "no" },
+                    column
+                        .default_value
+                        .as_deref()
+                        .map(cell)
+                        .unwrap_or_default(),
+                    if column.primary_key { "yes" } else { "no" },
+                ));
+            }
+
+            if !indexes.is_empty() {
+                markdown.push_str("\n**Indexes**\n\n");
+                for index in &indexes {
+                    markdown.push_str(&format!("- {}\n", index));
+                }
+            }
+
+            if !foreign_keys.is_empty() {
+                markdown.push_str("\n**Foreign keys**\n\n");
+                for fk in &foreign_keys {
+                    markdown.push_str(&format!(
+                        "- {} → {}.{}\n",
+                        fk.from_column,
+                        fk.to_table,
+                        fk.to_column.as_deref().unwrap_or("(primary key)")
+                    ));
+                }
+            }
+        }
+
+        let output_path = match req.output_path {
+            Some(path) => {
+                let path = self.validate_file_path(Path::new(&path), &["md"])?;
+                fs::write(&path, &markdown)?;
+                Some(path.display().to_string())
+            }
+            None => None,
+        };
+
+        Ok(GenerateSchemaReportResult {
+            markdown,
+            table_count: table_names.len(),
+            output_path,
+        })
+    }
+
+    pub async fn export_er_diagram_tool(
+        &self,
+        req: ExportErDiagramRequest,
+    ) -> Result<ExportErDiagramResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+
+        let mut stmt = conn.prepare(
+            "SELECT name FROM sqlite_master WHERE type='table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+        )?;
+        let table_names = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut tables = Vec::with_capacity(table_names.len());
+        let mut relationship_count = 0;
+        for table_name in table_names {
+            let columns = Self::table_columns(conn, &table_name)?;
+            let foreign_keys = Self::table_foreign_keys(conn, &table_name)?;
+            relationship_count += foreign_keys.len();
+            tables.push((table_name, columns, foreign_keys));
+        }
+
+        let diagram = match req.format {
+            DiagramFormat::Mermaid => {
+                // Mermaid entity names and attribute types must be plain words.
+                let word = |s: &str| -> String {
+                    let w: String = s
+                        .chars()
+                        .map(|c| {
+                            if c.is_ascii_alphanumeric() || c == '_' {
+                                c
+                            } else {
+                                '_'
+                            }
+                        })
+                        .collect();
+                    if w.is_empty() { "ANY".to_string() } else { w }
+                };
+
+                let mut out = String::from("erDiagram\n");
+                for (table_name, columns, _) in &tables {
+                    out.push_str(&format!("    {} {{\n", word(table_name)));
+                    for column in columns {
+                        out.push_str(&format!(
+                            "        {} {}{}\n",
+                            word(&column.data_type),
+                            word(&column.name),
+                            if column.primary_key { " PK" } else { "" }
+                        ));
+                    }
+                    out.push_str("    }\n");
+                }
+                for (table_name, _, foreign_keys) in &tables {
+                    for fk in foreign_keys {
+                        out.push_str(&format!(
+                            "    {} ||--o{{ {} : \"{}\"\n",
+                            word(&fk.to_table),
+                            word(table_name),
+                            fk.from_column.replace('"', "'")
+                        ));
+                    }
+                }
+                out
+            }
+            DiagramFormat::Dot => {
+                let escape = |s: &str| -> String {
+                    s.chars()
+                        .flat_map(|c| match c {
+                            '{' | '}' | '|' | '<' | '>' | '"' | '\\' => vec!['\\', c],
+                            _ => vec![c],
+                        })
+                        .collect()
+                };
+
+                let mut out =
+                    String::from("digraph schema {\n    rankdir=LR;\n    node [shape=record];\n");
+                for (table_name, columns, _) in &tables {
+                    let fields: Vec<String> = columns
+                        .iter()
+                        .map(|c| {
+                            format!(
+                                "{}{} {}\\l",
+                                if c.primary_key { "* " } else { "" },
+                                escape(&c.name),
+                                escape(&c.data_type)
+                            )
+                        })
+                        .collect();
+                    out.push_str(&format!(
+                        "    \"{}\" [label=\"{{{}|{}}}\"];\n",
+                        escape(table_name),
+                        escape(table_name),
+                        fields.concat()
+                    ));
+                }
+                for (table_name, _, foreign_keys) in &tables {
+                    for fk in foreign_keys {
+                        out.push_str(&format!(
+                            "    \"{}\" -> \"{}\" [label=\"{}\"];\n",
+                            escape(table_name),
+                            escape(&fk.to_table),
+                            escape(&fk.from_column)
+                        ));
+                    }
+                }
+                out.push_str("}\n");
+                out
+            }
+        };
+
+        let output_path = match req.output_path {
+            Some(path) => {
+                let extensions: &[&str] = match req.format {
+                    DiagramFormat::Mermaid => &["mmd"],
+                    DiagramFormat::Dot => &["dot", "gv"],
+                };
+                let path = self.validate_file_path(Path::new(&path), extensions)?;
+                fs::write(&path, &diagram)?;
+                Some(path.display().to_string())
+            }
+            None => None,
+        };
+
+        Ok(ExportErDiagramResult {
+            diagram,
+            table_count: tables.len(),
+            relationship_count,
+            output_path,
+        })
+    }
+
+    /// Find child rows whose foreign key points at a parent row that does not exist, for
+    /// databases where foreign key enforcement was off when the data was written.
+    pub async fn find_orphans_tool(
+        &self,
+        req: FindOrphansRequest,
+    ) -> Result<FindOrphansResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+
+        let limit = req.limit.unwrap_or(100);
+
+        // Group foreign key columns by constraint id so composite keys are joined together.
+        let mut constraints: Vec<(String, Vec<String>, Vec<Option<String>>)> = Vec::new();
+        let mut last_id = None;
+        for fk in Self::table_foreign_keys(conn, &req.table_name)? {
+            if last_id != Some(fk.id) {
+                constraints.push((fk.to_table.clone(), Vec::new(), Vec::new()));
+                last_id = Some(fk.id);
+            }
+            let (_, from, to) = constraints.last_mut().unwrap();
+            from.push(fk.from_column);
+            to.push(fk.to_column);
+        }
+
+        let mut relationships = Vec::new();
+        let mut total_orphans = 0;
+        for (parent_table, from_columns, to_columns) in constraints {
+            // A reference without explicit columns targets the parent's primary key.
+            let to_columns: Vec<String> = if to_columns.iter().all(Option::is_some) {
+                to_columns.into_iter().flatten().collect()
+            } else {
+                Self::table_columns(conn, &parent_table)?
+                    .into_iter()
+                    .filter(|c| c.primary_key)
+                    .map(|c| c.name)
+                    .collect()
+            };
+
+            let parent_exists: bool = conn.query_row(
+                "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type='table' AND name = ?)",
+                [&parent_table],
+                |row| row.get(0),
+            )?;
+
+            let not_null = from_columns
+                .iter()
+                .map(|c| Ok(format!("c.{} IS NOT NULL", Self::quote_ident(c)?)))
+                .collect::<Result<Vec<_>, UniSqliteError>>()?
+                .join(" AND ");
+
+            let from = if parent_exists && to_columns.len() == from_columns.len() {
+                let join = from_columns
+                    .iter()
+                    .zip(&to_columns)
+                    .map(|(from, to)| {
+                        Ok(format!(
+                            "c.{} = p.{}",
+                            Self::quote_ident(from)?,
+                            Self::quote_ident(to)?
+                        ))
+                    })
+                    .collect::<Result<Vec<_>, UniSqliteError>>()?
+                    .join(" AND ");
+                format!(
+                    "FROM {} c LEFT JOIN {} p ON {} WHERE {} AND p.{} IS NULL",
+                    Self::quote_ident(&req.table_name)?,
+                    Self::quote_ident(&parent_table)?,
+                    join,
+                    not_null,
+                    Self::quote_ident(&to_columns[0])?
+                )
+            } else {
+                // Every non-null reference to a missing parent table is an orphan.
+                format!(
+                    "FROM {} c WHERE {}",
+                    Self::quote_ident(&req.table_name)?,
+                    not_null
+                )
+            };
+
+            // Count every orphan but only fetch the sample
+            let orphan_count: i64 =
+                conn.query_row(&format!("SELECT COUNT(*) {from}"), [], |row| row.get(0))?;
+            let orphan_count = orphan_count as usize;
+            let mut stmt = conn.prepare(&format!("SELECT c.* {from} LIMIT ?"))?;
+            let (columns, rows) = Self::collect_rows(&mut stmt, &[&(limit as i64)], false)?;
+            total_orphans += orphan_count;
+
+            relationships.push(OrphanRelationship {
+                parent_table,
+                from_columns,
+                to_columns,
+                orphan_count,
+                columns,
+                rows,
+            });
+        }
+
+        Ok(FindOrphansResult {
+            table_name: req.table_name,
+            relationships,
+            total_orphans,
+        })
+    }
+
+    pub async fn foreign_key_check_tool(
+        &self,
+        req: ForeignKeyCheckRequest,
+    ) -> Result<ForeignKeyCheckResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+
+        let sql = match &req.table_name {
+            Some(table_name) => format!(
+                "PRAGMA foreign_key_check({})",
+                Self::quote_ident(table_name)?
+            ),
+            None => "PRAGMA foreign_key_check".to_string(),
+        };
+
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map([], |row| {
+            Ok(ForeignKeyViolation {
+                table: row.get(0)?,
+                rowid: row.get(1)?,
+                parent: row.get(2)?,
+                fk_index: row.get(3)?,
+            })
+        })?;
+
+        let mut violations = Vec::new();
+        for row in rows {
+            violations.push(row?);
+        }
+
+        Ok(ForeignKeyCheckResult {
+            total_count: violations.len(),
+            violations,
+        })
+    }
+
+    pub async fn object_exists_tool(
+        &self,
+        req: ObjectExistsRequest,
+    ) -> Result<ObjectExistsResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+
+        let object_type = req
+            .object_type
+            .as_ref()
+            .map(|t| t.trim().to_ascii_lowercase());
+        if let Some(object_type) = &object_type
+            && !["table", "view", "index", "trigger"].contains(&object_type.as_str())
+        {
+            return Err(UniSqliteError::QueryFailed(format!(
+                "Invalid object type '{object_type}': expected table, view, index, or trigger"
+            )));
+        }
+
+        let found: Option<(String, Option<String>)> = conn
+            .query_row(
+                "SELECT type, sql FROM sqlite_master WHERE name = ?1 AND (?2 IS NULL OR type = ?2)",
+                rusqlite::params![req.name, object_type],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+
+        Ok(match found {
+            Some((object_type, sql)) => ObjectExistsResult {
+                exists: true,
+                name: req.name,
+                object_type: Some(object_type),
+                sql,
+            },
+            None => ObjectExistsResult {
+                exists: false,
+                name: req.name,
+                object_type,
+                sql: None,
+            },
+        })
+    }
+
+    pub async fn get_create_sql_tool(
+        &self,
+        req: GetCreateSqlRequest,
+    ) -> Result<GetCreateSqlResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+
+        let found: Option<(String, Option<String>)> = conn
+            .query_row(
+                "SELECT type, sql FROM sqlite_master WHERE name = ?",
+                [&req.name],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+
+        match found {
+            Some((object_type, Some(sql))) => Ok(GetCreateSqlResult {
+                name: req.name,
+                object_type,
+                sql,
+            }),
+            // Automatic indexes (e.g. from UNIQUE constraints) have no stored SQL
+            Some((object_type, None)) => Err(UniSqliteError::QueryFailed(format!(
+                "The {object_type} '{}' was created implicitly and has no CREATE statement",
+                req.name
+            ))),
+            None => Err(UniSqliteError::QueryFailed(format!(
+                "Object '{}' not found",
+                req.name
+            ))),
+        }
+    }
+
+    pub async fn get_table_ddl_with_indexes_tool(
+        &self,
+        req: GetTableDdlRequest,
+    ) -> Result<TableDdlResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+
+        Self::table_ddl(conn, &req.table_name)
+    }
+
+    /// Create an empty table with the source table's columns (`CREATE TABLE .. AS SELECT ..
+    /// WHERE 0`), optionally recreating its explicit indexes as `<new_table>_<index>`.
+    pub async fn clone_table_structure_tool(
+        &self,
+        req: CloneTableStructureRequest,
+    ) -> Result<CloneTableStructureResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+        self.invalidate_query_cache();
+
+        let ddl = Self::table_ddl(conn, &req.source_table)?;
+
+        let tx = conn.unchecked_transaction()?;
+        tx.execute(
+            &format!(
+                "CREATE TABLE {} AS SELECT * FROM {} WHERE 0",
+                Self::quote_ident(&req.new_table)?,
+                Self::quote_ident(&req.source_table)?
+            ),
+            [],
+        )?;
+
+        let mut indexes = Vec::new();
+        if req.copy_indexes {
+            for index in &ddl.indexes {
+                let index_name = format!("{}_{}", req.new_table, index.name);
+                tx.execute(
+                    &Self::retarget_index_sql(&index.sql, &index_name, &req.new_table)?,
+                    [],
+                )?;
+                indexes.push(index_name);
+            }
+        }
+        tx.commit()?;
+
+        Ok(CloneTableStructureResult {
+            success: true,
+            message: format!(
+                "Created empty table '{}' from '{}' with {} index(es)",
+                req.new_table,
+                req.source_table,
+                indexes.len()
+            ),
+            table_name: req.new_table,
+            indexes,
+        })
+    }
+
+    /// Swap two tables' names in one transaction. References from views, triggers, and
+    /// foreign keys keep pointing at the names, so they see the swapped-in table.
+    pub async fn swap_tables_tool(
+        &self,
+        req: SwapTablesRequest,
+    ) -> Result<SwapTablesResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+        self.invalidate_query_cache();
+
+        if req.table_a == req.table_b {
+            return Err(UniSqliteError::QueryFailed(
+                "Cannot swap a table with itself".into(),
+            ));
+        }
+        for table in [&req.table_a, &req.table_b] {
+            let exists: bool = conn.query_row(
+                "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?)",
+                [table],
+                |row| row.get(0),
+            )?;
+            if !exists {
+                return Err(UniSqliteError::QueryFailed(format!(
+                    "Table '{table}' not found"
+                )));
+            }
+        }
+
+        let temp_name = format!("_swap_{}", uuid::Uuid::new_v4().simple());
+        let rename = |from: &str, to: &str| -> Result<String, UniSqliteError> {
+            Ok(format!(
+                "ALTER TABLE {} RENAME TO {};",
+                Self::quote_ident(from)?,
+                Self::quote_ident(to)?
+            ))
+        };
+        let renames = [
+            rename(&req.table_a, &temp_name)?,
+            rename(&req.table_b, &req.table_a)?,
+            rename(&temp_name, &req.table_b)?,
+        ]
+        .concat();
+
+        // Legacy rename semantics stop SQLite from rewriting dependents to follow the
+        // original table through the temporary name
+        let legacy: bool = conn.pragma_query_value(None, "legacy_alter_table", |row| row.get(0))?;
+        conn.pragma_update(None, "legacy_alter_table", true)?;
+        let swapped = conn
+            .unchecked_transaction()
+            .and_then(|tx| tx.execute_batch(&renames).and_then(|_| tx.commit()));
+        conn.pragma_update(None, "legacy_alter_table", legacy)?;
+        swapped?;
+
+        Ok(SwapTablesResult {
+            success: true,
+            message: format!("Swapped tables '{}' and '{}'", req.table_a, req.table_b),
+        })
+    }
+
+    pub async fn generate_insert_template_tool(
+        &self,
+        req: GenerateInsertTemplateRequest,
+    ) -> Result<GenerateInsertTemplateResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+
+        let mut stmt = conn.prepare(&format!(
+            "PRAGMA table_info({})",
+            Self::quote_ident(&req.table_name)?
+        ))?;
+        let rows = stmt.query_map([], |row| {
+            Ok(ColumnInfo {
+                name: row.get(1)?,
+                data_type: row.get(2)?,
+                not_null: row.get::<_, i32>(3)? != 0,
+                default_value: row.get(4)?,
+                primary_key: row.get::<_, i32>(5)? != 0,
+            })
+        })?;
+
+        let mut columns = Vec::new();
+        for row in rows {
+            columns.push(row?);
+        }
+
+        if columns.is_empty() {
+            return Err(UniSqliteError::QueryFailed(format!(
+                "Table '{}' not found",
+                req.table_name
+            )));
+        }
+
+        let placeholder_count = columns.len();
+        let sql = format!(
+            "INSERT INTO {} ({}) VALUES ({})",
+            Self::quote_ident(&req.table_name)?,
+            Self::quote_ident_list(columns.iter().map(|c| c.name.as_str()))?,
+            vec!["?"; placeholder_count].join(", ")
+        );
+
+        Ok(GenerateInsertTemplateResult {
+            table_name: req.table_name,
+            sql,
+            columns,
+            placeholder_count,
+        })
+    }
+
+    pub async fn backup_tool(&self, req: BackupRequest) -> Result<BackupResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+
+        let backup_path = PathBuf::from(&req.destination_path);
+        let validated_path = self.validate_db_path(&backup_path)?;
+
+        // Use SQLite's backup API
+        let mut backup_conn = Connection::open(&validated_path)?;
+        let backup = rusqlite::backup::Backup::new(conn, &mut backup_conn)?;
+        backup.run_to_completion(5, std::time::Duration::from_millis(250), None)?;
+
+        let backup_size = fs::metadata(&validated_path).ok().map(|m| m.len());
+
+        Ok(BackupResult {
+            success: true,
+            message: "Backup completed successfully".into(),
+            backup_path: validated_path.display().to_string(),
+            backup_size,
+            timestamp: Utc::now(),
+        })
+    }
+
+    /// Copy one table, with its DDL and explicit indexes, into a new database file.
+    pub async fn export_table_to_db_tool(
+        &self,
+        req: ExportTableToDbRequest,
+    ) -> Result<ExportTableToDbResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+        self.invalidate_query_cache();
+
+        let dest_path = self.validate_db_path(&PathBuf::from(&req.dest_path))?;
+        if dest_path.exists() {
+            return Err(UniSqliteError::InvalidPath(
+                "Destination file already exists".into(),
+            ));
+        }
+
+        let ddl = Self::table_ddl(conn, &req.table_name)?;
+        let table = Self::quote_ident(&req.table_name)?;
+
+        let dest = Connection::open(&dest_path)?;
+        dest.execute_batch(&ddl.table_sql)?;
+
+        // Copy rows before building indexes so the inserts don't maintain them
+        conn.execute(
+            "ATTACH DATABASE ? AS export_dest",
+            [dest_path.to_string_lossy()],
+        )?;
+        let copied = conn.execute(
+            &format!("INSERT INTO export_dest.{table} SELECT * FROM main.{table}"),
+            [],
+        );
+        conn.execute("DETACH DATABASE export_dest", [])?;
+        let rows_copied = copied?;
+
+        for index in &ddl.indexes {
+            dest.execute_batch(&index.sql)?;
+        }
+        drop(dest);
+
+        let dest_size = fs::metadata(&dest_path).ok().map(|m| m.len());
+
+        Ok(ExportTableToDbResult {
+            success: true,
+            message: format!(
+                "Exported {rows_copied} rows of '{}' to new database",
+                req.table_name
+            ),
+            dest_path: dest_path.display().to_string(),
+            rows_copied,
+            indexes_created: ddl.indexes.len(),
+            dest_size,
+        })
+    }
+
+    /// Diff two tables by key: rows only in A, only in B, and rows whose other columns differ.
+    pub async fn compare_tables_tool(
+        &self,
+        req: CompareTablesRequest,
+    ) -> Result<CompareTablesResult, UniSqliteError> {
+        if req.key_columns.is_empty() {
+            return Err(UniSqliteError::QueryFailed(
+                "At least one key column is required".into(),
+            ));
+        }
+        let database_b = match &req.database_b {
+            Some(path) => {
+                let path = self.validate_db_path(Path::new(path))?;
+                if !path.exists() {
+                    return Err(UniSqliteError::DatabaseNotFound(path.display().to_string()));
+                }
+                Some(path)
+            }
+            None => None,
+        };
+        let limit = req.sample_limit.unwrap_or(20);
+
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+
+        let columns: Vec<String> = Self::table_columns(conn, &req.table_a)?
+            .into_iter()
+            .map(|c| c.name)
+            .collect();
+        if columns.is_empty() {
+            return Err(UniSqliteError::QueryFailed(format!(
+                "Table '{}' not found",
+                req.table_a
+            )));
+        }
+        if let Some(missing) = req.key_columns.iter().find(|k| !columns.contains(k)) {
+            return Err(UniSqliteError::QueryFailed(format!(
+                "Key column '{missing}' does not exist in '{}'",
+                req.table_a
+            )));
+        }
+
+        let table_a = format!("main.{}", Self::quote_ident(&req.table_a)?);
+        let table_b = match database_b {
+            Some(_) => format!("compare_b.{}", Self::quote_ident(&req.table_b)?),
+            None => format!("main.{}", Self::quote_ident(&req.table_b)?),
+        };
+        let join = req
+            .key_columns
+            .iter()
+            .map(|k| {
+                let k = Self::quote_ident(k)?;
+                Ok(format!("a.{k} = b.{k}"))
+            })
+            .collect::<Result<Vec<_>, UniSqliteError>>()?
+            .join(" AND ");
+        let compared: Vec<&String> = columns
+            .iter()
+            .filter(|c| !req.key_columns.contains(c))
+            .collect();
+        let differs = if compared.is_empty() {
+            "0".to_string()
+        } else {
+            compared
+                .iter()
+                .map(|c| {
+                    let c = Self::quote_ident(c)?;
+                    Ok(format!("a.{c} IS NOT b.{c}"))
+                })
+                .collect::<Result<Vec<_>, UniSqliteError>>()?
+                .join(" OR ")
+        };
+        let mut differing_select = Vec::new();
+        for k in &req.key_columns {
+            differing_select.push(format!("a.{}", Self::quote_ident(k)?));
+        }
+        for c in &compared {
+            differing_select.push(format!(
+                "a.{} AS {}",
+                Self::quote_ident(c)?,
+                Self::quote_ident(&format!("{c}_a"))?
+            ));
+            differing_select.push(format!(
+                "b.{} AS {}",
+                Self::quote_ident(c)?,
+                Self::quote_ident(&format!("{c}_b"))?
+            ));
+        }
+
+        let only_in_a_from =
+            format!("FROM {table_a} a WHERE NOT EXISTS (SELECT 1 FROM {table_b} b WHERE {join})");
+        let only_in_b_from =
+            format!("FROM {table_b} b WHERE NOT EXISTS (SELECT 1 FROM {table_a} a WHERE {join})");
+        let differing_from = format!("FROM {table_a} a JOIN {table_b} b ON {join} WHERE {differs}");
+
+        if let Some(path) = &database_b {
+            conn.execute(
+                "ATTACH DATABASE ? AS compare_b",
+                [Self::read_only_uri(path)],
+            )?;
+        }
+
+        let run = || -> Result<CompareTablesResult, UniSqliteError> {
+            let count = |from: &str| -> Result<i64, UniSqliteError> {
+                Ok(conn.query_row(&format!("SELECT COUNT(*) {from}"), [], |row| row.get(0))?)
+            };
+            let sample = |select: &str, from: &str| -> Result<RowSample, UniSqliteError> {
+                let mut stmt = conn.prepare(&format!("SELECT {select} {from} LIMIT {limit}"))?;
+                let (columns, rows) = Self::collect_rows(&mut stmt, &[], false)?;
+                Ok(RowSample { columns, rows })
+            };
+
+            let only_in_a_count = count(&only_in_a_from)?;
+            let only_in_b_count = count(&only_in_b_from)?;
+            let differing_count = count(&differing_from)?;
+
+            Ok(CompareTablesResult {
+                identical: only_in_a_count == 0 && only_in_b_count == 0 && differing_count == 0,
+                only_in_a_count,
+                only_in_b_count,
+                differing_count,
+                only_in_a: sample("a.*", &only_in_a_from)?,
+                only_in_b: sample("b.*", &only_in_b_from)?,
+                differing: sample(&differing_select.join(", "), &differing_from)?,
+            })
+        };
+        let result = run();
+
+        if database_b.is_some()
+            && let Err(e) = conn.execute("DETACH DATABASE compare_b", [])
+        {
+            tracing::warn!("Failed to detach compare_b: {}", e);
+        }
+
+        result
+    }
+
+    /// Return `count` rows ordered by a seeded hash of their rowid, so the same seed
+    /// yields the same sample.
+    pub async fn random_sample_tool(
+        &self,
+        req: RandomSampleRequest,
+    ) -> Result<RandomSampleResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+
+        self.register_sample_hash_function(conn)?;
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT * FROM {} ORDER BY sample_hash(?1, rowid), rowid LIMIT ?2",
+            Self::quote_ident(&req.table_name)?
+        ))?;
+        let (columns, rows) =
+            Self::collect_rows(&mut stmt, &[&req.seed, &(req.count as i64)], false)?;
+
+        Ok(RandomSampleResult {
+            seed: req.seed,
+            columns,
+            row_count: rows.len(),
+            rows,
+        })
+    }
+
+    /// Fetch one page ordered by `key_columns`, starting after the `last_seen` key tuple.
+    /// Unlike OFFSET paging, the cost doesn't grow with page depth when the keys are indexed.
+    pub async fn keyset_page_tool(
+        &self,
+        req: KeysetPageRequest,
+    ) -> Result<KeysetPageResult, UniSqliteError> {
+        if req.key_columns.is_empty() {
+            return Err(UniSqliteError::QueryFailed(
+                "At least one key column is required".into(),
+            ));
+        }
+        if let Some(last_seen) = &req.last_seen
+            && last_seen.len() != req.key_columns.len()
+        {
+            return Err(UniSqliteError::QueryFailed(format!(
+                "last_seen has {} values but there are {} key columns",
+                last_seen.len(),
+                req.key_columns.len()
+            )));
+        }
+        let limit = req.limit.unwrap_or(100);
+
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+
+        let keys = Self::quote_ident_list(req.key_columns.iter().map(String::as_str))?;
+        let filter = match &req.last_seen {
+            Some(last_seen) => format!(
+                "WHERE ({keys}) > ({})",
+                vec!["?"; last_seen.len()].join(", ")
+            ),
+            None => String::new(),
+        };
+        let sql = format!(
+            "SELECT * FROM {} {filter} ORDER BY {keys} LIMIT {limit}",
+            Self::quote_ident(&req.table_name)?
+        );
+
+        let params: Vec<Box<dyn rusqlite::ToSql>> = req
+            .last_seen
+            .iter()
+            .flatten()
+            .map(Self::json_to_sql_param)
+            .collect::<Result<_, _>>()?;
+        let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| &**p).collect();
+
+        let mut stmt = conn.prepare(&sql)?;
+        let (columns, rows) = Self::collect_rows(&mut stmt, &param_refs, false)?;
+
+        let key_positions = req
+            .key_columns
+            .iter()
+            .map(|k| {
+                columns.iter().position(|c| c == k).ok_or_else(|| {
+                    UniSqliteError::QueryFailed(format!(
+                        "Key column '{k}' is not a column of '{}'",
+                        req.table_name
+                    ))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let next_cursor = match rows.last() {
+            Some(last) if rows.len() == limit => {
+                Some(key_positions.iter().map(|&i| last[i].clone()).collect())
+            }
+            _ => None,
+        };
+
+        Ok(KeysetPageResult {
+            columns,
+            rows,
+            next_cursor,
+        })
+    }
+
+    /// Estimate a table's row count without scanning it: from `sqlite_stat1` when ANALYZE
+    /// has run, otherwise from the rowid range. Small tables are counted exactly.
+    pub async fn count_estimate_tool(
+        &self,
+        req: CountEstimateRequest,
+    ) -> Result<CountEstimateResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+
+        let table = Self::quote_ident(&req.table_name)?;
+        let exact_threshold = req.exact_threshold.unwrap_or(10_000);
+
+        let has_stat1: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'sqlite_stat1')",
+            [],
+            |row| row.get(0),
+        )?;
+        // The first number of each stat1 row is the table's row count
+        let stat1_estimate = if has_stat1 {
+            conn.query_row(
+                "SELECT CAST(stat AS INTEGER) FROM sqlite_stat1 WHERE tbl = ? ORDER BY idx IS NOT NULL LIMIT 1",
+                [&req.table_name],
+                |row| row.get::<_, i64>(0),
+            )
+            .optional()?
+        } else {
+            None
+        };
+
+        let estimate = match stat1_estimate {
+            Some(count) => Some((count, "sqlite_stat1")),
+            None => {
+                // Both ends of the rowid B-tree are a single seek; fails for WITHOUT ROWID tables
+                let range = conn.query_row(
+                    &format!("SELECT max(rowid) - min(rowid) + 1 FROM {table}"),
+                    [],
+                    |row| row.get::<_, Option<i64>>(0),
+                );
+                match range {
+                    Ok(count) => Some((count.unwrap_or(0), "rowid_range")),
+                    Err(_) => None,
+                }
+            }
+        };
+
+        let (row_count, approximate, source) = match estimate {
+            Some((count, source)) if count > exact_threshold => (count, true, source),
+            _ => {
+                let count: i64 =
+                    conn.query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| {
+                        row.get(0)
+                    })?;
+                (count, false, "exact")
+            }
+        };
+
+        Ok(CountEstimateResult {
+            table_name: req.table_name,
+            row_count,
+            approximate,
+            source: source.to_string(),
+        })
+    }
+
+    pub async fn batch_insert_tool(
+        &self,
+        req: BatchInsertRequest,
+    ) -> Result<BatchInsertResult, UniSqliteError> {
+        self.with_blocking_connection(move |handler, conn| {
+            handler.invalidate_query_cache();
+            let tx = conn.unchecked_transaction()?;
+            let mut busy_retries = 0;
+            let mut result = handler.batch_insert_on(&tx, req, &mut busy_retries)?;
+            handler.commit_with_busy_retry(tx, &mut busy_retries)?;
+            result.busy_retries = busy_retries;
+            Ok(result)
+        })
+        .await
+    }
+
+    /// Insert a batch of rows on `conn`, which the caller has placed in a transaction;
+    /// shared by batch_insert and batch.
+    fn batch_insert_on(
+        &self,
+        conn: &Connection,
+        req: BatchInsertRequest,
+        busy_retries: &mut u32,
+    ) -> Result<BatchInsertResult, UniSqliteError> {
+        let placeholders = vec!["?"; req.columns.len()].join(", ");
+        let insert_type = match (req.on_conflict, req.replace_on_conflict) {
+            (Some(strategy), false) | (Some(strategy @ ConflictStrategy::Replace), true) => {
+                strategy.insert_verb()
+            }
+            (Some(_), true) => {
+                return Err(UniSqliteError::QueryFailed(
+                    "replace_on_conflict contradicts on_conflict; set only on_conflict".into(),
+                ));
+            }
+            (None, true) => ConflictStrategy::Replace.insert_verb(),
+            (None, false) => "INSERT",
+        };
+        let sql = format!(
+            "{} INTO {} ({}) VALUES ({})",
+            insert_type,
+            Self::quote_ident(&req.table_name)?,
+            Self::quote_ident_list(req.columns.iter().map(String::as_str))?,
+            placeholders
+        );
+
+        // A row ignored by OR IGNORE executes without changing anything
+        let insert_all = || -> Result<(usize, usize), UniSqliteError> {
+            let mut rows_inserted = 0;
+            let mut rows_ignored = 0;
+
+            self.statement_cache.lock().unwrap().record(&sql);
+            let mut stmt = conn.prepare_cached(&sql)?;
+
+            for row in req.rows {
+                if row.len() != req.columns.len() {
+                    return Err(UniSqliteError::QueryFailed(
+                        "Row data length doesn't match column count".into(),
+                    ));
+                }
+
+                let params: Vec<Box<dyn rusqlite::ToSql>> = row
+                    .iter()
+                    .map(Self::json_to_sql_param)
+                    .collect::<Result<_, _>>()?;
+
+                let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| &**p).collect();
+
+                match self.with_busy_retry(busy_retries, || stmt.execute(&param_refs[..]))? {
+                    0 => rows_ignored += 1,
+                    _ => rows_inserted += 1,
+                }
+            }
+
+            for record in req.records {
+                if let Some(unknown) = record.keys().find(|k| !req.columns.contains(k)) {
+                    return Err(UniSqliteError::QueryFailed(format!(
+                        "Record key '{unknown}' is not one of the listed columns"
+                    )));
+                }
+
+                match self.insert_keyed_record(
+                    conn,
+                    insert_type,
+                    &req.table_name,
+                    &req.columns,
+                    &record,
+                )? {
+                    0 => rows_ignored += 1,
+                    _ => rows_inserted += 1,
+                }
+            }
+
+            Ok((rows_inserted, rows_ignored))
+        };
+
+        let changes_before = conn.total_changes();
+        let ((rows_inserted, rows_ignored), message) = if req.dry_run {
+            let counts = Self::with_rollback(conn, insert_all)?;
+            (
+                counts,
+                format!("Dry run: would insert {} rows; rolled back", counts.0),
+            )
+        } else {
+            let counts = insert_all()?;
+            (counts, format!("Successfully inserted {} rows", counts.0))
+        };
+        let message = if rows_ignored > 0 {
+            format!("{message} ({rows_ignored} ignored as conflicts)")
+        } else {
+            message
+        };
+
+        Ok(BatchInsertResult {
+            success: true,
+            message,
+            rows_inserted,
+            rows_ignored,
+            total_changes: req
+                .report_total_changes
+                .then(|| conn.total_changes() - changes_before),
+            busy_retries: *busy_retries,
+            dry_run: req.dry_run,
+        })
+    }
+
+    /// Insert one column-keyed record, naming only the columns it provides so the rest
+    /// fall back to their DEFAULT.
+    fn insert_keyed_record(
+        &self,
+        conn: &Connection,
+        insert_type: &str,
+        table_name: &str,
+        columns: &[String],
+        record: &serde_json::Map<String, serde_json::Value>,
+    ) -> Result<usize, UniSqliteError> {
+        let present: Vec<&String> = columns.iter().filter(|c| record.contains_key(*c)).collect();
+        let sql = if present.is_empty() {
+            format!(
+                "{insert_type} INTO {} DEFAULT VALUES",
+                Self::quote_ident(table_name)?
+            )
+        } else {
+            format!(
+                "{} INTO {} ({}) VALUES ({})",
+                insert_type,
+                Self::quote_ident(table_name)?,
+                Self::quote_ident_list(present.iter().map(|c| c.as_str()))?,
+                vec!["?"; present.len()].join(", ")
+            )
+        };
+
+        let params: Vec<Box<dyn rusqlite::ToSql>> = present
+            .iter()
+            .map(|c| Self::json_to_sql_param(&record[c.as_str()]))
+            .collect::<Result<_, _>>()?;
+
+        let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| &**p).collect();
+
+        self.statement_cache.lock().unwrap().record(&sql);
+        Ok(conn.prepare_cached(&sql)?.execute(&param_refs[..])?)
+    }
+
+    pub async fn batch_update_tool(
+        &self,
+        req: BatchUpdateRequest,
+    ) -> Result<BatchUpdateResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+        self.invalidate_query_cache();
+
+        let tx = conn.unchecked_transaction()?;
+        let result = Self::batch_update_on(&tx, req)?;
+        tx.commit()?;
+        Ok(result)
+    }
+
+    /// Apply keyed updates on `conn`, which the caller has placed in a transaction;
+    /// shared by batch_update and batch.
+    fn batch_update_on(
+        conn: &Connection,
+        req: BatchUpdateRequest,
+    ) -> Result<BatchUpdateResult, UniSqliteError> {
+        if req.set_columns.is_empty() || req.where_columns.is_empty() {
+            return Err(UniSqliteError::QueryFailed(
+                "Both set_columns and where_columns must be provided".into(),
+            ));
+        }
+
+        let set_clause = req
+            .set_columns
+            .iter()
+            .map(|c| Ok(format!("{} = ?", Self::quote_ident(c)?)))
+            .collect::<Result<Vec<_>, UniSqliteError>>()?
+            .join(", ");
+        let where_clause = req
+            .where_columns
+            .iter()
+            .map(|c| Ok(format!("{} = ?", Self::quote_ident(c)?)))
+            .collect::<Result<Vec<_>, UniSqliteError>>()?
+            .join(" AND ");
+        // Skip rows that already hold the new values so changed and matched can differ
+        let unchanged_clause = req
+            .set_columns
+            .iter()
+            .map(|c| Ok(format!("{} IS ?", Self::quote_ident(c)?)))
+            .collect::<Result<Vec<_>, UniSqliteError>>()?
+            .join(" AND ");
+
+        let update_sql = format!(
+            "UPDATE {} SET {} WHERE {} AND NOT ({})",
+            Self::quote_ident(&req.table_name)?,
+            set_clause,
+            where_clause,
+            unchanged_clause
+        );
+        let count_sql = format!(
+            "SELECT COUNT(*) FROM {} WHERE {}",
+            Self::quote_ident(&req.table_name)?,
+            where_clause
+        );
+
+        let mut rows_matched = 0;
+        let mut rows_changed = 0;
+
+        {
+            let mut update_stmt = conn.prepare(&update_sql)?;
+            let mut count_stmt = conn.prepare(&count_sql)?;
+            let set_len = req.set_columns.len();
+
+            for row in req.rows {
+                if row.len() != set_len + req.where_columns.len() {
+                    return Err(UniSqliteError::QueryFailed(
+                        "Row data length doesn't match set_columns + where_columns count".into(),
+                    ));
+                }
+
+                let params: Vec<Box<dyn rusqlite::ToSql>> = row
+                    .iter()
+                    .map(Self::json_to_sql_param)
+                    .collect::<Result<_, _>>()?;
+                let (set_params, where_params) = params.split_at(set_len);
+
+                let where_refs: Vec<&dyn rusqlite::ToSql> =
+                    where_params.iter().map(|p| &**p).collect();
+                let matched: i64 = count_stmt.query_row(&where_refs[..], |r| r.get(0))?;
+
+                let update_refs: Vec<&dyn rusqlite::ToSql> = set_params
+                    .iter()
+                    .chain(where_params)
+                    .chain(set_params)
+                    .map(|p| &**p)
+                    .collect();
+                update_stmt.execute(&update_refs[..])?;
+
+                rows_matched += matched as usize;
+                rows_changed += conn.changes() as usize;
+            }
+        }
+
+        Ok(BatchUpdateResult {
+            success: true,
+            message: format!("Matched {rows_matched} rows, changed {rows_changed} rows"),
+            rows_matched,
+            rows_changed,
+        })
+    }
+
+    pub async fn delete_rows_tool(
+        &self,
+        req: DeleteRowsRequest,
+    ) -> Result<DeleteRowsResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+        self.invalidate_query_cache();
+        Self::delete_rows_on(conn, req)
+    }
+
+    /// Delete matching rows on `conn`; shared by delete_rows and batch.
+    fn delete_rows_on(
+        conn: &Connection,
+        req: DeleteRowsRequest,
+    ) -> Result<DeleteRowsResult, UniSqliteError> {
+        let where_clause = req.where_clause.trim();
+        if where_clause.contains(';') {
+            return Err(UniSqliteError::QueryFailed(
+                "Multiple statements are not allowed".into(),
+            ));
+        }
+
+        let sql = if where_clause.is_empty() {
+            if !req.allow_full_table {
+                return Err(UniSqliteError::QueryFailed(
+                    "A where_clause is required; set allow_full_table to delete every row".into(),
+                ));
+            }
+            format!("DELETE FROM {}", Self::quote_ident(&req.table_name)?)
+        } else {
+            format!(
+                "DELETE FROM {} WHERE {}",
+                Self::quote_ident(&req.table_name)?,
+                where_clause
+            )
+        };
+
+        let params: Vec<Box<dyn rusqlite::ToSql>> = req
+            .parameters
+            .iter()
+            .map(Self::json_to_sql_param)
+            .collect::<Result<_, _>>()?;
+
+        let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| &**p).collect();
+
+        let changes_before = conn.total_changes();
+        let rows_deleted = if req.dry_run {
+            Self::with_rollback(conn, || Ok(conn.execute(&sql, &param_refs[..])?))?
+        } else {
+            conn.execute(&sql, &param_refs[..])?
+        };
+        let total_changes = req
+            .report_total_changes
+            .then(|| conn.total_changes() - changes_before);
+
+        Ok(DeleteRowsResult {
+            success: true,
+            message: if req.dry_run {
+                format!(
+                    "Dry run: would delete {rows_deleted} rows from '{}'; rolled back",
+                    req.table_name
+                )
+            } else {
+                format!("Deleted {rows_deleted} rows from '{}'", req.table_name)
+            },
+            rows_deleted,
+            dry_run: req.dry_run,
+            total_changes,
+        })
+    }
+
+    /// Read, and optionally set, an AUTOINCREMENT table's `sqlite_sequence` counter
+    pub async fn reset_sequence_tool(
+        &self,
+        req: ResetSequenceRequest,
+    ) -> Result<ResetSequenceResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+        self.invalidate_query_cache();
+
+        let sql: Option<String> = conn
+            .query_row(
+                "SELECT sql FROM sqlite_master WHERE type = 'table' AND name = ?1",
+                [&req.table_name],
+                |row| row.get(0),
+            )
+            .optional()?
+            .ok_or_else(|| {
+                UniSqliteError::QueryFailed(format!("Table '{}' not found", req.table_name))
+            })?;
+        // A table gets its sqlite_sequence row on first insert, so the DDL is checked too
+        let declared = sql.is_some_and(|sql| {
+            sql.to_ascii_uppercase()
+                .split(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .any(|word| word == "AUTOINCREMENT")
+        });
+        if !declared {
+            return Err(UniSqliteError::QueryFailed(format!(
+                "Table '{}' does not use AUTOINCREMENT",
+                req.table_name
+            )));
+        }
+
+        let read_sequence = || -> Result<Option<i64>, UniSqliteError> {
+            Ok(conn
+                .query_row(
+                    "SELECT seq FROM sqlite_sequence WHERE name = ?1",
+                    [&req.table_name],
+                    |row| row.get(0),
+                )
+                .optional()?)
+        };
+        let previous_value = read_sequence()?;
+
+        if let Some(value) = req.value {
+            if value < 0 {
+                return Err(UniSqliteError::QueryFailed(
+                    "Sequence value must not be negative".into(),
+                ));
+            }
+            if value == i64::MAX {
+                return Err(UniSqliteError::QueryFailed(
+                    "Sequence value must leave room for a next rowid (at most i64::MAX - 1)".into(),
+                ));
+            }
+            let tx = conn.unchecked_transaction()?;
+            if previous_value.is_some() {
+                tx.execute(
+                    "UPDATE sqlite_sequence SET seq = ?2 WHERE name = ?1",
+                    rusqlite::params![req.table_name, value],
+                )?;
+            } else {
+                tx.execute(
+                    "INSERT INTO sqlite_sequence (name, seq) VALUES (?1, ?2)",
+                    rusqlite::params![req.table_name, value],
+                )?;
+            }
+            tx.commit()?;
+        }
+
+        let value = read_sequence()?;
+        let max_rowid: Option<i64> = conn.query_row(
+            &format!(
+                "SELECT MAX(rowid) FROM {}",
+                Self::quote_ident(&req.table_name)?
+            ),
+            [],
+            |row| row.get(0),
+        )?;
+        // SQLite never reuses a rowid below the current maximum, whatever the counter says
+        let next_rowid = value
+            .unwrap_or(0)
+            .max(max_rowid.unwrap_or(0))
+            .checked_add(1)
+            .ok_or_else(|| {
+                UniSqliteError::QueryFailed(
+                    "The largest rowid is in use; the next AUTOINCREMENT insert fails with SQLITE_FULL"
+                        .into(),
+                )
+            })?;
+        let warning = match (req.value, max_rowid) {
+            (Some(requested), Some(max)) if requested < max => Some(format!(
+                "Rows up to rowid {max} still exist, so the next rowid is {next_rowid}, not {}",
+                requested.saturating_add(1)
+            )),
+            _ => None,
+        };
+
+        Ok(ResetSequenceResult {
+            table_name: req.table_name,
+            previous_value,
+            value,
+            max_rowid,
+            next_rowid,
+            warning,
+        })
+    }
+
+    pub async fn truncate_table_tool(
+        &self,
+        req: TruncateTableRequest,
+    ) -> Result<TruncateTableResult, UniSqliteError> {
+        if !req.confirm {
+            return Err(UniSqliteError::QueryFailed(
+                "Set confirm to true to delete every row".into(),
+            ));
+        }
+
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+        self.invalidate_query_cache();
+
+        let tx = conn.unchecked_transaction()?;
+        let rows_deleted = tx.execute(
+            &format!("DELETE FROM {}", Self::quote_ident(&req.table_name)?),
+            [],
+        )?;
+
+        // sqlite_sequence only exists once some table has used AUTOINCREMENT
+        let mut sequence_reset = false;
+        if req.reset_autoincrement {
+            let has_sequence: bool = tx.query_row(
+                "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type='table' AND name='sqlite_sequence')",
+                [],
+                |row| row.get(0),
+            )?;
+            if has_sequence {
+                tx.execute(
+                    "DELETE FROM sqlite_sequence WHERE name = ?",
+                    [&req.table_name],
+                )?;
+                sequence_reset = true;
+            }
+        }
+
+        tx.commit()?;
+
+        Ok(TruncateTableResult {
+            success: true,
+            message: format!("Deleted {rows_deleted} rows from '{}'", req.table_name),
+            rows_deleted,
+            sequence_reset,
+        })
+    }
+
+    /// Resolve an export's source to a single validated query: either `query` as given,
+    /// or a SELECT built from `table_name` and its projection, filter, and ordering.
+    fn resolve_export_query(
+        query: &str,
+        table_name: Option<&str>,
+        columns: Option<&[String]>,
+        where_clause: Option<&str>,
+        order_by: Option<&str>,
+    ) -> Result<String, UniSqliteError> {
+        let query = match table_name {
+            Some(_) if !query.trim().is_empty() => {
+                return Err(UniSqliteError::QueryFailed(
+                    "Provide either query or table_name, not both".into(),
+                ));
+            }
+            Some(table_name) => {
+                Self::table_export_query(table_name, columns, where_clause, order_by)?
+            }
+            None => query.to_string(),
+        };
+        Self::validate_sql_query(&query)?;
+        Ok(query)
+    }
+
+    /// Column names and raw row values of an export query.
+    fn export_rows<'s>(
+        stmt: &'s mut rusqlite::Statement<'_>,
+    ) -> Result<
+        (
+            Vec<String>,
+            impl Iterator<Item = rusqlite::Result<Vec<rusqlite::types::Value>>> + 's,
+        ),
+        UniSqliteError,
+    > {
+        let column_names: Vec<String> = stmt.column_names().iter().map(|s| s.to_string()).collect();
+        let column_count = column_names.len();
+        let rows = stmt.query_map([], move |row| {
+            (0..column_count)
+                .map(|i| row.get::<_, rusqlite::types::Value>(i))
+                .collect::<Result<Vec<_>, _>>()
+        })?;
+        Ok((column_names, rows))
+    }
+
+    /// Text form of an exported value. BLOBs use `blob_handling`; `external_files` is
+    /// written out by the caller, so it falls back to hex here.
+    fn export_cell_text(value: rusqlite::types::Value, blob_handling: BlobHandling) -> String {
+        match value {
+            rusqlite::types::Value::Null => String::new(),
+            rusqlite::types::Value::Integer(i) => i.to_string(),
+            rusqlite::types::Value::Real(f) => f.to_string(),
+            rusqlite::types::Value::Text(t) => t,
+            rusqlite::types::Value::Blob(b) => match blob_handling {
+                BlobHandling::Hex | BlobHandling::ExternalFiles => hex::encode(b),
+                BlobHandling::Base64 => BASE64_STANDARD.encode(b),
+                BlobHandling::Skip => String::new(),
+            },
+        }
+    }
+
+    pub async fn export_csv_tool(
+        &self,
+        mut req: ExportCsvRequest,
+    ) -> Result<ExportCsvResult, UniSqliteError> {
+        req.query = Self::resolve_export_query(
+            &req.query,
+            req.table_name.as_deref(),
+            req.columns.as_deref(),
+            req.where_clause.as_deref(),
+            req.order_by.as_deref(),
+        )?;
+
+        let db_path = self
+            .current_path
+            .lock()
+            .await
+            .clone()
+            .ok_or(UniSqliteError::NotConnected)?;
+        // The .gz file and the blobs directory are siblings of the validated CSV path
+        let csv_path = self.validate_file_path(Path::new(&req.output_path), &["csv"])?;
+
+        // Stream on a blocking thread with its own connection so a large export
+        // doesn't hold current_db and starve other tools
+        tokio::task::spawn_blocking(move || Self::export_csv_blocking(&db_path, &csv_path, req))
+            .await
+            .map_err(|e| UniSqliteError::ExportFailed(e.to_string()))?
+    }
+
+    /// Run a CSV export on a dedicated read-only connection. Only committed data is
+    /// visible, and functions registered on the main connection (e.g. `uuid()`) are not.
+    fn export_csv_blocking(
+        db_path: &Path,
+        csv_path: &Path,
+        req: ExportCsvRequest,
+    ) -> Result<ExportCsvResult, UniSqliteError> {
+        let conn = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+
+        let output_path = if req.compress {
+            PathBuf::from(format!("{}.gz", csv_path.display()))
+        } else {
+            csv_path.to_path_buf()
+        };
+
+        // Create CSV writer
+        let sink = ExportSink::create(&output_path, req.compress)?;
+        let mut wtr = csv::Writer::from_writer(CountingWriter {
+            inner: sink,
+            bytes: 0,
+        });
+
+        let mut stmt = conn.prepare(&req.query)?;
+        let (column_names, rows) = Self::export_rows(&mut stmt)?;
+
+        // Write headers if requested
+        if req.include_headers {
+            wtr.write_record(&column_names)?;
+        }
+
+        // External blob files go in a sibling "<stem>_blobs" directory next to the CSV
+        let blob_dir_name = format!(
+            "{}_blobs",
+            csv_path
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "export".into())
+        );
+        let blob_dir = csv_path.with_file_name(&blob_dir_name);
+        let mut blob_directory = None;
+
+        let mut rows_exported = 0;
+        for row in rows {
+            let mut record = Vec::new();
+            for (col, value) in row?.into_iter().enumerate() {
+                let cell = match value {
+                    rusqlite::types::Value::Blob(b)
+                        if req.blob_handling == BlobHandling::ExternalFiles =>
+                    {
+                        if blob_directory.is_none() {
+                            fs::create_dir_all(&blob_dir)?;
+                            blob_directory = Some(blob_dir.display().to_string());
+                        }
+                        let file_name = format!("row{}_col{}.bin", rows_exported + 1, col);
+                        fs::write(blob_dir.join(&file_name), b)?;
+                        format!("{blob_dir_name}/{file_name}")
+                    }
+                    value => Self::export_cell_text(value, req.blob_handling),
+                };
+                record.push(cell);
+            }
+            wtr.write_record(&record)?;
+            rows_exported += 1;
+        }
+
+        let counter = wtr
+            .into_inner()
+            .map_err(|e| UniSqliteError::Io(e.into_error()))?;
+        let uncompressed_bytes = counter.bytes;
+        counter.inner.finish()?;
+        let compressed_bytes = if req.compress {
+            Some(fs::metadata(&output_path)?.len())
+        } else {
+            None
+        };
+
+        Ok(ExportCsvResult {
+            success: true,
+            message: format!("Successfully exported {rows_exported} rows to CSV"),
+            output_path: output_path.display().to_string(),
+            rows_exported,
+            blob_directory,
+            uncompressed_bytes,
+            compressed_bytes,
+        })
+    }
+
+    /// Export query results to a single-worksheet XLSX file, keeping numbers numeric.
+    #[cfg(feature = "xlsx")]
+    pub async fn export_xlsx_tool(
+        &self,
+        mut req: ExportXlsxRequest,
+    ) -> Result<ExportXlsxResult, UniSqliteError> {
+        req.query = Self::resolve_export_query(
+            &req.query,
+            req.table_name.as_deref(),
+            req.columns.as_deref(),
+            req.where_clause.as_deref(),
+            req.order_by.as_deref(),
+        )?;
+        if req.blob_handling == BlobHandling::ExternalFiles {
+            return Err(UniSqliteError::ExportFailed(
+                "external_files blob handling is only supported for CSV".into(),
+            ));
+        }
+        let output_path = self.validate_file_path(Path::new(&req.output_path), &["xlsx"])?;
+
+        let db_path = self
+            .current_path
+            .lock()
+            .await
+            .clone()
+            .ok_or(UniSqliteError::NotConnected)?;
+
+        tokio::task::spawn_blocking(move || Self::export_xlsx_blocking(&db_path, &output_path, req))
+            .await
+            .map_err(|e| UniSqliteError::ExportFailed(e.to_string()))?
+    }
+
+    #[cfg(feature = "xlsx")]
+    fn export_xlsx_blocking(
+        db_path: &Path,
+        output_path: &Path,
+        req: ExportXlsxRequest,
+    ) -> Result<ExportXlsxResult, UniSqliteError> {
+        use rust_xlsxwriter::{Format, Workbook};
+
+        // Largest integer an Excel double holds exactly; bigger ones are written as text
+        const MAX_EXACT_INTEGER: u64 = 1 << 53;
+
+        let xlsx_err = |e: rust_xlsxwriter::XlsxError| UniSqliteError::ExportFailed(e.to_string());
+        let conn = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+
+        let mut workbook = Workbook::new();
+        let worksheet = workbook.add_worksheet();
+        worksheet
+            .set_name(req.sheet_name.as_deref().unwrap_or("Sheet1"))
+            .map_err(xlsx_err)?;
+
+        let mut stmt = conn.prepare(&req.query)?;
+        let (column_names, rows) = Self::export_rows(&mut stmt)?;
+
+        let bold = Format::new().set_bold();
+        for (col, name) in column_names.iter().enumerate() {
+            worksheet
+                .write_string_with_format(0, col as u16, name, &bold)
+                .map_err(xlsx_err)?;
+        }
+
+        let mut rows_exported = 0;
+        for row in rows {
+            let sheet_row = rows_exported as u32 + 1;
+            for (col, value) in row?.into_iter().enumerate() {
+                let col = col as u16;
+                match value {
+                    rusqlite::types::Value::Null => {}
+                    rusqlite::types::Value::Integer(i) if i.unsigned_abs() <= MAX_EXACT_INTEGER => {
+                        worksheet
+                            .write_number(sheet_row, col, i as f64)
+                            .map_err(xlsx_err)?;
+                    }
+                    rusqlite::types::Value::Real(f) => {
+                        worksheet
+                            .write_number(sheet_row, col, f)
+                            .map_err(xlsx_err)?;
+                    }
+                    value => {
+                        worksheet
+                            .write_string(
+                                sheet_row,
+                                col,
+                                Self::export_cell_text(value, req.blob_handling),
+                            )
+                            .map_err(xlsx_err)?;
+                    }
+                }
+            }
+            rows_exported += 1;
+        }
+
+        workbook.save(output_path).map_err(xlsx_err)?;
+
+        Ok(ExportXlsxResult {
+            success: true,
+            message: format!("Successfully exported {rows_exported} rows to XLSX"),
+            output_path: output_path.display().to_string(),
+            rows_exported,
+        })
+    }
+
+    pub async fn import_csv_tool(
+        &self,
+        req: ImportCsvRequest,
+    ) -> Result<ImportCsvResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+        self.invalidate_query_cache();
+
+        let input = self.validate_file_path(Path::new(&req.input_path), &["csv"])?;
+        let reject_path = req
+            .reject_path
+            .as_deref()
+            .map(|path| self.validate_file_path(Path::new(path), &["csv"]))
+            .transpose()?;
+        Self::import_csv(conn, &req, &input, reject_path.as_deref())
+    }
+
+    /// Import `input` as described by `req`, writing failed rows to `reject_path`.
+    /// Both paths must already be validated.
+    fn import_csv(
+        conn: &Connection,
+        req: &ImportCsvRequest,
+        input: &Path,
+        reject_path: Option<&Path>,
+    ) -> Result<ImportCsvResult, UniSqliteError> {
+        // Declared column types drive coercion, so "00501" stays text in a TEXT column
+        let mut info_stmt = conn.prepare(&format!(
+            "PRAGMA table_info({})",
+            Self::quote_ident(&req.table_name)?
+        ))?;
+        let table_columns: Vec<(String, ColumnAffinity)> = info_stmt
+            .query_map([], |row| {
+                let name: String = row.get(1)?;
+                let declared: String = row.get(2)?;
+                Ok((name, ColumnAffinity::from_declared_type(&declared)))
+            })?
+            .collect::<Result<_, _>>()?;
+
+        if table_columns.is_empty() {
+            return Err(UniSqliteError::ImportFailed(format!(
+                "Table '{}' not found",
+                req.table_name
+            )));
+        }
+
+        let (source, encoding, warning) = Self::open_csv_source(input, req.encoding.as_deref())?;
+
+        // Field counts are checked per record so bad rows can be rejected rather than abort
+        let mut rdr = csv::ReaderBuilder::new()
+            .has_headers(req.has_headers)
+            .flexible(true)
+            .from_reader(source);
+
+        let target_columns: Vec<(String, ColumnAffinity)> = if req.has_headers {
+            rdr.headers()?
+                .iter()
+                .map(|header| {
+                    table_columns
+                        .iter()
+                        .find(|(name, _)| name.eq_ignore_ascii_case(header.trim()))
+                        .cloned()
+                        .ok_or_else(|| {
+                            UniSqliteError::ImportFailed(format!(
+                                "CSV column '{header}' does not exist in table '{}'",
+                                req.table_name
+                            ))
+                        })
+                })
+                .collect::<Result<_, _>>()?
+        } else {
+            table_columns
+        };
+
+        let sql = format!(
+            "INSERT INTO {} ({}) VALUES ({})",
+            Self::quote_ident(&req.table_name)?,
+            Self::quote_ident_list(target_columns.iter().map(|(name, _)| name.as_str()))?,
+            vec!["?"; target_columns.len()].join(", ")
+        );
+
+        let mut rejects = match reject_path {
+            Some(path) => {
+                let mut wtr = csv::WriterBuilder::new().flexible(true).from_path(path)?;
+                if req.has_headers {
+                    let mut headers = rdr.byte_headers()?.clone();
+                    headers.push_field(b"error");
+                    wtr.write_byte_record(&headers)?;
+                }
+                Some(wtr)
+            }
+            None => None,
+        };
+
+        let tx = conn.unchecked_transaction()?;
+        let mut rows_imported = 0;
+        let mut rows_rejected = 0;
+
+        {
+            let mut stmt = tx.prepare(&sql)?;
+
+            for (line, record) in rdr.byte_records().enumerate() {
+                let record = record?;
+
+                let mut import_record = || -> Result<(), UniSqliteError> {
+                    if record.len() != target_columns.len() {
+                        return Err(UniSqliteError::ImportFailed(format!(
+                            "Record {} has {} fields, expected {}",
+                            line + 1,
+                            record.len(),
+                            target_columns.len()
+                        )));
+                    }
+
+                    let mut values = Vec::with_capacity(record.len());
+                    for (field, (_, affinity)) in record.iter().zip(&target_columns) {
+                        let field = std::str::from_utf8(field).map_err(|_| {
+                            UniSqliteError::ImportFailed(format!(
+                                "Record {} contains invalid UTF-8",
+                                line + 1
+                            ))
+                        })?;
+                        values.push(affinity.coerce_csv_field(field));
+                    }
+
+                    stmt.execute(rusqlite::params_from_iter(values))?;
+                    Ok(())
+                };
+
+                match (import_record(), rejects.as_mut()) {
+                    (Ok(()), _) => rows_imported += 1,
+                    (Err(e), Some(wtr)) => {
+                        // Rejected rows are copied verbatim with the failure appended
+                        let mut rejected = record.clone();
+                        rejected.push_field(e.to_string().as_bytes());
+                        wtr.write_byte_record(&rejected)?;
+                        rows_rejected += 1;
+                    }
+                    (Err(e), None) => return Err(e),
+                }
+            }
+        }
+
+        tx.commit()?;
+
+        if let Some(wtr) = rejects.as_mut() {
+            wtr.flush()?;
+        }
+
+        Ok(ImportCsvResult {
+            success: true,
+            message: format!(
+                "Successfully imported {rows_imported} rows from CSV ({rows_rejected} rejected)"
+            ),
+            rows_imported,
+            rows_rejected,
+            encoding: encoding.name().to_string(),
+            warning,
+        })
+    }
+
+    /// Create a table shaped like a CSV (names from the header, types from a sample of rows)
+    /// and optionally load the file into it
+    pub async fn create_table_from_csv_tool(
+        &self,
+        req: CreateTableFromCsvRequest,
+    ) -> Result<CreateTableFromCsvResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+        self.invalidate_query_cache();
+
+        let path = self.validate_file_path(Path::new(&req.input_path), &["csv"])?;
+        let sample = Self::scan_csv_sample(
+            &path,
+            req.has_headers,
+            req.encoding.as_deref(),
+            req.sample_rows.unwrap_or(1000),
+        )?;
+        if sample.headers.is_empty() {
+            return Err(UniSqliteError::ImportFailed(format!(
+                "No columns found in {}",
+                path.display()
+            )));
+        }
+
+        let columns = sample
+            .headers
+            .iter()
+            .zip(&sample.types)
+            .map(|(name, field_type)| {
+                let column = Self::quote_ident(name)?;
+                Ok(match field_type.declared_type() {
+                    Some(declared) => format!("{column} {declared}"),
+                    None => column,
+                })
+            })
+            .collect::<Result<Vec<_>, UniSqliteError>>()?;
+        let ddl = format!(
+            "CREATE TABLE {} ({})",
+            Self::quote_ident(&req.table_name)?,
+            columns.join(", ")
+        );
+
+        // The import runs its own transaction, so the table is created first and dropped
+        // again if loading fails
+        conn.execute_batch(&ddl)?;
+        let imported = if req.import {
+            let import_req = ImportCsvRequest {
+                table_name: req.table_name.clone(),
+                input_path: path.display().to_string(),
+                has_headers: req.has_headers,
+                reject_path: None,
+                encoding: req.encoding.clone(),
+            };
+            match Self::import_csv(conn, &import_req, &path, None) {
+                Ok(result) => Some(result),
+                Err(e) => {
+                    conn.execute_batch(&format!(
+                        "DROP TABLE {}",
+                        Self::quote_ident(&req.table_name)?
+                    ))?;
+                    return Err(e);
+                }
+            }
+        } else {
+            None
+        };
+
+        let rows_imported = imported.as_ref().map_or(0, |r| r.rows_imported);
+        Ok(CreateTableFromCsvResult {
+            success: true,
+            message: format!(
+                "Created table '{}' with {} columns and imported {rows_imported} rows",
+                req.table_name,
+                columns.len()
+            ),
+            ddl,
+            rows_imported,
+            warning: imported.and_then(|r| r.warning).or(sample.warning),
+        })
+    }
+
+    /// Show a CSV's columns, their inferred types, and leading rows without touching the database
+    pub async fn preview_csv_tool(
+        &self,
+        req: PreviewCsvRequest,
+    ) -> Result<PreviewCsvResult, UniSqliteError> {
+        let path = self.validate_file_path(Path::new(&req.input_path), &["csv"])?;
+        let sample = Self::scan_csv_sample(
+            &path,
+            req.has_headers,
+            req.encoding.as_deref(),
+            req.rows.unwrap_or(20),
+        )?;
+
+        let columns = sample
+            .headers
+            .into_iter()
+            .zip(sample.types)
+            .zip(sample.empty_counts)
+            .map(|((name, inferred_type), empty_count)| CsvColumnPreview {
+                name,
+                inferred_type,
+                empty_count,
+            })
+            .collect();
+
+        Ok(PreviewCsvResult {
+            columns,
+            rows_read: sample.rows.len(),
+            sample: sample.rows,
+            encoding: sample.encoding.name().to_string(),
+            warning: sample.warning,
+        })
+    }
+
+    /// Guess a text file's encoding from its BOM, UTF-8 validity, or NUL byte layout
+    pub async fn detect_encoding_tool(
+        &self,
+        req: DetectEncodingRequest,
+    ) -> Result<DetectEncodingResult, UniSqliteError> {
+        use std::io::Read;
+
+        let path = self.validate_path(Path::new(&req.path))?;
+        let limit = req.sample_bytes.unwrap_or(65_536).max(4) as u64;
+        let mut sample = Vec::new();
+        fs::File::open(&path)?
+            .take(limit)
+            .read_to_end(&mut sample)?;
+
+        let (encoding, has_bom, basis) = match encoding_rs::Encoding::for_bom(&sample) {
+            Some((encoding, _)) => (encoding, true, "bom"),
+            None => match std::str::from_utf8(&sample) {
+                // A multi-byte character cut off by the sample limit is still valid UTF-8
+                Ok(_) => (encoding_rs::UTF_8, false, "valid_utf8"),
+                Err(e) if e.error_len().is_none() => (encoding_rs::UTF_8, false, "valid_utf8"),
+                Err(_) => (Self::guess_legacy_encoding(&sample), false, "heuristic"),
+            },
+        };
+
+        Ok(DetectEncodingResult {
+            encoding: encoding.name().to_string(),
+            has_bom,
+            basis: basis.to_string(),
+        })
+    }
+
+    pub async fn import_json_tool(
+        &self,
+        req: ImportJsonRequest,
+    ) -> Result<ImportJsonResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+        self.invalidate_query_cache();
+
+        let mut info_stmt = conn.prepare(&format!(
+            "PRAGMA table_info({})",
+            Self::quote_ident(&req.table_name)?
+        ))?;
+        let table_columns: Vec<String> = info_stmt
+            .query_map([], |row| row.get(1))?
+            .collect::<Result<_, _>>()?;
+
+        if table_columns.is_empty() {
+            return Err(UniSqliteError::ImportFailed(format!(
+                "Table '{}' not found",
+                req.table_name
+            )));
+        }
+
+        let input = self.validate_file_path(Path::new(&req.input_path), &["json"])?;
+        let file = std::fs::File::open(input)?;
+        let records: Vec<serde_json::Value> =
+            serde_json::from_reader(std::io::BufReader::new(file))?;
+
+        let tx = conn.unchecked_transaction()?;
+        let mut rows_imported = 0;
+
+        for (index, value) in records.into_iter().enumerate() {
+            let serde_json::Value::Object(object) = value else {
+                return Err(UniSqliteError::ImportFailed(format!(
+                    "Element {index} is not a JSON object"
+                )));
+            };
+
+            let mut record = serde_json::Map::new();
+            for (key, value) in object {
+                if !table_columns.contains(&key) {
+                    if req.ignore_unknown_keys {
+                        continue;
+                    }
+                    return Err(UniSqliteError::ImportFailed(format!(
+                        "Element {index} key '{key}' does not exist in table '{}'",
+                        req.table_name
+                    )));
+                }
+                // Nested values are stored as JSON text for use with SQLite's JSON functions
+                let value = match value {
+                    serde_json::Value::Array(_) | serde_json::Value::Object(_) => {
+                        serde_json::Value::String(value.to_string())
+                    }
+                    other => other,
+                };
+                record.insert(key, value);
+            }
+
+            for column in &table_columns {
+                if record.contains_key(column) {
+                    continue;
+                }
+                match req.missing_keys {
+                    MissingKeyHandling::Default => {}
+                    MissingKeyHandling::Null => {
+                        record.insert(column.clone(), serde_json::Value::Null);
+                    }
+                    MissingKeyHandling::Error => {
+                        return Err(UniSqliteError::ImportFailed(format!(
+                            "Element {index} is missing column '{column}'"
+                        )));
+                    }
+                }
+            }
+
+            self.insert_keyed_record(&tx, "INSERT", &req.table_name, &table_columns, &record)?;
+            rows_imported += 1;
+        }
+
+        tx.commit()?;
+
+        Ok(ImportJsonResult {
+            success: true,
+            message: format!("Successfully imported {rows_imported} rows from JSON"),
+            rows_imported,
+        })
+    }
+
+    pub async fn optimize_tool(
+        &self,
+        req: OptimizeRequest,
+    ) -> Result<OptimizeResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+        self.invalidate_query_cache();
+
+        if let Some(limit) = req.analysis_limit {
+            conn.pragma_update(None, "analysis_limit", limit)?;
+        }
+        let analysis_limit: i64 =
+            conn.pragma_query_value(None, "analysis_limit", |row| row.get(0))?;
+
+        // 0xfffe is SQLite's default mask; 0x10000 widens it to all tables
+        let mask: u32 = if req.check_all_tables {
+            0x1fffe
+        } else {
+            0xfffe
+        };
+
+        // Bit 0x1 is debug mode: list the ANALYZE statements without running them
+        let mut stmt = conn.prepare(&format!("PRAGMA optimize({:#x})", mask | 0x1))?;
+        let operations: Vec<String> = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<_, _>>()?;
+
+        conn.execute_batch(&format!("PRAGMA optimize({mask:#x})"))?;
+
+        Ok(OptimizeResult {
+            success: true,
+            message: if operations.is_empty() {
+                "Query planner statistics are up to date".into()
+            } else {
+                format!("Ran {} optimization(s)", operations.len())
+            },
+            operations,
+            analysis_limit,
+        })
+    }
+
+    pub async fn checkpoint_and_truncate_wal_tool(
+        &self,
+    ) -> Result<CheckpointResult, UniSqliteError> {
+        let db_guard = self.lock_db().await?;
+        let path_guard = self.current_path.lock().await;
+        let (conn, path) = match (db_guard.as_ref(), path_guard.as_ref()) {
+            (Some(conn), Some(path)) => (conn, path),
+            _ => return Err(UniSqliteError::NotConnected),
+        };
+
+        let journal_mode: String =
+            conn.pragma_query_value(None, "journal_mode", |row| row.get(0))?;
+        let (busy, log_frames, checkpointed_frames): (i64, i64, i64) =
+            conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })?;
+
+        if busy != 0 {
+            return Err(UniSqliteError::QueryFailed(
+                "WAL checkpoint was blocked by active readers or writers; retry once they finish"
+                    .into(),
+            ));
+        }
+
+        let mut wal_path = path.clone().into_os_string();
+        wal_path.push("-wal");
+        let wal_size = fs::metadata(&wal_path).ok().map(|m| m.len());
+
+        let message = if journal_mode.eq_ignore_ascii_case("wal") {
+            format!(
+                "Checkpointed {checkpointed_frames} of {log_frames} frames and truncated the WAL"
+            )
+        } else {
+            format!("Database is in {journal_mode} journal mode; there is no WAL to checkpoint")
+        };
+
+        Ok(CheckpointResult {
+            success: true,
+            message,
+            journal_mode,
+            log_frames,
+            checkpointed_frames,
+            wal_size,
+        })
+    }
+
+    pub async fn table_size_bytes_tool(
+        &self,
+        req: TableSizeBytesRequest,
+    ) -> Result<TableSizeBytesResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT s.name, m.type, COUNT(*), SUM(s.pgsize) \
+                 FROM dbstat AS s JOIN sqlite_master AS m ON m.name = s.name \
+                 WHERE s.schema = 'main' AND m.tbl_name = ? \
+                 GROUP BY s.name ORDER BY m.type = 'index', s.name",
+            )
+            .map_err(Self::dbstat_error)?;
+
+        let rows = stmt.query_map([&req.table_name], |row| {
+            Ok(ObjectSizeInfo {
+                name: row.get(0)?,
+                object_type: row.get(1)?,
+                pages: row.get(2)?,
+                bytes: row.get(3)?,
+                percent: 0.0,
+            })
+        })?;
+
+        let mut objects = Vec::new();
+        for row in rows {
+            objects.push(row?);
+        }
+
+        if !objects.iter().any(|o| o.object_type == "table") {
+            return Err(UniSqliteError::QueryFailed(format!(
+                "Table '{}' not found",
+                req.table_name
+            )));
+        }
+
+        let (mut table_pages, mut table_bytes, mut index_pages, mut index_bytes) = (0, 0, 0, 0);
+        for object in &objects {
+            if object.object_type == "index" {
+                index_pages += object.pages;
+                index_bytes += object.bytes;
+            } else {
+                table_pages += object.pages;
+                table_bytes += object.bytes;
+            }
+        }
+        let total_bytes = table_bytes + index_bytes;
+        if total_bytes > 0 {
+            for object in &mut objects {
+                object.percent = object.bytes as f64 * 100.0 / total_bytes as f64;
+            }
+        }
+
+        Ok(TableSizeBytesResult {
+            table_name: req.table_name,
+            table_pages,
+            table_bytes,
+            index_pages,
+            index_bytes,
+            total_pages: table_pages + index_pages,
+            total_bytes,
+            objects,
+        })
+    }
+
+    pub async fn database_size_breakdown_tool(
+        &self,
+    ) -> Result<DatabaseSizeBreakdownResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT s.name, COALESCE(m.type, 'table'), COUNT(*), SUM(s.pgsize) \
+                 FROM dbstat AS s LEFT JOIN sqlite_master AS m ON m.name = s.name \
+                 WHERE s.schema = 'main' \
+                 GROUP BY s.name ORDER BY SUM(s.pgsize) DESC, s.name",
+            )
+            .map_err(Self::dbstat_error)?;
+
+        let rows = stmt.query_map([], |row| {
+            Ok(ObjectSizeInfo {
+                name: row.get(0)?,
+                object_type: row.get(1)?,
+                pages: row.get(2)?,
+                bytes: row.get(3)?,
+                percent: 0.0,
+            })
+        })?;
+
+        let mut objects = Vec::new();
+        for row in rows {
+            objects.push(row?);
+        }
+
+        let total_pages = objects.iter().map(|o| o.pages).sum();
+        let total_bytes: i64 = objects.iter().map(|o| o.bytes).sum();
+        if total_bytes > 0 {
+            for object in &mut objects {
+                object.percent = object.bytes as f64 * 100.0 / total_bytes as f64;
+            }
+        }
+
+        Ok(DatabaseSizeBreakdownResult {
+            objects,
+            total_pages,
+            total_bytes,
+        })
+    }
+
+    pub async fn list_attached_databases_tool(
+        &self,
+    ) -> Result<ListAttachedDatabasesResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+
+        let databases = Self::attached_databases(conn)?;
+        let total_count = databases.len();
+
+        Ok(ListAttachedDatabasesResult {
+            databases,
+            total_count,
+        })
+    }
+
+    pub async fn connection_info_tool(&self) -> Result<ConnectionInfoResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let path = self
+            .current_path
+            .lock()
+            .await
+            .clone()
+            .ok_or(UniSqliteError::NotConnected)?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+
+        let open_flags = self
+            .idle
+            .lock()
+            .unwrap()
+            .last_connect
+            .as_ref()
+            .map(|req| Self::open_flag_names(Self::open_flags(req)))
+            .unwrap_or_default();
+        let file_writable = fs::metadata(&path)
+            .map(|m| !m.permissions().readonly())
+            .unwrap_or(false);
+
+        Ok(ConnectionInfoResult {
+            database_path: path.display().to_string(),
+            readonly: conn.is_readonly(rusqlite::DatabaseName::Main)?,
+            file_writable,
+            open_flags,
+            journal_mode: conn.query_row("PRAGMA journal_mode", [], |row| row.get(0))?,
+            foreign_keys_on: conn.query_row("PRAGMA foreign_keys", [], |row| row.get(0))?,
+            busy_timeout_ms: conn.query_row("PRAGMA busy_timeout", [], |row| row.get(0))?,
+        })
+    }
+
+    pub async fn list_functions_tool(&self) -> Result<ListFunctionsResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+
+        // JSON is built in since 3.38 unless omitted, so probe it rather than the compile options
+        let capabilities = SqlCapabilities {
+            json1: conn
+                .query_row("SELECT json_valid('{}')", [], |row| row.get::<_, bool>(0))
+                .is_ok(),
+            fts5: Self::compile_option_used(conn, "ENABLE_FTS5"),
+            rtree: Self::compile_option_used(conn, "ENABLE_RTREE"),
+            math_functions: Self::compile_option_used(conn, "ENABLE_MATH_FUNCTIONS"),
+            window_functions: Self::probe_window_functions(conn),
+        };
+
+        Ok(ListFunctionsResult {
+            functions: self.custom_functions.lock().unwrap().clone(),
+            capabilities,
+        })
+    }
+
+    /// Lower (or restore) SQLite runtime limits on the connection. The limits stay in
+    /// effect for connections opened later, so an operator can harden the server once.
+    pub async fn set_limits_tool(
+        &self,
+        req: SetLimitsRequest,
+    ) -> Result<SetLimitsResult, UniSqliteError> {
+        if let Some((name, _, _)) = req
+            .entries()
+            .into_iter()
+            .find(|(_, _, value)| value.is_some_and(|v| v < 0))
+        {
+            return Err(UniSqliteError::QueryFailed(format!(
+                "Limit '{name}' must not be negative"
+            )));
+        }
+
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+
+        let previous: Vec<i32> = req
+            .entries()
+            .into_iter()
+            .map(|(_, limit, _)| conn.limit(limit))
+            .collect();
+        Self::apply_limits(conn, &req);
+        self.limits.lock().unwrap().merge(&req);
+
+        let limits = req
+            .entries()
+            .into_iter()
+            .zip(previous)
+            .map(|((name, limit, _), previous)| LimitChange {
+                name: name.to_string(),
+                previous,
+                current: conn.limit(limit),
+            })
+            .collect();
+
+        Ok(SetLimitsResult { limits })
+    }
+
+    pub async fn health_check_tool(&self) -> Result<HealthCheckResult, UniSqliteError> {
+        let db_guard = self.current_db.lock().await;
+        let path_guard = self.current_path.lock().await;
+
+        let connected = db_guard.is_some();
+        let database_path = path_guard.as_ref().map(|p| p.display().to_string());
+
+        let (database_size, table_count, last_modified) = if let (Some(conn), Some(path)) =
+            (db_guard.as_ref(), path_guard.as_ref())
+        {
+            let size = fs::metadata(path).ok().map(|m| m.len());
+            let modified = fs::metadata(path)
+                .ok()
+                .and_then(|m| m.modified().ok())
+                .map(|t| DateTime::from(t));
+
+            let count: Result<i32, _> = conn.query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name NOT LIKE 'sqlite_%'",
+                [],
+                |row| row.get(0)
+            );
+
+            (size, count.ok().map(|c| c as usize), modified)
+        } else {
+            (None, None, None)
+        };
+
+        // Get SQLite version
+        let sqlite_version = if let Some(conn) = db_guard.as_ref() {
+            conn.query_row("SELECT sqlite_version()", [], |row| {
+                let version: String = row.get(0)?;
+                Ok(version)
+            })
+            .unwrap_or_else(|_| "Unknown".to_string())
+        } else {
+            "Not connected".to_string()
+        };
+
+        let supports_window_functions = db_guard.as_ref().map(Self::probe_window_functions);
+        let attached_databases = db_guard
+            .as_ref()
+            .and_then(|conn| Self::attached_databases(conn).ok());
+        let statement_cache = if connected {
+            Some(self.statement_cache.lock().unwrap().stats())
+        } else {
+            None
+        };
+        let file_changed_since_connect = match (connected, path_guard.as_ref()) {
+            (true, Some(path)) => (*self.file_identity.lock().unwrap()).map(|identity| {
+                FileIdentity::of(path).is_none_or(|current| !identity.same_file(&current))
+            }),
+            _ => None,
+        };
+        let warning = (file_changed_since_connect == Some(true)).then(|| {
+            "Database file was deleted or replaced since connect; reconnect to see the current file"
+                .to_string()
+        });
+        let loaded_extensions = connected.then(|| self.loaded_extensions.lock().unwrap().clone());
+
+        Ok(HealthCheckResult {
+            connected,
+            database_path,
+            database_size,
+            table_count,
+            last_modified,
+            sqlite_version,
+            supports_window_functions,
+            statement_cache,
+            attached_databases,
+            file_changed_since_connect,
+            warning,
+            loaded_extensions,
+        })
+    }
+
+    pub async fn new_uuid_tool(&self) -> Result<NewUuidResult, UniSqliteError> {
+        Ok(NewUuidResult {
+            uuid: uuid::Uuid::new_v4().to_string(),
+        })
+    }
+
+    pub async fn pack_embedding_tool(
+        &self,
+        req: PackEmbeddingRequest,
+    ) -> Result<EmbeddingResult, UniSqliteError> {
+        Ok(EmbeddingResult {
+            base64: BASE64_STANDARD.encode(Self::pack_f32(&req.values)),
+            dimensions: req.values.len(),
+            values: req.values,
+        })
+    }
+
+    pub async fn unpack_embedding_tool(
+        &self,
+        req: UnpackEmbeddingRequest,
+    ) -> Result<EmbeddingResult, UniSqliteError> {
+        let bytes = BASE64_STANDARD
+            .decode(req.base64.trim())
+            .map_err(|e| UniSqliteError::QueryFailed(format!("Invalid base64: {e}")))?;
+        let values = Self::unpack_f32(&bytes).map_err(UniSqliteError::QueryFailed)?;
+
+        Ok(EmbeddingResult {
+            base64: req.base64,
+            dimensions: values.len(),
+            values,
+        })
+    }
+
+    pub async fn similarity_search_tool(
+        &self,
+        req: SimilaritySearchRequest,
+    ) -> Result<SimilaritySearchResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+
+        if req.k == 0 {
+            return Err(UniSqliteError::QueryFailed(
+                "k must be greater than zero".into(),
+            ));
+        }
+        if req.query_vector.is_empty() {
+            return Err(UniSqliteError::QueryFailed("query_vector is empty".into()));
+        }
+        // Works without any vector extension; registering again is harmless
+        Self::register_vector_functions(conn, &mut self.custom_functions.lock().unwrap())?;
+
+        let column = Self::quote_ident(&req.column)?;
+        let mut select = format!("rowid, cosine_similarity({column}, ?1) AS similarity");
+        if !req.return_columns.is_empty() {
+            select.push_str(", ");
+            select.push_str(&Self::quote_ident_list(
+                req.return_columns.iter().map(String::as_str),
+            )?);
+        }
+        let sql = format!(
+            "SELECT {select} FROM {} WHERE {column} IS NOT NULL \
+             ORDER BY similarity DESC LIMIT ?2",
+            Self::quote_ident(&req.table)?
+        );
+
+        let query_blob = Self::pack_f32(&req.query_vector);
+        let k = req.k as i64;
+        let mut stmt = conn.prepare(&sql)?;
+        let (columns, rows) = Self::collect_rows(&mut stmt, &[&query_blob, &k], false)?;
+
+        Ok(SimilaritySearchResult {
+            columns,
+            row_count: rows.len(),
+            rows,
+        })
+    }
+
+    pub async fn vacuum_tool(&self) -> Result<VacuumResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+
+        let bytes_before = Self::database_bytes(conn)?;
+        let started = Instant::now();
+        conn.execute_batch("VACUUM")?;
+        let duration_ms = started.elapsed().as_millis() as u64;
+        let bytes_after = Self::database_bytes(conn)?;
+        let bytes_reclaimed = bytes_before - bytes_after;
+
+        Ok(VacuumResult {
+            success: true,
+            message: format!("VACUUM reclaimed {bytes_reclaimed} bytes in {duration_ms} ms"),
+            bytes_before,
+            bytes_after,
+            bytes_reclaimed,
+            duration_ms,
+        })
+    }
+
+    pub async fn vacuum_check_tool(
+        &self,
+        req: VacuumCheckRequest,
+    ) -> Result<VacuumCheckResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+
+        let threshold = req.threshold.unwrap_or(DEFAULT_VACUUM_THRESHOLD);
+        if !(0.0..=1.0).contains(&threshold) {
+            return Err(UniSqliteError::QueryFailed(format!(
+                "threshold must be between 0 and 1, got {threshold}"
+            )));
+        }
+
+        let page_count: i64 = conn.pragma_query_value(None, "page_count", |row| row.get(0))?;
+        let freelist_count: i64 =
+            conn.pragma_query_value(None, "freelist_count", |row| row.get(0))?;
+        let page_size: i64 = conn.pragma_query_value(None, "page_size", |row| row.get(0))?;
+        let auto_vacuum_mode = Self::auto_vacuum_mode(conn)?;
+
+        let fragmentation_ratio = if page_count > 0 {
+            freelist_count as f64 / page_count as f64
+        } else {
+            0.0
+        };
+        let reclaimable_bytes = freelist_count * page_size;
+        let vacuum_recommended = freelist_count > 0 && fragmentation_ratio > threshold;
+
+        let recommendation = if vacuum_recommended {
+            format!(
+                "VACUUM recommended: {:.1}% of pages are free (~{reclaimable_bytes} bytes reclaimable)",
+                fragmentation_ratio * 100.0
+            )
+        } else {
+            format!(
+                "VACUUM not needed: {:.1}% of pages are free, below the {:.1}% threshold",
+                fragmentation_ratio * 100.0,
+                threshold * 100.0
+            )
+        };
+
+        Ok(VacuumCheckResult {
+            page_count,
+            freelist_count,
+            page_size,
+            fragmentation_ratio,
+            reclaimable_bytes,
+            auto_vacuum_mode,
+            threshold,
+            vacuum_recommended,
+            recommendation,
+        })
+    }
+
+    pub async fn vacuum_into_tool(
+        &self,
+        req: VacuumIntoRequest,
+    ) -> Result<VacuumIntoResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+
+        let destination = PathBuf::from(&req.destination_path);
+        let validated_path = self.validate_db_path(&destination)?;
+
+        if validated_path.exists() {
+            return Err(UniSqliteError::InvalidPath(
+                "Destination file already exists".into(),
+            ));
+        }
+
+        // VACUUM INTO accepts an expression, so the filename is bound rather than interpolated
+        let destination_str = validated_path.to_string_lossy().into_owned();
+        conn.execute("VACUUM INTO ?", [&destination_str])?;
+
+        let destination_size = fs::metadata(&validated_path).ok().map(|m| m.len());
+
+        Ok(VacuumIntoResult {
+            success: true,
+            message: "Database vacuumed into new file successfully".into(),
+            destination_path: validated_path.display().to_string(),
+            destination_size,
+        })
+    }
+
+    pub async fn set_auto_vacuum_tool(
+        &self,
+        req: SetAutoVacuumRequest,
+    ) -> Result<SetAutoVacuumResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+
+        let mode = req.mode.trim().to_ascii_uppercase();
+        if !["NONE", "FULL", "INCREMENTAL"].contains(&mode.as_str()) {
+            return Err(UniSqliteError::QueryFailed(format!(
+                "Invalid auto_vacuum mode '{}': expected NONE, FULL, or INCREMENTAL",
+                req.mode
+            )));
+        }
+
+        let previous_mode = Self::auto_vacuum_mode(conn)?;
+        conn.pragma_update(None, "auto_vacuum", &mode)?;
+
+        if req.vacuum {
+            conn.execute_batch("VACUUM")?;
+        }
+
+        let current_mode = Self::auto_vacuum_mode(conn)?;
+        let message = if current_mode == mode {
+            format!("auto_vacuum set to {current_mode}")
+        } else {
+            format!("auto_vacuum is still {current_mode}; run VACUUM for {mode} to take effect")
+        };
+
+        Ok(SetAutoVacuumResult {
+            success: true,
+            message,
+            previous_mode,
+            mode: current_mode,
+            vacuumed: req.vacuum,
+        })
+    }
+
+    pub async fn incremental_vacuum_tool(
+        &self,
+        req: IncrementalVacuumRequest,
+    ) -> Result<IncrementalVacuumResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+
+        let auto_vacuum_mode = Self::auto_vacuum_mode(conn)?;
+        let freelist_pages_before: i64 =
+            conn.pragma_query_value(None, "freelist_count", |row| row.get(0))?;
+
+        // A bare incremental_vacuum reclaims the whole freelist
+        let sql = match req.pages {
+            Some(pages) => format!("PRAGMA incremental_vacuum({pages})"),
+            None => "PRAGMA incremental_vacuum".to_string(),
+        };
+
+        // The pragma frees one page per step, so drain it to completion
+        let mut stmt = conn.prepare(&sql)?;
+        let mut rows = stmt.query([])?;
+        while rows.next()?.is_some() {}
+
+        let freelist_pages_after: i64 =
+            conn.pragma_query_value(None, "freelist_count", |row| row.get(0))?;
+
+        let message = if auto_vacuum_mode == "INCREMENTAL" {
+            format!(
+                "Reclaimed {} freelist pages",
+                freelist_pages_before - freelist_pages_after
+            )
+        } else {
+            format!("auto_vacuum is {auto_vacuum_mode}; incremental_vacuum has no effect")
+        };
+
+        Ok(IncrementalVacuumResult {
+            success: true,
+            message,
+            auto_vacuum_mode,
+            freelist_pages_before,
+            freelist_pages_after,
+        })
+    }
+
+    pub async fn tune_performance_tool(
+        &self,
+        req: PerformanceSettings,
+    ) -> Result<PerformanceSettingsResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+
+        Self::apply_performance_settings(conn, &req)?;
+        Self::read_performance_settings(conn)
+    }
+
+    pub async fn enable_sql_trace_tool(
+        &self,
+        req: EnableSqlTraceRequest,
+    ) -> Result<SqlTraceResult, UniSqliteError> {
+        let state = SqlTraceState {
+            enabled: true,
+            verbose: req.verbose,
+        };
+        *self.sql_trace.lock().await = state;
+
+        if let Some(conn) = self.current_db.lock().await.as_mut() {
+            Self::apply_sql_trace(conn, state);
+        }
+
+        Ok(SqlTraceResult {
+            success: true,
+            message: if req.verbose {
+                "SQL tracing enabled (including parameter values)".into()
+            } else {
+                "SQL tracing enabled".into()
+            },
+            state,
+        })
+    }
+
+    pub async fn disable_sql_trace_tool(&self) -> Result<SqlTraceResult, UniSqliteError> {
+        let state = SqlTraceState::default();
+        *self.sql_trace.lock().await = state;
+
+        if let Some(conn) = self.current_db.lock().await.as_mut() {
+            Self::apply_sql_trace(conn, state);
+        }
+
+        Ok(SqlTraceResult {
+            success: true,
+            message: "SQL tracing disabled".into(),
+            state,
+        })
+    }
+
+    pub async fn blob_read_tool(
+        &self,
+        req: BlobReadRequest,
+    ) -> Result<BlobReadResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+
+        let blob = conn.blob_open(
+            rusqlite::DatabaseName::Main,
+            &req.table_name,
+            &req.column,
+            req.rowid,
+            true,
+        )?;
+        let blob_size = blob.len();
+
+        if req.offset > blob_size {
+            return Err(UniSqliteError::QueryFailed(format!(
+                "Offset {} is past the end of the {blob_size}-byte BLOB",
+                req.offset
+            )));
+        }
+        let length = req
+            .length
+            .unwrap_or(blob_size - req.offset)
+            .min(blob_size - req.offset);
+
+        let mut buf = vec![0u8; length];
+        blob.read_at_exact(&mut buf, req.offset)?;
+
+        Ok(BlobReadResult {
+            data_base64: BASE64_STANDARD.encode(&buf),
+            offset: req.offset,
+            bytes_read: length,
+            blob_size,
+        })
+    }
+
+    pub async fn blob_write_tool(
+        &self,
+        req: BlobWriteRequest,
+    ) -> Result<BlobWriteResult, UniSqliteError> {
+        let data = BASE64_STANDARD
+            .decode(&req.data_base64)
+            .map_err(|e| UniSqliteError::QueryFailed(format!("Invalid base64 data: {e}")))?;
+
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+        self.invalidate_query_cache();
+
+        let mut blob = conn.blob_open(
+            rusqlite::DatabaseName::Main,
+            &req.table_name,
+            &req.column,
+            req.rowid,
+            false,
+        )?;
+        let blob_size = blob.len();
+
+        // Incremental I/O cannot resize a BLOB
+        if req
+            .offset
+            .checked_add(data.len())
+            .is_none_or(|end| end > blob_size)
+        {
+            return Err(UniSqliteError::QueryFailed(format!(
+                "Writing {} bytes at offset {} exceeds the {blob_size}-byte BLOB; preallocate it with zeroblob()",
+                data.len(),
+                req.offset
+            )));
+        }
+
+        blob.write_at(&data, req.offset)?;
+
+        Ok(BlobWriteResult {
+            success: true,
+            offset: req.offset,
+            bytes_written: data.len(),
+            blob_size,
+        })
+    }
+
+    #[cfg(feature = "extensions")]
+    pub async fn load_extension_tool(
+        &self,
+        req: LoadExtensionRequest,
+    ) -> Result<LoadExtensionResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+
+        let dir = std::env::var_os(EXTENSION_DIR_ENV).ok_or_else(|| {
+            UniSqliteError::InvalidPath(format!(
+                "Extension loading is disabled; set {EXTENSION_DIR_ENV} to an allowlisted directory"
+            ))
+        })?;
+        let path = Self::resolve_extension(Path::new(&dir), &req.name)?;
+
+        // SAFETY: only files inside the operator-configured directory are loaded, and
+        // the guard turns extension loading back off as soon as this call returns
+        unsafe {
+            let _enabled = rusqlite::LoadExtensionGuard::new(conn)?;
+            conn.load_extension(&path, req.entry_point.as_deref())?;
+        }
+
+        let extension = LoadedExtension {
+            name: req.name,
+            path: path.display().to_string(),
+            entry_point: req.entry_point,
+        };
+        let mut loaded = self.loaded_extensions.lock().unwrap();
+        loaded.push(extension.clone());
+
+        Ok(LoadExtensionResult {
+            success: true,
+            extension,
+            loaded_extensions: loaded.clone(),
+        })
+    }
+
+    #[cfg(feature = "extensions")]
+    pub async fn create_vector_index_tool(
+        &self,
+        req: CreateVectorIndexRequest,
+    ) -> Result<CreateVectorIndexResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+        self.invalidate_query_cache();
+
+        if req.dimensions == 0 {
+            return Err(UniSqliteError::QueryFailed(
+                "dimensions must be greater than zero".into(),
+            ));
+        }
+        let column = Self::vector_column(req.column.as_deref())?;
+        let definition = match req.backend {
+            VectorBackend::SqliteVec => format!("{column} float[{}]", req.dimensions),
+            VectorBackend::Vss => format!("{column}({})", req.dimensions),
+        };
+        let sql = format!(
+            "CREATE VIRTUAL TABLE {}{} USING {}({definition})",
+            if req.if_not_exists {
+                "IF NOT EXISTS "
+            } else {
+                ""
+            },
+            Self::quote_ident(&req.table)?,
+            req.backend.module()
+        );
+        conn.execute_batch(&sql)
+            .map_err(|e| Self::vector_module_error(e, req.backend))?;
+
+        Ok(CreateVectorIndexResult {
+            success: true,
+            table: req.table,
+            sql,
+        })
+    }
+
+    #[cfg(feature = "extensions")]
+    pub async fn vector_search_tool(
+        &self,
+        req: VectorSearchRequest,
+    ) -> Result<VectorSearchResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+
+        if req.k == 0 {
+            return Err(UniSqliteError::QueryFailed(
+                "k must be greater than zero".into(),
+            ));
+        }
+        let query_vector = Self::vector_json(&req.query_vector)?;
+        let sql = Self::vector_search_sql(
+            req.backend,
+            &req.table,
+            &Self::vector_column(req.column.as_deref())?,
+            req.join_table.as_deref(),
+        )?;
+
+        let k = req.k as i64;
+        let mut stmt = conn
+            .prepare(&sql)
+            .map_err(|e| Self::vector_module_error(e, req.backend))?;
+        let (columns, rows) = Self::collect_rows(&mut stmt, &[&query_vector, &k], false)?;
+
+        Ok(VectorSearchResult {
+            columns,
+            row_count: rows.len(),
+            rows,
+        })
+    }
+
+    /// Create `_changelog` if needed and AFTER INSERT/UPDATE/DELETE triggers on the table
+    /// that record each change's operation, rowid, and UTC timestamp.
+    pub async fn enable_change_tracking_tool(
+        &self,
+        req: ChangeTrackingRequest,
+    ) -> Result<ChangeTrackingResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+        self.invalidate_query_cache();
+
+        Self::ensure_table_exists(conn, &req.table)?;
+        if req.table == CHANGELOG_TABLE {
+            return Err(UniSqliteError::QueryFailed(
+                "The changelog table cannot track itself".into(),
+            ));
+        }
+        // The triggers log NEW.rowid/OLD.rowid, which would fail every write to the table
+        let without_rowid: bool = conn.query_row(
+            "SELECT wr FROM pragma_table_list WHERE schema = 'main' AND name = ?",
+            [&req.table],
+            |row| row.get(0),
+        )?;
+        if without_rowid {
+            return Err(UniSqliteError::QueryFailed(format!(
+                "'{}' is a WITHOUT ROWID table; change tracking records rowids",
+                req.table
+            )));
+        }
+
+        let table = Self::quote_ident(&req.table)?;
+        let table_literal = req.table.replace('\'', "''");
+        let tx = conn.unchecked_transaction()?;
+        tx.execute_batch(&format!(
+            "CREATE TABLE IF NOT EXISTS {CHANGELOG_TABLE} (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                table_name TEXT NOT NULL,
+                op TEXT NOT NULL,
+                row_id INTEGER NOT NULL,
+                changed_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+            );
+            CREATE INDEX IF NOT EXISTS {CHANGELOG_TABLE}_changed_at
+                ON {CHANGELOG_TABLE} (changed_at);"
+        ))?;
+
+        let mut triggers = Vec::new();
+        for (op, row) in [("INSERT", "NEW"), ("UPDATE", "NEW"), ("DELETE", "OLD")] {
+            let trigger = Self::changelog_trigger_name(&req.table, op);
+            tx.execute_batch(&format!(
+                "CREATE TRIGGER IF NOT EXISTS {} AFTER {op} ON {table} BEGIN
+                    INSERT INTO {CHANGELOG_TABLE} (table_name, op, row_id)
+                    VALUES ('{table_literal}', '{op}', {row}.rowid);
+                END",
+                Self::quote_ident(&trigger)?
+            ))?;
+            triggers.push(trigger);
+        }
+        tx.commit()?;
+
+        Ok(ChangeTrackingResult {
+            success: true,
+            message: format!("Tracking changes to '{}' in {CHANGELOG_TABLE}", req.table),
+            table: req.table,
+            triggers,
+        })
+    }
+
+    /// Drop the table's change tracking triggers; `_changelog` and its history are kept.
+    pub async fn disable_change_tracking_tool(
+        &self,
+        req: ChangeTrackingRequest,
+    ) -> Result<ChangeTrackingResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+        self.invalidate_query_cache();
+
+        let tx = conn.unchecked_transaction()?;
+        let mut triggers = Vec::new();
+        for op in ["INSERT", "UPDATE", "DELETE"] {
+            let trigger = Self::changelog_trigger_name(&req.table, op);
+            let exists: bool = tx.query_row(
+                "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'trigger' AND name = ?)",
+                [&trigger],
+                |row| row.get(0),
+            )?;
+            if exists {
+                tx.execute_batch(&format!("DROP TRIGGER {}", Self::quote_ident(&trigger)?))?;
+                triggers.push(trigger);
+            }
+        }
+        tx.commit()?;
+
+        Ok(ChangeTrackingResult {
+            success: true,
+            message: format!(
+                "Dropped {} change tracking trigger(s) on '{}'",
+                triggers.len(),
+                req.table
+            ),
+            table: req.table,
+            triggers,
+        })
+    }
+
+    pub async fn get_changes_since_tool(
+        &self,
+        req: GetChangesSinceRequest,
+    ) -> Result<GetChangesSinceResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+
+        Self::ensure_table_exists(conn, CHANGELOG_TABLE)?;
+        let since = DateTime::parse_from_rfc3339(req.timestamp.trim())
+            .map_err(|e| UniSqliteError::QueryFailed(format!("Invalid timestamp: {e}")))?
+            .with_timezone(&Utc)
+            .format("%Y-%m-%dT%H:%M:%S%.3fZ")
+            .to_string();
+        let limit = req.limit.unwrap_or(1000) as i64;
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT id, table_name, op, row_id, changed_at FROM {CHANGELOG_TABLE}
+             WHERE changed_at > ?1 AND (?2 IS NULL OR table_name = ?2)
+             ORDER BY id LIMIT ?3"
+        ))?;
+        let changes = stmt
+            .query_map(rusqlite::params![since, req.table, limit], |row| {
+                Ok(ChangeLogEntry {
+                    id: row.get(0)?,
+                    table: row.get(1)?,
+                    op: row.get(2)?,
+                    rowid: row.get(3)?,
+                    changed_at: row.get(4)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(GetChangesSinceResult {
+            count: changes.len(),
+            latest: changes.last().map(|c| c.changed_at.clone()),
+            changes,
+        })
+    }
+
+    /// Add a nullable `deleted_at` column (if missing) and a view selecting the rows where
+    /// it is NULL.
+    pub async fn enable_soft_delete_tool(
+        &self,
+        req: EnableSoftDeleteRequest,
+    ) -> Result<EnableSoftDeleteResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+        self.invalidate_query_cache();
+
+        Self::ensure_table_exists(conn, &req.table_name)?;
+        let column_added = !Self::table_columns(conn, &req.table_name)?
+            .iter()
+            .any(|c| c.name.eq_ignore_ascii_case(SOFT_DELETE_COLUMN));
+        let view_name = req
+            .view_name
+            .unwrap_or_else(|| format!("{}_active", req.table_name));
+        let table = Self::quote_ident(&req.table_name)?;
+
+        let tx = conn.unchecked_transaction()?;
+        if column_added {
+            tx.execute_batch(&format!(
+                "ALTER TABLE {table} ADD COLUMN {SOFT_DELETE_COLUMN} TEXT"
+            ))?;
+        }
+        tx.execute_batch(&format!(
+            "CREATE VIEW IF NOT EXISTS {} AS SELECT * FROM {table} WHERE {SOFT_DELETE_COLUMN} IS NULL",
+            Self::quote_ident(&view_name)?
+        ))?;
+        tx.commit()?;
+
+        Ok(EnableSoftDeleteResult {
+            success: true,
+            message: format!(
+                "Soft deletes enabled on '{}'; live rows are in view '{view_name}'",
+                req.table_name
+            ),
+            column_added,
+            view_name,
+        })
+    }
+
+    /// Stamp `deleted_at` on matching live rows.
+    pub async fn soft_delete_tool(
+        &self,
+        req: SoftDeleteRequest,
+    ) -> Result<SoftDeleteResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+        self.invalidate_query_cache();
+
+        let rows_affected = Self::set_soft_deleted(conn, &req, true)?;
+        Ok(SoftDeleteResult {
+            success: true,
+            message: format!("Soft-deleted {rows_affected} rows in '{}'", req.table_name),
+            rows_affected,
+        })
+    }
+
+    /// Clear `deleted_at` on matching soft-deleted rows.
+    pub async fn restore_tool(
+        &self,
+        req: SoftDeleteRequest,
+    ) -> Result<SoftDeleteResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+        self.invalidate_query_cache();
+
+        let rows_affected = Self::set_soft_deleted(conn, &req, false)?;
+        Ok(SoftDeleteResult {
+            success: true,
+            message: format!("Restored {rows_affected} rows in '{}'", req.table_name),
+            rows_affected,
+        })
+    }
+
+    /// Rows past a rowid cursor in ascending rowid order, for polling append-mostly tables.
+    pub async fn tail_tool(&self, req: TailRequest) -> Result<TailResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+
+        Self::ensure_table_exists(conn, &req.table_name)?;
+        let limit = req.limit.unwrap_or(100) as i64;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT rowid AS rowid, * FROM {} WHERE rowid > ?1 ORDER BY rowid LIMIT ?2",
+            Self::quote_ident(&req.table_name)?
+        ))?;
+        let (columns, rows) =
+            Self::collect_rows(&mut stmt, &[&req.after_rowid, &limit], req.typed)?;
+
+        let next_cursor = rows
+            .last()
+            .and_then(|row| row.first())
+            .and_then(Value::as_i64)
+            .unwrap_or(req.after_rowid);
+
+        Ok(TailResult {
+            columns,
+            row_count: rows.len(),
+            rows,
+            next_cursor,
+        })
+    }
+
+    pub async fn set_meta_tool(&self, req: SetMetaRequest) -> Result<MetaEntry, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+        self.invalidate_query_cache();
+
+        conn.execute_batch(&format!(
+            "CREATE TABLE IF NOT EXISTS {META_TABLE} (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            )"
+        ))?;
+        let updated_at: String = conn.query_row(
+            &format!(
+                "INSERT INTO {META_TABLE} (key, value, updated_at)
+                 VALUES (?1, ?2, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))
+                 ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at
+                 RETURNING updated_at"
+            ),
+            rusqlite::params![req.key, serde_json::to_string(&req.value)?],
+            |row| row.get(0),
+        )?;
+
+        Ok(MetaEntry {
+            key: req.key,
+            value: req.value,
+            updated_at,
+        })
+    }
+
+    pub async fn get_meta_tool(
+        &self,
+        req: GetMetaRequest,
+    ) -> Result<GetMetaResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+
+        let entry = Self::meta_entries(conn, Some(&req.key))?.pop();
+        Ok(GetMetaResult {
+            found: entry.is_some(),
+            entry,
+        })
+    }
+
+    pub async fn list_meta_tool(&self) -> Result<ListMetaResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+
+        let entries = Self::meta_entries(conn, None)?;
+        Ok(ListMetaResult {
+            total_count: entries.len(),
+            entries,
+        })
+    }
+
+    /// Materialize a SELECT into `CREATE TEMP TABLE .. AS`. Temp tables live until the
+    /// connection closes, so later queries can join against the staged rows.
+    pub async fn query_to_temp_table_tool(
+        &self,
+        req: QueryToTempTableRequest,
+    ) -> Result<QueryToTempTableResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+        self.invalidate_query_cache();
+
+        let select = req.sql.trim().trim_end_matches(';').trim_end();
+        if select.contains(';') {
+            return Err(UniSqliteError::QueryFailed(
+                "Multiple statements are not allowed".into(),
+            ));
+        }
+        if !Self::is_select_query(select) {
+            return Err(UniSqliteError::QueryFailed(
+                "Only SELECT, WITH ... SELECT, or VALUES queries can fill a temp table".into(),
+            ));
+        }
+        // Catches writes hidden behind a SELECT-looking prefix
+        if !conn.prepare(select)?.readonly() {
+            return Err(UniSqliteError::QueryFailed(
+                "The query must not modify the database".into(),
+            ));
+        }
+
+        let table = format!("temp.{}", Self::quote_ident(&req.table_name)?);
+        let params: Vec<Box<dyn rusqlite::ToSql>> = req
+            .parameters
+            .iter()
+            .map(Self::json_to_sql_param)
+            .collect::<Result<_, _>>()?;
+        let param_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| &**p).collect();
+
+        let tx = conn.unchecked_transaction()?;
+        if req.replace {
+            tx.execute_batch(&format!("DROP TABLE IF EXISTS {table}"))?;
+        }
+        tx.execute(
+            &format!("CREATE TEMP TABLE {table} AS {select}"),
+            &param_refs[..],
+        )?;
+        let row_count: i64 = tx.query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| {
+            row.get(0)
+        })?;
+        let columns = tx
+            .prepare(&format!("SELECT * FROM {table}"))?
+            .column_names()
+            .into_iter()
+            .map(String::from)
+            .collect();
+        tx.commit()?;
+
+        Ok(QueryToTempTableResult {
+            success: true,
+            table_name: req.table_name,
+            columns,
+            row_count,
+        })
+    }
+
+    /// Diff the current schema against a target (CREATE script or reference database) and
+    /// generate the statements that migrate one into the other. Tables change in place with
+    /// ADD/DROP COLUMN when possible and are otherwise rebuilt, copying shared columns.
+    pub async fn generate_migration_tool(
+        &self,
+        req: GenerateMigrationRequest,
+    ) -> Result<GenerateMigrationResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+        self.invalidate_query_cache();
+
+        let target = match (&req.target_sql, &req.target_database) {
+            (Some(sql), None) => {
+                let scratch = Connection::open_in_memory()?;
+                // The script only has to build a schema; keep it away from other files
+                scratch.set_limit(rusqlite::limits::Limit::SQLITE_LIMIT_ATTACHED, 0);
+                scratch.execute_batch(sql)?;
+                scratch
+            }
+            (None, Some(path)) => {
+                let path = self.validate_db_path(Path::new(path))?;
+                Connection::open_with_flags(
+                    Self::read_only_uri(&path),
+                    OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_URI,
+                )?
+            }
+            _ => {
+                return Err(UniSqliteError::QueryFailed(
+                    "Provide exactly one of target_sql or target_database".into(),
+                ));
+            }
+        };
+
+        let (statements, destructive_changes) = Self::migration_statements(conn, &target)?;
+        if req.apply && !destructive_changes.is_empty() && !req.allow_destructive {
+            return Err(UniSqliteError::QueryFailed(format!(
+                "Migration is destructive ({}); set allow_destructive to apply it",
+                destructive_changes.join("; ")
+            )));
+        }
+
+        let applied = req.apply && !statements.is_empty();
+        if applied {
+            if !conn.is_autocommit() {
+                return Err(UniSqliteError::QueryFailed(
+                    "Cannot apply a migration inside an open transaction; commit or roll back first"
+                        .into(),
+                ));
+            }
+            // SQLite's table rebuild procedure: with enforcement on, dropping a rebuilt
+            // parent would run ON DELETE actions against its children. The pragma is a
+            // no-op inside a transaction, so it is switched off around it.
+            let foreign_keys: bool = conn.query_row("PRAGMA foreign_keys", [], |row| row.get(0))?;
+            if foreign_keys {
+                conn.execute_batch("PRAGMA foreign_keys = OFF")?;
+            }
+            let result = Self::apply_migration(conn, &statements, foreign_keys);
+            if foreign_keys {
+                conn.execute_batch("PRAGMA foreign_keys = ON")?;
+            }
+            result?;
+        }
+
+        Ok(GenerateMigrationResult {
+            up_to_date: statements.is_empty(),
+            sql: statements
+                .iter()
+                .map(|s| format!("{s};"))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            statements,
+            destructive_changes,
+            applied,
+        })
+    }
+
+    /// Run migration statements in one transaction. When foreign keys were enforced, the
+    /// result must pass `PRAGMA foreign_key_check` before it commits.
+    fn apply_migration(
+        conn: &Connection,
+        statements: &[String],
+        check_foreign_keys: bool,
+    ) -> Result<(), UniSqliteError> {
+        let tx = conn.unchecked_transaction()?;
+        for statement in statements {
+            tx.execute_batch(statement)?;
+        }
+        if check_foreign_keys {
+            let violations: Vec<String> = tx
+                .prepare("PRAGMA foreign_key_check")?
+                .query_map([], |row| {
+                    Ok(format!(
+                        "{} row {} references missing {} row",
+                        row.get::<_, String>(0)?,
+                        row.get::<_, Option<i64>>(1)?
+                            .map_or_else(|| "?".to_string(), |rowid| rowid.to_string()),
+                        row.get::<_, String>(2)?
+                    ))
+                })?
+                .collect::<Result<_, _>>()?;
+            if !violations.is_empty() {
+                return Err(UniSqliteError::QueryFailed(format!(
+                    "Migration would break foreign keys ({}); rolled back",
+                    violations.join("; ")
+                )));
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Write the schema and rows as SQL text, either as one script wrapped in a
+    /// transaction or as one file per table for version control and selective restore.
+    pub async fn dump_to_sql_tool(
+        &self,
+        req: DumpToSqlRequest,
+    ) -> Result<DumpToSqlResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+
+        let tables = Self::dump_table_order(conn)?;
+        let entries = Self::schema_entries(conn)?;
+        let views: Vec<&SchemaEntry> = Self::entries_of(&entries, "view");
+        let table_objects = |table: &str| -> Vec<&SchemaEntry> {
+            entries
+                .iter()
+                .filter(|e| {
+                    matches!(e.object_type.as_str(), "index" | "trigger")
+                        && e.table.eq_ignore_ascii_case(table)
+                })
+                .collect()
+        };
+
+        let mut files = Vec::new();
+        let mut rows = 0;
+        if req.export_sql_per_table {
+            let dir = self.validate_path(Path::new(&req.output_path))?;
+            fs::create_dir_all(&dir)?;
+            for (i, table) in tables.iter().enumerate() {
+                let path = dir.join(format!(
+                    "{:04}_{}.sql",
+                    i + 1,
+                    Self::file_name_component(&table.name)
+                ));
+                let mut out = std::io::BufWriter::new(fs::File::create(&path)?);
+                rows += Self::dump_table(conn, &mut out, table, &table_objects(&table.name))?;
+                out.flush()?;
+                files.push(path.display().to_string());
+            }
+            if !views.is_empty() {
+                let path = dir.join(format!("{:04}_views.sql", tables.len() + 1));
+                let mut out = std::io::BufWriter::new(fs::File::create(&path)?);
+                for view in &views {
+                    writeln!(out, "{};", view.sql)?;
+                }
+                out.flush()?;
+                files.push(path.display().to_string());
+            }
+        } else {
+            let path = self.validate_file_path(Path::new(&req.output_path), &["sql"])?;
+            let mut out = std::io::BufWriter::new(fs::File::create(&path)?);
+            writeln!(out, "PRAGMA foreign_keys=OFF;")?;
+            writeln!(out, "BEGIN TRANSACTION;")?;
+            for table in &tables {
+                rows += Self::dump_table(conn, &mut out, table, &table_objects(&table.name))?;
+            }
+            for view in &views {
+                writeln!(out, "{};", view.sql)?;
+            }
+            writeln!(out, "COMMIT;")?;
+            out.flush()?;
+            files.push(path.display().to_string());
+        }
+
+        Ok(DumpToSqlResult {
+            success: true,
+            message: format!(
+                "Dumped {} tables and {rows} rows to {} file(s)",
+                tables.len(),
+                files.len()
+            ),
+            files,
+            tables: tables.len(),
+            rows,
+        })
+    }
+
+    /// Apply every `.sql` file in a directory inside one transaction: CREATE TABLE
+    /// statements from all files first, then everything else, each pass in file name
+    /// order (the `NNNN_` prefixes of a per-table dump). Foreign keys are checked at commit.
+    pub async fn restore_from_directory_tool(
+        &self,
+        req: RestoreFromDirectoryRequest,
+    ) -> Result<RestoreFromDirectoryResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+        self.invalidate_query_cache();
+
+        let dir = self.validate_path(Path::new(&req.directory))?;
+        let mut files: Vec<PathBuf> = fs::read_dir(&dir)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .filter(|p| p.is_file() && p.extension().is_some_and(|e| e == "sql"))
+            .collect();
+        files.sort();
+        if files.is_empty() {
+            return Err(UniSqliteError::ImportFailed(format!(
+                "No .sql files in {}",
+                dir.display()
+            )));
+        }
+
+        let tx = conn.unchecked_transaction()?;
+        tx.execute_batch("PRAGMA defer_foreign_keys = ON")?;
+        let mut statements_executed = 0;
+        for schema_pass in [true, false] {
+            for file in &files {
+                let reader = std::io::BufReader::new(fs::File::open(file)?);
+                for statement in SqlStatements::new(reader) {
+                    let statement = statement?;
+                    if Self::is_transaction_control(&statement) {
+                        continue;
+                    }
+                    let head = Self::strip_leading_comments(&statement).to_ascii_uppercase();
+                    let creates_table = Self::starts_with_keyword(&head, "CREATE")
+                        && head.split_whitespace().take(3).any(|word| word == "TABLE");
+                    if creates_table != schema_pass {
+                        continue;
+                    }
+                    tx.execute_batch(&statement).map_err(|e| {
+                        UniSqliteError::ImportFailed(format!("{}: {e}", file.display()))
+                    })?;
+                    statements_executed += 1;
+                }
+            }
+        }
+        tx.commit()?;
+
+        let files_applied: Vec<String> = files.iter().map(|f| f.display().to_string()).collect();
+        Ok(RestoreFromDirectoryResult {
+            success: true,
+            message: format!(
+                "Applied {} files ({statements_executed} statements)",
+                files_applied.len()
+            ),
+            files_applied,
+            statements_executed,
+        })
+    }
+
+    /// Run a SQL script statement by statement as it is read, so multi-gigabyte dumps
+    /// never sit in memory whole. Everything runs in one transaction.
+    pub async fn restore_from_sql_tool(
+        &self,
+        req: RestoreFromSqlRequest,
+    ) -> Result<RestoreFromSqlResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+        self.invalidate_query_cache();
+
+        let path = self.validate_file_path(Path::new(&req.input_path), &["sql"])?;
+        let total_bytes = fs::metadata(&path)?.len();
+        let progress_interval = req.progress_interval.unwrap_or(10_000).max(1);
+        let started = Instant::now();
+
+        let mut statements = SqlStatements::new(std::io::BufReader::new(fs::File::open(&path)?));
+        let tx = conn.unchecked_transaction()?;
+        tx.execute_batch("PRAGMA defer_foreign_keys = ON")?;
+        let mut statements_executed = 0;
+        while let Some(statement) = statements.next() {
+            let statement = statement?;
+            if Self::is_transaction_control(&statement) {
+                continue;
+            }
+            tx.execute_batch(&statement).map_err(|e| {
+                UniSqliteError::ImportFailed(format!(
+                    "Statement {} failed: {e}",
+                    statements_executed + 1
+                ))
+            })?;
+            statements_executed += 1;
+            if statements_executed % progress_interval == 0 {
+                tracing::info!(
+                    "Restore progress: {} statements, {}/{} bytes",
+                    statements_executed,
+                    statements.bytes_read(),
+                    total_bytes
+                );
+            }
+        }
+        tx.commit()?;
+
+        let elapsed_ms = started.elapsed().as_millis() as u64;
+        Ok(RestoreFromSqlResult {
+            success: true,
+            message: format!(
+                "Restored {statements_executed} statements from {} in {elapsed_ms} ms",
+                path.display()
+            ),
+            statements_executed,
+            bytes_read: statements.bytes_read(),
+            elapsed_ms,
+        })
+    }
+
+    /// Compute an order-independent checksum of a table's rows: each row is hashed on its
+    /// own and the hashes are summed, so two copies match regardless of storage order.
+    pub async fn table_checksum_tool(
+        &self,
+        req: TableChecksumRequest,
+    ) -> Result<TableChecksumResult, UniSqliteError> {
+        use sha2::{Digest, Sha256};
+
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+
+        Self::ensure_table_exists(conn, &req.table_name)?;
+        let columns = match req.columns {
+            Some(columns) if !columns.is_empty() => columns,
+            _ => conn
+                .prepare("SELECT name FROM pragma_table_info(?1) ORDER BY cid")?
+                .query_map([&req.table_name], |row| row.get(0))?
+                .collect::<Result<_, _>>()?,
+        };
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM {}",
+            Self::quote_ident_list(columns.iter().map(String::as_str))?,
+            Self::quote_ident(&req.table_name)?
+        ))?;
+
+        // Summing (rather than XORing) row hashes keeps duplicate rows from cancelling out
+        let mut sum = [0u64; 4];
+        let mut row_count = 0u64;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let mut hasher = Sha256::new();
+            for i in 0..columns.len() {
+                Self::hash_value(&mut hasher, row.get_ref(i)?);
+            }
+            let row_hash = hasher.finalize();
+            for (lane, chunk) in sum.iter_mut().zip(row_hash.chunks_exact(8)) {
+                *lane = lane.wrapping_add(u64::from_be_bytes(chunk.try_into().unwrap()));
+            }
+            row_count += 1;
+        }
+
+        let mut hasher = Sha256::new();
+        hasher.update(row_count.to_be_bytes());
+        for lane in sum {
+            hasher.update(lane.to_be_bytes());
+        }
+
+        Ok(TableChecksumResult {
+            table_name: req.table_name,
+            columns,
+            row_count,
+            digest: hex::encode(hasher.finalize()),
+        })
+    }
+
+    /// Insert `count` rows of plausible random data, choosing a generator per column from
+    /// `PRAGMA table_xinfo` and filling foreign keys from existing parent rows.
+    pub async fn generate_fake_data_tool(
+        &self,
+        req: GenerateFakeDataRequest,
+    ) -> Result<GenerateFakeDataResult, UniSqliteError> {
+        const MAX_FAKE_ROWS: usize = 100_000;
+
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+        self.invalidate_query_cache();
+
+        if req.count > MAX_FAKE_ROWS {
+            return Err(UniSqliteError::QueryFailed(format!(
+                "count may be at most {MAX_FAKE_ROWS}"
+            )));
+        }
+        Self::ensure_table_exists(conn, &req.table_name)?;
+
+        // (from column, parent table, parent column or None for the parent's primary key)
+        let foreign_keys: Vec<(String, String, Option<String>)> = conn
+            .prepare("SELECT \"from\", \"table\", \"to\" FROM pragma_foreign_key_list(?1)")?
+            .query_map([&req.table_name], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })?
+            .collect::<Result<_, _>>()?;
+
+        // (name, declared type, pk position, hidden)
+        let columns: Vec<(String, String, i64, i64)> = conn
+            .prepare("SELECT name, type, pk, hidden FROM pragma_table_xinfo(?1) ORDER BY cid")?
+            .query_map([&req.table_name], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })?
+            .collect::<Result<_, _>>()?;
+        let pk_count = columns.iter().filter(|(_, _, pk, _)| *pk > 0).count();
+
+        let mut targets = Vec::new();
+        for (name, declared, pk, hidden) in &columns {
+            // Generated columns and an INTEGER PRIMARY KEY (the rowid) fill themselves
+            let rowid_alias = *pk > 0 && pk_count == 1 && declared.eq_ignore_ascii_case("INTEGER");
+            if *hidden != 0 || rowid_alias {
+                continue;
+            }
+            let generator = match foreign_keys.iter().find(|(from, _, _)| from == name) {
+                Some((_, parent, to)) => {
+                    let parent_column = match to {
+                        Some(to) => to.clone(),
+                        None => conn.query_row(
+                            "SELECT name FROM pragma_table_info(?1) WHERE pk = 1",
+                            [parent],
+                            |row| row.get(0),
+                        )?,
+                    };
+                    let values: Vec<rusqlite::types::Value> = conn
+                        .prepare(&format!(
+                            "SELECT DISTINCT {} FROM {} LIMIT 10000",
+                            Self::quote_ident(&parent_column)?,
+                            Self::quote_ident(parent)?
+                        ))?
+                        .query_map([], |row| row.get(0))?
+                        .collect::<Result<_, _>>()?;
+                    if values.is_empty() {
+                        return Err(UniSqliteError::QueryFailed(format!(
+                            "Column '{name}' references '{parent}', which has no rows; seed it first"
+                        )));
+                    }
+                    FakeGenerator::ForeignKey(format!("{parent}.{parent_column}"), values)
+                }
+                None => FakeGenerator::for_column(name, declared),
+            };
+            targets.push((name.as_str(), generator));
+        }
+
+        let sql = if targets.is_empty() {
+            format!(
+                "INSERT INTO {} DEFAULT VALUES",
+                Self::quote_ident(&req.table_name)?
+            )
+        } else {
+            format!(
+                "INSERT INTO {} ({}) VALUES ({})",
+                Self::quote_ident(&req.table_name)?,
+                Self::quote_ident_list(targets.iter().map(|(name, _)| *name))?,
+                vec!["?"; targets.len()].join(", ")
+            )
+        };
+
+        let seed = req.seed.unwrap_or(1);
+        let mut rng = SplitMix64(seed);
+        let tx = conn.unchecked_transaction()?;
+        {
+            let mut stmt = tx.prepare(&sql)?;
+            for row in 0..req.count {
+                let values: Vec<rusqlite::types::Value> = targets
+                    .iter()
+                    .map(|(_, generator)| generator.generate(&mut rng, row))
+                    .collect();
+                stmt.execute(rusqlite::params_from_iter(values))?;
+            }
+        }
+        tx.commit()?;
+
+        Ok(GenerateFakeDataResult {
+            success: true,
+            message: format!(
+                "Inserted {} fake rows into '{}' (seed {seed})",
+                req.count, req.table_name
+            ),
+            rows_inserted: req.count,
+            seed,
+            generators: targets
+                .iter()
+                .map(|(name, generator)| FakeColumnGenerator {
+                    column: name.to_string(),
+                    generator: generator.label(),
+                })
+                .collect(),
+        })
+    }
+
+    /// Report who holds locks on the database by probing it from a second, non-waiting
+    /// connection. SQLite has no lock-inspection API, so this is best-effort.
+    pub async fn lock_status_tool(
+        &self,
+        req: LockStatusRequest,
+    ) -> Result<LockStatusResult, UniSqliteError> {
+        let guard = self.lock_db().await?;
+        let conn = guard.as_ref().ok_or(UniSqliteError::NotConnected)?;
+
+        let journal_mode: String = conn.query_row("PRAGMA journal_mode", [], |row| row.get(0))?;
+        let wal = journal_mode.eq_ignore_ascii_case("wal");
+        // SAFETY: the handle is valid while `conn` is borrowed and the schema name is a
+        // NUL-terminated literal
+        let own_state =
+            unsafe { rusqlite::ffi::sqlite3_txn_state(conn.handle(), c"main".as_ptr()) };
+        let own_transaction = match own_state {
+            rusqlite::ffi::SQLITE_TXN_READ => "read",
+            rusqlite::ffi::SQLITE_TXN_WRITE => "write",
+            _ => "none",
+        };
+
+        let mut status = LockStatusResult {
+            journal_mode,
+            lock_state: "unknown".into(),
+            own_transaction: own_transaction.into(),
+            readers_blocked: None,
+            writer_active: None,
+            wal_frames: None,
+            wal_frames_checkpointed: None,
+            wal_readers_blocking_checkpoint: None,
+            notes: Vec::new(),
+        };
+        if own_transaction != "none" {
+            status
+                .notes
+                .push("This server has a transaction open; the probe sees its locks too".into());
+        }
+
+        let path = match conn.path() {
+            Some(path) if !path.is_empty() => path.to_string(),
+            _ => {
+                status
+                    .notes
+                    .push("In-memory databases cannot be probed from another connection".into());
+                return Ok(status);
+            }
+        };
+        let probe = match Connection::open_with_flags(
+            &path,
+            OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        ) {
+            Ok(probe) => probe,
+            Err(e) => {
+                status
+                    .notes
+                    .push(format!("Could not open a probe connection: {e}"));
+                return Ok(status);
+            }
+        };
+        probe.busy_timeout(Duration::ZERO)?;
+
+        let readers_blocked =
+            match probe.query_row("SELECT COUNT(*) FROM sqlite_master", [], |row| {
+                row.get::<_, i64>(0)
+            }) {
+                Ok(_) => false,
+                Err(e) if Self::is_busy_error(&e) => true,
+                Err(e) => return Err(e.into()),
+            };
+        status.readers_blocked = Some(readers_blocked);
+        if !req.probe_locks {
+            if readers_blocked {
+                status.lock_state = "pending_or_exclusive".into();
+            } else {
+                status.notes.push(
+                    "Set probe_locks to tell reserved, shared and unlocked apart (and measure WAL reader pressure); it briefly takes locks and runs a passive checkpoint"
+                        .into(),
+                );
+            }
+            return Ok(status);
+        }
+
+        let writer_active = !readers_blocked && !Self::try_lock(&probe, "BEGIN IMMEDIATE")?;
+        status.writer_active = Some(writer_active);
+
+        status.lock_state = if readers_blocked {
+            "pending_or_exclusive"
+        } else if writer_active {
+            "reserved"
+        } else if !wal && !Self::try_lock(&probe, "BEGIN EXCLUSIVE")? {
+            // Outside WAL, EXCLUSIVE is refused only while someone holds a SHARED lock
+            "shared"
+        } else {
+            "unlocked"
+        }
+        .into();
+
+        if wal {
+            status.notes.push(
+                "WAL readers never block the writer; they show up only as frames a checkpoint cannot copy back"
+                    .into(),
+            );
+            if !readers_blocked {
+                let (busy, log, checkpointed): (i64, i64, i64) =
+                    probe.query_row("PRAGMA wal_checkpoint(PASSIVE)", [], |row| {
+                        Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+                    })?;
+                status.wal_frames = Some(log);
+                status.wal_frames_checkpointed = Some(checkpointed);
+                status.wal_readers_blocking_checkpoint = Some(busy != 0 || checkpointed < log);
+            }
+        }
+
+        Ok(status)
+    }
+
+    fn get_tools() -> Vec<Tool> {
+        vec![
+            Tool {
+                name: Cow::Borrowed("connect"),
+                description: Some(Cow::Borrowed("Connect to a SQLite database")),
+                input_schema: serde_json::to_value(schemars::schema_for!(ConnectRequest).schema)
+                    .unwrap()
+                    .as_object()
+                    .unwrap()
+                    .clone()
+                    .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("query"),
+                description: Some(Cow::Borrowed(
+                    "Execute a SQL query (SELECT, INSERT, UPDATE, DELETE, CREATE, ALTER, DROP, PRAGMA)",
+                )),
+                input_schema: serde_json::to_value(schemars::schema_for!(QueryRequest).schema)
+                    .unwrap()
+                    .as_object()
+                    .unwrap()
+                    .clone()
+                    .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("transaction"),
+                description: Some(Cow::Borrowed("Execute multiple queries in a transaction")),
+                input_schema: serde_json::to_value(
+                    schemars::schema_for!(TransactionRequest).schema,
+                )
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("create_table"),
+                description: Some(Cow::Borrowed("Create a new table with specified columns")),
+                input_schema: serde_json::to_value(
+                    schemars::schema_for!(CreateTableRequest).schema,
+                )
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("list_tables"),
+                description: Some(Cow::Borrowed(
+                    "List all tables in the database with metadata",
+                )),
+                input_schema: serde_json::json!({
+                    "type": "object",
+                    "properties": {},
+                    "additionalProperties": false
+                })
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("describe_table"),
+                description: Some(Cow::Borrowed(
+                    "Get detailed information about a table's structure",
+                )),
+                input_schema: serde_json::to_value(
+                    schemars::schema_for!(DescribeTableRequest).schema,
+                )
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("backup"),
+                description: Some(Cow::Borrowed("Create a backup of the current database")),
+                input_schema: serde_json::to_value(schemars::schema_for!(BackupRequest).schema)
+                    .unwrap()
+                    .as_object()
+                    .unwrap()
+                    .clone()
+                    .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("batch_insert"),
+                description: Some(Cow::Borrowed(
+                    "Insert multiple rows efficiently in a single transaction",
+                )),
+                input_schema: serde_json::to_value(
+                    schemars::schema_for!(BatchInsertRequest).schema,
+                )
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("export_csv"),
+                description: Some(Cow::Borrowed("Export query results to a CSV file")),
+                input_schema: serde_json::to_value(schemars::schema_for!(ExportCsvRequest).schema)
+                    .unwrap()
+                    .as_object()
+                    .unwrap()
+                    .clone()
+                    .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            #[cfg(feature = "xlsx")]
+            Tool {
+                name: Cow::Borrowed("export_xlsx"),
+                description: Some(Cow::Borrowed(
+                    "Export query results to an Excel (XLSX) worksheet with a header row and typed cells",
+                )),
+                input_schema: serde_json::to_value(schemars::schema_for!(ExportXlsxRequest).schema)
+                    .unwrap()
+                    .as_object()
+                    .unwrap()
+                    .clone()
+                    .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("health_check"),
+                description: Some(Cow::Borrowed(
+                    "Get health and status information about the current database connection",
+                )),
+                input_schema: serde_json::json!({
+                    "type": "object",
+                    "properties": {},
+                    "additionalProperties": false
+                })
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("new_uuid"),
+                description: Some(Cow::Borrowed("Generate a new random (v4) UUID")),
+                input_schema: serde_json::json!({
+                    "type": "object",
+                    "properties": {},
+                    "additionalProperties": false
+                })
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("vacuum_into"),
+                description: Some(Cow::Borrowed(
+                    "Write a compacted copy of the current database to a new file",
+                )),
+                input_schema: serde_json::to_value(schemars::schema_for!(VacuumIntoRequest).schema)
+                    .unwrap()
+                    .as_object()
+                    .unwrap()
+                    .clone()
+                    .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("set_auto_vacuum"),
+                description: Some(Cow::Borrowed(
+                    "Configure the auto_vacuum mode (NONE, FULL, INCREMENTAL), optionally running VACUUM",
+                )),
+                input_schema: serde_json::to_value(
+                    schemars::schema_for!(SetAutoVacuumRequest).schema,
+                )
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("incremental_vacuum"),
+                description: Some(Cow::Borrowed(
+                    "Reclaim freelist pages in INCREMENTAL auto_vacuum mode without a full VACUUM",
+                )),
+                input_schema: serde_json::to_value(
+                    schemars::schema_for!(IncrementalVacuumRequest).schema,
+                )
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("tune_performance"),
+                description: Some(Cow::Borrowed(
+                    "Set cache_size, mmap_size, temp_store, and synchronous on the current connection",
+                )),
+                input_schema: serde_json::to_value(
+                    schemars::schema_for!(PerformanceSettings).schema,
+                )
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("enable_sql_trace"),
+                description: Some(Cow::Borrowed(
+                    "Log every executed SQL statement and its timing via tracing",
+                )),
+                input_schema: serde_json::to_value(
+                    schemars::schema_for!(EnableSqlTraceRequest).schema,
+                )
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("disable_sql_trace"),
+                description: Some(Cow::Borrowed("Stop logging executed SQL statements")),
+                input_schema: serde_json::json!({
+                    "type": "object",
+                    "properties": {},
+                    "additionalProperties": false
+                })
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("batch_update"),
+                description: Some(Cow::Borrowed(
+                    "Update multiple rows by key columns in a single transaction",
+                )),
+                input_schema: serde_json::to_value(
+                    schemars::schema_for!(BatchUpdateRequest).schema,
+                )
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("delete_rows"),
+                description: Some(Cow::Borrowed(
+                    "Delete rows matching a required WHERE clause (full-table deletes need explicit opt-in)",
+                )),
+                input_schema: serde_json::to_value(schemars::schema_for!(DeleteRowsRequest).schema)
+                    .unwrap()
+                    .as_object()
+                    .unwrap()
+                    .clone()
+                    .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("truncate_table"),
+                description: Some(Cow::Borrowed(
+                    "Delete all rows from a table, optionally resetting its AUTOINCREMENT counter",
+                )),
+                input_schema: serde_json::to_value(
+                    schemars::schema_for!(TruncateTableRequest).schema,
+                )
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("object_exists"),
+                description: Some(Cow::Borrowed(
+                    "Check whether a table, view, index, or trigger exists",
+                )),
+                input_schema: serde_json::to_value(
+                    schemars::schema_for!(ObjectExistsRequest).schema,
+                )
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("get_create_sql"),
+                description: Some(Cow::Borrowed(
+                    "Get the stored CREATE statement for a table, view, index, or trigger",
+                )),
+                input_schema: serde_json::to_value(
+                    schemars::schema_for!(GetCreateSqlRequest).schema,
+                )
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("generate_insert_template"),
+                description: Some(Cow::Borrowed(
+                    "Generate a parameterized INSERT statement and column types for a table",
+                )),
+                input_schema: serde_json::to_value(
+                    schemars::schema_for!(GenerateInsertTemplateRequest).schema,
+                )
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("import_csv"),
+                description: Some(Cow::Borrowed(
+                    "Import a CSV file into an existing table, coercing fields to the declared column types",
+                )),
+                input_schema: serde_json::to_value(schemars::schema_for!(ImportCsvRequest).schema)
+                    .unwrap()
+                    .as_object()
+                    .unwrap()
+                    .clone()
+                    .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("import_json"),
+                description: Some(Cow::Borrowed(
+                    "Import an array of JSON objects from a file into an existing table",
+                )),
+                input_schema: serde_json::to_value(schemars::schema_for!(ImportJsonRequest).schema)
+                    .unwrap()
+                    .as_object()
+                    .unwrap()
+                    .clone()
+                    .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("database_size_breakdown"),
+                description: Some(Cow::Borrowed(
+                    "Report per-table and per-index space usage using the dbstat virtual table",
+                )),
+                input_schema: serde_json::json!({
+                    "type": "object",
+                    "properties": {},
+                    "additionalProperties": false
+                })
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("optimize"),
+                description: Some(Cow::Borrowed(
+                    "Run PRAGMA optimize to refresh query planner statistics, reporting what was analyzed",
+                )),
+                input_schema: serde_json::to_value(schemars::schema_for!(OptimizeRequest).schema)
+                    .unwrap()
+                    .as_object()
+                    .unwrap()
+                    .clone()
+                    .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("checkpoint_and_truncate_wal"),
+                description: Some(Cow::Borrowed(
+                    "Checkpoint the WAL into the database and truncate the -wal file to zero bytes",
+                )),
+                input_schema: serde_json::json!({
+                    "type": "object",
+                    "properties": {},
+                    "additionalProperties": false
+                })
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("switch_database"),
+                description: Some(Cow::Borrowed(
+                    "Close the current database and connect to another one in a single step",
+                )),
+                input_schema: serde_json::to_value(schemars::schema_for!(ConnectRequest).schema)
+                    .unwrap()
+                    .as_object()
+                    .unwrap()
+                    .clone()
+                    .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("list_attached_databases"),
+                description: Some(Cow::Borrowed(
+                    "List the main, temp, and attached databases with their file paths",
+                )),
+                input_schema: serde_json::json!({
+                    "type": "object",
+                    "properties": {},
+                    "additionalProperties": false
+                })
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("query_scalar"),
+                description: Some(Cow::Borrowed(
+                    "Run a query that must return exactly one row and one column, returning that value",
+                )),
+                input_schema: serde_json::to_value(schemars::schema_for!(QueryRequest).schema)
+                    .unwrap()
+                    .as_object()
+                    .unwrap()
+                    .clone()
+                    .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("query_row"),
+                description: Some(Cow::Borrowed(
+                    "Run a query that must return exactly one row, returning it as an object keyed by column name",
+                )),
+                input_schema: serde_json::to_value(schemars::schema_for!(QueryRowRequest).schema)
+                    .unwrap()
+                    .as_object()
+                    .unwrap()
+                    .clone()
+                    .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("prepare_statement"),
+                description: Some(Cow::Borrowed(
+                    "Prepare a SQL statement for repeated execution and return its statement id",
+                )),
+                input_schema: serde_json::to_value(
+                    schemars::schema_for!(PrepareStatementRequest).schema,
+                )
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("execute_prepared"),
+                description: Some(Cow::Borrowed(
+                    "Execute a previously prepared statement with the given parameters",
+                )),
+                input_schema: serde_json::to_value(
+                    schemars::schema_for!(ExecutePreparedRequest).schema,
+                )
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("finalize_statement"),
+                description: Some(Cow::Borrowed("Release a prepared statement id")),
+                input_schema: serde_json::to_value(
+                    schemars::schema_for!(FinalizeStatementRequest).schema,
+                )
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("cross_query"),
+                description: Some(Cow::Borrowed(
+                    "Run a SELECT across several database files attached read-only for the duration of the query",
+                )),
+                input_schema: serde_json::to_value(schemars::schema_for!(CrossQueryRequest).schema)
+                    .unwrap()
+                    .as_object()
+                    .unwrap()
+                    .clone()
+                    .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("generate_schema_report"),
+                description: Some(Cow::Borrowed(
+                    "Generate a Markdown document describing every table, its columns, indexes, and foreign keys",
+                )),
+                input_schema: serde_json::to_value(
+                    schemars::schema_for!(GenerateSchemaReportRequest).schema,
+                )
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("export_er_diagram"),
+                description: Some(Cow::Borrowed(
+                    "Export an entity-relationship diagram of tables and foreign keys as Mermaid or Graphviz DOT",
+                )),
+                input_schema: serde_json::to_value(
+                    schemars::schema_for!(ExportErDiagramRequest).schema,
+                )
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("find_orphans"),
+                description: Some(Cow::Borrowed(
+                    "Find rows in a child table whose foreign keys reference missing parent rows",
+                )),
+                input_schema: serde_json::to_value(
+                    schemars::schema_for!(FindOrphansRequest).schema,
+                )
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("foreign_key_check"),
+                description: Some(Cow::Borrowed(
+                    "Run PRAGMA foreign_key_check and return foreign key violations, optionally for one table",
+                )),
+                input_schema: serde_json::to_value(
+                    schemars::schema_for!(ForeignKeyCheckRequest).schema,
+                )
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("blob_read"),
+                description: Some(Cow::Borrowed(
+                    "Read a byte range of a BLOB cell by table, column, and rowid, returned as base64",
+                )),
+                input_schema: serde_json::to_value(schemars::schema_for!(BlobReadRequest).schema)
+                    .unwrap()
+                    .as_object()
+                    .unwrap()
+                    .clone()
+                    .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("blob_write"),
+                description: Some(Cow::Borrowed(
+                    "Write base64-encoded bytes into an existing BLOB cell at an offset without resizing it",
+                )),
+                input_schema: serde_json::to_value(schemars::schema_for!(BlobWriteRequest).schema)
+                    .unwrap()
+                    .as_object()
+                    .unwrap()
+                    .clone()
+                    .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("get_table_ddl_with_indexes"),
+                description: Some(Cow::Borrowed(
+                    "Return a table's CREATE statement plus its index and trigger DDL in creation order",
+                )),
+                input_schema: serde_json::to_value(
+                    schemars::schema_for!(GetTableDdlRequest).schema,
+                )
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("clone_table_structure"),
+                description: Some(Cow::Borrowed(
+                    "Create an empty copy of a table under a new name, optionally recreating its indexes",
+                )),
+                input_schema: serde_json::to_value(
+                    schemars::schema_for!(CloneTableStructureRequest).schema,
+                )
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("swap_tables"),
+                description: Some(Cow::Borrowed("Atomically swap two tables' names")),
+                input_schema: serde_json::to_value(schemars::schema_for!(SwapTablesRequest).schema)
+                    .unwrap()
+                    .as_object()
+                    .unwrap()
+                    .clone()
+                    .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("export_table_to_db"),
+                description: Some(Cow::Borrowed(
+                    "Export a single table with its indexes and rows to a new SQLite database file",
+                )),
+                input_schema: serde_json::to_value(
+                    schemars::schema_for!(ExportTableToDbRequest).schema,
+                )
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("compare_tables"),
+                description: Some(Cow::Borrowed(
+                    "Compare two tables by key columns, reporting rows only in A, only in B, and rows whose values differ",
+                )),
+                input_schema: serde_json::to_value(
+                    schemars::schema_for!(CompareTablesRequest).schema,
+                )
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("random_sample"),
+                description: Some(Cow::Borrowed(
+                    "Return a reproducible random sample of rows from a table using a seed",
+                )),
+                input_schema: serde_json::to_value(
+                    schemars::schema_for!(RandomSampleRequest).schema,
+                )
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("keyset_page"),
+                description: Some(Cow::Borrowed(
+                    "Page through a table by key columns using a last-seen cursor instead of OFFSET",
+                )),
+                input_schema: serde_json::to_value(schemars::schema_for!(KeysetPageRequest).schema)
+                    .unwrap()
+                    .as_object()
+                    .unwrap()
+                    .clone()
+                    .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("count_estimate"),
+                description: Some(Cow::Borrowed(
+                    "Estimate a table row count from ANALYZE statistics or the rowid range, counting exactly for small tables",
+                )),
+                input_schema: serde_json::to_value(
+                    schemars::schema_for!(CountEstimateRequest).schema,
+                )
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("table_size_bytes"),
+                description: Some(Cow::Borrowed(
+                    "Report pages and bytes used by one table and its indexes",
+                )),
+                input_schema: serde_json::to_value(
+                    schemars::schema_for!(TableSizeBytesRequest).schema,
+                )
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("connection_info"),
+                description: Some(Cow::Borrowed(
+                    "Report how the current connection is opened: read-only state, open flags, journal mode, foreign key enforcement, busy timeout, and canonical path",
+                )),
+                input_schema: serde_json::json!({
+                    "type": "object",
+                    "properties": {},
+                    "additionalProperties": false
+                })
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("list_functions"),
+                description: Some(Cow::Borrowed(
+                    "List SQL functions this server registered on the connection (e.g. uuid) and which SQLite extensions are available (JSON1, FTS5, R*Tree, math, window functions)",
+                )),
+                input_schema: serde_json::json!({
+                    "type": "object",
+                    "properties": {},
+                    "additionalProperties": false
+                })
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("set_limits"),
+                description: Some(Cow::Borrowed(
+                    "Set SQLite runtime limits (string/BLOB length, SQL length, expression depth, host parameters, etc.) to harden the connection against pathological queries; omitted limits are unchanged. Returns previous and new values",
+                )),
+                input_schema: serde_json::to_value(schemars::schema_for!(SetLimitsRequest).schema)
+                    .unwrap()
+                    .as_object()
+                    .unwrap()
+                    .clone()
+                    .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("vacuum"),
+                description: Some(Cow::Borrowed(
+                    "Rebuild the database with VACUUM, reporting its size before and after, bytes reclaimed, and duration",
+                )),
+                input_schema: serde_json::json!({
+                    "type": "object",
+                    "properties": {},
+                    "additionalProperties": false
+                })
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("clear_query_cache"),
+                description: Some(Cow::Borrowed("Drop all cached query results")),
+                input_schema: serde_json::json!({
+                    "type": "object",
+                    "properties": {},
+                    "additionalProperties": false
+                })
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("batch"),
+                description: Some(Cow::Borrowed(
+                    "Run a list of typed operations (insert, update, delete, create_table, query) atomically in one transaction, returning per-operation results; any failure rolls back the whole batch",
+                )),
+                input_schema: serde_json::to_value(schemars::schema_for!(BatchRequest).schema)
+                    .unwrap()
+                    .as_object()
+                    .unwrap()
+                    .clone()
+                    .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("describe_query"),
+                description: Some(Cow::Borrowed(
+                    "Describe the result columns of a query without executing it: name, declared type, and origin schema/table/column (null for expressions)",
+                )),
+                input_schema: serde_json::to_value(
+                    schemars::schema_for!(DescribeQueryRequest).schema,
+                )
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("vacuum_check"),
+                description: Some(Cow::Borrowed(
+                    "Report the fragmentation ratio (freelist_count / page_count) and bytes reclaimable, with a recommendation on whether a VACUUM is worthwhile",
+                )),
+                input_schema: serde_json::to_value(
+                    schemars::schema_for!(VacuumCheckRequest).schema,
+                )
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            #[cfg(feature = "extensions")]
+            Tool {
+                name: Cow::Borrowed("load_extension"),
+                description: Some(Cow::Borrowed(
+                    "Load a SQLite extension by name from the directory allowlisted in UNI_SQLITE_EXTENSION_DIR; arbitrary paths are rejected",
+                )),
+                input_schema: serde_json::to_value(
+                    schemars::schema_for!(LoadExtensionRequest).schema,
+                )
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            #[cfg(feature = "extensions")]
+            Tool {
+                name: Cow::Borrowed("create_vector_index"),
+                description: Some(Cow::Borrowed(
+                    "Create a sqlite-vec (vec0) or sqlite-vss (vss0) virtual table for vectors of a fixed dimension; requires the extension to be loaded",
+                )),
+                input_schema: serde_json::to_value(
+                    schemars::schema_for!(CreateVectorIndexRequest).schema,
+                )
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            #[cfg(feature = "extensions")]
+            Tool {
+                name: Cow::Borrowed("vector_search"),
+                description: Some(Cow::Borrowed(
+                    "Return the k nearest rows to a query vector (JSON array) with distances from a vector index, optionally joined to a table sharing its rowids",
+                )),
+                input_schema: serde_json::to_value(
+                    schemars::schema_for!(VectorSearchRequest).schema,
+                )
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("pack_embedding"),
+                description: Some(Cow::Borrowed(
+                    "Pack a JSON float array into a little-endian float32 BLOB, returned base64-encoded",
+                )),
+                input_schema: serde_json::to_value(
+                    schemars::schema_for!(PackEmbeddingRequest).schema,
+                )
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("unpack_embedding"),
+                description: Some(Cow::Borrowed(
+                    "Unpack a base64-encoded little-endian float32 BLOB into a float array",
+                )),
+                input_schema: serde_json::to_value(
+                    schemars::schema_for!(UnpackEmbeddingRequest).schema,
+                )
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("similarity_search"),
+                description: Some(Cow::Borrowed(
+                    "Rank rows by cosine similarity between an embedding column (float32 BLOBs or JSON arrays) and a query vector, without any vector extension",
+                )),
+                input_schema: serde_json::to_value(
+                    schemars::schema_for!(SimilaritySearchRequest).schema,
+                )
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("enable_change_tracking"),
+                description: Some(Cow::Borrowed(
+                    "Create triggers that log every insert, update, and delete on a table (operation, rowid, UTC timestamp) to the _changelog table",
+                )),
+                input_schema: serde_json::to_value(
+                    schemars::schema_for!(ChangeTrackingRequest).schema,
+                )
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("disable_change_tracking"),
+                description: Some(Cow::Borrowed(
+                    "Drop a table's change tracking triggers, keeping the _changelog history",
+                )),
+                input_schema: serde_json::to_value(
+                    schemars::schema_for!(ChangeTrackingRequest).schema,
+                )
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("get_changes_since"),
+                description: Some(Cow::Borrowed(
+                    "List changes recorded in _changelog after an RFC 3339 timestamp, optionally for one table, oldest first",
+                )),
+                input_schema: serde_json::to_value(
+                    schemars::schema_for!(GetChangesSinceRequest).schema,
+                )
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("enable_soft_delete"),
+                description: Some(Cow::Borrowed(
+                    "Add a nullable deleted_at column to a table and a view (default <table>_active) over the rows where it is NULL",
+                )),
+                input_schema: serde_json::to_value(
+                    schemars::schema_for!(EnableSoftDeleteRequest).schema,
+                )
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("soft_delete"),
+                description: Some(Cow::Borrowed(
+                    "Set deleted_at to the current UTC time on live rows matching a WHERE clause, returning the affected row count",
+                )),
+                input_schema: serde_json::to_value(schemars::schema_for!(SoftDeleteRequest).schema)
+                    .unwrap()
+                    .as_object()
+                    .unwrap()
+                    .clone()
+                    .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("restore"),
+                description: Some(Cow::Borrowed(
+                    "Clear deleted_at on soft-deleted rows matching a WHERE clause, returning the affected row count",
+                )),
+                input_schema: serde_json::to_value(schemars::schema_for!(SoftDeleteRequest).schema)
+                    .unwrap()
+                    .as_object()
+                    .unwrap()
+                    .clone()
+                    .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("tail"),
+                description: Some(Cow::Borrowed(
+                    "Return rows with rowid greater than a cursor in ascending order, with the next cursor, for polling a growing table",
+                )),
+                input_schema: serde_json::to_value(schemars::schema_for!(TailRequest).schema)
+                    .unwrap()
+                    .as_object()
+                    .unwrap()
+                    .clone()
+                    .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("set_meta"),
+                description: Some(Cow::Borrowed(
+                    "Store a JSON value under a key in the _uni_meta table (created on first use), replacing any previous value",
+                )),
+                input_schema: serde_json::to_value(schemars::schema_for!(SetMetaRequest).schema)
+                    .unwrap()
+                    .as_object()
+                    .unwrap()
+                    .clone()
+                    .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("get_meta"),
+                description: Some(Cow::Borrowed(
+                    "Read a value from the _uni_meta key/value table",
+                )),
+                input_schema: serde_json::to_value(schemars::schema_for!(GetMetaRequest).schema)
+                    .unwrap()
+                    .as_object()
+                    .unwrap()
+                    .clone()
+                    .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("list_meta"),
+                description: Some(Cow::Borrowed(
+                    "List every key/value entry in the _uni_meta table, ordered by key",
+                )),
+                input_schema: serde_json::json!({
+                    "type": "object",
+                    "properties": {},
+                    "additionalProperties": false
+                })
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("query_to_temp_table"),
+                description: Some(Cow::Borrowed(
+                    "Materialize a SELECT into a temporary table (dropped when the connection closes) for later queries to join against, returning its columns and row count",
+                )),
+                input_schema: serde_json::to_value(
+                    schemars::schema_for!(QueryToTempTableRequest).schema,
+                )
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("list_tables_detailed"),
+                description: Some(Cow::Borrowed(
+                    "List tables and views with schema, type, column count, and WITHOUT ROWID / STRICT flags (PRAGMA table_list on SQLite 3.37+, sqlite_master otherwise)",
+                )),
+                input_schema: serde_json::json!({
+                    "type": "object",
+                    "properties": {},
+                    "additionalProperties": false
+                })
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("generate_migration"),
+                description: Some(Cow::Borrowed(
+                    "Diff the current schema against a target (CREATE script or reference database) and return the CREATE/ALTER/DROP statements that migrate it; apply runs them in one transaction",
+                )),
+                input_schema: serde_json::to_value(
+                    schemars::schema_for!(GenerateMigrationRequest).schema,
+                )
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("dump_to_sql"),
+                description: Some(Cow::Borrowed(
+                    "Dump the schema and rows as SQL text: one transaction-wrapped .sql script, or with export_sql_per_table one NNNN_<table>.sql file per table in foreign-key order plus a views file",
+                )),
+                input_schema: serde_json::to_value(schemars::schema_for!(DumpToSqlRequest).schema)
+                    .unwrap()
+                    .as_object()
+                    .unwrap()
+                    .clone()
+                    .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("restore_from_directory"),
+                description: Some(Cow::Borrowed(
+                    "Apply every .sql file in a directory (e.g. a per-table dump) in one transaction: CREATE TABLE statements from all files first, then data and other objects, in file name order",
+                )),
+                input_schema: serde_json::to_value(
+                    schemars::schema_for!(RestoreFromDirectoryRequest).schema,
+                )
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("restore_from_sql"),
+                description: Some(Cow::Borrowed(
+                    "Run a .sql script (e.g. from dump_to_sql) by streaming it statement by statement inside one transaction, so large dumps are not loaded into memory",
+                )),
+                input_schema: serde_json::to_value(
+                    schemars::schema_for!(RestoreFromSqlRequest).schema,
+                )
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("detect_encoding"),
+                description: Some(Cow::Borrowed(
+                    "Guess a text file's encoding (BOM, UTF-8 validity, or UTF-16/Windows-1252 heuristics) before importing it",
+                )),
+                input_schema: serde_json::to_value(
+                    schemars::schema_for!(DetectEncodingRequest).schema,
+                )
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("preview_csv"),
+                description: Some(Cow::Borrowed(
+                    "Read the first rows of a CSV and report its headers, each column's inferred type (int/float/text/empty), and a sample, without touching the database",
+                )),
+                input_schema: serde_json::to_value(schemars::schema_for!(PreviewCsvRequest).schema)
+                    .unwrap()
+                    .as_object()
+                    .unwrap()
+                    .clone()
+                    .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("create_table_from_csv"),
+                description: Some(Cow::Borrowed(
+                    "Create a table from a CSV file, naming columns from its header and typing them from a sample of rows, then optionally import the data",
+                )),
+                input_schema: serde_json::to_value(
+                    schemars::schema_for!(CreateTableFromCsvRequest).schema,
+                )
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("query_explain_analyze"),
+                description: Some(Cow::Borrowed(
+                    "Run a read-only query, timing it, and return its EXPLAIN QUERY PLAN in the same response as { plan, elapsed_ms, rows }",
+                )),
+                input_schema: serde_json::to_value(
+                    schemars::schema_for!(QueryExplainAnalyzeRequest).schema,
+                )
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("table_checksum"),
+                description: Some(Cow::Borrowed(
+                    "Compute an order-independent SHA-256 checksum of a table's rows (optionally limited to some columns) for comparing copies across databases",
+                )),
+                input_schema: serde_json::to_value(
+                    schemars::schema_for!(TableChecksumRequest).schema,
+                )
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("generate_fake_data"),
+                description: Some(Cow::Borrowed(
+                    "Insert N rows of plausible random data into a table, with generators inferred from each column's name and type (emails, names, dates, numbers, text, existing foreign keys) and a seed for reproducibility",
+                )),
+                input_schema: serde_json::to_value(
+                    schemars::schema_for!(GenerateFakeDataRequest).schema,
+                )
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("lock_status"),
+                description: Some(Cow::Borrowed(
+                    "Report the database's current locking state (unlocked/shared/reserved/pending_or_exclusive), this connection's transaction, and WAL reader pressure, probed from a non-waiting second connection. Best-effort. Without probe_locks only a read is attempted; with it the probe briefly takes write/exclusive locks and runs a passive WAL checkpoint",
+                )),
+                input_schema: serde_json::to_value(schemars::schema_for!(LockStatusRequest).schema)
+                    .unwrap()
+                    .as_object()
+                    .unwrap()
+                    .clone()
+                    .into(),
+                annotations: None,
+                output_schema: None,
+            },
+            Tool {
+                name: Cow::Borrowed("reset_sequence"),
+                description: Some(Cow::Borrowed(
+                    "Read or set the sqlite_sequence counter of an AUTOINCREMENT table to reset or advance its next rowid",
+                )),
+                input_schema: serde_json::to_value(
+                    schemars::schema_for!(ResetSequenceRequest).schema,
+                )
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .clone()
+                .into(),
+                annotations: None,
+                output_schema: None,
+            },
+        ]
+    }
+
+    async fn list_tools_handler(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<rmcp::service::RoleServer>,
+    ) -> Result<ListToolsResult, rmcp::ErrorData> {
+        Ok(ListToolsResult {
+            tools: Self::get_tools(),
+            next_cursor: None,
+        })
+    }
+
+    async fn call_tool_handler(
+        &self,
+        request: CallToolRequestParam,
+        _context: RequestContext<rmcp::service::RoleServer>,
+    ) -> Result<CallToolResult, rmcp::ErrorData> {
+        match request.name.as_ref() {
+            "connect" => {
+                let params: ConnectRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .connect_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "query" => {
+                let params: QueryRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .query_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "transaction" => {
+                let params: TransactionRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .transaction_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "create_table" => {
+                let params: CreateTableRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .create_table_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "list_tables" => {
+                let result = self
+                    .list_tables_tool()
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "describe_table" => {
+                let params: DescribeTableRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .describe_table_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "backup" => {
+                let params: BackupRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .backup_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "batch_insert" => {
+                let params: BatchInsertRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .batch_insert_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "export_csv" => {
+                let params: ExportCsvRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .export_csv_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            #[cfg(feature = "xlsx")]
+            "export_xlsx" => {
+                let params: ExportXlsxRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .export_xlsx_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "health_check" => {
+                let result = self
+                    .health_check_tool()
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "new_uuid" => {
+                let result = self.new_uuid_tool().await.map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "vacuum_into" => {
+                let params: VacuumIntoRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .vacuum_into_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "set_auto_vacuum" => {
+                let params: SetAutoVacuumRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .set_auto_vacuum_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "incremental_vacuum" => {
+                let params: IncrementalVacuumRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .incremental_vacuum_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "tune_performance" => {
+                let params: PerformanceSettings =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .tune_performance_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "enable_sql_trace" => {
+                let params: EnableSqlTraceRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .enable_sql_trace_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "disable_sql_trace" => {
+                let result = self
+                    .disable_sql_trace_tool()
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "batch_update" => {
+                let params: BatchUpdateRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .batch_update_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "delete_rows" => {
+                let params: DeleteRowsRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .delete_rows_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "truncate_table" => {
+                let params: TruncateTableRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .truncate_table_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "object_exists" => {
+                let params: ObjectExistsRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .object_exists_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "get_create_sql" => {
+                let params: GetCreateSqlRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .get_create_sql_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "generate_insert_template" => {
+                let params: GenerateInsertTemplateRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .generate_insert_template_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "import_csv" => {
+                let params: ImportCsvRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .import_csv_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "import_json" => {
+                let params: ImportJsonRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .import_json_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "database_size_breakdown" => {
+                let result = self
+                    .database_size_breakdown_tool()
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "optimize" => {
+                let params: OptimizeRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .optimize_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "checkpoint_and_truncate_wal" => {
+                let result = self
+                    .checkpoint_and_truncate_wal_tool()
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "switch_database" => {
+                let params: ConnectRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .switch_database_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "list_attached_databases" => {
+                let result = self
+                    .list_attached_databases_tool()
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "query_scalar" => {
+                let params: QueryRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .query_scalar_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "query_row" => {
+                let params: QueryRowRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .query_row_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "prepare_statement" => {
+                let params: PrepareStatementRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .prepare_statement_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "execute_prepared" => {
+                let params: ExecutePreparedRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .execute_prepared_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "finalize_statement" => {
+                let params: FinalizeStatementRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .finalize_statement_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "cross_query" => {
+                let params: CrossQueryRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .cross_query_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "generate_schema_report" => {
+                let params: GenerateSchemaReportRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .generate_schema_report_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "export_er_diagram" => {
+                let params: ExportErDiagramRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .export_er_diagram_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "find_orphans" => {
+                let params: FindOrphansRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .find_orphans_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "foreign_key_check" => {
+                let params: ForeignKeyCheckRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .foreign_key_check_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "blob_read" => {
+                let params: BlobReadRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .blob_read_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "blob_write" => {
+                let params: BlobWriteRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .blob_write_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "get_table_ddl_with_indexes" => {
+                let params: GetTableDdlRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .get_table_ddl_with_indexes_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "clone_table_structure" => {
+                let params: CloneTableStructureRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .clone_table_structure_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "swap_tables" => {
+                let params: SwapTablesRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .swap_tables_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "export_table_to_db" => {
+                let params: ExportTableToDbRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .export_table_to_db_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "compare_tables" => {
+                let params: CompareTablesRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .compare_tables_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "random_sample" => {
+                let params: RandomSampleRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .random_sample_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "keyset_page" => {
+                let params: KeysetPageRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .keyset_page_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "count_estimate" => {
+                let params: CountEstimateRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .count_estimate_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "table_size_bytes" => {
+                let params: TableSizeBytesRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .table_size_bytes_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "connection_info" => {
+                let result = self
+                    .connection_info_tool()
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "list_functions" => {
+                let result = self
+                    .list_functions_tool()
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "set_limits" => {
+                let params: SetLimitsRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .set_limits_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "vacuum" => {
+                let result = self.vacuum_tool().await.map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "clear_query_cache" => {
+                let result = self
+                    .clear_query_cache_tool()
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "batch" => {
+                let params: BatchRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .batch_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "describe_query" => {
+                let params: DescribeQueryRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .describe_query_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "vacuum_check" => {
+                let params: VacuumCheckRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .vacuum_check_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            #[cfg(feature = "extensions")]
+            "load_extension" => {
+                let params: LoadExtensionRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .load_extension_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            #[cfg(feature = "extensions")]
+            "create_vector_index" => {
+                let params: CreateVectorIndexRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .create_vector_index_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            #[cfg(feature = "extensions")]
+            "vector_search" => {
+                let params: VectorSearchRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .vector_search_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "pack_embedding" => {
+                let params: PackEmbeddingRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .pack_embedding_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "unpack_embedding" => {
+                let params: UnpackEmbeddingRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .unpack_embedding_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "similarity_search" => {
+                let params: SimilaritySearchRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .similarity_search_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "enable_change_tracking" => {
+                let params: ChangeTrackingRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .enable_change_tracking_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "disable_change_tracking" => {
+                let params: ChangeTrackingRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .disable_change_tracking_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "get_changes_since" => {
+                let params: GetChangesSinceRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .get_changes_since_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "enable_soft_delete" => {
+                let params: EnableSoftDeleteRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .enable_soft_delete_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "soft_delete" => {
+                let params: SoftDeleteRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .soft_delete_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "restore" => {
+                let params: SoftDeleteRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .restore_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "tail" => {
+                let params: TailRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .tail_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "set_meta" => {
+                let params: SetMetaRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .set_meta_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "get_meta" => {
+                let params: GetMetaRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .get_meta_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "list_meta" => {
+                let result = self.list_meta_tool().await.map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "query_to_temp_table" => {
+                let params: QueryToTempTableRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .query_to_temp_table_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "list_tables_detailed" => {
+                let result = self
+                    .list_tables_detailed_tool()
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "generate_migration" => {
+                let params: GenerateMigrationRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .generate_migration_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "dump_to_sql" => {
+                let params: DumpToSqlRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .dump_to_sql_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "restore_from_directory" => {
+                let params: RestoreFromDirectoryRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .restore_from_directory_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "restore_from_sql" => {
+                let params: RestoreFromSqlRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .restore_from_sql_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "detect_encoding" => {
+                let params: DetectEncodingRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .detect_encoding_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "preview_csv" => {
+                let params: PreviewCsvRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .preview_csv_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "create_table_from_csv" => {
+                let params: CreateTableFromCsvRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .create_table_from_csv_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "query_explain_analyze" => {
+                let params: QueryExplainAnalyzeRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .query_explain_analyze_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "table_checksum" => {
+                let params: TableChecksumRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .table_checksum_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "generate_fake_data" => {
+                let params: GenerateFakeDataRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .generate_fake_data_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "lock_status" => {
+                let params: LockStatusRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .lock_status_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            "reset_sequence" => {
+                let params: ResetSequenceRequest =
+                    serde_json::from_value(request.arguments.unwrap_or_default().into())
+                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+
+                let result = self
+                    .reset_sequence_tool(params)
+                    .await
+                    .map_err(rmcp::ErrorData::from)?;
+
+                Ok(CallToolResult {
+                    content: vec![],
+                    structured_content: Some(serde_json::to_value(result).unwrap()),
+                    is_error: Some(false),
+                })
+            }
+            _ => Err(rmcp::ErrorData::invalid_params("Tool not found", None)),
+        }
+    }
+}
+
+// Implement ServerHandler trait
+impl ServerHandler for SqliteHandler {
+    fn get_info(&self) -> ServerInfo {
+        ServerInfo {
+            protocol_version: ProtocolVersion::V_2024_11_05,
+            server_info: Implementation {
+                name: "uni-sqlite".into(),
+                version: env!("CARGO_PKG_VERSION").into(),
+            },
+            capabilities: ServerCapabilities {
+                tools: Some(Default::default()),
+                ..Default::default()
+            },
+            instructions: Some(
+                "A comprehensive SQLite administration MCP server for consciousness persistence infrastructure. \
+                Features: database connections, schema management, transactions, backups, batch operations, \
+                CSV export/import, and health monitoring. Designed for LegacyMind project requirements."
+                    .into(),
+            ),
+        }
+    }
+
+    fn list_tools(
+        &self,
+        request: Option<PaginatedRequestParam>,
+        context: RequestContext<rmcp::service::RoleServer>,
+    ) -> impl std::future::Future<Output = Result<ListToolsResult, rmcp::ErrorData>> + Send + '_
+    {
+        self.list_tools_handler(request, context)
+    }
+
+    fn call_tool(
+        &self,
+        request: CallToolRequestParam,
+        context: RequestContext<rmcp::service::RoleServer>,
+    ) -> impl std::future::Future<Output = Result<CallToolResult, rmcp::ErrorData>> + Send + '_
+    {
+        self.call_tool_handler(request, context)
+    }
+}
+
+pub async fn run() -> anyhow::Result<()> {
+    let handler = SqliteHandler::new();
+
+    // Unparsable, zero, or out-of-range values leave idle closing off
+    if let Some((minutes, seconds)) = std::env::var(IDLE_TIMEOUT_ENV)
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .filter(|m| *m > 0)
+        .and_then(|m| Some((m, m.checked_mul(60)?)))
+    {
+        tracing::info!(
+            "Idle connections will be closed after {} minute(s)",
+            minutes
+        );
+        handler.spawn_idle_monitor(Duration::from_secs(seconds));
+    }
+
+    // Serve the handler with stdio transport
+    let server = handler.serve(stdio()).await?;
+
+    // Wait for service to complete
+    server.waiting().await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    async fn create_test_handler_with_db() -> (SqliteHandler, TempDir, PathBuf) {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.db");
+
+        let handler = SqliteHandler::new();
+
+        // Connect to the test database
+        let connect_req = ConnectRequest {
+            path: db_path.display().to_string(),
+            create_if_missing: true,
+            ..Default::default()
+        };
+
+        handler.connect_tool(connect_req).await.unwrap();
+
+        (handler, temp_dir, db_path)
+    }
+
+    #[tokio::test]
+    async fn test_connect_and_health_check() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        let health = handler.health_check_tool().await.unwrap();
+        assert!(health.connected);
+        // Check that the path ends with the expected filename (handles canonicalization differences)
+        assert!(health.database_path.as_ref().unwrap().ends_with("test.db"));
+        assert!(health.database_size.is_some());
+        assert_eq!(health.table_count, Some(0));
+        assert_eq!(health.supports_window_functions, Some(true));
+    }
+
+    #[tokio::test]
+    async fn test_create_table_and_list_tables() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        // Create a test table
+        let create_req = CreateTableRequest {
+            table_name: "consciousness_data".to_string(),
+            columns: "id INTEGER PRIMARY KEY, timestamp TEXT, data TEXT".to_string(),
+            if_not_exists: true,
+            ..Default::default()
+        };
+
+        let result = handler.create_table_tool(create_req).await.unwrap();
+        assert!(result.success);
+        assert_eq!(result.table_name, "consciousness_data");
+
+        // List tables
+        let tables = handler.list_tables_tool().await.unwrap();
+        assert_eq!(tables.total_count, 1);
+        assert_eq!(tables.tables[0].name, "consciousness_data");
+        assert_eq!(tables.tables[0].row_count, Some(0));
+    }
+
+    #[tokio::test]
+    async fn test_describe_table() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        // Create a test table
+        let create_req = CreateTableRequest {
+            table_name: "test_table".to_string(),
+            columns: "id INTEGER PRIMARY KEY, name TEXT NOT NULL, active BOOLEAN DEFAULT 1"
+                .to_string(),
+            if_not_exists: true,
+            ..Default::default()
+        };
+        handler.create_table_tool(create_req).await.unwrap();
+
+        // Describe the table
+        let describe_req = DescribeTableRequest {
+            table_name: "test_table".to_string(),
+        };
+
+        let result = handler.describe_table_tool(describe_req).await.unwrap();
+        assert_eq!(result.table_name, "test_table");
+        assert_eq!(result.columns.len(), 3);
+
+        // Check column details
+        let id_col = &result.columns[0];
+        assert_eq!(id_col.name, "id");
+        assert_eq!(id_col.data_type, "INTEGER");
+        assert!(id_col.primary_key);
+
+        let name_col = &result.columns[1];
+        assert_eq!(name_col.name, "name");
+        assert_eq!(name_col.data_type, "TEXT");
+        assert!(name_col.not_null);
+    }
+
+    #[tokio::test]
+    async fn test_query_operations() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        // Create table
+        let create_req = CreateTableRequest {
+            table_name: "users".to_string(),
+            columns: "id INTEGER PRIMARY KEY, name TEXT, email TEXT".to_string(),
+            if_not_exists: true,
+            ..Default::default()
+        };
+        handler.create_table_tool(create_req).await.unwrap();
+
+        // Insert data
+        let insert_req = QueryRequest {
+            sql: "INSERT INTO users (name, email) VALUES (?, ?)".to_string(),
+            parameters: vec![
+                serde_json::Value::String("Alice".to_string()),
+                serde_json::Value::String("alice@example.com".to_string()),
+            ],
+            ..Default::default()
+        };
+
+        let insert_result = handler.query_tool(insert_req).await.unwrap();
+        assert_eq!(insert_result.rows_affected, Some(1));
+        assert!(insert_result.elapsed_ms.is_some());
+
+        // Select data
+        let select_req = QueryRequest {
+            sql: "SELECT * FROM users WHERE name = ?".to_string(),
+            parameters: vec![serde_json::Value::String("Alice".to_string())],
+            ..Default::default()
+        };
+
+        let select_result = handler.query_tool(select_req).await.unwrap();
+        assert_eq!(select_result.rows_affected, Some(1));
+        assert!(select_result.data.is_some());
+        assert!(select_result.columns.is_some());
+        assert!(select_result.elapsed_ms.is_some());
+
+        let data = select_result.data.unwrap();
+        assert_eq!(data.len(), 1);
+        assert_eq!(data[0][1], serde_json::Value::String("Alice".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_batch_insert() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        // Create table
+        let create_req = CreateTableRequest {
+            table_name: "batch_test".to_string(),
+            columns: "id INTEGER PRIMARY KEY, value TEXT".to_string(),
+            if_not_exists: true,
+            ..Default::default()
+        };
+        handler.create_table_tool(create_req).await.unwrap();
+
+        // Batch insert
+        let batch_req = BatchInsertRequest {
+            table_name: "batch_test".to_string(),
+            columns: vec!["value".to_string()],
+            rows: vec![
+                vec![serde_json::Value::String("row1".to_string())],
+                vec![serde_json::Value::String("row2".to_string())],
+                vec![serde_json::Value::String("row3".to_string())],
+            ],
+            replace_on_conflict: false,
+            records: vec![],
+            ..Default::default()
+        };
+
+        let result = handler.batch_insert_tool(batch_req).await.unwrap();
+        assert!(result.success);
+        assert_eq!(result.rows_inserted, 3);
+
+        // Verify data was inserted
+        let select_req = QueryRequest {
+            sql: "SELECT COUNT(*) FROM batch_test".to_string(),
+            parameters: vec![],
+            ..Default::default()
+        };
+
+        let select_result = handler.query_tool(select_req).await.unwrap();
+        let count = &select_result.data.unwrap()[0][0];
+        assert_eq!(*count, serde_json::Value::Number(3.into()));
+    }
+
+    #[tokio::test]
+    async fn test_transaction() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        // Create table
+        let create_req = CreateTableRequest {
+            table_name: "tx_test".to_string(),
+            columns: "id INTEGER PRIMARY KEY, value TEXT".to_string(),
+            if_not_exists: true,
+            ..Default::default()
+        };
+        handler.create_table_tool(create_req).await.unwrap();
+
+        // Transaction with multiple queries
+        let tx_req = TransactionRequest {
+            queries: vec![
+                QueryRequest {
+                    sql: "INSERT INTO tx_test (value) VALUES (?)".to_string(),
+                    parameters: vec![serde_json::Value::String("tx1".to_string())],
+                    ..Default::default()
+                },
+                QueryRequest {
+                    sql: "INSERT INTO tx_test (value) VALUES (?)".to_string(),
+                    parameters: vec![serde_json::Value::String("tx2".to_string())],
+                    ..Default::default()
+                },
+            ],
+            rollback_on_error: true,
+        };
+
+        let result = handler.transaction_tool(tx_req).await.unwrap();
+        assert!(result.success);
+        assert_eq!(result.total_rows_affected, 2);
+        assert_eq!(result.results.len(), 2);
+
+        // Verify both rows were inserted
+        let select_req = QueryRequest {
+            sql: "SELECT COUNT(*) FROM tx_test".to_string(),
+            parameters: vec![],
+            ..Default::default()
+        };
+
+        let select_result = handler.query_tool(select_req).await.unwrap();
+        let count = &select_result.data.unwrap()[0][0];
+        assert_eq!(*count, serde_json::Value::Number(2.into()));
+    }
+
+    #[tokio::test]
+    async fn test_backup() {
+        let (handler, temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        // Create some data
+        let create_req = CreateTableRequest {
+            table_name: "backup_test".to_string(),
+            columns: "id INTEGER PRIMARY KEY, data TEXT".to_string(),
+            if_not_exists: true,
+            ..Default::default()
+        };
+        handler.create_table_tool(create_req).await.unwrap();
+
+        let insert_req = QueryRequest {
+            sql: "INSERT INTO backup_test (data) VALUES (?)".to_string(),
+            parameters: vec![serde_json::Value::String("test_data".to_string())],
+            ..Default::default()
+        };
+        handler.query_tool(insert_req).await.unwrap();
+
+        // Create backup
+        let backup_path = temp_dir.path().join("backup.db");
+        let backup_req = BackupRequest {
+            destination_path: backup_path.display().to_string(),
+        };
+
+        let result = handler.backup_tool(backup_req).await.unwrap();
+        assert!(result.success);
+        assert!(result.backup_size.is_some());
+        assert!(backup_path.exists());
+
+        // Verify backup contains data
+        let backup_conn = Connection::open(&backup_path).unwrap();
+        let count: i32 = backup_conn
+            .query_row("SELECT COUNT(*) FROM backup_test", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_export_csv() {
+        let (handler, temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        // Create and populate table
+        let create_req = CreateTableRequest {
+            table_name: "csv_test".to_string(),
+            columns: "id INTEGER PRIMARY KEY, name TEXT, value INTEGER".to_string(),
+            if_not_exists: true,
+            ..Default::default()
+        };
+        handler.create_table_tool(create_req).await.unwrap();
+
+        let batch_req = BatchInsertRequest {
+            table_name: "csv_test".to_string(),
+            columns: vec!["name".to_string(), "value".to_string()],
+            rows: vec![
+                vec![
+                    serde_json::Value::String("Alice".to_string()),
+                    serde_json::Value::Number(100.into()),
+                ],
+                vec![
+                    serde_json::Value::String("Bob".to_string()),
+                    serde_json::Value::Number(200.into()),
+                ],
+            ],
+            replace_on_conflict: false,
+            records: vec![],
+            ..Default::default()
+        };
+        handler.batch_insert_tool(batch_req).await.unwrap();
+
+        // Export to CSV
+        let csv_path = temp_dir.path().join("export.csv");
+        let export_req = ExportCsvRequest {
+            query: "SELECT name, value FROM csv_test ORDER BY name".to_string(),
+            output_path: csv_path.display().to_string(),
+            include_headers: true,
+            blob_handling: BlobHandling::Hex,
+            ..Default::default()
+        };
+
+        let result = handler.export_csv_tool(export_req).await.unwrap();
+        assert!(result.success);
+        assert_eq!(result.rows_exported, 2);
+        assert!(csv_path.exists());
+
+        // Verify CSV content
+        let csv_content = fs::read_to_string(&csv_path).unwrap();
+        assert!(csv_content.contains("name,value"));
+        assert!(csv_content.contains("Alice,100"));
+        assert!(csv_content.contains("Bob,200"));
+    }
+
+    #[tokio::test]
+    async fn test_sql_validation() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        // Test invalid SQL (multiple statements)
+        let invalid_req = QueryRequest {
+            sql: "SELECT 1; DROP TABLE users;".to_string(),
+            parameters: vec![],
+            ..Default::default()
+        };
+
+        let result = handler.query_tool(invalid_req).await;
+        assert!(result.is_err());
+
+        // Test disallowed command
+        let disallowed_req = QueryRequest {
+            sql: "ATTACH DATABASE 'other.db' AS other".to_string(),
+            parameters: vec![],
+            ..Default::default()
+        };
+
+        let result = handler.query_tool(disallowed_req).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_path_validation() {
+        let handler = SqliteHandler::new();
+        let temp_dir = TempDir::new().unwrap();
+
+        // Test invalid extension in temp directory
+        let invalid_ext_path = temp_dir.path().join("test.txt");
+        let invalid_ext_req = ConnectRequest {
+            path: invalid_ext_path.display().to_string(),
+            create_if_missing: true,
+            ..Default::default()
+        };
+
+        let result = handler.connect_tool(invalid_ext_req).await;
+        assert!(result.is_err());
+
+        // Test valid path in temp directory should work
+        let valid_path = temp_dir.path().join("test.db");
+        let valid_req = ConnectRequest {
+            path: valid_path.display().to_string(),
+            create_if_missing: true,
+            ..Default::default()
+        };
+
+        let result = handler.connect_tool(valid_req).await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_uuid_function() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("uuid.db");
+        let handler = SqliteHandler::new();
+
+        handler
+            .connect_tool(ConnectRequest {
+                path: db_path.display().to_string(),
+                create_if_missing: true,
+                enable_uuid_function: true,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let create_req = CreateTableRequest {
+            table_name: "records".to_string(),
+            columns: "id TEXT PRIMARY KEY DEFAULT (uuid()), note TEXT".to_string(),
+            if_not_exists: true,
+            ..Default::default()
+        };
+        handler.create_table_tool(create_req).await.unwrap();
+
+        let insert_req = QueryRequest {
+            sql: "INSERT INTO records (note) VALUES (?)".to_string(),
+            parameters: vec![serde_json::Value::String("first".to_string())],
+            ..Default::default()
+        };
+        handler.query_tool(insert_req).await.unwrap();
+
+        let select_req = QueryRequest {
+            sql: "SELECT id FROM records".to_string(),
+            parameters: vec![],
+            ..Default::default()
+        };
+        let data = handler.query_tool(select_req).await.unwrap().data.unwrap();
+        let id = data[0][0].as_str().unwrap();
+        assert!(uuid::Uuid::parse_str(id).is_ok());
+
+        let generated = handler.new_uuid_tool().await.unwrap();
+        assert!(uuid::Uuid::parse_str(&generated.uuid).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_vacuum_into() {
+        let (handler, temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        let create_req = CreateTableRequest {
+            table_name: "vacuum_test".to_string(),
+            columns: "id INTEGER PRIMARY KEY, data TEXT".to_string(),
+            if_not_exists: true,
+            ..Default::default()
+        };
+        handler.create_table_tool(create_req).await.unwrap();
+
+        let insert_req = QueryRequest {
+            sql: "INSERT INTO vacuum_test (data) VALUES (?)".to_string(),
+            parameters: vec![serde_json::Value::String("payload".to_string())],
+            ..Default::default()
+        };
+        handler.query_tool(insert_req).await.unwrap();
+
+        // A quote in the filename must not break out of the statement
+        let dest_path = temp_dir.path().join("it's compacted.db");
+        let vacuum_req = VacuumIntoRequest {
+            destination_path: dest_path.display().to_string(),
+        };
+
+        let result = handler.vacuum_into_tool(vacuum_req).await.unwrap();
+        assert!(result.success);
+        assert!(result.destination_size.unwrap() > 0);
+
+        let copy_conn = Connection::open(&dest_path).unwrap();
+        let count: i32 = copy_conn
+            .query_row("SELECT COUNT(*) FROM vacuum_test", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 1);
+
+        // Refuses to overwrite an existing file
+        let repeat_req = VacuumIntoRequest {
+            destination_path: dest_path.display().to_string(),
+        };
+        assert!(handler.vacuum_into_tool(repeat_req).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_set_auto_vacuum() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        let create_req = CreateTableRequest {
+            table_name: "av_test".to_string(),
+            columns: "id INTEGER PRIMARY KEY".to_string(),
+            if_not_exists: true,
+            ..Default::default()
+        };
+        handler.create_table_tool(create_req).await.unwrap();
+
+        let result = handler
+            .set_auto_vacuum_tool(SetAutoVacuumRequest {
+                mode: "incremental".to_string(),
+                vacuum: true,
+            })
+            .await
+            .unwrap();
+        assert_eq!(result.previous_mode, "NONE");
+        assert_eq!(result.mode, "INCREMENTAL");
+        assert!(result.vacuumed);
+
+        let invalid = handler
+            .set_auto_vacuum_tool(SetAutoVacuumRequest {
+                mode: "SOMETIMES".to_string(),
+                vacuum: false,
+            })
+            .await;
+        assert!(invalid.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_incremental_vacuum() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        handler
+            .set_auto_vacuum_tool(SetAutoVacuumRequest {
+                mode: "INCREMENTAL".to_string(),
+                vacuum: true,
+            })
+            .await
+            .unwrap();
+
+        let create_req = CreateTableRequest {
+            table_name: "iv_test".to_string(),
+            columns: "id INTEGER PRIMARY KEY, data TEXT".to_string(),
+            if_not_exists: true,
+            ..Default::default()
+        };
+        handler.create_table_tool(create_req).await.unwrap();
+
+        let rows = (0..200)
+            .map(|_| vec![serde_json::Value::String("x".repeat(1000))])
+            .collect();
+        let batch_req = BatchInsertRequest {
+            table_name: "iv_test".to_string(),
+            columns: vec!["data".to_string()],
+            rows,
+            replace_on_conflict: false,
+            records: vec![],
+            ..Default::default()
+        };
+        handler.batch_insert_tool(batch_req).await.unwrap();
+
+        let delete_req = QueryRequest {
+            sql: "DELETE FROM iv_test".to_string(),
+            parameters: vec![],
+            ..Default::default()
+        };
+        handler.query_tool(delete_req).await.unwrap();
+
+        let result = handler
+            .incremental_vacuum_tool(IncrementalVacuumRequest { pages: Some(10) })
+            .await
+            .unwrap();
+        assert_eq!(result.auto_vacuum_mode, "INCREMENTAL");
+        assert!(result.freelist_pages_before > 10);
+        assert_eq!(
+            result.freelist_pages_after,
+            result.freelist_pages_before - 10
+        );
+
+        let result = handler
+            .incremental_vacuum_tool(IncrementalVacuumRequest { pages: None })
+            .await
+            .unwrap();
+        assert_eq!(result.freelist_pages_after, 0);
+    }
+
+    #[tokio::test]
+    async fn test_tune_performance() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("tuned.db");
+        let handler = SqliteHandler::new();
+
+        let connected = handler
+            .connect_tool(ConnectRequest {
+                path: db_path.display().to_string(),
+                create_if_missing: true,
+                performance: Some(PerformanceSettings {
+                    cache_size: Some(-4000),
+                    synchronous: Some("normal".to_string()),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let performance = connected.performance.unwrap();
+        assert_eq!(performance.cache_size, -4000);
+        assert_eq!(performance.synchronous, "NORMAL");
+
+        let tuned = handler
+            .tune_performance_tool(PerformanceSettings {
+                cache_size: Some(500),
+                mmap_size: Some(1 << 20),
+                temp_store: Some("MEMORY".to_string()),
+                synchronous: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(tuned.cache_size, 500);
+        assert_eq!(tuned.mmap_size, 1 << 20);
+        assert_eq!(tuned.temp_store, "MEMORY");
+        assert_eq!(tuned.synchronous, "NORMAL");
+
+        let invalid = handler
+            .tune_performance_tool(PerformanceSettings {
+                synchronous: Some("SOMETIMES".to_string()),
+                ..Default::default()
+            })
+            .await;
+        assert!(invalid.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_sql_trace_toggle() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        let enabled = handler
+            .enable_sql_trace_tool(EnableSqlTraceRequest { verbose: true })
+            .await
+            .unwrap();
+        assert!(enabled.state.enabled);
+        assert!(enabled.state.verbose);
+
+        let select_req = QueryRequest {
+            sql: "SELECT ?".to_string(),
+            parameters: vec![serde_json::Value::Number(1.into())],
+            ..Default::default()
+        };
+        handler.query_tool(select_req).await.unwrap();
+
+        let disabled = handler.disable_sql_trace_tool().await.unwrap();
+        assert!(!disabled.state.enabled);
+        assert!(!handler.sql_trace.lock().await.enabled);
+    }
+
+    #[tokio::test]
+    async fn test_statement_cache() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("cached.db");
+        let handler = SqliteHandler::new();
+
+        handler
+            .connect_tool(ConnectRequest {
+                path: db_path.display().to_string(),
+                create_if_missing: true,
+                use_cache: true,
+                statement_cache_capacity: Some(4),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        for i in 0..3 {
+            let select_req = QueryRequest {
+                sql: "SELECT ?".to_string(),
+                parameters: vec![serde_json::Value::Number(i.into())],
+                ..Default::default()
+            };
+            handler.query_tool(select_req).await.unwrap();
+        }
+
+        let health = handler.health_check_tool().await.unwrap();
+        let stats = health.statement_cache.unwrap();
+        assert!(stats.enabled);
+        assert_eq!(stats.capacity, 4);
+        assert_eq!(stats.misses, 1);
+        assert_eq!(stats.hits, 2);
+    }
+
+    #[tokio::test]
+    async fn test_batch_update() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        let create_req = CreateTableRequest {
+            table_name: "update_test".to_string(),
+            columns: "id INTEGER PRIMARY KEY, status TEXT".to_string(),
+            if_not_exists: true,
+            ..Default::default()
+        };
+        handler.create_table_tool(create_req).await.unwrap();
+
+        let batch_req = BatchInsertRequest {
+            table_name: "update_test".to_string(),
+            columns: vec!["id".to_string(), "status".to_string()],
+            rows: vec![
+                vec![1.into(), "new".into()],
+                vec![2.into(), "new".into()],
+                vec![3.into(), "done".into()],
+            ],
+            replace_on_conflict: false,
+            records: vec![],
+            ..Default::default()
+        };
+        handler.batch_insert_tool(batch_req).await.unwrap();
+
+        let update_req = BatchUpdateRequest {
+            table_name: "update_test".to_string(),
+            set_columns: vec!["status".to_string()],
+            where_columns: vec!["id".to_string()],
+            rows: vec![
+                vec!["done".into(), 1.into()],
+                vec!["done".into(), 3.into()],
+                vec!["done".into(), 99.into()],
+            ],
+        };
+
+        let result = handler.batch_update_tool(update_req).await.unwrap();
+        assert!(result.success);
+        assert_eq!(result.rows_matched, 2);
+        assert_eq!(result.rows_changed, 1);
+
+        let select_req = QueryRequest {
+            sql: "SELECT COUNT(*) FROM update_test WHERE status = 'done'".to_string(),
+            parameters: vec![],
+            ..Default::default()
+        };
+        let data = handler.query_tool(select_req).await.unwrap().data.unwrap();
+        assert_eq!(data[0][0], serde_json::Value::Number(2.into()));
+    }
+
+    #[tokio::test]
+    async fn test_delete_rows() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        let create_req = CreateTableRequest {
+            table_name: "delete_test".to_string(),
+            columns: "id INTEGER PRIMARY KEY, kind TEXT".to_string(),
+            if_not_exists: true,
+            ..Default::default()
+        };
+        handler.create_table_tool(create_req).await.unwrap();
+
+        let batch_req = BatchInsertRequest {
+            table_name: "delete_test".to_string(),
+            columns: vec!["kind".to_string()],
+            rows: vec![vec!["a".into()], vec!["b".into()], vec!["b".into()]],
+            replace_on_conflict: false,
+            records: vec![],
+            ..Default::default()
+        };
+        handler.batch_insert_tool(batch_req).await.unwrap();
+
+        // Unqualified deletes are refused without opt-in
+        let unguarded = handler
+            .delete_rows_tool(DeleteRowsRequest {
+                table_name: "delete_test".to_string(),
+                where_clause: "  ".to_string(),
+                parameters: vec![],
+                allow_full_table: false,
+                ..Default::default()
+            })
+            .await;
+        assert!(unguarded.is_err());
+
+        let result = handler
+            .delete_rows_tool(DeleteRowsRequest {
+                table_name: "delete_test".to_string(),
+                where_clause: "kind = ?".to_string(),
+                parameters: vec!["b".into()],
+                allow_full_table: false,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(result.rows_deleted, 2);
+
+        let result = handler
+            .delete_rows_tool(DeleteRowsRequest {
+                table_name: "delete_test".to_string(),
+                where_clause: String::new(),
+                parameters: vec![],
+                allow_full_table: true,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(result.rows_deleted, 1);
+    }
+
+    #[tokio::test]
+    async fn test_truncate_table() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        let create_req = CreateTableRequest {
+            table_name: "truncate_test".to_string(),
+            columns: "id INTEGER PRIMARY KEY AUTOINCREMENT, data TEXT".to_string(),
+            if_not_exists: true,
+            ..Default::default()
+        };
+        handler.create_table_tool(create_req).await.unwrap();
+
+        let batch_req = BatchInsertRequest {
+            table_name: "truncate_test".to_string(),
+            columns: vec!["data".to_string()],
+            rows: vec![vec!["a".into()], vec!["b".into()]],
+            replace_on_conflict: false,
+            records: vec![],
+            ..Default::default()
+        };
+        handler.batch_insert_tool(batch_req).await.unwrap();
+
+        let unconfirmed = handler
+            .truncate_table_tool(TruncateTableRequest {
+                table_name: "truncate_test".to_string(),
+                reset_autoincrement: true,
+                confirm: false,
+            })
+            .await;
+        assert!(unconfirmed.is_err());
+
+        let result = handler
+            .truncate_table_tool(TruncateTableRequest {
+                table_name: "truncate_test".to_string(),
+                reset_autoincrement: true,
+                confirm: true,
+            })
+            .await
+            .unwrap();
+        assert_eq!(result.rows_deleted, 2);
+        assert!(result.sequence_reset);
+
+        // The counter restarts at 1
+        let insert_req = QueryRequest {
+            sql: "INSERT INTO truncate_test (data) VALUES ('c')".to_string(),
+            parameters: vec![],
+            ..Default::default()
+        };
+        handler.query_tool(insert_req).await.unwrap();
+        let select_req = QueryRequest {
+            sql: "SELECT id FROM truncate_test".to_string(),
+            parameters: vec![],
+            ..Default::default()
+        };
+        let data = handler.query_tool(select_req).await.unwrap().data.unwrap();
+        assert_eq!(data[0][0], serde_json::Value::Number(1.into()));
+    }
+
+    #[tokio::test]
+    async fn test_object_exists() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        let create_req = CreateTableRequest {
+            table_name: "exists_test".to_string(),
+            columns: "id INTEGER PRIMARY KEY".to_string(),
+            if_not_exists: true,
+            ..Default::default()
+        };
+        handler.create_table_tool(create_req).await.unwrap();
+
+        let found = handler
+            .object_exists_tool(ObjectExistsRequest {
+                name: "exists_test".to_string(),
+                object_type: Some("TABLE".to_string()),
+            })
+            .await
+            .unwrap();
+        assert!(found.exists);
+        assert_eq!(found.object_type.as_deref(), Some("table"));
+        assert!(found.sql.unwrap().contains("CREATE TABLE"));
+
+        let wrong_type = handler
+            .object_exists_tool(ObjectExistsRequest {
+                name: "exists_test".to_string(),
+                object_type: Some("view".to_string()),
+            })
+            .await
+            .unwrap();
+        assert!(!wrong_type.exists);
+
+        let missing = handler
+            .object_exists_tool(ObjectExistsRequest {
+                name: "missing".to_string(),
+                object_type: None,
+            })
+            .await
+            .unwrap();
+        assert!(!missing.exists);
+    }
+
+    #[tokio::test]
+    async fn test_get_create_sql() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        let create_req = CreateTableRequest {
+            table_name: "ddl_test".to_string(),
+            columns: "id INTEGER PRIMARY KEY, email TEXT".to_string(),
+            if_not_exists: false,
+            ..Default::default()
+        };
+        handler.create_table_tool(create_req).await.unwrap();
+
+        let index_req = QueryRequest {
+            sql: "CREATE INDEX idx_ddl_email ON ddl_test (email)".to_string(),
+            parameters: vec![],
+            ..Default::default()
+        };
+        handler.query_tool(index_req).await.unwrap();
+
+        let result = handler
+            .get_create_sql_tool(GetCreateSqlRequest {
+                name: "idx_ddl_email".to_string(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(result.object_type, "index");
+        assert_eq!(result.sql, "CREATE INDEX idx_ddl_email ON ddl_test (email)");
+
+        let missing = handler
+            .get_create_sql_tool(GetCreateSqlRequest {
+                name: "missing".to_string(),
+            })
+            .await;
+        assert!(missing.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_generate_insert_template() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        let create_req = CreateTableRequest {
+            table_name: "template_test".to_string(),
+            columns: "id INTEGER PRIMARY KEY, name TEXT NOT NULL, score REAL".to_string(),
+            if_not_exists: true,
+            ..Default::default()
+        };
+        handler.create_table_tool(create_req).await.unwrap();
+
+        let result = handler
+            .generate_insert_template_tool(GenerateInsertTemplateRequest {
+                table_name: "template_test".to_string(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(
+            result.sql,
+            "INSERT INTO \"template_test\" (\"id\", \"name\", \"score\") VALUES (?, ?, ?)"
+        );
+        assert_eq!(result.placeholder_count, 3);
+        assert_eq!(result.columns[2].data_type, "REAL");
+
+        let missing = handler
+            .generate_insert_template_tool(GenerateInsertTemplateRequest {
+                table_name: "missing".to_string(),
+            })
+            .await;
+        assert!(missing.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_batch_insert_records() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        let create_req = CreateTableRequest {
+            table_name: "record_test".to_string(),
+            columns: "id INTEGER PRIMARY KEY, name TEXT, status TEXT DEFAULT 'active'".to_string(),
+            if_not_exists: true,
+            ..Default::default()
+        };
+        handler.create_table_tool(create_req).await.unwrap();
+
+        let records = vec![
+            serde_json::json!({"status": "archived", "name": "Alice"}),
+            serde_json::json!({"name": "Bob"}),
+        ]
+        .into_iter()
+        .map(|v| v.as_object().unwrap().clone())
+        .collect();
+
+        let batch_req = BatchInsertRequest {
+            table_name: "record_test".to_string(),
+            columns: vec!["name".to_string(), "status".to_string()],
+            rows: vec![],
+            replace_on_conflict: false,
+            records,
+            ..Default::default()
+        };
+        let result = handler.batch_insert_tool(batch_req).await.unwrap();
+        assert_eq!(result.rows_inserted, 2);
+
+        let select_req = QueryRequest {
+            sql: "SELECT name, status FROM record_test ORDER BY name".to_string(),
+            parameters: vec![],
+            ..Default::default()
+        };
+        let data = handler.query_tool(select_req).await.unwrap().data.unwrap();
+        assert_eq!(
+            data[0],
+            vec![serde_json::json!("Alice"), serde_json::json!("archived")]
+        );
+        assert_eq!(
+            data[1],
+            vec![serde_json::json!("Bob"), serde_json::json!("active")]
+        );
+
+        // Keys outside the column list are rejected
+        let bad_req = BatchInsertRequest {
+            table_name: "record_test".to_string(),
+            columns: vec!["name".to_string()],
+            rows: vec![],
+            replace_on_conflict: false,
+            records: vec![
+                serde_json::json!({"nmae": "typo"})
                     .as_object()
                     .unwrap()
-                    .clone()
-                    .into(),
-                annotations: None,
-                output_schema: None,
-            },
-            Tool {
-                name: Cow::Borrowed("query"),
-                description: Some(Cow::Borrowed(
-                    "Execute a SQL query (SELECT, INSERT, UPDATE, DELETE, CREATE, ALTER, DROP, PRAGMA)",
-                )),
-                input_schema: serde_json::to_value(schemars::schema_for!(QueryRequest).schema)
+                    .clone(),
+            ],
+            ..Default::default()
+        };
+        assert!(handler.batch_insert_tool(bad_req).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_export_csv_blob_handling() {
+        let (handler, temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        let create_req = CreateTableRequest {
+            table_name: "blob_test".to_string(),
+            columns: "id INTEGER PRIMARY KEY, payload BLOB".to_string(),
+            if_not_exists: true,
+            ..Default::default()
+        };
+        handler.create_table_tool(create_req).await.unwrap();
+
+        let insert_req = QueryRequest {
+            sql: "INSERT INTO blob_test (payload) VALUES (x'DEADBEEF')".to_string(),
+            parameters: vec![],
+            ..Default::default()
+        };
+        handler.query_tool(insert_req).await.unwrap();
+
+        let base64_path = temp_dir.path().join("base64.csv");
+        handler
+            .export_csv_tool(ExportCsvRequest {
+                query: "SELECT payload FROM blob_test".to_string(),
+                output_path: base64_path.display().to_string(),
+                include_headers: false,
+                blob_handling: BlobHandling::Base64,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(fs::read_to_string(&base64_path).unwrap().trim(), "3q2+7w==");
+
+        let files_path = temp_dir.path().join("files.csv");
+        let result = handler
+            .export_csv_tool(ExportCsvRequest {
+                query: "SELECT id, payload FROM blob_test".to_string(),
+                output_path: files_path.display().to_string(),
+                include_headers: false,
+                blob_handling: BlobHandling::ExternalFiles,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert!(result.blob_directory.is_some());
+        assert_eq!(
+            fs::read_to_string(&files_path).unwrap().trim(),
+            "1,files_blobs/row1_col1.bin"
+        );
+        let blob = fs::read(temp_dir.path().join("files_blobs/row1_col1.bin")).unwrap();
+        assert_eq!(blob, vec![0xDE, 0xAD, 0xBE, 0xEF]);
+    }
+
+    #[tokio::test]
+    async fn test_import_csv_typed() {
+        let (handler, temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        let create_req = CreateTableRequest {
+            table_name: "contacts".to_string(),
+            columns: "id INTEGER PRIMARY KEY, zip TEXT, phone VARCHAR(20), score REAL, misc"
+                .to_string(),
+            if_not_exists: true,
+            ..Default::default()
+        };
+        handler.create_table_tool(create_req).await.unwrap();
+
+        let csv_path = temp_dir.path().join("contacts.csv");
+        fs::write(
+            &csv_path,
+            "zip,phone,score,misc\n00501,0123456789,4,42\n02134,,3.5,abc\n",
+        )
+        .unwrap();
+
+        let result = handler
+            .import_csv_tool(ImportCsvRequest {
+                table_name: "contacts".to_string(),
+                input_path: csv_path.display().to_string(),
+                has_headers: true,
+                reject_path: None,
+                encoding: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(result.rows_imported, 2);
+
+        let select_req = QueryRequest {
+            sql: "SELECT zip, phone, typeof(score), typeof(misc) FROM contacts ORDER BY id"
+                .to_string(),
+            parameters: vec![],
+            ..Default::default()
+        };
+        let data = handler.query_tool(select_req).await.unwrap().data.unwrap();
+        assert_eq!(data[0][0], serde_json::json!("00501"));
+        assert_eq!(data[0][1], serde_json::json!("0123456789"));
+        assert_eq!(data[0][2], serde_json::json!("real"));
+        assert_eq!(data[0][3], serde_json::json!("integer"));
+        assert_eq!(data[1][1], serde_json::Value::Null);
+        assert_eq!(data[1][3], serde_json::json!("text"));
+    }
+
+    #[tokio::test]
+    async fn test_import_json() {
+        let (handler, temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        let create_req = CreateTableRequest {
+            table_name: "events".to_string(),
+            columns: "id INTEGER PRIMARY KEY, kind TEXT DEFAULT 'note', payload TEXT".to_string(),
+            if_not_exists: true,
+            ..Default::default()
+        };
+        handler.create_table_tool(create_req).await.unwrap();
+
+        let json_path = temp_dir.path().join("events.json");
+        fs::write(
+            &json_path,
+            r#"[{"kind": "alert", "payload": {"level": 3}}, {"payload": "plain", "extra": 1}]"#,
+        )
+        .unwrap();
+
+        // Unknown keys are rejected unless explicitly ignored
+        let strict = handler
+            .import_json_tool(ImportJsonRequest {
+                table_name: "events".to_string(),
+                input_path: json_path.display().to_string(),
+                missing_keys: MissingKeyHandling::Default,
+                ignore_unknown_keys: false,
+            })
+            .await;
+        assert!(strict.is_err());
+
+        let result = handler
+            .import_json_tool(ImportJsonRequest {
+                table_name: "events".to_string(),
+                input_path: json_path.display().to_string(),
+                missing_keys: MissingKeyHandling::Default,
+                ignore_unknown_keys: true,
+            })
+            .await
+            .unwrap();
+        assert_eq!(result.rows_imported, 2);
+
+        let not_json = handler
+            .import_json_tool(ImportJsonRequest {
+                table_name: "events".to_string(),
+                input_path: temp_dir.path().join("events.txt").display().to_string(),
+                missing_keys: MissingKeyHandling::Default,
+                ignore_unknown_keys: true,
+            })
+            .await;
+        assert!(matches!(not_json, Err(UniSqliteError::InvalidPath(_))));
+
+        let select_req = QueryRequest {
+            sql: "SELECT kind, payload FROM events ORDER BY id".to_string(),
+            parameters: vec![],
+            ..Default::default()
+        };
+        let data = handler.query_tool(select_req).await.unwrap().data.unwrap();
+        assert_eq!(data.len(), 2);
+        assert_eq!(data[0][1], serde_json::json!(r#"{"level":3}"#));
+        assert_eq!(data[1][0], serde_json::json!("note"));
+    }
+
+    #[tokio::test]
+    async fn test_import_csv_reject_rows() {
+        let (handler, temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        let create_req = CreateTableRequest {
+            table_name: "readings".to_string(),
+            columns: "id INTEGER PRIMARY KEY, sensor TEXT NOT NULL, value REAL".to_string(),
+            if_not_exists: true,
+            ..Default::default()
+        };
+        handler.create_table_tool(create_req).await.unwrap();
+
+        let csv_path = temp_dir.path().join("readings.csv");
+        fs::write(
+            &csv_path,
+            "sensor,value\nalpha,1.5\n,2.0\nbeta,3.0,extra\ngamma,4.5\n",
+        )
+        .unwrap();
+
+        let reject_path = temp_dir.path().join("rejects.csv");
+        let result = handler
+            .import_csv_tool(ImportCsvRequest {
+                table_name: "readings".to_string(),
+                input_path: csv_path.display().to_string(),
+                has_headers: true,
+                reject_path: Some(reject_path.display().to_string()),
+                encoding: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(result.rows_imported, 2);
+        assert_eq!(result.rows_rejected, 2);
+
+        let rejects = fs::read_to_string(&reject_path).unwrap();
+        let lines: Vec<&str> = rejects.lines().collect();
+        assert_eq!(lines[0], "sensor,value,error");
+        assert!(lines[1].starts_with(",2.0,"));
+        assert!(lines[1].contains("NOT NULL"));
+        assert!(lines[2].starts_with("beta,3.0,extra,"));
+
+        // The reject file is validated like any other output path
+        let result = handler
+            .import_csv_tool(ImportCsvRequest {
+                table_name: "readings".to_string(),
+                input_path: csv_path.display().to_string(),
+                has_headers: true,
+                reject_path: Some(temp_dir.path().join("rejects.log").display().to_string()),
+                encoding: None,
+            })
+            .await;
+        assert!(matches!(result, Err(UniSqliteError::InvalidPath(_))));
+
+        // Without a reject file the first bad row aborts the import
+        let result = handler
+            .import_csv_tool(ImportCsvRequest {
+                table_name: "readings".to_string(),
+                input_path: csv_path.display().to_string(),
+                has_headers: true,
+                reject_path: None,
+                encoding: None,
+            })
+            .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_export_csv_does_not_block_other_tools() {
+        let (handler, temp_dir, db_path) = create_test_handler_with_db().await;
+
+        handler
+            .query_tool(QueryRequest {
+                sql: "CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT)".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        handler
+            .query_tool(QueryRequest {
+                sql: "INSERT INTO items (name) VALUES ('a'), ('b'), ('c')".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        // Another process holds the database, so the export stalls in its busy handler
+        // after creating the output file and until the lock is released
+        let holder = Connection::open(&db_path).unwrap();
+        holder.execute_batch("BEGIN EXCLUSIVE").unwrap();
+
+        let csv_path = temp_dir.path().join("items.csv");
+        let export_handler = handler.clone();
+        let export_req = ExportCsvRequest {
+            query: "SELECT id, name FROM items".to_string(),
+            output_path: csv_path.display().to_string(),
+            include_headers: true,
+            ..Default::default()
+        };
+        let export = tokio::spawn(async move { export_handler.export_csv_tool(export_req).await });
+
+        tokio::time::timeout(Duration::from_secs(2), async {
+            while !csv_path.exists() {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("export never started");
+        let guard = tokio::time::timeout(Duration::from_secs(1), handler.current_db.lock())
+            .await
+            .expect("export holds current_db");
+        assert!(guard.is_some());
+        drop(guard);
+        assert!(!export.is_finished());
+
+        holder.execute_batch("COMMIT").unwrap();
+        let result = export.await.unwrap().unwrap();
+        assert_eq!(result.rows_exported, 3);
+
+        let invalid = handler
+            .export_csv_tool(ExportCsvRequest {
+                query: "SELECT id FROM items".to_string(),
+                output_path: temp_dir.path().join("items.sh").display().to_string(),
+                ..Default::default()
+            })
+            .await;
+        assert!(matches!(invalid, Err(UniSqliteError::InvalidPath(_))));
+    }
+
+    #[tokio::test]
+    async fn test_database_size_breakdown() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        let create_req = CreateTableRequest {
+            table_name: "big".to_string(),
+            columns: "id INTEGER PRIMARY KEY, data TEXT".to_string(),
+            if_not_exists: true,
+            ..Default::default()
+        };
+        handler.create_table_tool(create_req).await.unwrap();
+
+        let create_req = CreateTableRequest {
+            table_name: "small".to_string(),
+            columns: "id INTEGER PRIMARY KEY".to_string(),
+            if_not_exists: true,
+            ..Default::default()
+        };
+        handler.create_table_tool(create_req).await.unwrap();
+
+        let rows = (0..100)
+            .map(|_| vec![serde_json::Value::String("x".repeat(500))])
+            .collect();
+        let batch_req = BatchInsertRequest {
+            table_name: "big".to_string(),
+            columns: vec!["data".to_string()],
+            rows,
+            replace_on_conflict: false,
+            records: vec![],
+            ..Default::default()
+        };
+        handler.batch_insert_tool(batch_req).await.unwrap();
+
+        let result = handler.database_size_breakdown_tool().await.unwrap();
+        assert_eq!(result.objects[0].name, "big");
+        assert_eq!(result.objects[0].object_type, "table");
+        assert!(result.objects[0].pages > 1);
+        assert!(result.objects.iter().any(|o| o.name == "small"));
+
+        let percent: f64 = result.objects.iter().map(|o| o.percent).sum();
+        assert!((percent - 100.0).abs() < 0.001);
+    }
+
+    #[tokio::test]
+    async fn test_optimize() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        let create_req = CreateTableRequest {
+            table_name: "opt_test".to_string(),
+            columns: "id INTEGER PRIMARY KEY, tag TEXT".to_string(),
+            if_not_exists: true,
+            ..Default::default()
+        };
+        handler.create_table_tool(create_req).await.unwrap();
+
+        let index_req = QueryRequest {
+            sql: "CREATE INDEX idx_opt_tag ON opt_test (tag)".to_string(),
+            parameters: vec![],
+            ..Default::default()
+        };
+        handler.query_tool(index_req).await.unwrap();
+
+        let rows = (0..200)
+            .map(|i| vec![serde_json::json!(format!("t{}", i % 7))])
+            .collect();
+        let batch_req = BatchInsertRequest {
+            table_name: "opt_test".to_string(),
+            columns: vec!["tag".to_string()],
+            rows,
+            replace_on_conflict: false,
+            records: vec![],
+            ..Default::default()
+        };
+        handler.batch_insert_tool(batch_req).await.unwrap();
+
+        let result = handler
+            .optimize_tool(OptimizeRequest {
+                analysis_limit: Some(400),
+                check_all_tables: true,
+            })
+            .await
+            .unwrap();
+        assert!(result.success);
+        assert_eq!(result.analysis_limit, 400);
+        assert!(result.operations.iter().any(|op| op.contains("opt_test")));
+
+        let stats_req = QueryRequest {
+            sql: "SELECT COUNT(*) FROM sqlite_stat1 WHERE tbl = 'opt_test'".to_string(),
+            parameters: vec![],
+            ..Default::default()
+        };
+        let data = handler.query_tool(stats_req).await.unwrap().data.unwrap();
+        assert_ne!(data[0][0], serde_json::json!(0));
+    }
+
+    #[tokio::test]
+    async fn test_checkpoint_and_truncate_wal() {
+        let (handler, _temp_dir, db_path) = create_test_handler_with_db().await;
+
+        let wal_req = QueryRequest {
+            sql: "PRAGMA journal_mode = WAL".to_string(),
+            parameters: vec![],
+            ..Default::default()
+        };
+        handler.query_tool(wal_req).await.unwrap();
+
+        let create_req = CreateTableRequest {
+            table_name: "wal_test".to_string(),
+            columns: "id INTEGER PRIMARY KEY, data TEXT".to_string(),
+            if_not_exists: true,
+            ..Default::default()
+        };
+        handler.create_table_tool(create_req).await.unwrap();
+
+        let insert_req = QueryRequest {
+            sql: "INSERT INTO wal_test (data) VALUES ('pending')".to_string(),
+            parameters: vec![],
+            ..Default::default()
+        };
+        handler.query_tool(insert_req).await.unwrap();
+
+        let wal_path = db_path.with_file_name("test.db-wal");
+        assert!(fs::metadata(&wal_path).unwrap().len() > 0);
+
+        let result = handler.checkpoint_and_truncate_wal_tool().await.unwrap();
+        assert!(result.success);
+        assert_eq!(result.journal_mode, "wal");
+        assert_eq!(result.wal_size, Some(0));
+    }
+
+    #[tokio::test]
+    async fn test_connect_reports_locked_database() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("locked.db");
+
+        let holder = Connection::open(&db_path).unwrap();
+        holder
+            .execute_batch(
+                "CREATE TABLE t (id INTEGER); BEGIN EXCLUSIVE; INSERT INTO t VALUES (1);",
+            )
+            .unwrap();
+
+        let handler = SqliteHandler::new();
+        let result = handler
+            .connect_tool(ConnectRequest {
+                path: db_path.display().to_string(),
+                ..Default::default()
+            })
+            .await;
+        assert!(matches!(result, Err(UniSqliteError::DatabaseLocked(_))));
+
+        holder.execute_batch("COMMIT").unwrap();
+        let result = handler
+            .connect_tool(ConnectRequest {
+                path: db_path.display().to_string(),
+                ..Default::default()
+            })
+            .await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_switch_database() {
+        let (handler, temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        let other_path = temp_dir.path().join("other.db");
+        let result = handler
+            .switch_database_tool(ConnectRequest {
+                path: other_path.display().to_string(),
+                create_if_missing: true,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert!(result.previous_path.unwrap().ends_with("test.db"));
+        assert!(result.connection.path.ends_with("other.db"));
+
+        // A failed switch keeps the existing connection
+        let missing_path = temp_dir.path().join("missing.db");
+        let failed = handler
+            .switch_database_tool(ConnectRequest {
+                path: missing_path.display().to_string(),
+                create_if_missing: false,
+                ..Default::default()
+            })
+            .await;
+        assert!(failed.is_err());
+
+        let health = handler.health_check_tool().await.unwrap();
+        assert!(health.database_path.unwrap().ends_with("other.db"));
+    }
+
+    #[tokio::test]
+    async fn test_list_attached_databases() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        let result = handler.list_attached_databases_tool().await.unwrap();
+        assert_eq!(result.total_count, 1);
+        assert_eq!(result.databases[0].name, "main");
+        assert!(
+            result.databases[0]
+                .file
+                .as_ref()
+                .unwrap()
+                .ends_with("test.db")
+        );
+
+        let health = handler.health_check_tool().await.unwrap();
+        assert_eq!(health.attached_databases.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_query_typed_mode() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        let create_req = CreateTableRequest {
+            table_name: "typed_test".to_string(),
+            columns: "id INTEGER PRIMARY KEY, active BOOLEAN, meta JSON".to_string(),
+            if_not_exists: true,
+            ..Default::default()
+        };
+        handler.create_table_tool(create_req).await.unwrap();
+
+        let insert_req = QueryRequest {
+            sql: "INSERT INTO typed_test (active, meta) VALUES (1, '{\"tags\": [\"a\"]}')"
+                .to_string(),
+            ..Default::default()
+        };
+        handler.query_tool(insert_req).await.unwrap();
+
+        let select_req = QueryRequest {
+            sql: "SELECT active, meta, active + 0 FROM typed_test".to_string(),
+            typed: true,
+            ..Default::default()
+        };
+        let data = handler.query_tool(select_req).await.unwrap().data.unwrap();
+        assert_eq!(data[0][0], serde_json::json!(true));
+        assert_eq!(data[0][1], serde_json::json!({"tags": ["a"]}));
+        // Expressions have no declared type and are left alone
+        assert_eq!(data[0][2], serde_json::json!(1));
+
+        let untyped_req = QueryRequest {
+            sql: "SELECT active FROM typed_test".to_string(),
+            ..Default::default()
+        };
+        let data = handler.query_tool(untyped_req).await.unwrap().data.unwrap();
+        assert_eq!(data[0][0], serde_json::json!(1));
+    }
+
+    #[tokio::test]
+    async fn test_query_scalar() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        let result = handler
+            .query_scalar_tool(QueryRequest {
+                sql: "SELECT ? * 2 AS doubled".to_string(),
+                parameters: vec![serde_json::json!(21)],
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(result.column, "doubled");
+        assert_eq!(result.value, serde_json::json!(42));
+
+        let two_columns = handler
+            .query_scalar_tool(QueryRequest {
+                sql: "SELECT 1, 2".to_string(),
+                ..Default::default()
+            })
+            .await;
+        assert!(two_columns.is_err());
+
+        let no_rows = handler
+            .query_scalar_tool(QueryRequest {
+                sql: "SELECT 1 WHERE 0".to_string(),
+                ..Default::default()
+            })
+            .await;
+        assert!(no_rows.is_err());
+
+        let write = handler
+            .query_scalar_tool(QueryRequest {
+                sql: "CREATE TABLE nope (id INTEGER)".to_string(),
+                ..Default::default()
+            })
+            .await;
+        assert!(write.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_query_row() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        handler
+            .query_tool(QueryRequest {
+                sql: "CREATE TABLE people (id INTEGER PRIMARY KEY, name TEXT)".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        handler
+            .query_tool(QueryRequest {
+                sql: "INSERT INTO people (name) VALUES ('Ada'), ('Grace')".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let result = handler
+            .query_row_tool(QueryRowRequest {
+                sql: "SELECT id, name FROM people WHERE id = ?".to_string(),
+                parameters: vec![serde_json::json!(2)],
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let row = result.row.unwrap();
+        assert_eq!(row["id"], serde_json::json!(2));
+        assert_eq!(row["name"], serde_json::json!("Grace"));
+
+        let missing = handler
+            .query_row_tool(QueryRowRequest {
+                sql: "SELECT * FROM people WHERE id = 99".to_string(),
+                ..Default::default()
+            })
+            .await;
+        assert!(missing.is_err());
+
+        let allowed_missing = handler
+            .query_row_tool(QueryRowRequest {
+                sql: "SELECT * FROM people WHERE id = 99".to_string(),
+                allow_none: true,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert!(allowed_missing.row.is_none());
+
+        let many = handler
+            .query_row_tool(QueryRowRequest {
+                sql: "SELECT * FROM people".to_string(),
+                allow_none: true,
+                ..Default::default()
+            })
+            .await;
+        assert!(many.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_query_with_cte() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        handler
+            .query_tool(QueryRequest {
+                sql: "CREATE TABLE nums (n INTEGER)".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        // WITH ... SELECT returns rows.
+        let result = handler
+            .query_tool(QueryRequest {
+                sql: "WITH RECURSIVE seq(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM seq WHERE n < 3) SELECT n FROM seq"
+                    .to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let data = result.data.unwrap();
+        assert_eq!(data.len(), 3);
+        assert_eq!(data[2][0], serde_json::json!(3));
+
+        // WITH ... INSERT is executed as a write.
+        let result = handler
+            .query_tool(QueryRequest {
+                sql: "WITH src(n) AS (SELECT 10 UNION ALL SELECT 20) INSERT INTO nums SELECT n FROM src"
+                    .to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(result.rows_affected, Some(2));
+        assert!(result.data.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_query_values() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        let result = handler
+            .query_tool(QueryRequest {
+                sql: "VALUES (1, 'a'), (2, 'b')".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let data = result.data.unwrap();
+        assert_eq!(data.len(), 2);
+        assert_eq!(data[1][1], serde_json::json!("b"));
+    }
+
+    #[tokio::test]
+    async fn test_query_leading_comments() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        handler
+            .query_tool(QueryRequest {
+                sql: "-- schema\nCREATE TABLE notes (body TEXT)".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let result = handler
+            .query_tool(QueryRequest {
+                sql: "/* seed */ INSERT INTO notes VALUES ('hi')".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(result.rows_affected, Some(1));
+        assert!(result.data.is_none());
+
+        let result = handler
+            .query_tool(QueryRequest {
+                sql: "-- fetch\n/* all */ SELECT body FROM notes".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(result.data.unwrap(), vec![vec![serde_json::json!("hi")]]);
+
+        let result = handler
+            .query_tool(QueryRequest {
+                sql: "/* sneaky */ ATTACH DATABASE 'x.db' AS x".to_string(),
+                ..Default::default()
+            })
+            .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_prepared_statements() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        handler
+            .query_tool(QueryRequest {
+                sql: "CREATE TABLE events (id INTEGER PRIMARY KEY, name TEXT)".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let prepared = handler
+            .prepare_statement_tool(PrepareStatementRequest {
+                sql: "INSERT INTO events (name) VALUES (?)".to_string(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(prepared.parameter_count, 1);
+
+        for name in ["start", "stop"] {
+            let result = handler
+                .execute_prepared_tool(ExecutePreparedRequest {
+                    statement_id: prepared.statement_id.clone(),
+                    parameters: vec![serde_json::json!(name)],
+                })
+                .await
+                .unwrap();
+            assert_eq!(result.rows_affected, Some(1));
+        }
+        // use_cache is off, yet the registered statement stays prepared on the connection
+        {
+            let guard = handler.current_db.lock().await;
+            let conn = guard.as_ref().unwrap();
+            let live =
+                unsafe { rusqlite::ffi::sqlite3_next_stmt(conn.handle(), std::ptr::null_mut()) };
+            assert!(!live.is_null());
+        }
+
+        let count = handler
+            .query_scalar_tool(QueryRequest {
+                sql: "SELECT COUNT(*) FROM events".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(count.value, serde_json::json!(2));
+
+        handler
+            .finalize_statement_tool(FinalizeStatementRequest {
+                statement_id: prepared.statement_id.clone(),
+            })
+            .await
+            .unwrap();
+        let result = handler
+            .execute_prepared_tool(ExecutePreparedRequest {
+                statement_id: prepared.statement_id,
+                parameters: vec![serde_json::json!("late")],
+            })
+            .await;
+        assert!(matches!(result, Err(UniSqliteError::QueryFailed(_))));
+    }
+
+    #[tokio::test]
+    async fn test_cross_query() {
+        let (handler, temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        handler
+            .query_tool(QueryRequest {
+                sql: "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT)".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        handler
+            .query_tool(QueryRequest {
+                sql: "INSERT INTO users VALUES (1, 'Ada'), (2, 'Grace')".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let other_path = temp_dir.path().join("orders.db");
+        {
+            let other = Connection::open(&other_path).unwrap();
+            other
+                .execute_batch(
+                    "CREATE TABLE orders (user_id INTEGER, total INTEGER);
+                     INSERT INTO orders VALUES (1, 10), (1, 5), (2, 7);",
+                )
+                .unwrap();
+        }
+
+        let result = handler
+            .cross_query_tool(CrossQueryRequest {
+                databases: vec![CrossQueryDatabase {
+                    alias: "sales".to_string(),
+                    path: other_path.to_string_lossy().to_string(),
+                }],
+                sql: "SELECT u.name, SUM(o.total) FROM users u JOIN sales.orders o ON o.user_id = u.id GROUP BY u.name ORDER BY u.name"
+                    .to_string(),
+                parameters: vec![],
+                typed: false,
+            })
+            .await
+            .unwrap();
+        assert_eq!(
+            result.data.unwrap(),
+            vec![
+                vec![serde_json::json!("Ada"), serde_json::json!(15)],
+                vec![serde_json::json!("Grace"), serde_json::json!(7)],
+            ]
+        );
+
+        // A failing query still detaches.
+        let result = handler
+            .cross_query_tool(CrossQueryRequest {
+                databases: vec![CrossQueryDatabase {
+                    alias: "sales".to_string(),
+                    path: other_path.to_string_lossy().to_string(),
+                }],
+                sql: "SELECT * FROM sales.missing".to_string(),
+                parameters: vec![],
+                typed: false,
+            })
+            .await;
+        assert!(result.is_err());
+
+        let attached = handler.list_attached_databases_tool().await.unwrap();
+        assert_eq!(attached.total_count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_generate_schema_report() {
+        let (handler, temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        handler
+            .query_tool(QueryRequest {
+                sql: "CREATE TABLE authors (id INTEGER PRIMARY KEY, name TEXT NOT NULL)"
+                    .to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        handler
+            .query_tool(QueryRequest {
+                sql: "CREATE TABLE books (id INTEGER PRIMARY KEY, author_id INTEGER REFERENCES authors(id), title TEXT DEFAULT 'untitled')"
+                    .to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        handler
+            .query_tool(QueryRequest {
+                sql: "CREATE INDEX idx_books_author ON books(author_id)".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let report_path = temp_dir.path().join("schema.md");
+        let result = handler
+            .generate_schema_report_tool(GenerateSchemaReportRequest {
+                output_path: Some(report_path.display().to_string()),
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(result.table_count, 2);
+        assert!(result.markdown.contains("## authors"));
+        assert!(result.markdown.contains("| name | TEXT | yes |  | no |"));
+        assert!(result.markdown.contains("- idx_books_author"));
+        assert!(result.markdown.contains("- author_id → authors.id"));
+        assert_eq!(fs::read_to_string(&report_path).unwrap(), result.markdown);
+
+        let wrong_extension = handler
+            .generate_schema_report_tool(GenerateSchemaReportRequest {
+                output_path: Some(temp_dir.path().join("schema.sh").display().to_string()),
+            })
+            .await;
+        assert!(matches!(
+            wrong_extension,
+            Err(UniSqliteError::InvalidPath(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_export_er_diagram() {
+        let (handler, temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        handler
+            .query_tool(QueryRequest {
+                sql: "CREATE TABLE authors (id INTEGER PRIMARY KEY, name VARCHAR(50))".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        handler
+            .query_tool(QueryRequest {
+                sql: "CREATE TABLE books (id INTEGER PRIMARY KEY, author_id INTEGER REFERENCES authors(id))"
+                    .to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let mermaid = handler
+            .export_er_diagram_tool(ExportErDiagramRequest {
+                format: DiagramFormat::Mermaid,
+                output_path: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(mermaid.table_count, 2);
+        assert_eq!(mermaid.relationship_count, 1);
+        assert!(mermaid.diagram.starts_with("erDiagram"));
+        assert!(mermaid.diagram.contains("VARCHAR_50_ name"));
+        assert!(
+            mermaid
+                .diagram
+                .contains("authors ||--o{ books : \"author_id\"")
+        );
+
+        let dot = handler
+            .export_er_diagram_tool(ExportErDiagramRequest {
+                format: DiagramFormat::Dot,
+                output_path: None,
+            })
+            .await
+            .unwrap();
+        assert!(dot.diagram.starts_with("digraph schema {"));
+        assert!(
+            dot.diagram
+                .contains("\"books\" -> \"authors\" [label=\"author_id\"];")
+        );
+
+        let dot_path = temp_dir.path().join("schema.dot");
+        handler
+            .export_er_diagram_tool(ExportErDiagramRequest {
+                format: DiagramFormat::Dot,
+                output_path: Some(dot_path.display().to_string()),
+            })
+            .await
+            .unwrap();
+        assert_eq!(fs::read_to_string(&dot_path).unwrap(), dot.diagram);
+
+        // The extension has to match the format
+        let mismatched = handler
+            .export_er_diagram_tool(ExportErDiagramRequest {
+                format: DiagramFormat::Mermaid,
+                output_path: Some(temp_dir.path().join("schema.dot").display().to_string()),
+            })
+            .await;
+        assert!(matches!(mismatched, Err(UniSqliteError::InvalidPath(_))));
+    }
+
+    #[tokio::test]
+    async fn test_find_orphans() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        for sql in [
+            "PRAGMA foreign_keys = OFF",
+            "CREATE TABLE authors (id INTEGER PRIMARY KEY, name TEXT)",
+            "CREATE TABLE books (id INTEGER PRIMARY KEY, author_id INTEGER REFERENCES authors, title TEXT)",
+            "INSERT INTO authors VALUES (1, 'Ada')",
+            "INSERT INTO books VALUES (1, 1, 'ok'), (2, 7, 'orphan'), (3, NULL, 'no author')",
+        ] {
+            handler
+                .query_tool(QueryRequest {
+                    sql: sql.to_string(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
+
+        let result = handler
+            .find_orphans_tool(FindOrphansRequest {
+                table_name: "books".to_string(),
+                limit: None,
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(result.total_orphans, 1);
+        let relationship = &result.relationships[0];
+        assert_eq!(relationship.parent_table, "authors");
+        assert_eq!(relationship.from_columns, vec!["author_id"]);
+        assert_eq!(relationship.to_columns, vec!["id"]);
+        assert_eq!(relationship.rows[0][2], serde_json::json!("orphan"));
+
+        // The count covers every orphan even when the sample is cut short
+        handler
+            .query_tool(QueryRequest {
+                sql: "INSERT INTO books VALUES (4, 9, 'orphan too')".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let result = handler
+            .find_orphans_tool(FindOrphansRequest {
+                table_name: "books".to_string(),
+                limit: Some(1),
+            })
+            .await
+            .unwrap();
+        assert_eq!(result.total_orphans, 2);
+        assert_eq!(result.relationships[0].orphan_count, 2);
+        assert_eq!(result.relationships[0].rows.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_foreign_key_check() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        for sql in [
+            "PRAGMA foreign_keys = OFF",
+            "CREATE TABLE authors (id INTEGER PRIMARY KEY)",
+            "CREATE TABLE books (id INTEGER PRIMARY KEY, author_id INTEGER REFERENCES authors(id))",
+            "INSERT INTO authors VALUES (1)",
+            "INSERT INTO books VALUES (10, 1), (11, 2)",
+        ] {
+            handler
+                .query_tool(QueryRequest {
+                    sql: sql.to_string(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
+
+        let result = handler
+            .foreign_key_check_tool(ForeignKeyCheckRequest {
+                table_name: Some("books".to_string()),
+            })
+            .await
+            .unwrap();
+        assert_eq!(result.total_count, 1);
+        let violation = &result.violations[0];
+        assert_eq!(violation.table, "books");
+        assert_eq!(violation.rowid, Some(11));
+        assert_eq!(violation.parent, "authors");
+        assert_eq!(violation.fk_index, 0);
+
+        let result = handler
+            .foreign_key_check_tool(ForeignKeyCheckRequest {
+                table_name: Some("authors".to_string()),
+            })
+            .await
+            .unwrap();
+        assert_eq!(result.total_count, 0);
+    }
+
+    #[tokio::test]
+    async fn test_adversarial_identifiers() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        assert_eq!(SqliteHandler::quote_ident("plain").unwrap(), "\"plain\"");
+        assert_eq!(SqliteHandler::quote_ident("a\"b").unwrap(), "\"a\"\"b\"");
+        assert!(SqliteHandler::quote_ident("").is_err());
+
+        for table_name in ["odd]name", "quote\"name", "x\"; DROP TABLE keep; --"] {
+            handler
+                .create_table_tool(CreateTableRequest {
+                    table_name: table_name.to_string(),
+                    columns: "id INTEGER PRIMARY KEY, \"we]ird\" TEXT".to_string(),
+                    if_not_exists: false,
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+
+            let described = handler
+                .describe_table_tool(DescribeTableRequest {
+                    table_name: table_name.to_string(),
+                })
+                .await
+                .unwrap();
+            assert_eq!(described.columns.len(), 2);
+
+            let inserted = handler
+                .batch_insert_tool(BatchInsertRequest {
+                    table_name: table_name.to_string(),
+                    columns: vec!["we]ird".to_string()],
+                    rows: vec![vec![serde_json::json!("v")]],
+                    replace_on_conflict: false,
+                    records: vec![],
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+            assert_eq!(inserted.rows_inserted, 1);
+        }
+
+        let tables = handler.list_tables_tool().await.unwrap();
+        assert_eq!(tables.tables.len(), 3);
+        assert!(tables.tables.iter().all(|t| t.row_count == Some(1)));
+    }
+
+    #[tokio::test]
+    async fn test_create_table_rejects_injected_columns() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        for columns in [
+            "x TEXT); DROP TABLE keep; --",
+            "x TEXT) AS SELECT 1",
+            "x TEXT -- trailing comment",
+            "x TEXT DEFAULT 'open",
+            "",
+        ] {
+            let result = handler
+                .create_table_tool(CreateTableRequest {
+                    table_name: "victim".to_string(),
+                    columns: columns.to_string(),
+                    if_not_exists: false,
+                    ..Default::default()
+                })
+                .await;
+            assert!(result.is_err(), "accepted {columns:?}");
+        }
+
+        // Quoted text and nested parentheses are fine.
+        handler
+            .create_table_tool(CreateTableRequest {
+                table_name: "ok".to_string(),
+                columns: "id INTEGER PRIMARY KEY, note TEXT DEFAULT 'a; b -- c', qty INTEGER CHECK (qty > (0))"
+                    .to_string(),
+                if_not_exists: false,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let tables = handler.list_tables_tool().await.unwrap();
+        assert_eq!(tables.tables.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_query_timeout() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        let result = handler
+            .query_tool(QueryRequest {
+                sql: "WITH RECURSIVE r(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM r) SELECT COUNT(*) FROM r"
+                    .to_string(),
+                timeout_ms: Some(50),
+                ..Default::default()
+            })
+            .await;
+        assert!(matches!(result, Err(UniSqliteError::Timeout(_))));
+
+        let error: rmcp::ErrorData = result.unwrap_err().into();
+        assert_eq!(error.data, Some(serde_json::json!({ "retryable": true })));
+
+        // The connection is still usable afterwards, and fast queries are unaffected.
+        let result = handler
+            .query_tool(QueryRequest {
+                sql: "SELECT 1".to_string(),
+                timeout_ms: Some(5_000),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(result.data.unwrap(), vec![vec![serde_json::json!(1)]]);
+    }
+
+    #[tokio::test]
+    async fn test_error_codes() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        handler
+            .query_tool(QueryRequest {
+                sql: "CREATE TABLE uniq (id INTEGER PRIMARY KEY)".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        handler
+            .query_tool(QueryRequest {
+                sql: "INSERT INTO uniq VALUES (1)".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let code = |err: UniSqliteError| rmcp::ErrorData::from(err).code;
+
+        for sql in [
+            "INSERT INTO uniq VALUES (1)",
+            "SELECT * FROM missing_table",
+            "SELECT missing_column FROM uniq",
+            "SELECT FROM WHERE",
+        ] {
+            let err = handler
+                .query_tool(QueryRequest {
+                    sql: sql.to_string(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap_err();
+            assert_eq!(code(err), rmcp::model::ErrorCode::INVALID_PARAMS, "{sql}");
+        }
+
+        let io = UniSqliteError::Io(std::io::Error::other("disk gone"));
+        assert_eq!(code(io), rmcp::model::ErrorCode::INTERNAL_ERROR);
+
+        let err = SqliteHandler::new().list_tables_tool().await.unwrap_err();
+        assert!(matches!(err, UniSqliteError::NotConnected));
+        let error = rmcp::ErrorData::from(err);
+        assert_eq!(error.code, rmcp::model::ErrorCode(-32001));
+        assert_eq!(
+            error.data,
+            Some(serde_json::json!({ "not_connected": true }))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_busy_retry() {
+        let (handler, _temp_dir, db_path) = create_test_handler_with_db().await;
+
+        handler
+            .connect_tool(ConnectRequest {
+                path: db_path.to_string_lossy().to_string(),
+                busy_retry: Some(BusyRetryPolicy {
+                    max_retries: Some(20),
+                    backoff_ms: Some(10),
+                }),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        handler
+            .query_tool(QueryRequest {
+                sql: "CREATE TABLE log (msg TEXT)".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        // Fail immediately on a lock so the retry loop is what waits it out.
+        handler
+            .query_tool(QueryRequest {
+                sql: "PRAGMA busy_timeout = 0".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let other = Connection::open(&db_path).unwrap();
+        other.execute_batch("BEGIN EXCLUSIVE").unwrap();
+        let releaser = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            other.execute_batch("COMMIT").unwrap();
+        });
+        // The test runtime has a single thread; the ticker only advances if the retry
+        // backoff leaves it free.
+        let ticks = Arc::new(std::sync::atomic::AtomicU32::new(0));
+        let ticker = tokio::spawn({
+            let ticks = ticks.clone();
+            async move {
+                loop {
+                    tokio::time::sleep(Duration::from_millis(5)).await;
+                    ticks.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                }
+            }
+        });
+
+        let result = handler
+            .query_tool(QueryRequest {
+                sql: "INSERT INTO log VALUES ('hello')".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        releaser.join().unwrap();
+        ticker.abort();
+
+        assert_eq!(result.rows_affected, Some(1));
+        assert!(result.busy_retries > 0);
+        assert!(ticks.load(std::sync::atomic::Ordering::Relaxed) > 0);
+    }
+
+    #[tokio::test]
+    async fn test_idle_auto_close() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        handler
+            .query_tool(QueryRequest {
+                sql: "CREATE TABLE kv (k TEXT PRIMARY KEY, v TEXT)".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let prepared = handler
+            .prepare_statement_tool(PrepareStatementRequest {
+                sql: "INSERT INTO kv VALUES (?, ?)".to_string(),
+            })
+            .await
+            .unwrap();
+
+        let monitor = handler.spawn_idle_monitor(Duration::from_millis(50));
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(handler.current_db.lock().await.is_none());
+
+        // The next call reopens the same database transparently.
+        handler
+            .execute_prepared_tool(ExecutePreparedRequest {
+                statement_id: prepared.statement_id,
+                parameters: vec![serde_json::json!("a"), serde_json::json!("1")],
+            })
+            .await
+            .unwrap();
+        let count = handler
+            .query_scalar_tool(QueryRequest {
+                sql: "SELECT COUNT(*) FROM kv".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(count.value, serde_json::json!(1));
+
+        monitor.abort();
+    }
+
+    #[tokio::test]
+    async fn test_blob_read_write() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        handler
+            .query_tool(QueryRequest {
+                sql: "CREATE TABLE files (id INTEGER PRIMARY KEY, body BLOB)".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        handler
+            .query_tool(QueryRequest {
+                sql: "INSERT INTO files VALUES (1, zeroblob(8))".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let written = handler
+            .blob_write_tool(BlobWriteRequest {
+                table_name: "files".to_string(),
+                column: "body".to_string(),
+                rowid: 1,
+                offset: 2,
+                data_base64: BASE64_STANDARD.encode(b"abcd"),
+            })
+            .await
+            .unwrap();
+        assert_eq!(written.bytes_written, 4);
+        assert_eq!(written.blob_size, 8);
+
+        let overflow = handler
+            .blob_write_tool(BlobWriteRequest {
+                table_name: "files".to_string(),
+                column: "body".to_string(),
+                rowid: 1,
+                offset: usize::MAX,
+                data_base64: BASE64_STANDARD.encode(b"abcd"),
+            })
+            .await;
+        assert!(matches!(overflow, Err(UniSqliteError::QueryFailed(_))));
+
+        let read = handler
+            .blob_read_tool(BlobReadRequest {
+                table_name: "files".to_string(),
+                column: "body".to_string(),
+                rowid: 1,
+                offset: 1,
+                length: Some(5),
+            })
+            .await
+            .unwrap();
+        assert_eq!(read.bytes_read, 5);
+        assert_eq!(BASE64_STANDARD.decode(read.data_base64).unwrap(), b"\0abcd");
+
+        let too_long = handler
+            .blob_write_tool(BlobWriteRequest {
+                table_name: "files".to_string(),
+                column: "body".to_string(),
+                rowid: 1,
+                offset: 6,
+                data_base64: BASE64_STANDARD.encode(b"xyz"),
+            })
+            .await;
+        assert!(too_long.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_get_table_ddl_with_indexes() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        for sql in [
+            "CREATE TABLE items (id INTEGER PRIMARY KEY, sku TEXT UNIQUE, qty INTEGER)",
+            "CREATE INDEX idx_items_qty ON items(qty)",
+            "CREATE TABLE audit (msg TEXT)",
+            "CREATE TRIGGER items_audit AFTER INSERT ON items BEGIN INSERT INTO audit VALUES (new.sku); END",
+        ] {
+            handler
+                .query_tool(QueryRequest {
+                    sql: sql.to_string(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
+
+        let ddl = handler
+            .get_table_ddl_with_indexes_tool(GetTableDdlRequest {
+                table_name: "items".to_string(),
+            })
+            .await
+            .unwrap();
+
+        assert!(ddl.table_sql.starts_with("CREATE TABLE items"));
+        assert_eq!(ddl.indexes.len(), 1);
+        assert_eq!(ddl.indexes[0].name, "idx_items_qty");
+        assert_eq!(ddl.triggers.len(), 1);
+        assert_eq!(ddl.triggers[0].name, "items_audit");
+
+        // The script recreates the table elsewhere.
+        let copy = Connection::open_in_memory().unwrap();
+        copy.execute_batch("CREATE TABLE audit (msg TEXT);")
+            .unwrap();
+        copy.execute_batch(&ddl.script).unwrap();
+        let objects: i64 = copy
+            .query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE tbl_name = 'items'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(objects, 4);
+    }
+
+    #[tokio::test]
+    async fn test_clone_table_structure() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        for sql in [
+            "CREATE TABLE items (id INTEGER, sku TEXT, qty INTEGER)",
+            "CREATE UNIQUE INDEX \"items sku\" ON items (sku)",
+            "CREATE INDEX IF NOT EXISTS idx_items_qty ON [items](qty DESC) WHERE qty > 0",
+            "INSERT INTO items VALUES (1, 'a', 3)",
+        ] {
+            handler
+                .query_tool(QueryRequest {
+                    sql: sql.to_string(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
+
+        let result = handler
+            .clone_table_structure_tool(CloneTableStructureRequest {
+                source_table: "items".to_string(),
+                new_table: "items_staging".to_string(),
+                copy_indexes: true,
+            })
+            .await
+            .unwrap();
+        assert_eq!(
+            result.indexes,
+            vec!["items_staging_items sku", "items_staging_idx_items_qty"]
+        );
+
+        let described = handler
+            .describe_table_tool(DescribeTableRequest {
+                table_name: "items_staging".to_string(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(described.columns.len(), 3);
+        assert_eq!(described.indexes.len(), 2);
+
+        let count = handler
+            .query_scalar_tool(QueryRequest {
+                sql: "SELECT COUNT(*) FROM items_staging".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(count.value, serde_json::json!(0));
+
+        let partial = handler
+            .get_create_sql_tool(GetCreateSqlRequest {
+                name: "items_staging_idx_items_qty".to_string(),
+            })
+            .await
+            .unwrap();
+        assert!(partial.sql.ends_with("(qty DESC) WHERE qty > 0"));
+    }
+
+    #[tokio::test]
+    async fn test_swap_tables() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        for sql in [
+            "CREATE TABLE live (v TEXT)",
+            "CREATE TABLE staging (v TEXT)",
+            "INSERT INTO live VALUES ('old')",
+            "INSERT INTO staging VALUES ('new')",
+            "CREATE VIEW live_view AS SELECT v FROM live",
+        ] {
+            handler
+                .query_tool(QueryRequest {
+                    sql: sql.to_string(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
+
+        handler
+            .swap_tables_tool(SwapTablesRequest {
+                table_a: "live".to_string(),
+                table_b: "staging".to_string(),
+            })
+            .await
+            .unwrap();
+
+        let value = |sql: &str| {
+            let handler = handler.clone();
+            let sql = sql.to_string();
+            async move {
+                handler
+                    .query_scalar_tool(QueryRequest {
+                        sql,
+                        ..Default::default()
+                    })
+                    .await
+                    .unwrap()
+                    .value
+            }
+        };
+        assert_eq!(value("SELECT v FROM live").await, serde_json::json!("new"));
+        assert_eq!(
+            value("SELECT v FROM staging").await,
+            serde_json::json!("old")
+        );
+        // The view follows the name, not the old table.
+        assert_eq!(
+            value("SELECT v FROM live_view").await,
+            serde_json::json!("new")
+        );
+
+        let missing = handler
+            .swap_tables_tool(SwapTablesRequest {
+                table_a: "live".to_string(),
+                table_b: "nope".to_string(),
+            })
+            .await;
+        assert!(missing.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_export_table_to_db() {
+        let (handler, temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        for sql in [
+            "CREATE TABLE keep (id INTEGER PRIMARY KEY, name TEXT)",
+            "CREATE INDEX idx_keep_name ON keep(name)",
+            "CREATE TABLE other (x INTEGER)",
+            "INSERT INTO keep (name) VALUES ('a'), ('b'), ('c')",
+        ] {
+            handler
+                .query_tool(QueryRequest {
+                    sql: sql.to_string(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
+
+        let dest_path = temp_dir.path().join("keep_only.db");
+        let result = handler
+            .export_table_to_db_tool(ExportTableToDbRequest {
+                table_name: "keep".to_string(),
+                dest_path: dest_path.display().to_string(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(result.rows_copied, 3);
+        assert_eq!(result.indexes_created, 1);
+
+        let dest = Connection::open(&dest_path).unwrap();
+        let names: Vec<String> = dest
+            .prepare("SELECT name FROM sqlite_master ORDER BY name")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(names, vec!["idx_keep_name", "keep"]);
+        let rows: i64 = dest
+            .query_row("SELECT COUNT(*) FROM keep", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(rows, 3);
+
+        // The destination is no longer attached, and existing files are not overwritten.
+        let attached = handler.list_attached_databases_tool().await.unwrap();
+        assert_eq!(attached.total_count, 1);
+        let again = handler
+            .export_table_to_db_tool(ExportTableToDbRequest {
+                table_name: "keep".to_string(),
+                dest_path: dest_path.display().to_string(),
+            })
+            .await;
+        assert!(again.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_compare_tables() {
+        let (handler, temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        for sql in [
+            "CREATE TABLE old (id INTEGER PRIMARY KEY, name TEXT, score INTEGER)",
+            "CREATE TABLE new (id INTEGER PRIMARY KEY, name TEXT, score INTEGER)",
+            "INSERT INTO old VALUES (1, 'a', 10), (2, 'b', 20), (3, 'c', NULL)",
+            "INSERT INTO new VALUES (2, 'b', 25), (3, 'c', NULL), (4, 'd', 40)",
+        ] {
+            handler
+                .query_tool(QueryRequest {
+                    sql: sql.to_string(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
+
+        let result = handler
+            .compare_tables_tool(CompareTablesRequest {
+                table_a: "old".to_string(),
+                table_b: "new".to_string(),
+                database_b: None,
+                key_columns: vec!["id".to_string()],
+                sample_limit: None,
+            })
+            .await
+            .unwrap();
+        assert!(!result.identical);
+        assert_eq!(result.only_in_a_count, 1);
+        assert_eq!(result.only_in_b_count, 1);
+        assert_eq!(result.differing_count, 1);
+        assert_eq!(result.only_in_a.rows[0][0], serde_json::json!(1));
+        assert_eq!(result.only_in_b.rows[0][0], serde_json::json!(4));
+        assert_eq!(
+            result.differing.columns,
+            vec!["id", "name_a", "name_b", "score_a", "score_b"]
+        );
+        assert_eq!(
+            result.differing.rows[0],
+            vec![
+                serde_json::json!(2),
+                serde_json::json!("b"),
+                serde_json::json!("b"),
+                serde_json::json!(20),
+                serde_json::json!(25)
+            ]
+        );
+
+        // Table B can live in another database file.
+        let other_path = temp_dir.path().join("other.db");
+        {
+            let other = Connection::open(&other_path).unwrap();
+            other
+                .execute_batch(
+                    "CREATE TABLE old (id INTEGER PRIMARY KEY, name TEXT, score INTEGER);
+                     INSERT INTO old VALUES (1, 'a', 10), (2, 'b', 20), (3, 'c', NULL);",
+                )
+                .unwrap();
+        }
+        let result = handler
+            .compare_tables_tool(CompareTablesRequest {
+                table_a: "old".to_string(),
+                table_b: "old".to_string(),
+                database_b: Some(other_path.display().to_string()),
+                key_columns: vec!["id".to_string()],
+                sample_limit: Some(5),
+            })
+            .await
+            .unwrap();
+        assert!(result.identical);
+        let attached = handler.list_attached_databases_tool().await.unwrap();
+        assert_eq!(attached.total_count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_random_sample() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        handler
+            .query_tool(QueryRequest {
+                sql: "CREATE TABLE nums (n INTEGER)".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        handler
+            .query_tool(QueryRequest {
+                sql: "WITH RECURSIVE r(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM r WHERE n < 100) INSERT INTO nums SELECT n FROM r"
+                    .to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let sample = |seed: i64| {
+            let handler = handler.clone();
+            async move {
+                handler
+                    .random_sample_tool(RandomSampleRequest {
+                        table_name: "nums".to_string(),
+                        count: 10,
+                        seed,
+                    })
+                    .await
                     .unwrap()
-                    .as_object()
+                    .rows
+            }
+        };
+
+        let first = sample(42).await;
+        assert_eq!(first.len(), 10);
+        assert_eq!(first, sample(42).await);
+        assert_ne!(first, sample(7).await);
+        // Not simply the first rows of the table.
+        assert_ne!(
+            first,
+            (1..=10)
+                .map(|n| vec![serde_json::json!(n)])
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_keyset_page() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        for sql in [
+            "CREATE TABLE events (day TEXT, seq INTEGER, name TEXT)",
+            "INSERT INTO events VALUES ('d1', 1, 'a'), ('d1', 2, 'b'), ('d2', 1, 'c'), ('d2', 2, 'd'), ('d3', 1, 'e')",
+        ] {
+            handler
+                .query_tool(QueryRequest {
+                    sql: sql.to_string(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
+
+        let mut names = Vec::new();
+        let mut cursor = None;
+        let mut pages = 0;
+        loop {
+            let page = handler
+                .keyset_page_tool(KeysetPageRequest {
+                    table_name: "events".to_string(),
+                    key_columns: vec!["day".to_string(), "seq".to_string()],
+                    last_seen: cursor,
+                    limit: Some(2),
+                })
+                .await
+                .unwrap();
+            pages += 1;
+            names.extend(page.rows.iter().map(|r| r[2].clone()));
+            cursor = page.next_cursor;
+            if cursor.is_none() {
+                break;
+            }
+        }
+
+        assert_eq!(pages, 3);
+        assert_eq!(
+            names,
+            ["a", "b", "c", "d", "e"]
+                .map(|n| serde_json::json!(n))
+                .to_vec()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_count_estimate() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        for sql in [
+            "CREATE TABLE big (id INTEGER PRIMARY KEY, v TEXT)",
+            "CREATE INDEX idx_big_v ON big(v)",
+            "WITH RECURSIVE r(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM r WHERE n < 500) INSERT INTO big SELECT n, 'x' || n FROM r",
+            "DELETE FROM big WHERE id % 2 = 0",
+        ] {
+            handler
+                .query_tool(QueryRequest {
+                    sql: sql.to_string(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
+
+        let estimate = |threshold: i64| {
+            let handler = handler.clone();
+            async move {
+                handler
+                    .count_estimate_tool(CountEstimateRequest {
+                        table_name: "big".to_string(),
+                        exact_threshold: Some(threshold),
+                    })
+                    .await
                     .unwrap()
-                    .clone()
-                    .into(),
-                annotations: None,
-                output_schema: None,
-            },
-            Tool {
-                name: Cow::Borrowed("transaction"),
-                description: Some(Cow::Borrowed("Execute multiple queries in a transaction")),
-                input_schema: serde_json::to_value(
-                    schemars::schema_for!(TransactionRequest).schema,
-                )
-                .unwrap()
-                .as_object()
-                .unwrap()
-                .clone()
-                .into(),
-                annotations: None,
-                output_schema: None,
-            },
-            Tool {
-                name: Cow::Borrowed("create_table"),
-                description: Some(Cow::Borrowed("Create a new table with specified columns")),
-                input_schema: serde_json::to_value(
-                    schemars::schema_for!(CreateTableRequest).schema,
-                )
+            }
+        };
+
+        let exact = estimate(10_000).await;
+        assert_eq!((exact.row_count, exact.approximate), (250, false));
+
+        let range = estimate(0).await;
+        assert!(range.approximate);
+        assert_eq!(range.source, "rowid_range");
+        assert_eq!(range.row_count, 499);
+
+        handler
+            .query_tool(QueryRequest {
+                sql: "ANALYZE".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let stat = estimate(0).await;
+        assert_eq!(stat.source, "sqlite_stat1");
+        assert_eq!(stat.row_count, 250);
+    }
+
+    #[tokio::test]
+    async fn test_table_size_bytes() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        for sql in [
+            "CREATE TABLE docs (id INTEGER PRIMARY KEY, body TEXT UNIQUE)",
+            "CREATE INDEX idx_docs_len ON docs(length(body))",
+            "CREATE TABLE other (x INTEGER)",
+            "WITH RECURSIVE r(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM r WHERE n < 200) INSERT INTO docs (body) SELECT printf('%0500d', n) FROM r",
+        ] {
+            handler
+                .query_tool(QueryRequest {
+                    sql: sql.to_string(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
+
+        let size = handler
+            .table_size_bytes_tool(TableSizeBytesRequest {
+                table_name: "docs".to_string(),
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(size.objects.len(), 3);
+        assert_eq!(size.objects[0].name, "docs");
+        assert!(size.table_bytes > 100_000);
+        assert!(size.index_bytes > 0);
+        assert_eq!(size.total_bytes, size.table_bytes + size.index_bytes);
+        assert!(size.objects.iter().all(|o| o.name != "other"));
+
+        let missing = handler
+            .table_size_bytes_tool(TableSizeBytesRequest {
+                table_name: "missing".to_string(),
+            })
+            .await;
+        assert!(missing.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_export_csv_table_projection() {
+        let (handler, temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        for sql in [
+            "CREATE TABLE people (id INTEGER PRIMARY KEY, name TEXT, \"home town\" TEXT, age INTEGER)",
+            "INSERT INTO people VALUES (1, 'Ada', 'London', 36), (2, 'Grace', 'New York', 85), (3, 'Alan', 'London', 41)",
+        ] {
+            handler
+                .query_tool(QueryRequest {
+                    sql: sql.to_string(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
+
+        let csv_path = temp_dir.path().join("londoners.csv");
+        let result = handler
+            .export_csv_tool(ExportCsvRequest {
+                table_name: Some("people".to_string()),
+                columns: Some(vec!["home town".to_string(), "name".to_string()]),
+                where_clause: Some("\"home town\" = 'London'".to_string()),
+                order_by: Some("age DESC".to_string()),
+                output_path: csv_path.display().to_string(),
+                include_headers: true,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(result.rows_exported, 2);
+        assert_eq!(
+            fs::read_to_string(&csv_path).unwrap(),
+            "home town,name\nLondon,Alan\nLondon,Ada\n"
+        );
+
+        let injected = handler
+            .export_csv_tool(ExportCsvRequest {
+                table_name: Some("people".to_string()),
+                where_clause: Some("1; DROP TABLE people".to_string()),
+                output_path: csv_path.display().to_string(),
+                ..Default::default()
+            })
+            .await;
+        assert!(injected.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_export_csv_compressed() {
+        let (handler, temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        for sql in [
+            "CREATE TABLE gz (id INTEGER PRIMARY KEY, note TEXT)",
+            "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 200) INSERT INTO gz (note) SELECT 'repetitive text ' || i FROM n",
+        ] {
+            handler
+                .query_tool(QueryRequest {
+                    sql: sql.to_string(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
+
+        let csv_path = temp_dir.path().join("gz.csv");
+        let result = handler
+            .export_csv_tool(ExportCsvRequest {
+                table_name: Some("gz".to_string()),
+                output_path: csv_path.display().to_string(),
+                include_headers: true,
+                compress: true,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let gz_path = temp_dir.path().join("gz.csv.gz");
+        assert_eq!(result.output_path, gz_path.display().to_string());
+        assert!(!csv_path.exists());
+        let compressed = result.compressed_bytes.unwrap();
+        assert_eq!(compressed, fs::metadata(&gz_path).unwrap().len());
+        assert!(compressed < result.uncompressed_bytes);
+
+        let mut csv_content = String::new();
+        std::io::Read::read_to_string(
+            &mut flate2::read::GzDecoder::new(fs::File::open(&gz_path).unwrap()),
+            &mut csv_content,
+        )
+        .unwrap();
+        assert_eq!(csv_content.len() as u64, result.uncompressed_bytes);
+        assert!(csv_content.starts_with("id,note\n1,repetitive text 1\n"));
+        assert_eq!(csv_content.lines().count(), 201);
+    }
+
+    #[cfg(feature = "xlsx")]
+    #[tokio::test]
+    async fn test_export_xlsx() {
+        let (handler, temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        for sql in [
+            "CREATE TABLE sales (region TEXT, units INTEGER, price REAL, note TEXT)",
+            "INSERT INTO sales VALUES ('north', 12, 9.5, NULL), ('south', 7, 12.25, 'promo')",
+        ] {
+            handler
+                .query_tool(QueryRequest {
+                    sql: sql.to_string(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
+
+        let bad_ext = handler
+            .export_xlsx_tool(ExportXlsxRequest {
+                table_name: Some("sales".to_string()),
+                output_path: temp_dir.path().join("sales.csv").display().to_string(),
+                ..Default::default()
+            })
+            .await;
+        assert!(matches!(bad_ext, Err(UniSqliteError::InvalidPath(_))));
+
+        let xlsx_path = temp_dir.path().join("sales.xlsx");
+        let result = handler
+            .export_xlsx_tool(ExportXlsxRequest {
+                table_name: Some("sales".to_string()),
+                order_by: Some("region".to_string()),
+                output_path: xlsx_path.display().to_string(),
+                sheet_name: Some("Sales".to_string()),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert!(result.success);
+        assert_eq!(result.rows_exported, 2);
+
+        // XLSX files are zip archives
+        let bytes = fs::read(&xlsx_path).unwrap();
+        assert!(bytes.starts_with(b"PK\x03\x04"));
+    }
+
+    #[tokio::test]
+    async fn test_connection_info() {
+        let (handler, _temp_dir, db_path) = create_test_handler_with_db().await;
+
+        let info = handler.connection_info_tool().await.unwrap();
+        assert_eq!(
+            info.database_path,
+            db_path.canonicalize().unwrap().display().to_string()
+        );
+        assert!(!info.readonly);
+        assert!(info.file_writable);
+        assert!(info.open_flags.contains(&"READ_WRITE".to_string()));
+        assert!(info.open_flags.contains(&"URI".to_string()));
+        assert_eq!(info.journal_mode, "delete");
+        assert_eq!(
+            info.busy_timeout_ms,
+            DEFAULT_BUSY_TIMEOUT.as_millis() as i64
+        );
+
+        handler
+            .query_tool(QueryRequest {
+                sql: "PRAGMA foreign_keys = ON".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert!(
+            handler
+                .connection_info_tool()
+                .await
                 .unwrap()
-                .as_object()
+                .foreign_keys_on
+        );
+    }
+
+    #[tokio::test]
+    async fn test_list_functions() {
+        let (handler, _temp_dir, db_path) = create_test_handler_with_db().await;
+
+        let listed = handler.list_functions_tool().await.unwrap();
+        assert!(listed.functions.is_empty());
+        assert!(listed.capabilities.json1);
+        assert!(listed.capabilities.window_functions);
+
+        handler
+            .connect_tool(ConnectRequest {
+                path: db_path.display().to_string(),
+                enable_uuid_function: true,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        handler
+            .query_tool(QueryRequest {
+                sql: "CREATE TABLE t (x INTEGER)".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        handler
+            .random_sample_tool(RandomSampleRequest {
+                table_name: "t".to_string(),
+                count: 1,
+                seed: 7,
+            })
+            .await
+            .unwrap();
+        handler
+            .random_sample_tool(RandomSampleRequest {
+                table_name: "t".to_string(),
+                count: 1,
+                seed: 7,
+            })
+            .await
+            .unwrap();
+
+        let names: Vec<String> = handler
+            .list_functions_tool()
+            .await
+            .unwrap()
+            .functions
+            .into_iter()
+            .map(|f| f.name)
+            .collect();
+        assert_eq!(names, vec!["uuid", "sample_hash"]);
+    }
+
+    #[tokio::test]
+    async fn test_set_limits() {
+        let (handler, _temp_dir, db_path) = create_test_handler_with_db().await;
+
+        let result = handler
+            .set_limits_tool(SetLimitsRequest {
+                sql_length: Some(64),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let sql_length = result
+            .limits
+            .iter()
+            .find(|l| l.name == "sql_length")
+            .unwrap();
+        assert!(sql_length.previous > 64);
+        assert_eq!(sql_length.current, 64);
+        let expr_depth = result
+            .limits
+            .iter()
+            .find(|l| l.name == "expr_depth")
+            .unwrap();
+        assert_eq!(expr_depth.previous, expr_depth.current);
+
+        let long_sql = format!("SELECT '{}'", "x".repeat(100));
+        let query = |sql: String| {
+            let handler = handler.clone();
+            async move {
+                handler
+                    .query_tool(QueryRequest {
+                        sql,
+                        ..Default::default()
+                    })
+                    .await
+            }
+        };
+        assert!(query(long_sql.clone()).await.is_err());
+
+        // Limits carry over to a reconnect
+        handler
+            .connect_tool(ConnectRequest {
+                path: db_path.display().to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert!(query(long_sql).await.is_err());
+        assert!(query("SELECT 1".to_string()).await.is_ok());
+
+        let negative = handler
+            .set_limits_tool(SetLimitsRequest {
+                attached: Some(-1),
+                ..Default::default()
+            })
+            .await;
+        assert!(matches!(negative, Err(UniSqliteError::QueryFailed(_))));
+    }
+
+    #[tokio::test]
+    async fn test_query_count_only() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        for sql in [
+            "CREATE TABLE items (id INTEGER PRIMARY KEY, price REAL)",
+            "INSERT INTO items (price) VALUES (1.5), (20.0), (35.0), (7.25)",
+        ] {
+            handler
+                .query_tool(QueryRequest {
+                    sql: sql.to_string(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
+
+        let result = handler
+            .query_tool(QueryRequest {
+                sql: "SELECT * FROM items WHERE price > ?;".to_string(),
+                parameters: vec![serde_json::json!(5)],
+                count_only: true,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(result.rows_affected, Some(3));
+        assert!(result.data.is_none());
+        assert!(result.columns.is_none());
+
+        let result = handler
+            .query_tool(QueryRequest {
+                sql: "WITH cheap AS (SELECT * FROM items WHERE price < 10) SELECT * FROM cheap"
+                    .to_string(),
+                count_only: true,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(result.rows_affected, Some(2));
+
+        let result = handler
+            .query_tool(QueryRequest {
+                sql: "DELETE FROM items".to_string(),
+                count_only: true,
+                ..Default::default()
+            })
+            .await;
+        assert!(matches!(result, Err(UniSqliteError::QueryFailed(_))));
+    }
+
+    #[tokio::test]
+    async fn test_query_stable_order() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        for sql in [
+            "CREATE TABLE people (name TEXT, age INTEGER)",
+            "CREATE INDEX idx_people_age ON people (age DESC)",
+            "INSERT INTO people VALUES ('ann', 30), ('bob', 50), ('cy', 40)",
+            "CREATE TABLE kv (k TEXT PRIMARY KEY, v TEXT) WITHOUT ROWID",
+        ] {
+            handler
+                .query_tool(QueryRequest {
+                    sql: sql.to_string(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
+
+        let query = |sql: &str| {
+            let handler = handler.clone();
+            let sql = sql.to_string();
+            async move {
+                handler
+                    .query_tool(QueryRequest {
+                        sql,
+                        stable_order: true,
+                        ..Default::default()
+                    })
+                    .await
+                    .unwrap()
+            }
+        };
+
+        // The index on age would otherwise drive the scan order
+        let result = query("SELECT name FROM people p WHERE age > 20 LIMIT 3").await;
+        assert_eq!(
+            result.data.unwrap(),
+            vec![
+                vec![serde_json::json!("ann")],
+                vec![serde_json::json!("bob")],
+                vec![serde_json::json!("cy")]
+            ]
+        );
+        assert_eq!(
+            result.ordering_note.as_deref(),
+            Some("Ordered by rowid of 'people'")
+        );
+
+        let result = query("SELECT name FROM people ORDER BY age").await;
+        assert!(result.ordering_note.is_none());
+        assert_eq!(result.data.unwrap()[0], vec![serde_json::json!("ann")]);
+
+        for (sql, reason) in [
+            ("SELECT COUNT(*) FROM people", "aggregate"),
+            ("SELECT * FROM kv", "WITHOUT ROWID"),
+            ("SELECT * FROM people, kv", "single-table"),
+        ] {
+            let note = query(sql).await.ordering_note.unwrap();
+            assert!(note.starts_with("stable_order not applied"), "{sql}");
+            assert!(note.contains(reason), "{sql}: {note}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_vacuum() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        for sql in [
+            "CREATE TABLE bulk (payload TEXT)",
+            "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 500) INSERT INTO bulk SELECT printf('%.1000c', 'x') FROM n",
+            "DELETE FROM bulk",
+        ] {
+            handler
+                .query_tool(QueryRequest {
+                    sql: sql.to_string(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
+
+        let result = handler.vacuum_tool().await.unwrap();
+        assert!(result.success);
+        assert!(result.bytes_reclaimed > 0);
+        assert_eq!(
+            result.bytes_reclaimed,
+            result.bytes_before - result.bytes_after
+        );
+
+        // Nothing left to reclaim the second time round
+        assert_eq!(handler.vacuum_tool().await.unwrap().bytes_reclaimed, 0);
+    }
+
+    #[tokio::test]
+    async fn test_dry_run() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        for sql in [
+            "CREATE TABLE stock (sku TEXT PRIMARY KEY, qty INTEGER)",
+            "INSERT INTO stock VALUES ('a', 1), ('b', 0), ('c', 0)",
+        ] {
+            handler
+                .query_tool(QueryRequest {
+                    sql: sql.to_string(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
+        let total = |handler: SqliteHandler| async move {
+            handler
+                .query_scalar_tool(QueryRequest {
+                    sql: "SELECT COUNT(*) || ':' || SUM(qty) FROM stock".to_string(),
+                    ..Default::default()
+                })
+                .await
                 .unwrap()
-                .clone()
-                .into(),
-                annotations: None,
-                output_schema: None,
-            },
-            Tool {
-                name: Cow::Borrowed("list_tables"),
-                description: Some(Cow::Borrowed(
-                    "List all tables in the database with metadata",
-                )),
-                input_schema: serde_json::json!({
-                    "type": "object",
-                    "properties": {},
-                    "additionalProperties": false
+                .value
+        };
+
+        let result = handler
+            .query_tool(QueryRequest {
+                sql: "UPDATE stock SET qty = qty + 10 WHERE qty = 0".to_string(),
+                dry_run: true,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert!(result.dry_run);
+        assert_eq!(result.rows_affected, Some(2));
+
+        let result = handler
+            .delete_rows_tool(DeleteRowsRequest {
+                table_name: "stock".to_string(),
+                where_clause: "qty = ?".to_string(),
+                parameters: vec![serde_json::json!(0)],
+                dry_run: true,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(result.rows_deleted, 2);
+
+        let result = handler
+            .batch_insert_tool(BatchInsertRequest {
+                table_name: "stock".to_string(),
+                columns: vec!["sku".to_string(), "qty".to_string()],
+                rows: vec![vec![serde_json::json!("d"), serde_json::json!(5)]],
+                dry_run: true,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(result.rows_inserted, 1);
+
+        // A failing dry run is rolled back too
+        let result = handler
+            .query_tool(QueryRequest {
+                sql: "INSERT INTO stock VALUES ('e', 1), ('a', 2)".to_string(),
+                dry_run: true,
+                ..Default::default()
+            })
+            .await;
+        assert!(result.is_err());
+
+        assert_eq!(total(handler.clone()).await, serde_json::json!("3:1"));
+    }
+
+    #[tokio::test]
+    async fn test_query_cache() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        let query = |sql: &str, cache: bool| {
+            let handler = handler.clone();
+            let sql = sql.to_string();
+            async move {
+                handler
+                    .query_tool(QueryRequest {
+                        sql,
+                        cache,
+                        ..Default::default()
+                    })
+                    .await
+                    .unwrap()
+            }
+        };
+        query("CREATE TABLE notes (body TEXT)", false).await;
+        query("INSERT INTO notes VALUES ('one')", false).await;
+
+        let select = "SELECT COUNT(*) FROM notes";
+        assert!(!query(select, true).await.cached);
+        let hit = query(select, true).await;
+        assert!(hit.cached);
+        assert_eq!(hit.data.unwrap(), vec![vec![serde_json::json!(1)]]);
+        assert!(!query(select, false).await.cached);
+
+        // Any write invalidates, including ones made by other tools
+        handler
+            .batch_insert_tool(BatchInsertRequest {
+                table_name: "notes".to_string(),
+                columns: vec!["body".to_string()],
+                rows: vec![vec![serde_json::json!("two")]],
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let fresh = query(select, true).await;
+        assert!(!fresh.cached);
+        assert_eq!(fresh.data.unwrap(), vec![vec![serde_json::json!(2)]]);
+
+        query("CREATE TABLE other (x)", false).await;
+        assert!(!query(select, true).await.cached);
+
+        let cleared = handler.clear_query_cache_tool().await.unwrap();
+        assert_eq!(cleared.entries_cleared, 1);
+        assert!(!query(select, true).await.cached);
+    }
+
+    #[tokio::test]
+    async fn test_batch() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        let req: BatchRequest = serde_json::from_value(serde_json::json!({
+            "operations": [
+                {"op": "create_table", "table_name": "tasks", "columns": "id INTEGER PRIMARY KEY, title TEXT UNIQUE, done INTEGER DEFAULT 0"},
+                {"op": "insert", "table_name": "tasks", "columns": ["title"], "rows": [["write"], ["review"], ["ship"]]},
+                {"op": "update", "table_name": "tasks", "set_columns": ["done"], "where_columns": ["title"], "rows": [[1, "write"]]},
+                {"op": "delete", "table_name": "tasks", "where_clause": "title = ?", "parameters": ["ship"]},
+                {"op": "query", "sql": "SELECT title, done FROM tasks ORDER BY id"}
+            ]
+        }))
+        .unwrap();
+        let result = handler.batch_tool(req).await.unwrap();
+        assert!(result.success);
+        assert_eq!(result.results.len(), 5);
+        match &result.results[4] {
+            BatchOperationResult::Query(query) => assert_eq!(
+                query.data.as_ref().unwrap(),
+                &vec![
+                    vec![serde_json::json!("write"), serde_json::json!(1)],
+                    vec![serde_json::json!("review"), serde_json::json!(0)]
+                ]
+            ),
+            other => panic!("unexpected result {other:?}"),
+        }
+
+        // A failing operation rolls back the ones before it
+        let result = handler
+            .batch_tool(BatchRequest {
+                operations: vec![
+                    BatchOperation::Delete(DeleteRowsRequest {
+                        table_name: "tasks".to_string(),
+                        allow_full_table: true,
+                        ..Default::default()
+                    }),
+                    BatchOperation::Insert(BatchInsertRequest {
+                        table_name: "tasks".to_string(),
+                        columns: vec!["title".to_string()],
+                        rows: vec![vec![serde_json::json!("a")], vec![serde_json::json!("a")]],
+                        ..Default::default()
+                    }),
+                ],
+            })
+            .await
+            .unwrap();
+        assert!(!result.success);
+        assert_eq!(result.failed_operation, Some(1));
+        assert_eq!(result.results.len(), 1);
+
+        let count = handler
+            .query_scalar_tool(QueryRequest {
+                sql: "SELECT COUNT(*) FROM tasks".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(count.value, serde_json::json!(2));
+    }
+
+    #[tokio::test]
+    async fn test_query_param_types() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        let result = handler
+            .query_tool(QueryRequest {
+                sql: "SELECT typeof(?1), ?1, typeof(?2), ?2, typeof(?3), typeof(?4), typeof(?5)"
+                    .to_string(),
+                parameters: vec![
+                    serde_json::json!(501),
+                    serde_json::json!(" 42 "),
+                    serde_json::json!("2.5"),
+                    serde_json::json!("cafe"),
+                    serde_json::json!(null),
+                ],
+                param_types: vec![
+                    "text".to_string(),
+                    "INTEGER".to_string(),
+                    "real".to_string(),
+                    "blob".to_string(),
+                    "integer".to_string(),
+                ],
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(
+            result.data.unwrap()[0],
+            vec![
+                serde_json::json!("text"),
+                serde_json::json!("501"),
+                serde_json::json!("integer"),
+                serde_json::json!(42),
+                serde_json::json!("real"),
+                serde_json::json!("blob"),
+                serde_json::json!("null"),
+            ]
+        );
+
+        for (value, sql_type) in [("abc", "integer"), ("x", "date")] {
+            let result = handler
+                .query_tool(QueryRequest {
+                    sql: "SELECT ?".to_string(),
+                    parameters: vec![serde_json::json!(value)],
+                    param_types: vec![sql_type.to_string()],
+                    ..Default::default()
+                })
+                .await;
+            assert!(matches!(result, Err(UniSqliteError::QueryFailed(_))));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_describe_query() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        handler
+            .query_tool(QueryRequest {
+                sql: "CREATE TABLE orders (id INTEGER PRIMARY KEY, total DECIMAL(10,2), placed_at TEXT)"
+                    .to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let result = handler
+            .describe_query_tool(DescribeQueryRequest {
+                sql: "SELECT o.id AS order_id, total * 2 AS doubled, placed_at FROM orders o WHERE id > ?"
+                    .to_string(),
+            })
+            .await
+            .unwrap();
+        assert!(result.readonly);
+        assert_eq!(result.parameter_count, 1);
+        assert_eq!(result.columns.len(), 3);
+
+        let id = &result.columns[0];
+        assert_eq!(id.name, "order_id");
+        assert_eq!(id.declared_type.as_deref(), Some("INTEGER"));
+        assert_eq!(id.origin_database.as_deref(), Some("main"));
+        assert_eq!(id.origin_table.as_deref(), Some("orders"));
+        assert_eq!(id.origin_column.as_deref(), Some("id"));
+
+        let doubled = &result.columns[1];
+        assert!(doubled.declared_type.is_none());
+        assert!(doubled.origin_table.is_none());
+        assert!(doubled.origin_column.is_none());
+
+        assert_eq!(
+            result.columns[2].origin_column.as_deref(),
+            Some("placed_at")
+        );
+
+        let missing = handler
+            .describe_query_tool(DescribeQueryRequest {
+                sql: "SELECT * FROM nope".to_string(),
+            })
+            .await;
+        assert!(matches!(missing, Err(UniSqliteError::Database(_))));
+    }
+
+    #[tokio::test]
+    async fn test_health_check_file_changed_since_connect() {
+        let (handler, temp_dir, db_path) = create_test_handler_with_db().await;
+
+        handler
+            .query_tool(QueryRequest {
+                sql: "CREATE TABLE t (id INTEGER)".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let health = handler.health_check_tool().await.unwrap();
+        assert_eq!(health.file_changed_since_connect, Some(false));
+        assert!(health.warning.is_none());
+
+        let replacement = temp_dir.path().join("replacement.db");
+        Connection::open(&replacement)
+            .unwrap()
+            .execute_batch("CREATE TABLE other (id INTEGER)")
+            .unwrap();
+        fs::rename(&replacement, &db_path).unwrap();
+
+        let health = handler.health_check_tool().await.unwrap();
+        assert_eq!(health.file_changed_since_connect, Some(true));
+        assert!(health.warning.unwrap().contains("reconnect"));
+    }
+
+    #[tokio::test]
+    async fn test_vacuum_check() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        for sql in [
+            "CREATE TABLE bulk (payload TEXT)",
+            "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 500) INSERT INTO bulk SELECT printf('%.1000c', 'x') FROM n",
+        ] {
+            handler
+                .query_tool(QueryRequest {
+                    sql: sql.to_string(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
+
+        let result = handler
+            .vacuum_check_tool(VacuumCheckRequest::default())
+            .await
+            .unwrap();
+        assert_eq!(result.freelist_count, 0);
+        assert!(!result.vacuum_recommended);
+
+        handler
+            .query_tool(QueryRequest {
+                sql: "DELETE FROM bulk".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let result = handler
+            .vacuum_check_tool(VacuumCheckRequest::default())
+            .await
+            .unwrap();
+        assert!(result.fragmentation_ratio > DEFAULT_VACUUM_THRESHOLD);
+        assert_eq!(
+            result.reclaimable_bytes,
+            result.freelist_count * result.page_size
+        );
+        assert!(result.vacuum_recommended);
+        assert!(result.recommendation.starts_with("VACUUM recommended"));
+
+        // A threshold outside 0..=1 is rejected
+        assert!(
+            handler
+                .vacuum_check_tool(VacuumCheckRequest {
+                    threshold: Some(1.5)
+                })
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_connect_uri() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("uri test.db");
+        Connection::open(&db_path)
+            .unwrap()
+            .execute_batch("CREATE TABLE items (id INTEGER); INSERT INTO items VALUES (1);")
+            .unwrap();
+
+        let handler = SqliteHandler::new();
+        let path = db_path.display().to_string().replace(' ', "%20");
+        let result = handler
+            .connect_tool(ConnectRequest {
+                path: format!("file:{path}?mode=ro"),
+                uri: true,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert!(result.path.ends_with("uri test.db"));
+
+        let rows = handler
+            .query_tool(QueryRequest {
+                sql: "SELECT COUNT(*) AS n FROM items".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(rows.data.unwrap()[0][0], 1);
+
+        // mode=ro from the query string makes the connection read-only
+        let insert = handler
+            .query_tool(QueryRequest {
+                sql: "INSERT INTO items VALUES (2)".to_string(),
+                ..Default::default()
+            })
+            .await;
+        assert!(insert.is_err());
+
+        // The path component is still validated
+        let bad_extension = temp_dir.path().join("data.txt");
+        assert!(
+            handler
+                .connect_tool(ConnectRequest {
+                    path: format!("file:{}?mode=ro", bad_extension.display()),
+                    uri: true,
+                    create_if_missing: true,
+                    ..Default::default()
+                })
+                .await
+                .is_err()
+        );
+        assert!(
+            handler
+                .connect_tool(ConnectRequest {
+                    path: db_path.display().to_string(),
+                    uri: true,
+                    ..Default::default()
+                })
+                .await
+                .is_err()
+        );
+    }
+
+    #[cfg(feature = "extensions")]
+    #[tokio::test]
+    async fn test_load_extension_allowlist() {
+        let (handler, temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        let allowed = temp_dir.path().join("extensions");
+        fs::create_dir(&allowed).unwrap();
+        let library = allowed.join(format!("vec0.{}", std::env::consts::DLL_EXTENSION));
+        fs::write(&library, b"not really a library").unwrap();
+        fs::write(temp_dir.path().join("outside.so"), b"").unwrap();
+
+        let resolved = SqliteHandler::resolve_extension(&allowed, "vec0").unwrap();
+        assert_eq!(resolved, library.canonicalize().unwrap());
+
+        for name in ["../outside.so", "/etc/passwd", "sub/vec0", "missing", ""] {
+            assert!(
+                SqliteHandler::resolve_extension(&allowed, name).is_err(),
+                "{name} should be rejected"
+            );
+        }
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(temp_dir.path().join("outside.so"), allowed.join("link.so"))
+                .unwrap();
+            assert!(SqliteHandler::resolve_extension(&allowed, "link.so").is_err());
+        }
+
+        // Without the environment variable, loading is disabled outright
+        if std::env::var_os(EXTENSION_DIR_ENV).is_none() {
+            let result = handler
+                .load_extension_tool(LoadExtensionRequest {
+                    name: "vec0".to_string(),
+                    entry_point: None,
+                })
+                .await;
+            assert!(result.is_err());
+        }
+
+        let health = handler.health_check_tool().await.unwrap();
+        assert!(health.loaded_extensions.unwrap().is_empty());
+    }
+
+    #[cfg(feature = "extensions")]
+    #[tokio::test]
+    async fn test_vector_search() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        assert_eq!(
+            SqliteHandler::vector_search_sql(
+                VectorBackend::SqliteVec,
+                "docs_vec",
+                "embedding",
+                None
+            )
+            .unwrap(),
+            "SELECT rowid, distance FROM \"docs_vec\" WHERE embedding MATCH ?1 AND k = ?2 ORDER BY distance"
+        );
+        let joined = SqliteHandler::vector_search_sql(
+            VectorBackend::Vss,
+            "docs_vss",
+            "embedding",
+            Some("docs"),
+        )
+        .unwrap();
+        assert!(joined.contains("vss_search(embedding, vss_search_params(?1, ?2))"));
+        assert!(joined.contains("JOIN \"docs\" j ON j.rowid = m.rowid"));
+
+        assert_eq!(
+            SqliteHandler::vector_json(&[0.5, -1.0, 2.0]).unwrap(),
+            "[0.5,-1.0,2.0]"
+        );
+        assert!(SqliteHandler::vector_json(&[]).is_err());
+        assert!(SqliteHandler::vector_column(Some("embedding); DROP TABLE x;--")).is_err());
+
+        // Without the extension loaded the missing module is reported clearly
+        let err = handler
+            .create_vector_index_tool(CreateVectorIndexRequest {
+                table: "docs_vec".to_string(),
+                column: None,
+                dimensions: 3,
+                backend: VectorBackend::SqliteVec,
+                if_not_exists: false,
+            })
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("load_extension"));
+
+        let search = handler
+            .vector_search_tool(VectorSearchRequest {
+                table: "docs_vec".to_string(),
+                column: None,
+                query_vector: vec![0.1, 0.2, 0.3],
+                k: 0,
+                backend: VectorBackend::SqliteVec,
+                join_table: None,
+            })
+            .await;
+        assert!(search.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_embeddings() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        let packed = handler
+            .pack_embedding_tool(PackEmbeddingRequest {
+                values: vec![1.0, 0.0, -0.5],
+            })
+            .await
+            .unwrap();
+        assert_eq!(packed.dimensions, 3);
+        let unpacked = handler
+            .unpack_embedding_tool(UnpackEmbeddingRequest {
+                base64: packed.base64.clone(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(unpacked.values, vec![1.0, 0.0, -0.5]);
+        assert!(
+            handler
+                .unpack_embedding_tool(UnpackEmbeddingRequest {
+                    base64: BASE64_STANDARD.encode([0u8; 5]),
+                })
+                .await
+                .is_err()
+        );
+
+        // Embeddings may be stored as packed BLOBs or as JSON text
+        let blob = hex::encode_upper(BASE64_STANDARD.decode(&packed.base64).unwrap());
+        for sql in [
+            "CREATE TABLE docs (title TEXT, embedding BLOB)".to_string(),
+            format!("INSERT INTO docs VALUES ('same', X'{blob}')"),
+            "INSERT INTO docs VALUES ('orthogonal', '[0.0, 1.0, 0.0]')".to_string(),
+            "INSERT INTO docs VALUES ('opposite', '[-1.0, 0.0, 0.5]')".to_string(),
+            "INSERT INTO docs VALUES ('missing', NULL)".to_string(),
+        ] {
+            handler
+                .query_tool(QueryRequest {
+                    sql,
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
+
+        let result = handler
+            .similarity_search_tool(SimilaritySearchRequest {
+                table: "docs".to_string(),
+                column: "embedding".to_string(),
+                query_vector: vec![1.0, 0.0, -0.5],
+                k: 2,
+                return_columns: vec!["title".to_string()],
+            })
+            .await
+            .unwrap();
+        assert_eq!(result.columns, vec!["rowid", "similarity", "title"]);
+        assert_eq!(result.row_count, 2);
+        assert_eq!(result.rows[0][2], "same");
+        assert!((result.rows[0][1].as_f64().unwrap() - 1.0).abs() < 1e-6);
+        assert_eq!(result.rows[1][2], "orthogonal");
+
+        // The function stays registered for ad-hoc queries
+        let functions = handler.list_functions_tool().await.unwrap();
+        assert!(
+            functions
+                .functions
+                .iter()
+                .any(|f| f.name == "cosine_similarity")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_change_tracking() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        handler
+            .query_tool(QueryRequest {
+                sql: "CREATE TABLE notes (id INTEGER PRIMARY KEY, body TEXT)".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let enabled = handler
+            .enable_change_tracking_tool(ChangeTrackingRequest {
+                table: "notes".to_string(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(enabled.triggers.len(), 3);
+
+        for sql in [
+            "INSERT INTO notes (id, body) VALUES (1, 'a'), (2, 'b')",
+            "UPDATE notes SET body = 'c' WHERE id = 2",
+            "DELETE FROM notes WHERE id = 1",
+        ] {
+            handler
+                .query_tool(QueryRequest {
+                    sql: sql.to_string(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
+
+        let changes = handler
+            .get_changes_since_tool(GetChangesSinceRequest {
+                table: Some("notes".to_string()),
+                timestamp: "2000-01-01T00:00:00Z".to_string(),
+                limit: None,
+            })
+            .await
+            .unwrap();
+        let ops: Vec<(&str, i64)> = changes
+            .changes
+            .iter()
+            .map(|c| (c.op.as_str(), c.rowid))
+            .collect();
+        assert_eq!(
+            ops,
+            vec![("INSERT", 1), ("INSERT", 2), ("UPDATE", 2), ("DELETE", 1)]
+        );
+
+        // Nothing is newer than the latest change
+        let none = handler
+            .get_changes_since_tool(GetChangesSinceRequest {
+                table: None,
+                timestamp: changes.latest.unwrap(),
+                limit: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(none.count, 0);
+
+        let disabled = handler
+            .disable_change_tracking_tool(ChangeTrackingRequest {
+                table: "notes".to_string(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(disabled.triggers.len(), 3);
+        handler
+            .query_tool(QueryRequest {
+                sql: "INSERT INTO notes (id, body) VALUES (3, 'd')".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let after = handler
+            .get_changes_since_tool(GetChangesSinceRequest {
+                table: None,
+                timestamp: "2000-01-01T00:00:00Z".to_string(),
+                limit: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(after.count, 4);
+
+        assert!(
+            handler
+                .enable_change_tracking_tool(ChangeTrackingRequest {
+                    table: "missing".to_string(),
+                })
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_soft_delete() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        handler
+            .create_table_tool(CreateTableRequest {
+                table_name: "memories".to_string(),
+                columns: "id INTEGER PRIMARY KEY, kind TEXT".to_string(),
+                if_not_exists: false,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        handler
+            .batch_insert_tool(BatchInsertRequest {
+                table_name: "memories".to_string(),
+                columns: vec!["kind".to_string()],
+                rows: vec![vec!["a".into()], vec!["b".into()], vec!["b".into()]],
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let soft_delete = |where_clause: &str| SoftDeleteRequest {
+            table_name: "memories".to_string(),
+            where_clause: where_clause.to_string(),
+            parameters: vec![],
+            allow_full_table: false,
+        };
+
+        // The column must exist first
+        assert!(
+            handler
+                .soft_delete_tool(soft_delete("id = 1"))
+                .await
+                .is_err()
+        );
+
+        let enabled = handler
+            .enable_soft_delete_tool(EnableSoftDeleteRequest {
+                table_name: "memories".to_string(),
+                view_name: None,
+            })
+            .await
+            .unwrap();
+        assert!(enabled.column_added);
+        assert_eq!(enabled.view_name, "memories_active");
+
+        let deleted = handler
+            .soft_delete_tool(SoftDeleteRequest {
+                parameters: vec!["b".into()],
+                ..soft_delete("kind = ?")
+            })
+            .await
+            .unwrap();
+        assert_eq!(deleted.rows_affected, 2);
+        // Already-deleted rows are not stamped again
+        let repeat = handler
+            .soft_delete_tool(soft_delete("kind = 'b'"))
+            .await
+            .unwrap();
+        assert_eq!(repeat.rows_affected, 0);
+
+        let live = handler
+            .query_tool(QueryRequest {
+                sql: "SELECT COUNT(*) FROM memories_active".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(live.data.unwrap()[0][0], 1);
+
+        let restored = handler.restore_tool(soft_delete("id = 2")).await.unwrap();
+        assert_eq!(restored.rows_affected, 1);
+        assert!(handler.restore_tool(soft_delete("")).await.is_err());
+
+        // Enabling again keeps the existing column
+        let again = handler
+            .enable_soft_delete_tool(EnableSoftDeleteRequest {
+                table_name: "memories".to_string(),
+                view_name: None,
+            })
+            .await
+            .unwrap();
+        assert!(!again.column_added);
+    }
+
+    #[tokio::test]
+    async fn test_tail() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        handler
+            .create_table_tool(CreateTableRequest {
+                table_name: "events".to_string(),
+                columns: "message TEXT".to_string(),
+                if_not_exists: false,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let insert = |messages: &[&str]| BatchInsertRequest {
+            table_name: "events".to_string(),
+            columns: vec!["message".to_string()],
+            rows: messages.iter().map(|m| vec![(*m).into()]).collect(),
+            ..Default::default()
+        };
+        handler
+            .batch_insert_tool(insert(&["one", "two", "three"]))
+            .await
+            .unwrap();
+
+        let tail = |after_rowid: i64| TailRequest {
+            table_name: "events".to_string(),
+            after_rowid,
+            limit: Some(2),
+            typed: false,
+        };
+        let first = handler.tail_tool(tail(0)).await.unwrap();
+        assert_eq!(first.columns, vec!["rowid", "message"]);
+        assert_eq!(first.row_count, 2);
+        assert_eq!(first.next_cursor, 2);
+
+        let second = handler.tail_tool(tail(first.next_cursor)).await.unwrap();
+        assert_eq!(second.rows[0][1], "three");
+        assert_eq!(second.next_cursor, 3);
+
+        // Nothing new keeps the cursor where it was
+        let idle = handler.tail_tool(tail(second.next_cursor)).await.unwrap();
+        assert_eq!(idle.row_count, 0);
+        assert_eq!(idle.next_cursor, 3);
+
+        handler.batch_insert_tool(insert(&["four"])).await.unwrap();
+        let resumed = handler.tail_tool(tail(idle.next_cursor)).await.unwrap();
+        assert_eq!(resumed.rows[0][1], "four");
+        assert_eq!(resumed.next_cursor, 4);
+    }
+
+    #[tokio::test]
+    async fn test_meta_store() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        // Reads before the first write see an empty store
+        assert_eq!(handler.list_meta_tool().await.unwrap().total_count, 0);
+        let missing = handler
+            .get_meta_tool(GetMetaRequest {
+                key: "schema_version".to_string(),
+            })
+            .await
+            .unwrap();
+        assert!(!missing.found);
+
+        handler
+            .set_meta_tool(SetMetaRequest {
+                key: "schema_version".to_string(),
+                value: serde_json::json!(1),
+            })
+            .await
+            .unwrap();
+        handler
+            .set_meta_tool(SetMetaRequest {
+                key: "agent".to_string(),
+                value: serde_json::json!({"name": "scribe", "tags": ["a", "b"]}),
+            })
+            .await
+            .unwrap();
+        let updated = handler
+            .set_meta_tool(SetMetaRequest {
+                key: "schema_version".to_string(),
+                value: serde_json::json!(2),
+            })
+            .await
+            .unwrap();
+        assert!(!updated.updated_at.is_empty());
+
+        let version = handler
+            .get_meta_tool(GetMetaRequest {
+                key: "schema_version".to_string(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(version.entry.unwrap().value, serde_json::json!(2));
+
+        let all = handler.list_meta_tool().await.unwrap();
+        assert_eq!(all.total_count, 2);
+        assert_eq!(all.entries[0].key, "agent");
+        assert_eq!(all.entries[0].value["tags"][1], "b");
+    }
+
+    #[tokio::test]
+    async fn test_query_to_temp_table() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        handler
+            .create_table_tool(CreateTableRequest {
+                table_name: "sales".to_string(),
+                columns: "region TEXT, amount INTEGER".to_string(),
+                if_not_exists: false,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        handler
+            .batch_insert_tool(BatchInsertRequest {
+                table_name: "sales".to_string(),
+                columns: vec!["region".to_string(), "amount".to_string()],
+                rows: vec![
+                    vec!["north".into(), 10.into()],
+                    vec!["north".into(), 5.into()],
+                    vec!["south".into(), 7.into()],
+                ],
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let staged = |replace: bool| QueryToTempTableRequest {
+            table_name: "region_totals".to_string(),
+            sql: "SELECT region, SUM(amount) AS total FROM sales WHERE amount > ? GROUP BY region;"
+                .to_string(),
+            parameters: vec![5.into()],
+            replace,
+        };
+        let result = handler
+            .query_to_temp_table_tool(staged(false))
+            .await
+            .unwrap();
+        assert_eq!(result.columns, vec!["region", "total"]);
+        assert_eq!(result.row_count, 2);
+
+        let joined = handler
+            .query_tool(QueryRequest {
+                sql: "SELECT total FROM region_totals WHERE region = 'north'".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(joined.data.unwrap()[0][0], 10);
+
+        // Existing temp tables are only replaced on request
+        assert!(
+            handler
+                .query_to_temp_table_tool(staged(false))
+                .await
+                .is_err()
+        );
+        assert!(handler.query_to_temp_table_tool(staged(true)).await.is_ok());
+
+        // The temp table never lands in the main schema
+        let tables = handler.list_tables_tool().await.unwrap();
+        assert!(!tables.tables.iter().any(|t| t.name == "region_totals"));
+
+        for sql in ["DELETE FROM sales", "SELECT 1; DROP TABLE sales"] {
+            let result = handler
+                .query_to_temp_table_tool(QueryToTempTableRequest {
+                    table_name: "bad".to_string(),
+                    sql: sql.to_string(),
+                    parameters: vec![],
+                    replace: false,
                 })
-                .as_object()
+                .await;
+            assert!(result.is_err(), "{sql} should be rejected");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_tables_detailed() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        for sql in [
+            "CREATE TABLE plain (id INTEGER PRIMARY KEY, name TEXT)",
+            "CREATE TABLE keyed (k TEXT PRIMARY KEY, v BLOB) WITHOUT ROWID",
+            "CREATE TABLE checked (id INTEGER, n REAL) STRICT",
+            "CREATE VIEW plain_names AS SELECT name FROM plain",
+        ] {
+            handler
+                .query_tool(QueryRequest {
+                    sql: sql.to_string(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
+
+        let result = handler.list_tables_detailed_tool().await.unwrap();
+        assert_eq!(result.source, "table_list");
+        let main: Vec<&DetailedTableInfo> = result
+            .tables
+            .iter()
+            .filter(|t| t.schema == "main")
+            .collect();
+        let names: Vec<&str> = main.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["checked", "keyed", "plain", "plain_names"]);
+
+        // The sqlite_master fallback agrees with table_list on the main schema
+        let guard = handler.lock_db().await.unwrap();
+        let fallback = SqliteHandler::tables_from_sqlite_master(guard.as_ref().unwrap()).unwrap();
+        for (detailed, inferred) in main.iter().zip(&fallback) {
+            assert_eq!(detailed.name, inferred.name);
+            assert_eq!(detailed.table_type, inferred.table_type);
+            assert_eq!(detailed.column_count, inferred.column_count);
+            assert_eq!(detailed.without_rowid, inferred.without_rowid);
+            assert_eq!(detailed.strict, inferred.strict);
+        }
+        assert!(fallback[0].strict);
+        assert!(fallback[1].without_rowid);
+        assert_eq!(fallback[3].table_type, "view");
+    }
+
+    #[tokio::test]
+    async fn test_create_strict_table() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        let result = handler
+            .create_table_tool(CreateTableRequest {
+                table_name: "readings".to_string(),
+                columns: "id INTEGER PRIMARY KEY, \"sensor name\" TEXT NOT NULL, value REAL, raw ANY, CHECK (value >= 0)"
+                    .to_string(),
+                strict: true,
+                ..Default::default()
+            })
+            .await;
+        assert!(result.is_ok());
+
+        let bad_value = handler
+            .query_tool(QueryRequest {
+                sql: "INSERT INTO readings (\"sensor name\", value) VALUES ('a', 'not a number')"
+                    .to_string(),
+                ..Default::default()
+            })
+            .await;
+        assert!(bad_value.is_err());
+
+        for (columns, message) in [
+            ("id INTEGER, name VARCHAR(20)", "VARCHAR"),
+            ("id INTEGER, note NOT NULL", "needs a type"),
+        ] {
+            let err = handler
+                .create_table_tool(CreateTableRequest {
+                    table_name: "invalid".to_string(),
+                    columns: columns.to_string(),
+                    strict: true,
+                    ..Default::default()
+                })
+                .await
+                .unwrap_err();
+            assert!(err.to_string().contains(message), "{err}");
+        }
+
+        handler
+            .create_table_tool(CreateTableRequest {
+                table_name: "lookup".to_string(),
+                columns: "code TEXT PRIMARY KEY, label TEXT".to_string(),
+                strict: true,
+                without_rowid: true,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let tables = handler.list_tables_detailed_tool().await.unwrap();
+        let lookup = tables.tables.iter().find(|t| t.name == "lookup").unwrap();
+        assert!(lookup.strict && lookup.without_rowid);
+    }
+
+    #[tokio::test]
+    async fn test_generate_migration() {
+        let (handler, temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        handler
+            .query_tool(QueryRequest {
+                sql: "SELECT 1".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        {
+            let guard = handler.lock_db().await.unwrap();
+            guard
+                .as_ref()
                 .unwrap()
-                .clone()
-                .into(),
-                annotations: None,
-                output_schema: None,
-            },
-            Tool {
-                name: Cow::Borrowed("describe_table"),
-                description: Some(Cow::Borrowed(
-                    "Get detailed information about a table's structure",
-                )),
-                input_schema: serde_json::to_value(
-                    schemars::schema_for!(DescribeTableRequest).schema,
+                .execute_batch(
+                    "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT);
+                     CREATE TABLE posts (id INTEGER PRIMARY KEY, user_id INTEGER, body TEXT, draft INTEGER);
+                     CREATE TABLE legacy (x TEXT);
+                     CREATE INDEX idx_posts_user ON posts (user_id);
+                     CREATE VIEW user_names AS SELECT name FROM users;
+                     INSERT INTO users VALUES (1, 'ada');
+                     INSERT INTO posts VALUES (1, 1, 'hello', 0);",
                 )
+                .unwrap();
+        }
+
+        let target_sql = "
+            CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT, email TEXT);
+            CREATE TABLE posts (id INTEGER PRIMARY KEY, user_id INTEGER NOT NULL, body TEXT);
+            CREATE TABLE tags (post_id INTEGER, tag TEXT);
+            CREATE INDEX idx_posts_user ON posts (user_id);
+            CREATE VIEW user_names AS SELECT name, email FROM users;
+        ";
+        let request = |apply: bool, allow_destructive: bool| GenerateMigrationRequest {
+            target_sql: Some(target_sql.to_string()),
+            target_database: None,
+            apply,
+            allow_destructive,
+        };
+
+        let plan = handler
+            .generate_migration_tool(request(false, false))
+            .await
+            .unwrap();
+        assert!(!plan.up_to_date && !plan.applied);
+        assert!(
+            plan.statements
+                .contains(&"ALTER TABLE \"users\" ADD COLUMN email TEXT".to_string())
+        );
+        assert!(
+            plan.statements
+                .contains(&"DROP TABLE \"legacy\"".to_string())
+        );
+        assert!(
+            plan.statements
+                .iter()
+                .any(|s| s.starts_with("CREATE TABLE \"_migration_new_posts\""))
+        );
+        assert_eq!(plan.destructive_changes.len(), 2);
+
+        // Destructive migrations need explicit consent
+        assert!(
+            handler
+                .generate_migration_tool(request(true, false))
+                .await
+                .is_err()
+        );
+        let applied = handler
+            .generate_migration_tool(request(true, true))
+            .await
+            .unwrap();
+        assert!(applied.applied);
+
+        let rows = handler
+            .query_tool(QueryRequest {
+                sql: "SELECT p.body, u.name FROM posts p JOIN user_names u ON u.name = 'ada'"
+                    .to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(rows.data.unwrap()[0][0], "hello");
+
+        // Re-diffing the migrated database finds nothing left to do, also against a file
+        let again = handler
+            .generate_migration_tool(request(false, false))
+            .await
+            .unwrap();
+        assert!(again.up_to_date, "{:?}", again.statements);
+
+        let reference = temp_dir.path().join("reference.db");
+        Connection::open(&reference)
+            .unwrap()
+            .execute_batch(target_sql)
+            .unwrap();
+        let from_file = handler
+            .generate_migration_tool(GenerateMigrationRequest {
+                target_sql: None,
+                target_database: Some(reference.display().to_string()),
+                apply: false,
+                allow_destructive: false,
+            })
+            .await
+            .unwrap();
+        assert!(from_file.up_to_date, "{:?}", from_file.statements);
+    }
+
+    #[tokio::test]
+    async fn test_dump_to_sql() {
+        let (handler, temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        {
+            let guard = handler.lock_db().await.unwrap();
+            guard
+                .as_ref()
                 .unwrap()
-                .as_object()
-                .unwrap()
-                .clone()
-                .into(),
-                annotations: None,
-                output_schema: None,
-            },
-            Tool {
-                name: Cow::Borrowed("backup"),
-                description: Some(Cow::Borrowed("Create a backup of the current database")),
-                input_schema: serde_json::to_value(schemars::schema_for!(BackupRequest).schema)
-                    .unwrap()
-                    .as_object()
-                    .unwrap()
-                    .clone()
-                    .into(),
-                annotations: None,
-                output_schema: None,
-            },
-            Tool {
-                name: Cow::Borrowed("batch_insert"),
-                description: Some(Cow::Borrowed(
-                    "Insert multiple rows efficiently in a single transaction",
-                )),
-                input_schema: serde_json::to_value(
-                    schemars::schema_for!(BatchInsertRequest).schema,
+                .execute_batch(
+                    "CREATE TABLE posts (id INTEGER PRIMARY KEY, author_id INTEGER REFERENCES authors(id), body TEXT, score REAL, data BLOB);
+                     CREATE TABLE authors (id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT);
+                     CREATE INDEX idx_posts_author ON posts (author_id);
+                     CREATE VIEW author_posts AS SELECT a.name, p.body FROM authors a JOIN posts p ON p.author_id = a.id;
+                     INSERT INTO authors (name) VALUES ('O''Brien');
+                     INSERT INTO posts VALUES (1, 1, 'line one
+line two', 2.0, X'00FF'), (2, 1, NULL, NULL, NULL);",
                 )
-                .unwrap()
-                .as_object()
-                .unwrap()
-                .clone()
-                .into(),
-                annotations: None,
-                output_schema: None,
-            },
-            Tool {
-                name: Cow::Borrowed("export_csv"),
-                description: Some(Cow::Borrowed("Export query results to a CSV file")),
-                input_schema: serde_json::to_value(schemars::schema_for!(ExportCsvRequest).schema)
-                    .unwrap()
-                    .as_object()
+                .unwrap();
+        }
+
+        let single = temp_dir.path().join("dump.sql");
+        let result = handler
+            .dump_to_sql_tool(DumpToSqlRequest {
+                output_path: single.display().to_string(),
+                export_sql_per_table: false,
+            })
+            .await
+            .unwrap();
+        assert_eq!(result.tables, 2);
+        assert_eq!(result.rows, 3);
+
+        // The dump recreates an identical database
+        let restored = Connection::open_in_memory().unwrap();
+        restored
+            .execute_batch(&fs::read_to_string(&single).unwrap())
+            .unwrap();
+        let (body, score_type, data): (String, String, Vec<u8>) = restored
+            .query_row(
+                "SELECT body, typeof(score), data FROM posts WHERE id = 1",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!(body, "line one\nline two");
+        assert_eq!(score_type, "real");
+        assert_eq!(data, vec![0x00, 0xFF]);
+        let name: String = restored
+            .query_row("SELECT name FROM author_posts LIMIT 1", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(name, "O'Brien");
+        let seq: i64 = restored
+            .query_row(
+                "SELECT seq FROM sqlite_sequence WHERE name = 'authors'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(seq, 1);
+
+        // Per-table files put referenced tables first and views last
+        let dir = temp_dir.path().join("dump");
+        let result = handler
+            .dump_to_sql_tool(DumpToSqlRequest {
+                output_path: dir.display().to_string(),
+                export_sql_per_table: true,
+            })
+            .await
+            .unwrap();
+        let names: Vec<String> = result
+            .files
+            .iter()
+            .map(|f| {
+                Path::new(f)
+                    .file_name()
                     .unwrap()
-                    .clone()
-                    .into(),
-                annotations: None,
-                output_schema: None,
-            },
-            Tool {
-                name: Cow::Borrowed("health_check"),
-                description: Some(Cow::Borrowed(
-                    "Get health and status information about the current database connection",
-                )),
-                input_schema: serde_json::json!({
-                    "type": "object",
-                    "properties": {},
-                    "additionalProperties": false
-                })
-                .as_object()
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect();
+        assert_eq!(
+            names,
+            vec!["0001_authors.sql", "0002_posts.sql", "0003_views.sql"]
+        );
+        let posts = fs::read_to_string(dir.join("0002_posts.sql")).unwrap();
+        assert!(posts.contains("CREATE INDEX idx_posts_author"));
+    }
+
+    #[tokio::test]
+    async fn test_restore_from_directory() {
+        let (handler, temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        {
+            let guard = handler.lock_db().await.unwrap();
+            guard
+                .as_ref()
                 .unwrap()
-                .clone()
-                .into(),
-                annotations: None,
-                output_schema: None,
-            },
-        ]
+                .execute_batch(
+                    "PRAGMA foreign_keys = ON;
+                     CREATE TABLE authors (id INTEGER PRIMARY KEY, name TEXT);
+                     CREATE TABLE posts (id INTEGER PRIMARY KEY, author_id INTEGER NOT NULL REFERENCES authors(id), body TEXT);
+                     CREATE TRIGGER posts_touch AFTER UPDATE ON posts BEGIN
+                         UPDATE posts SET body = body || ';' WHERE id = NEW.id AND 0;
+                     END;
+                     INSERT INTO authors VALUES (1, 'ada');
+                     INSERT INTO posts VALUES (1, 1, 'semi; colon'), (2, 1, '-- not a comment');",
+                )
+                .unwrap();
+        }
+        let dir = temp_dir.path().join("dump");
+        handler
+            .dump_to_sql_tool(DumpToSqlRequest {
+                output_path: dir.display().to_string(),
+                export_sql_per_table: true,
+            })
+            .await
+            .unwrap();
+        // File names deliberately put the child table first; the schema pass still works
+        fs::rename(dir.join("0001_authors.sql"), dir.join("0003_authors.sql")).unwrap();
+
+        let target = SqliteHandler::new();
+        target
+            .connect_tool(ConnectRequest {
+                path: temp_dir.path().join("restored.db").display().to_string(),
+                create_if_missing: true,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        target
+            .query_tool(QueryRequest {
+                sql: "PRAGMA foreign_keys = ON".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let result = target
+            .restore_from_directory_tool(RestoreFromDirectoryRequest {
+                directory: dir.display().to_string(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(result.files_applied.len(), 2);
+        // 2 CREATE TABLE, 3 INSERT, 1 CREATE TRIGGER
+        assert_eq!(result.statements_executed, 6);
+
+        let rows = target
+            .query_tool(QueryRequest {
+                sql: "SELECT body FROM posts ORDER BY id".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(
+            rows.data.unwrap(),
+            vec![
+                vec![Value::from("semi; colon")],
+                vec![Value::from("-- not a comment")]
+            ]
+        );
+
+        // A failing file rolls back the whole restore
+        fs::write(
+            dir.join("0004_broken.sql"),
+            "INSERT INTO missing VALUES (1);",
+        )
+        .unwrap();
+        let again = SqliteHandler::new();
+        again
+            .connect_tool(ConnectRequest {
+                path: temp_dir.path().join("partial.db").display().to_string(),
+                create_if_missing: true,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let err = again
+            .restore_from_directory_tool(RestoreFromDirectoryRequest {
+                directory: dir.display().to_string(),
+            })
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("0004_broken.sql"));
+        let tables = again.list_tables_tool().await.unwrap();
+        assert_eq!(tables.total_count, 0);
     }
 
-    async fn list_tools_handler(
-        &self,
-        _request: Option<PaginatedRequestParam>,
-        _context: RequestContext<rmcp::service::RoleServer>,
-    ) -> Result<ListToolsResult, rmcp::ErrorData> {
-        Ok(ListToolsResult {
-            tools: Self::get_tools(),
-            next_cursor: None,
-        })
+    #[tokio::test]
+    async fn test_restore_from_sql() {
+        let (handler, temp_dir, _db_path) = create_test_handler_with_db().await;
+
+        handler
+            .query_tool(QueryRequest {
+                sql: "CREATE TABLE log (id INTEGER PRIMARY KEY, line TEXT)".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        handler
+            .query_tool(QueryRequest {
+                sql:
+                    "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 250) \
+                      INSERT INTO log (line) SELECT 'line ' || i || ';' FROM n"
+                        .to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let dump = temp_dir.path().join("dump.sql");
+        handler
+            .dump_to_sql_tool(DumpToSqlRequest {
+                output_path: dump.display().to_string(),
+                export_sql_per_table: false,
+            })
+            .await
+            .unwrap();
+
+        let target = SqliteHandler::new();
+        target
+            .connect_tool(ConnectRequest {
+                path: temp_dir.path().join("restored.db").display().to_string(),
+                create_if_missing: true,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let result = target
+            .restore_from_sql_tool(RestoreFromSqlRequest {
+                input_path: dump.display().to_string(),
+                progress_interval: Some(100),
+            })
+            .await
+            .unwrap();
+        // PRAGMA foreign_keys, CREATE TABLE, and 250 INSERTs; BEGIN/COMMIT are skipped
+        assert_eq!(result.statements_executed, 252);
+        assert_eq!(result.bytes_read, fs::metadata(&dump).unwrap().len());
+
+        let count = target
+            .query_tool(QueryRequest {
+                sql: "SELECT COUNT(*) FROM log WHERE line LIKE '%;'".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(count.data.unwrap()[0][0], 250);
+
+        // A failing statement rolls back everything before it
+        let broken = temp_dir.path().join("broken.sql");
+        fs::write(
+            &broken,
+            "CREATE TABLE fresh (x);\nINSERT INTO fresh VALUES (1);\nINSERT INTO nowhere VALUES (2);\n",
+        )
+        .unwrap();
+        let err = target
+            .restore_from_sql_tool(RestoreFromSqlRequest {
+                input_path: broken.display().to_string(),
+                progress_interval: None,
+            })
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Statement 3"));
+        let tables = target.list_tables_tool().await.unwrap();
+        assert!(!tables.tables.iter().any(|t| t.name == "fresh"));
     }
 
-    async fn call_tool_handler(
-        &self,
-        request: CallToolRequestParam,
-        _context: RequestContext<rmcp::service::RoleServer>,
-    ) -> Result<CallToolResult, rmcp::ErrorData> {
-        match request.name.as_ref() {
-            "connect" => {
-                let params: ConnectRequest =
-                    serde_json::from_value(request.arguments.unwrap_or_default().into())
-                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+    #[tokio::test]
+    async fn test_import_csv_encodings() {
+        let (handler, temp_dir, _db_path) = create_test_handler_with_db().await;
+        handler
+            .query_tool(QueryRequest {
+                sql: "CREATE TABLE people (name TEXT, city TEXT)".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
 
-                let result = self
-                    .connect_tool(params)
-                    .await
-                    .map_err(rmcp::ErrorData::from)?;
+        // Latin-1 "José,Zürich" with no BOM
+        let latin1 = temp_dir.path().join("latin1.csv");
+        fs::write(&latin1, b"name,city\nJos\xe9,Z\xfcrich\n").unwrap();
+        let detected = handler
+            .detect_encoding_tool(DetectEncodingRequest {
+                path: latin1.display().to_string(),
+                sample_bytes: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(detected.encoding, "windows-1252");
+        assert_eq!(detected.basis, "heuristic");
 
-                Ok(CallToolResult {
-                    content: vec![],
-                    structured_content: Some(serde_json::to_value(result).unwrap()),
-                    is_error: Some(false),
-                })
-            }
-            "query" => {
-                let params: QueryRequest =
-                    serde_json::from_value(request.arguments.unwrap_or_default().into())
-                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+        let result = handler
+            .import_csv_tool(ImportCsvRequest {
+                table_name: "people".to_string(),
+                input_path: latin1.display().to_string(),
+                has_headers: true,
+                reject_path: None,
+                encoding: Some("latin1".to_string()),
+            })
+            .await
+            .unwrap();
+        assert_eq!(result.rows_imported, 1);
+        assert_eq!(result.encoding, "windows-1252");
+        assert!(result.warning.is_none());
 
-                let result = self
-                    .query_tool(params)
-                    .await
-                    .map_err(rmcp::ErrorData::from)?;
+        // UTF-16LE with a BOM is picked up without naming the encoding
+        let utf16 = temp_dir.path().join("utf16.csv");
+        let mut bytes = vec![0xFF, 0xFE];
+        for unit in "name,city\nAnaïs,Köln\n".encode_utf16() {
+            bytes.extend_from_slice(&unit.to_le_bytes());
+        }
+        fs::write(&utf16, &bytes).unwrap();
+        let detected = handler
+            .detect_encoding_tool(DetectEncodingRequest {
+                path: utf16.display().to_string(),
+                sample_bytes: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(detected.encoding, "UTF-16LE");
+        assert!(detected.has_bom);
 
-                Ok(CallToolResult {
-                    content: vec![],
-                    structured_content: Some(serde_json::to_value(result).unwrap()),
-                    is_error: Some(false),
-                })
-            }
-            "transaction" => {
-                let params: TransactionRequest =
-                    serde_json::from_value(request.arguments.unwrap_or_default().into())
-                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+        let result = handler
+            .import_csv_tool(ImportCsvRequest {
+                table_name: "people".to_string(),
+                input_path: utf16.display().to_string(),
+                has_headers: true,
+                reject_path: None,
+                encoding: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(result.encoding, "UTF-16LE");
 
-                let result = self
-                    .transaction_tool(params)
-                    .await
-                    .map_err(rmcp::ErrorData::from)?;
+        // Undecodable bytes are replaced and reported rather than failing the import
+        let broken = temp_dir.path().join("broken.csv");
+        fs::write(&broken, b"name,city\nBad\xff,Oslo\n").unwrap();
+        let result = handler
+            .import_csv_tool(ImportCsvRequest {
+                table_name: "people".to_string(),
+                input_path: broken.display().to_string(),
+                has_headers: true,
+                reject_path: None,
+                encoding: Some("utf-8".to_string()),
+            })
+            .await
+            .unwrap();
+        assert_eq!(result.rows_imported, 1);
+        assert!(result.warning.unwrap().contains("U+FFFD"));
 
-                Ok(CallToolResult {
-                    content: vec![],
-                    structured_content: Some(serde_json::to_value(result).unwrap()),
-                    is_error: Some(false),
-                })
-            }
-            "create_table" => {
-                let params: CreateTableRequest =
-                    serde_json::from_value(request.arguments.unwrap_or_default().into())
-                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+        let rows = handler
+            .query_tool(QueryRequest {
+                sql: "SELECT name || '/' || city FROM people ORDER BY rowid".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap()
+            .data
+            .unwrap();
+        assert_eq!(rows[0][0], "José/Zürich");
+        assert_eq!(rows[1][0], "Anaïs/Köln");
+        assert_eq!(rows[2][0], "Bad\u{FFFD}/Oslo");
+    }
 
-                let result = self
-                    .create_table_tool(params)
-                    .await
-                    .map_err(rmcp::ErrorData::from)?;
+    #[tokio::test]
+    async fn test_preview_csv() {
+        let (handler, temp_dir, _db_path) = create_test_handler_with_db().await;
 
-                Ok(CallToolResult {
-                    content: vec![],
-                    structured_content: Some(serde_json::to_value(result).unwrap()),
-                    is_error: Some(false),
-                })
-            }
-            "list_tables" => {
-                let result = self
-                    .list_tables_tool()
-                    .await
-                    .map_err(rmcp::ErrorData::from)?;
+        let csv_path = temp_dir.path().join("orders.csv");
+        fs::write(
+            &csv_path,
+            "id,amount,note,code\n1,9.5,first,007\n2,10,,x1\n3,12,third\n4,8.25,fourth,12\n",
+        )
+        .unwrap();
 
-                Ok(CallToolResult {
-                    content: vec![],
-                    structured_content: Some(serde_json::to_value(result).unwrap()),
-                    is_error: Some(false),
-                })
-            }
-            "describe_table" => {
-                let params: DescribeTableRequest =
-                    serde_json::from_value(request.arguments.unwrap_or_default().into())
-                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+        let result = handler
+            .preview_csv_tool(PreviewCsvRequest {
+                input_path: csv_path.display().to_string(),
+                rows: Some(3),
+                has_headers: true,
+                encoding: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(result.rows_read, 3);
+        assert_eq!(result.sample[0], vec!["1", "9.5", "first", "007"]);
 
-                let result = self
-                    .describe_table_tool(params)
-                    .await
-                    .map_err(rmcp::ErrorData::from)?;
+        let summary: Vec<_> = result
+            .columns
+            .iter()
+            .map(|c| (c.name.as_str(), c.inferred_type, c.empty_count))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("id", CsvFieldType::Int, 0),
+                ("amount", CsvFieldType::Float, 0),
+                ("note", CsvFieldType::Text, 1),
+                ("code", CsvFieldType::Text, 1),
+            ]
+        );
 
-                Ok(CallToolResult {
-                    content: vec![],
-                    structured_content: Some(serde_json::to_value(result).unwrap()),
-                    is_error: Some(false),
-                })
-            }
-            "backup" => {
-                let params: BackupRequest =
-                    serde_json::from_value(request.arguments.unwrap_or_default().into())
-                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+        // Nothing was created in the connected database
+        let tables = handler.list_tables_tool().await.unwrap();
+        assert!(tables.tables.is_empty());
+    }
 
-                let result = self
-                    .backup_tool(params)
-                    .await
-                    .map_err(rmcp::ErrorData::from)?;
+    #[tokio::test]
+    async fn test_create_table_from_csv() {
+        let (handler, temp_dir, _db_path) = create_test_handler_with_db().await;
 
-                Ok(CallToolResult {
-                    content: vec![],
-                    structured_content: Some(serde_json::to_value(result).unwrap()),
-                    is_error: Some(false),
-                })
-            }
-            "batch_insert" => {
-                let params: BatchInsertRequest =
-                    serde_json::from_value(request.arguments.unwrap_or_default().into())
-                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+        let csv_path = temp_dir.path().join("stations.csv");
+        fs::write(
+            &csv_path,
+            "station id,elevation,name,notes\n7,112.5,Alpha,\n8,90,Beta,\n9,,00123,\n",
+        )
+        .unwrap();
 
-                let result = self
-                    .batch_insert_tool(params)
-                    .await
-                    .map_err(rmcp::ErrorData::from)?;
+        let result = handler
+            .create_table_from_csv_tool(CreateTableFromCsvRequest {
+                table_name: "stations".to_string(),
+                input_path: csv_path.display().to_string(),
+                sample_rows: None,
+                import: true,
+                has_headers: true,
+                encoding: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(
+            result.ddl,
+            "CREATE TABLE \"stations\" (\"station id\" INTEGER, \"elevation\" REAL, \"name\" TEXT, \"notes\")"
+        );
+        assert_eq!(result.rows_imported, 3);
 
-                Ok(CallToolResult {
-                    content: vec![],
-                    structured_content: Some(serde_json::to_value(result).unwrap()),
-                    is_error: Some(false),
-                })
-            }
-            "export_csv" => {
-                let params: ExportCsvRequest =
-                    serde_json::from_value(request.arguments.unwrap_or_default().into())
-                        .map_err(|e| rmcp::ErrorData::invalid_params(e.to_string(), None))?;
+        // TEXT affinity keeps the leading zeros the sample made it keep
+        let rows = handler
+            .query_tool(QueryRequest {
+                sql: "SELECT name, typeof(elevation) FROM stations ORDER BY \"station id\""
+                    .to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap()
+            .data
+            .unwrap();
+        assert_eq!(rows[2][0], "00123");
+        assert_eq!(rows[1][1], "real");
 
-                let result = self
-                    .export_csv_tool(params)
-                    .await
-                    .map_err(rmcp::ErrorData::from)?;
+        // Schema only, and an existing table is never replaced
+        let result = handler
+            .create_table_from_csv_tool(CreateTableFromCsvRequest {
+                table_name: "stations_empty".to_string(),
+                input_path: csv_path.display().to_string(),
+                sample_rows: Some(1),
+                import: false,
+                has_headers: true,
+                encoding: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(result.rows_imported, 0);
+        assert!(result.ddl.contains("\"name\" TEXT"));
 
-                Ok(CallToolResult {
-                    content: vec![],
-                    structured_content: Some(serde_json::to_value(result).unwrap()),
-                    is_error: Some(false),
-                })
-            }
-            "health_check" => {
-                let result = self
-                    .health_check_tool()
-                    .await
-                    .map_err(rmcp::ErrorData::from)?;
+        let err = handler
+            .create_table_from_csv_tool(CreateTableFromCsvRequest {
+                table_name: "stations".to_string(),
+                input_path: csv_path.display().to_string(),
+                sample_rows: None,
+                import: true,
+                has_headers: true,
+                encoding: None,
+            })
+            .await;
+        assert!(err.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_query_explain_analyze() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+        handler
+            .query_tool(QueryRequest {
+                sql: "CREATE TABLE items (id INTEGER PRIMARY KEY, category TEXT)".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        handler
+            .query_tool(QueryRequest {
+                sql:
+                    "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 50) \
+                      INSERT INTO items (category) SELECT 'c' || (i % 5) FROM n"
+                        .to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let result = handler
+            .query_explain_analyze_tool(QueryExplainAnalyzeRequest {
+                sql: "SELECT * FROM items WHERE category = ?".to_string(),
+                parameters: vec![serde_json::json!("c1")],
+            })
+            .await
+            .unwrap();
+        assert_eq!(result.rows, 10);
+        assert!(
+            result
+                .plan
+                .iter()
+                .any(|step| step.detail.contains("SCAN items"))
+        );
+
+        let result = handler
+            .query_explain_analyze_tool(QueryExplainAnalyzeRequest {
+                sql: "SELECT * FROM items WHERE id IN (SELECT id FROM items WHERE category = 'c2')"
+                    .to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(result.rows, 10);
+        assert!(result.plan.iter().any(|step| step.depth > 0));
+
+        let err = handler
+            .query_explain_analyze_tool(QueryExplainAnalyzeRequest {
+                sql: "DELETE FROM items".to_string(),
+                ..Default::default()
+            })
+            .await;
+        assert!(err.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_batch_insert_on_conflict() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+        handler
+            .query_tool(QueryRequest {
+                sql: "CREATE TABLE tags (name TEXT PRIMARY KEY, uses INTEGER)".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        let load = |strategy| BatchInsertRequest {
+            table_name: "tags".to_string(),
+            columns: vec!["name".to_string(), "uses".to_string()],
+            rows: vec![
+                vec![serde_json::json!("rust"), serde_json::json!(1)],
+                vec![serde_json::json!("sql"), serde_json::json!(2)],
+            ],
+            on_conflict: Some(strategy),
+            ..Default::default()
+        };
+
+        let first = handler
+            .batch_insert_tool(load(ConflictStrategy::Ignore))
+            .await
+            .unwrap();
+        assert_eq!((first.rows_inserted, first.rows_ignored), (2, 0));
 
-                Ok(CallToolResult {
-                    content: vec![],
-                    structured_content: Some(serde_json::to_value(result).unwrap()),
-                    is_error: Some(false),
+        // Re-running the same load is a no-op under ignore
+        let mut again = load(ConflictStrategy::Ignore);
+        again.records = vec![
+            serde_json::from_value(serde_json::json!({"name": "go"})).unwrap(),
+            serde_json::from_value(serde_json::json!({"name": "sql", "uses": 9})).unwrap(),
+        ];
+        let second = handler.batch_insert_tool(again).await.unwrap();
+        assert_eq!((second.rows_inserted, second.rows_ignored), (1, 3));
+
+        let err = handler
+            .batch_insert_tool(load(ConflictStrategy::Abort))
+            .await;
+        assert!(err.is_err());
+
+        let replaced = handler
+            .batch_insert_tool(load(ConflictStrategy::Replace))
+            .await
+            .unwrap();
+        assert_eq!((replaced.rows_inserted, replaced.rows_ignored), (2, 0));
+
+        let mut contradictory = load(ConflictStrategy::Ignore);
+        contradictory.replace_on_conflict = true;
+        assert!(handler.batch_insert_tool(contradictory).await.is_err());
+
+        let count = handler
+            .query_tool(QueryRequest {
+                sql: "SELECT COUNT(*) FROM tags".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(count.data.unwrap()[0][0], 3);
+    }
+
+    #[tokio::test]
+    async fn test_table_checksum() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+        for sql in [
+            "CREATE TABLE a (id INTEGER, name TEXT, note TEXT)",
+            "CREATE TABLE b (id INTEGER, name TEXT, note TEXT)",
+            "INSERT INTO a VALUES (1, 'x', 'first'), (2, 'y', NULL), (2, 'y', NULL)",
+            "INSERT INTO b VALUES (2, 'y', NULL), (1, 'x', 'other'), (2, 'y', NULL)",
+        ] {
+            handler
+                .query_tool(QueryRequest {
+                    sql: sql.to_string(),
+                    ..Default::default()
                 })
-            }
-            _ => Err(rmcp::ErrorData::invalid_params("Tool not found", None)),
+                .await
+                .unwrap();
         }
-    }
-}
 
-// Implement ServerHandler trait
-impl ServerHandler for SqliteHandler {
-    fn get_info(&self) -> ServerInfo {
-        ServerInfo {
-            protocol_version: ProtocolVersion::V_2024_11_05,
-            server_info: Implementation {
-                name: "uni-sqlite".into(),
-                version: env!("CARGO_PKG_VERSION").into(),
-            },
-            capabilities: ServerCapabilities {
-                tools: Some(Default::default()),
+        let checksum = |table: &str, columns: Option<Vec<&str>>| TableChecksumRequest {
+            table_name: table.to_string(),
+            columns: columns.map(|c| c.into_iter().map(str::to_string).collect()),
+        };
+
+        let a = handler
+            .table_checksum_tool(checksum("a", None))
+            .await
+            .unwrap();
+        let b = handler
+            .table_checksum_tool(checksum("b", None))
+            .await
+            .unwrap();
+        assert_eq!(a.row_count, 3);
+        assert_eq!(a.columns, vec!["id", "name", "note"]);
+        assert_eq!(a.digest.len(), 64);
+        assert_ne!(a.digest, b.digest);
+
+        // Row order doesn't matter, so the copies agree once `note` is left out
+        let a = handler
+            .table_checksum_tool(checksum("a", Some(vec!["id", "name"])))
+            .await
+            .unwrap();
+        let b = handler
+            .table_checksum_tool(checksum("b", Some(vec!["id", "name"])))
+            .await
+            .unwrap();
+        assert_eq!(a.digest, b.digest);
+
+        // A duplicated row still changes the digest
+        handler
+            .query_tool(QueryRequest {
+                sql: "DELETE FROM b WHERE rowid = 3".to_string(),
                 ..Default::default()
-            },
-            instructions: Some(
-                "A comprehensive SQLite administration MCP server for consciousness persistence infrastructure. \
-                Features: database connections, schema management, transactions, backups, batch operations, \
-                CSV export/import, and health monitoring. Designed for LegacyMind project requirements."
-                    .into(),
-            ),
-        }
-    }
+            })
+            .await
+            .unwrap();
+        let b = handler
+            .table_checksum_tool(checksum("b", Some(vec!["id", "name"])))
+            .await
+            .unwrap();
+        assert_ne!(a.digest, b.digest);
 
-    fn list_tools(
-        &self,
-        request: Option<PaginatedRequestParam>,
-        context: RequestContext<rmcp::service::RoleServer>,
-    ) -> impl std::future::Future<Output = Result<ListToolsResult, rmcp::ErrorData>> + Send + '_
-    {
-        self.list_tools_handler(request, context)
+        assert!(
+            handler
+                .table_checksum_tool(checksum("missing", None))
+                .await
+                .is_err()
+        );
     }
 
-    fn call_tool(
-        &self,
-        request: CallToolRequestParam,
-        context: RequestContext<rmcp::service::RoleServer>,
-    ) -> impl std::future::Future<Output = Result<CallToolResult, rmcp::ErrorData>> + Send + '_
-    {
-        self.call_tool_handler(request, context)
-    }
-}
+    #[tokio::test]
+    async fn test_generate_fake_data() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+        for sql in [
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, full_name TEXT, email TEXT, active BOOLEAN)",
+            "CREATE TABLE orders (id INTEGER PRIMARY KEY, user_id INTEGER REFERENCES users(id), \
+             total REAL, placed_at DATETIME, notes, code TEXT GENERATED ALWAYS AS (id || '-x'))",
+        ] {
+            handler
+                .query_tool(QueryRequest {
+                    sql: sql.to_string(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
 
-pub async fn run() -> anyhow::Result<()> {
-    let handler = SqliteHandler::new();
+        let fake = |table: &str, count| GenerateFakeDataRequest {
+            table_name: table.to_string(),
+            count,
+            seed: Some(7),
+        };
 
-    // Serve the handler with stdio transport
-    let server = handler.serve(stdio()).await?;
+        // Children need parent rows to point at
+        assert!(
+            handler
+                .generate_fake_data_tool(fake("orders", 5))
+                .await
+                .is_err()
+        );
 
-    // Wait for service to complete
-    server.waiting().await?;
+        let users = handler
+            .generate_fake_data_tool(fake("users", 10))
+            .await
+            .unwrap();
+        assert_eq!(users.rows_inserted, 10);
+        let generators: Vec<_> = users
+            .generators
+            .iter()
+            .map(|g| g.generator.as_str())
+            .collect();
+        assert_eq!(generators, vec!["person_name", "email", "boolean"]);
 
-    Ok(())
-}
+        let orders = handler
+            .generate_fake_data_tool(fake("orders", 50))
+            .await
+            .unwrap();
+        let generators: Vec<_> = orders
+            .generators
+            .iter()
+            .map(|g| g.generator.as_str())
+            .collect();
+        assert_eq!(
+            generators,
+            vec!["foreign_key(users.id)", "real", "datetime", "text"]
+        );
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::fs;
-    use tempfile::TempDir;
+        let orphans = handler
+            .query_tool(QueryRequest {
+                sql: "SELECT COUNT(*) FROM orders WHERE user_id NOT IN (SELECT id FROM users) \
+                      OR placed_at NOT LIKE '20__-__-__ __:__:__'"
+                    .to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(orphans.data.unwrap()[0][0], 0);
 
-    async fn create_test_handler_with_db() -> (SqliteHandler, TempDir, PathBuf) {
-        let temp_dir = TempDir::new().unwrap();
-        let db_path = temp_dir.path().join("test.db");
+        // The same seed reproduces the same rows
+        let rows = |sql: &str| {
+            let handler = &handler;
+            let sql = sql.to_string();
+            async move {
+                handler
+                    .query_tool(QueryRequest {
+                        sql,
+                        ..Default::default()
+                    })
+                    .await
+                    .unwrap()
+                    .data
+                    .unwrap()
+            }
+        };
+        handler
+            .generate_fake_data_tool(fake("users", 10))
+            .await
+            .unwrap();
+        assert_eq!(
+            rows("SELECT full_name, email, active FROM users WHERE id <= 10").await,
+            rows("SELECT full_name, email, active FROM users WHERE id > 10").await
+        );
+    }
 
-        let handler = SqliteHandler::new();
+    #[tokio::test]
+    async fn test_query_blob_as_size() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+        handler
+            .query_tool(QueryRequest {
+                sql: "CREATE TABLE files (name TEXT, body BLOB)".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        handler
+            .query_tool(QueryRequest {
+                sql: "INSERT INTO files VALUES ('big', zeroblob(100000)), ('small', x'CAFE'), ('none', NULL)"
+                    .to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
 
-        // Connect to the test database
-        let connect_req = ConnectRequest {
-            path: db_path.display().to_string(),
-            create_if_missing: true,
+        let select = |blob_as| QueryRequest {
+            sql: "SELECT name, body FROM files ORDER BY rowid".to_string(),
+            blob_as,
+            cache: true,
+            ..Default::default()
         };
 
-        handler.connect_tool(connect_req).await.unwrap();
+        let sized = handler.query_tool(select(BlobAs::Size)).await.unwrap();
+        let rows = sized.data.unwrap();
+        assert_eq!(rows[0][1], serde_json::json!({ "blob_bytes": 100000 }));
+        assert_eq!(rows[1][1], serde_json::json!({ "blob_bytes": 2 }));
+        assert_eq!(rows[2][1], Value::Null);
 
-        (handler, temp_dir, db_path)
+        // A cached size-mode result is not served for a content request
+        let content = handler.query_tool(select(BlobAs::Content)).await.unwrap();
+        assert!(!content.cached);
+        assert_eq!(content.data.unwrap()[1][1], "cafe");
     }
 
     #[tokio::test]
-    async fn test_connect_and_health_check() {
+    async fn test_report_total_changes() {
         let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+        for sql in [
+            "PRAGMA foreign_keys = ON",
+            "CREATE TABLE authors (id INTEGER PRIMARY KEY, name TEXT)",
+            "CREATE TABLE books (id INTEGER PRIMARY KEY, \
+             author_id INTEGER REFERENCES authors(id) ON DELETE CASCADE)",
+            "CREATE TABLE audit (note TEXT)",
+            "CREATE TRIGGER authors_audit AFTER INSERT ON authors \
+             BEGIN INSERT INTO audit VALUES ('added ' || NEW.name); END",
+        ] {
+            handler
+                .query_tool(QueryRequest {
+                    sql: sql.to_string(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
 
-        let health = handler.health_check_tool().await.unwrap();
-        assert!(health.connected);
-        // Check that the path ends with the expected filename (handles canonicalization differences)
-        assert!(health.database_path.as_ref().unwrap().ends_with("test.db"));
-        assert!(health.database_size.is_some());
-        assert_eq!(health.table_count, Some(0));
+        let inserted = handler
+            .query_tool(QueryRequest {
+                sql: "INSERT INTO authors (name) VALUES ('Le Guin'), ('Butler')".to_string(),
+                report_total_changes: true,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(inserted.rows_affected, Some(2));
+        assert_eq!(inserted.total_changes, Some(4));
+
+        let books = handler
+            .batch_insert_tool(BatchInsertRequest {
+                table_name: "books".to_string(),
+                columns: vec!["author_id".to_string()],
+                rows: vec![vec![serde_json::json!(1)]; 3],
+                report_total_changes: true,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(books.total_changes, Some(3));
+
+        let deleted = handler
+            .delete_rows_tool(DeleteRowsRequest {
+                table_name: "authors".to_string(),
+                where_clause: "id = 1".to_string(),
+                report_total_changes: true,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(deleted.rows_deleted, 1);
+        assert_eq!(deleted.total_changes, Some(4));
+
+        // Not requested, not reported
+        let plain = handler
+            .query_tool(QueryRequest {
+                sql: "DELETE FROM audit".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(plain.total_changes, None);
     }
 
     #[tokio::test]
-    async fn test_create_table_and_list_tables() {
-        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+    async fn test_lock_status() {
+        let (handler, _temp_dir, db_path) = create_test_handler_with_db().await;
+        handler
+            .query_tool(QueryRequest {
+                sql: "CREATE TABLE jobs (id INTEGER PRIMARY KEY)".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
 
-        // Create a test table
-        let create_req = CreateTableRequest {
-            table_name: "consciousness_data".to_string(),
-            columns: "id INTEGER PRIMARY KEY, timestamp TEXT, data TEXT".to_string(),
-            if_not_exists: true,
-        };
+        let status = handler
+            .lock_status_tool(LockStatusRequest { probe_locks: true })
+            .await
+            .unwrap();
+        assert_eq!(status.journal_mode, "delete");
+        assert_eq!(status.lock_state, "unlocked");
+        assert_eq!(status.own_transaction, "none");
 
-        let result = handler.create_table_tool(create_req).await.unwrap();
-        assert!(result.success);
-        assert_eq!(result.table_name, "consciousness_data");
+        // Without probe_locks a reader holding SHARED is invisible, and nothing is locked
+        let other = Connection::open(&db_path).unwrap();
+        other.execute_batch("BEGIN; SELECT * FROM jobs;").unwrap();
+        let status = handler
+            .lock_status_tool(LockStatusRequest::default())
+            .await
+            .unwrap();
+        assert_eq!(status.lock_state, "unknown");
+        assert_eq!(status.readers_blocked, Some(false));
+        assert_eq!(status.writer_active, None);
+        other.execute_batch("ROLLBACK").unwrap();
 
-        // List tables
-        let tables = handler.list_tables_tool().await.unwrap();
-        assert_eq!(tables.total_count, 1);
-        assert_eq!(tables.tables[0].name, "consciousness_data");
-        assert_eq!(tables.tables[0].row_count, Some(0));
+        // Another process reading holds SHARED
+        other.execute_batch("BEGIN; SELECT * FROM jobs;").unwrap();
+        let status = handler
+            .lock_status_tool(LockStatusRequest { probe_locks: true })
+            .await
+            .unwrap();
+        assert_eq!(status.lock_state, "shared");
+        other.execute_batch("ROLLBACK").unwrap();
+
+        // ...and writing holds RESERVED
+        other
+            .execute_batch("BEGIN IMMEDIATE; INSERT INTO jobs DEFAULT VALUES;")
+            .unwrap();
+        let status = handler
+            .lock_status_tool(LockStatusRequest { probe_locks: true })
+            .await
+            .unwrap();
+        assert_eq!(status.lock_state, "reserved");
+        assert_eq!(status.writer_active, Some(true));
+        assert_eq!(status.readers_blocked, Some(false));
+        other.execute_batch("ROLLBACK").unwrap();
+
+        // In WAL mode a reader on an old snapshot keeps frames from being checkpointed
+        handler
+            .query_tool(QueryRequest {
+                sql: "PRAGMA journal_mode = WAL".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        other.execute_batch("BEGIN; SELECT * FROM jobs;").unwrap();
+        handler
+            .query_tool(QueryRequest {
+                sql: "INSERT INTO jobs DEFAULT VALUES".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let status = handler
+            .lock_status_tool(LockStatusRequest { probe_locks: true })
+            .await
+            .unwrap();
+        assert_eq!(status.lock_state, "unlocked");
+        assert_eq!(status.wal_readers_blocking_checkpoint, Some(true));
+        other.execute_batch("ROLLBACK").unwrap();
     }
 
     #[tokio::test]
-    async fn test_describe_table() {
-        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+    async fn test_query_fetch_limit() {
+        let temp_dir = TempDir::new().unwrap();
+        let handler = SqliteHandler::new();
+        handler
+            .connect_tool(ConnectRequest {
+                path: temp_dir.path().join("limit.db").display().to_string(),
+                create_if_missing: true,
+                default_fetch_limit: Some(25),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
 
-        // Create a test table
-        let create_req = CreateTableRequest {
-            table_name: "test_table".to_string(),
-            columns: "id INTEGER PRIMARY KEY, name TEXT NOT NULL, active BOOLEAN DEFAULT 1"
+        let series = |limit, unlimited| QueryRequest {
+            sql: "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 40) \
+                  SELECT i FROM n"
                 .to_string(),
-            if_not_exists: true,
-        };
-        handler.create_table_tool(create_req).await.unwrap();
-
-        // Describe the table
-        let describe_req = DescribeTableRequest {
-            table_name: "test_table".to_string(),
+            limit,
+            unlimited,
+            ..Default::default()
         };
 
-        let result = handler.describe_table_tool(describe_req).await.unwrap();
-        assert_eq!(result.table_name, "test_table");
-        assert_eq!(result.columns.len(), 3);
+        let result = handler.query_tool(series(None, false)).await.unwrap();
+        assert_eq!(result.rows_affected, Some(25));
+        assert!(result.truncated);
+        assert_eq!(result.limit, Some(25));
 
-        // Check column details
-        let id_col = &result.columns[0];
-        assert_eq!(id_col.name, "id");
-        assert_eq!(id_col.data_type, "INTEGER");
-        assert!(id_col.primary_key);
+        let result = handler.query_tool(series(Some(40), false)).await.unwrap();
+        assert_eq!(result.rows_affected, Some(40));
+        assert!(!result.truncated);
 
-        let name_col = &result.columns[1];
-        assert_eq!(name_col.name, "name");
-        assert_eq!(name_col.data_type, "TEXT");
-        assert!(name_col.not_null);
+        for (limit, unlimited) in [(Some(0), false), (Some(5), true)] {
+            let result = handler.query_tool(series(limit, unlimited)).await.unwrap();
+            assert_eq!(result.rows_affected, Some(40));
+            assert!(!result.truncated);
+            assert_eq!(result.limit, None);
+        }
     }
 
     #[tokio::test]
-    async fn test_query_operations() {
+    async fn test_reset_sequence() {
         let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
-
-        // Create table
-        let create_req = CreateTableRequest {
-            table_name: "users".to_string(),
-            columns: "id INTEGER PRIMARY KEY, name TEXT, email TEXT".to_string(),
-            if_not_exists: true,
+        for sql in [
+            "CREATE TABLE tickets (id INTEGER PRIMARY KEY AUTOINCREMENT, title TEXT)",
+            "CREATE TABLE plain (id INTEGER PRIMARY KEY, title TEXT)",
+        ] {
+            handler
+                .query_tool(QueryRequest {
+                    sql: sql.to_string(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
+        let reset = |table: &str, value| ResetSequenceRequest {
+            table_name: table.to_string(),
+            value,
         };
-        handler.create_table_tool(create_req).await.unwrap();
 
-        // Insert data
-        let insert_req = QueryRequest {
-            sql: "INSERT INTO users (name, email) VALUES (?, ?)".to_string(),
-            parameters: vec![
-                serde_json::Value::String("Alice".to_string()),
-                serde_json::Value::String("alice@example.com".to_string()),
-            ],
-        };
+        // No row in sqlite_sequence before the first insert
+        let status = handler
+            .reset_sequence_tool(reset("tickets", None))
+            .await
+            .unwrap();
+        assert_eq!((status.value, status.next_rowid), (None, 1));
 
-        let insert_result = handler.query_tool(insert_req).await.unwrap();
-        assert_eq!(insert_result.rows_affected, Some(1));
+        let status = handler
+            .reset_sequence_tool(reset("tickets", Some(499)))
+            .await
+            .unwrap();
+        assert_eq!((status.previous_value, status.value), (None, Some(499)));
+        handler
+            .query_tool(QueryRequest {
+                sql: "INSERT INTO tickets (title) VALUES ('first')".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        let status = handler
+            .reset_sequence_tool(reset("tickets", None))
+            .await
+            .unwrap();
+        assert_eq!((status.value, status.max_rowid), (Some(500), Some(500)));
+
+        // Going below an existing rowid is allowed but has no effect, and says so
+        let status = handler
+            .reset_sequence_tool(reset("tickets", Some(10)))
+            .await
+            .unwrap();
+        assert_eq!(status.next_rowid, 501);
+        assert!(status.warning.is_some());
+
+        assert!(
+            handler
+                .reset_sequence_tool(reset("plain", Some(1)))
+                .await
+                .is_err()
+        );
+        assert!(
+            handler
+                .reset_sequence_tool(reset("missing", None))
+                .await
+                .is_err()
+        );
+        assert!(
+            handler
+                .reset_sequence_tool(reset("tickets", Some(-1)))
+                .await
+                .is_err()
+        );
+
+        // The top of the rowid range is an error rather than an overflow
+        assert!(matches!(
+            handler
+                .reset_sequence_tool(reset("tickets", Some(i64::MAX)))
+                .await,
+            Err(UniSqliteError::QueryFailed(_))
+        ));
+        let status = handler
+            .reset_sequence_tool(reset("tickets", Some(i64::MAX - 1)))
+            .await
+            .unwrap();
+        assert_eq!(status.next_rowid, i64::MAX);
+        handler
+            .query_tool(QueryRequest {
+                sql: "INSERT INTO tickets (title) VALUES ('last')".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert!(matches!(
+            handler.reset_sequence_tool(reset("tickets", None)).await,
+            Err(UniSqliteError::QueryFailed(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_unicode_nocase_collation() {
+        let temp_dir = TempDir::new().unwrap();
+        let handler = SqliteHandler::new();
+        handler
+            .connect_tool(ConnectRequest {
+                path: temp_dir.path().join("names.db").display().to_string(),
+                create_if_missing: true,
+                enable_unicode_collation: true,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        for sql in [
+            "CREATE TABLE words (w TEXT COLLATE UNICODE_NOCASE)",
+            "INSERT INTO words VALUES ('Österreich'), ('zebra'), ('ÉCOLE'), ('apfel'), ('école')",
+        ] {
+            handler
+                .query_tool(QueryRequest {
+                    sql: sql.to_string(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
 
-        // Select data
-        let select_req = QueryRequest {
-            sql: "SELECT * FROM users WHERE name = ?".to_string(),
-            parameters: vec![serde_json::Value::String("Alice".to_string())],
+        let run = |sql: &str| QueryRequest {
+            sql: sql.to_string(),
+            ..Default::default()
         };
+        let matches = handler
+            .query_tool(run("SELECT COUNT(*) FROM words WHERE w = 'École'"))
+            .await
+            .unwrap();
+        assert_eq!(matches.data.unwrap()[0][0], 2);
 
-        let select_result = handler.query_tool(select_req).await.unwrap();
-        assert_eq!(select_result.rows_affected, Some(1));
-        assert!(select_result.data.is_some());
-        assert!(select_result.columns.is_some());
+        // Built-in NOCASE folds only ASCII, so 'école' is missed
+        let matches = handler
+            .query_tool(run(
+                "SELECT COUNT(*) FROM words WHERE w = 'École' COLLATE NOCASE",
+            ))
+            .await
+            .unwrap();
+        assert_eq!(matches.data.unwrap()[0][0], 1);
 
-        let data = select_result.data.unwrap();
-        assert_eq!(data.len(), 1);
-        assert_eq!(data[0][1], serde_json::Value::String("Alice".to_string()));
+        let ordered = handler
+            .query_tool(run(
+                "SELECT w FROM words ORDER BY w COLLATE UNICODE_NOCASE, w",
+            ))
+            .await
+            .unwrap();
+        // Lowercased code points order 'é' and 'ö' after 'z'; ties fall back to binary order
+        let words: Vec<_> = ordered
+            .data
+            .unwrap()
+            .into_iter()
+            .map(|r| r[0].clone())
+            .collect();
+        assert_eq!(words, ["apfel", "zebra", "ÉCOLE", "école", "Österreich"]);
     }
 
     #[tokio::test]
-    async fn test_batch_insert() {
-        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+    async fn test_import_csv_validates_input_path() {
+        let (handler, temp_dir, _db_path) = create_test_handler_with_db().await;
+        handler
+            .query_tool(QueryRequest {
+                sql: "CREATE TABLE lines (line TEXT)".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
 
-        // Create table
-        let create_req = CreateTableRequest {
-            table_name: "batch_test".to_string(),
-            columns: "id INTEGER PRIMARY KEY, value TEXT".to_string(),
-            if_not_exists: true,
-        };
-        handler.create_table_tool(create_req).await.unwrap();
+        let not_csv = temp_dir.path().join("secrets.env");
+        fs::write(&not_csv, "line\nTOKEN=abc\n").unwrap();
+        let result = handler
+            .import_csv_tool(ImportCsvRequest {
+                table_name: "lines".to_string(),
+                input_path: not_csv.display().to_string(),
+                has_headers: true,
+                reject_path: None,
+                encoding: None,
+            })
+            .await;
+        assert!(matches!(result, Err(UniSqliteError::InvalidPath(_))));
+    }
 
-        // Batch insert
-        let batch_req = BatchInsertRequest {
-            table_name: "batch_test".to_string(),
-            columns: vec!["value".to_string()],
-            rows: vec![
-                vec![serde_json::Value::String("row1".to_string())],
-                vec![serde_json::Value::String("row2".to_string())],
-                vec![serde_json::Value::String("row3".to_string())],
-            ],
-            replace_on_conflict: false,
-        };
+    #[tokio::test]
+    async fn test_generate_migration_keeps_cascading_children() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+        for sql in [
+            "PRAGMA foreign_keys = ON",
+            "CREATE TABLE authors (id INTEGER PRIMARY KEY, name TEXT)",
+            "CREATE TABLE books (id INTEGER PRIMARY KEY, \
+             author_id INTEGER REFERENCES authors(id) ON DELETE CASCADE)",
+            "INSERT INTO authors VALUES (1, 'Le Guin'), (2, 'Butler')",
+            "INSERT INTO books (author_id) VALUES (1), (1), (2)",
+        ] {
+            handler
+                .query_tool(QueryRequest {
+                    sql: sql.to_string(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
 
-        let result = handler.batch_insert_tool(batch_req).await.unwrap();
-        assert!(result.success);
-        assert_eq!(result.rows_inserted, 3);
+        // A new NOT NULL constraint forces a rebuild of the parent table
+        let target = "CREATE TABLE authors (id INTEGER PRIMARY KEY, name TEXT NOT NULL);
+                      CREATE TABLE books (id INTEGER PRIMARY KEY,
+                          author_id INTEGER REFERENCES authors(id) ON DELETE CASCADE);";
+        let result = handler
+            .generate_migration_tool(GenerateMigrationRequest {
+                target_sql: Some(target.to_string()),
+                target_database: None,
+                apply: true,
+                allow_destructive: false,
+            })
+            .await
+            .unwrap();
+        assert!(result.applied);
+        assert!(
+            result
+                .statements
+                .iter()
+                .any(|s| s.starts_with("DROP TABLE \"authors\""))
+        );
 
-        // Verify data was inserted
-        let select_req = QueryRequest {
-            sql: "SELECT COUNT(*) FROM batch_test".to_string(),
-            parameters: vec![],
+        let run = |sql: &str| QueryRequest {
+            sql: sql.to_string(),
+            ..Default::default()
         };
+        let books = handler
+            .query_tool(run("SELECT COUNT(*) FROM books"))
+            .await
+            .unwrap();
+        assert_eq!(books.data.unwrap()[0][0], 3);
+        let enforced = handler
+            .query_tool(run("PRAGMA foreign_keys"))
+            .await
+            .unwrap();
+        assert_eq!(enforced.data.unwrap()[0][0], 1);
 
-        let select_result = handler.query_tool(select_req).await.unwrap();
-        let count = &select_result.data.unwrap()[0][0];
-        assert_eq!(*count, serde_json::Value::Number(3.into()));
+        // A result that fails foreign_key_check is rolled back
+        for sql in [
+            "PRAGMA foreign_keys = OFF",
+            "INSERT INTO books (author_id) VALUES (99)",
+            "PRAGMA foreign_keys = ON",
+        ] {
+            handler.query_tool(run(sql)).await.unwrap();
+        }
+        let target = "CREATE TABLE authors (id INTEGER PRIMARY KEY, name TEXT NOT NULL UNIQUE);
+                      CREATE TABLE books (id INTEGER PRIMARY KEY,
+                          author_id INTEGER REFERENCES authors(id) ON DELETE CASCADE);";
+        let err = handler
+            .generate_migration_tool(GenerateMigrationRequest {
+                target_sql: Some(target.to_string()),
+                target_database: None,
+                apply: true,
+                allow_destructive: false,
+            })
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("break foreign keys"));
+        let unique = handler
+            .query_tool(run("SELECT COUNT(*) FROM pragma_index_list('authors')"))
+            .await
+            .unwrap();
+        assert_eq!(unique.data.unwrap()[0][0], 0);
+        let enforced = handler
+            .query_tool(run("PRAGMA foreign_keys"))
+            .await
+            .unwrap();
+        assert_eq!(enforced.data.unwrap()[0][0], 1);
     }
 
     #[tokio::test]
-    async fn test_transaction() {
+    async fn test_idle_monitor_keeps_session_state() {
         let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
 
-        // Create table
-        let create_req = CreateTableRequest {
-            table_name: "tx_test".to_string(),
-            columns: "id INTEGER PRIMARY KEY, value TEXT".to_string(),
-            if_not_exists: true,
-        };
-        handler.create_table_tool(create_req).await.unwrap();
-
-        // Transaction with multiple queries
-        let tx_req = TransactionRequest {
-            queries: vec![
-                QueryRequest {
-                    sql: "INSERT INTO tx_test (value) VALUES (?)".to_string(),
-                    parameters: vec![serde_json::Value::String("tx1".to_string())],
-                },
-                QueryRequest {
-                    sql: "INSERT INTO tx_test (value) VALUES (?)".to_string(),
-                    parameters: vec![serde_json::Value::String("tx2".to_string())],
-                },
-            ],
-            rollback_on_error: true,
-        };
+        handler
+            .query_tool(QueryRequest {
+                sql: "CREATE TEMP TABLE scratch (x INTEGER)".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
 
-        let result = handler.transaction_tool(tx_req).await.unwrap();
-        assert!(result.success);
-        assert_eq!(result.total_rows_affected, 2);
-        assert_eq!(result.results.len(), 2);
+        let monitor = handler.spawn_idle_monitor(Duration::from_millis(50));
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(handler.current_db.lock().await.is_some());
 
-        // Verify both rows were inserted
-        let select_req = QueryRequest {
-            sql: "SELECT COUNT(*) FROM tx_test".to_string(),
-            parameters: vec![],
-        };
+        handler
+            .query_tool(QueryRequest {
+                sql: "DROP TABLE scratch".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        handler
+            .query_tool(QueryRequest {
+                sql: "PRAGMA cache_size = 500".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(handler.current_db.lock().await.is_some());
 
-        let select_result = handler.query_tool(select_req).await.unwrap();
-        let count = &select_result.data.unwrap()[0][0];
-        assert_eq!(*count, serde_json::Value::Number(2.into()));
+        monitor.abort();
     }
 
     #[tokio::test]
-    async fn test_backup() {
+    async fn test_cross_query_non_ascii_path() {
         let (handler, temp_dir, _db_path) = create_test_handler_with_db().await;
 
-        // Create some data
-        let create_req = CreateTableRequest {
-            table_name: "backup_test".to_string(),
-            columns: "id INTEGER PRIMARY KEY, data TEXT".to_string(),
-            if_not_exists: true,
-        };
-        handler.create_table_tool(create_req).await.unwrap();
+        let dir = temp_dir.path().join("données #1");
+        std::fs::create_dir(&dir).unwrap();
+        let other_path = dir.join("ventes é.db");
+        Connection::open(&other_path)
+            .unwrap()
+            .execute_batch("CREATE TABLE orders (total INTEGER); INSERT INTO orders VALUES (42);")
+            .unwrap();
 
-        let insert_req = QueryRequest {
-            sql: "INSERT INTO backup_test (data) VALUES (?)".to_string(),
-            parameters: vec![serde_json::Value::String("test_data".to_string())],
-        };
-        handler.query_tool(insert_req).await.unwrap();
+        let result = handler
+            .cross_query_tool(CrossQueryRequest {
+                databases: vec![CrossQueryDatabase {
+                    alias: "sales".to_string(),
+                    path: other_path.to_string_lossy().to_string(),
+                }],
+                sql: "SELECT total FROM sales.orders".to_string(),
+                parameters: vec![],
+                typed: false,
+            })
+            .await
+            .unwrap();
+        assert_eq!(result.data.unwrap(), vec![vec![serde_json::json!(42)]]);
+    }
 
-        // Create backup
-        let backup_path = temp_dir.path().join("backup.db");
-        let backup_req = BackupRequest {
-            destination_path: backup_path.display().to_string(),
-        };
+    #[tokio::test]
+    async fn test_change_tracking_rejects_without_rowid() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
 
-        let result = handler.backup_tool(backup_req).await.unwrap();
-        assert!(result.success);
-        assert!(result.backup_size.is_some());
-        assert!(backup_path.exists());
+        handler
+            .query_tool(QueryRequest {
+                sql: "CREATE TABLE w (k TEXT PRIMARY KEY, v) WITHOUT ROWID".to_string(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
 
-        // Verify backup contains data
-        let backup_conn = Connection::open(&backup_path).unwrap();
-        let count: i32 = backup_conn
-            .query_row("SELECT COUNT(*) FROM backup_test", [], |row| row.get(0))
+        let result = handler
+            .enable_change_tracking_tool(ChangeTrackingRequest {
+                table: "w".to_string(),
+            })
+            .await;
+        assert!(matches!(result, Err(UniSqliteError::QueryFailed(_))));
+
+        // No triggers were left behind, so writes still work
+        handler
+            .query_tool(QueryRequest {
+                sql: "INSERT INTO w VALUES ('a', 1)".to_string(),
+                ..Default::default()
+            })
+            .await
             .unwrap();
-        assert_eq!(count, 1);
     }
 
     #[tokio::test]
-    async fn test_export_csv() {
-        let (handler, temp_dir, _db_path) = create_test_handler_with_db().await;
+    async fn test_query_cache_cleared_by_blob_write() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
 
-        // Create and populate table
-        let create_req = CreateTableRequest {
-            table_name: "csv_test".to_string(),
-            columns: "id INTEGER PRIMARY KEY, name TEXT, value INTEGER".to_string(),
-            if_not_exists: true,
+        for sql in [
+            "CREATE TABLE b (id INTEGER PRIMARY KEY, data BLOB)",
+            "INSERT INTO b VALUES (1, zeroblob(4))",
+        ] {
+            handler
+                .query_tool(QueryRequest {
+                    sql: sql.to_string(),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+        }
+        let read = || QueryRequest {
+            sql: "SELECT hex(data) FROM b".to_string(),
+            cache: true,
+            ..Default::default()
         };
-        handler.create_table_tool(create_req).await.unwrap();
+        let first = handler.query_tool(read()).await.unwrap();
+        assert_eq!(first.data.unwrap()[0][0], serde_json::json!("00000000"));
 
-        let batch_req = BatchInsertRequest {
-            table_name: "csv_test".to_string(),
-            columns: vec!["name".to_string(), "value".to_string()],
-            rows: vec![
-                vec![
-                    serde_json::Value::String("Alice".to_string()),
-                    serde_json::Value::Number(100.into()),
-                ],
-                vec![
-                    serde_json::Value::String("Bob".to_string()),
-                    serde_json::Value::Number(200.into()),
-                ],
-            ],
-            replace_on_conflict: false,
-        };
-        handler.batch_insert_tool(batch_req).await.unwrap();
+        // Incremental blob I/O leaves the data stamp alone, so the write must clear the cache
+        handler
+            .blob_write_tool(BlobWriteRequest {
+                table_name: "b".to_string(),
+                column: "data".to_string(),
+                rowid: 1,
+                offset: 0,
+                data_base64: BASE64_STANDARD.encode([0xff; 4]),
+            })
+            .await
+            .unwrap();
+        let second = handler.query_tool(read()).await.unwrap();
+        assert!(!second.cached);
+        assert_eq!(second.data.unwrap()[0][0], serde_json::json!("FFFFFFFF"));
+    }
 
-        // Export to CSV
-        let csv_path = temp_dir.path().join("export.csv");
-        let export_req = ExportCsvRequest {
-            query: "SELECT name, value FROM csv_test ORDER BY name".to_string(),
-            output_path: csv_path.display().to_string(),
-            include_headers: true,
-        };
+    #[tokio::test]
+    async fn test_failed_switch_keeps_session_lists() {
+        let (handler, temp_dir, _db_path) = create_test_handler_with_db().await;
+        handler
+            .switch_database_tool(ConnectRequest {
+                path: temp_dir.path().join("first.db").display().to_string(),
+                create_if_missing: true,
+                enable_uuid_function: true,
+                ..Default::default()
+            })
+            .await
+            .unwrap();
 
-        let result = handler.export_csv_tool(export_req).await.unwrap();
-        assert!(result.success);
-        assert_eq!(result.rows_exported, 2);
-        assert!(csv_path.exists());
+        // Fails while applying performance settings, after functions were registered
+        let failed = handler
+            .switch_database_tool(ConnectRequest {
+                path: temp_dir.path().join("second.db").display().to_string(),
+                create_if_missing: true,
+                performance: Some(PerformanceSettings {
+                    temp_store: Some("BOGUS".to_string()),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })
+            .await;
+        assert!(failed.is_err());
 
-        // Verify CSV content
-        let csv_content = fs::read_to_string(&csv_path).unwrap();
-        assert!(csv_content.contains("name,value"));
-        assert!(csv_content.contains("Alice,100"));
-        assert!(csv_content.contains("Bob,200"));
+        let functions = handler.list_functions_tool().await.unwrap();
+        assert_eq!(functions.functions.len(), 1);
+        assert_eq!(functions.functions[0].name, "uuid");
+        let health = handler.health_check_tool().await.unwrap();
+        assert!(health.database_path.unwrap().ends_with("first.db"));
     }
 
     #[tokio::test]
-    async fn test_sql_validation() {
-        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
+    async fn test_preview_csv_validates_input_path() {
+        let (handler, temp_dir, _db_path) = create_test_handler_with_db().await;
 
-        // Test invalid SQL (multiple statements)
-        let invalid_req = QueryRequest {
-            sql: "SELECT 1; DROP TABLE users;".to_string(),
-            parameters: vec![],
-        };
+        let not_csv = temp_dir.path().join("secrets.env");
+        fs::write(&not_csv, "line\nTOKEN=abc\n").unwrap();
+        let result = handler
+            .preview_csv_tool(PreviewCsvRequest {
+                input_path: not_csv.display().to_string(),
+                rows: None,
+                has_headers: true,
+                encoding: None,
+            })
+            .await;
+        assert!(matches!(result, Err(UniSqliteError::InvalidPath(_))));
+    }
 
-        let result = handler.query_tool(invalid_req).await;
-        assert!(result.is_err());
+    #[tokio::test]
+    async fn test_create_table_from_csv_validates_input_path() {
+        let (handler, temp_dir, _db_path) = create_test_handler_with_db().await;
 
-        // Test disallowed command
-        let disallowed_req = QueryRequest {
-            sql: "ATTACH DATABASE 'other.db' AS other".to_string(),
-            parameters: vec![],
-        };
+        let not_csv = temp_dir.path().join("secrets.env");
+        fs::write(&not_csv, "line\nTOKEN=abc\n").unwrap();
+        let result = handler
+            .create_table_from_csv_tool(CreateTableFromCsvRequest {
+                table_name: "lines".to_string(),
+                input_path: not_csv.display().to_string(),
+                sample_rows: None,
+                import: true,
+                has_headers: true,
+                encoding: None,
+            })
+            .await;
+        assert!(matches!(result, Err(UniSqliteError::InvalidPath(_))));
 
-        let result = handler.query_tool(disallowed_req).await;
-        assert!(result.is_err());
+        let tables = handler.list_tables_tool().await.unwrap();
+        assert!(tables.tables.is_empty());
     }
 
     #[tokio::test]
-    async fn test_path_validation() {
-        let handler = SqliteHandler::new();
-        let temp_dir = TempDir::new().unwrap();
-
-        // Test invalid extension in temp directory
-        let invalid_ext_path = temp_dir.path().join("test.txt");
-        let invalid_ext_req = ConnectRequest {
-            path: invalid_ext_path.display().to_string(),
-            create_if_missing: true,
-        };
+    async fn test_query_scalar_stops_after_second_row() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
 
-        let result = handler.connect_tool(invalid_ext_req).await;
-        assert!(result.is_err());
+        // The third row would raise an overflow error, so reaching it means every row was read
+        let result = handler
+            .query_scalar_tool(QueryRequest {
+                sql:
+                    "SELECT CASE WHEN column1 > 2 THEN abs(-9223372036854775808) ELSE column1 END \
+                      FROM (VALUES (1), (2), (3))"
+                        .to_string(),
+                ..Default::default()
+            })
+            .await;
+        match result {
+            Err(UniSqliteError::QueryFailed(msg)) => assert!(msg.contains("more than one")),
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
 
-        // Test valid path in temp directory should work
-        let valid_path = temp_dir.path().join("test.db");
-        let valid_req = ConnectRequest {
-            path: valid_path.display().to_string(),
-            create_if_missing: true,
-        };
+    #[tokio::test]
+    async fn test_query_row_stops_after_second_row() {
+        let (handler, _temp_dir, _db_path) = create_test_handler_with_db().await;
 
-        let result = handler.connect_tool(valid_req).await;
-        assert!(result.is_ok());
+        // The third row would raise an overflow error, so reaching it means every row was read
+        let result = handler
+            .query_row_tool(QueryRowRequest {
+                sql: "SELECT CASE WHEN column1 > 2 THEN abs(-9223372036854775808) ELSE column1 END AS n \
+                      FROM (VALUES (1), (2), (3))"
+                    .to_string(),
+                parameters: vec![],
+                typed: false,
+                allow_none: true,
+            })
+            .await;
+        match result {
+            Err(UniSqliteError::QueryFailed(msg)) => assert!(msg.contains("more than one")),
+            other => panic!("unexpected result: {other:?}"),
+        }
     }
 }